@@ -0,0 +1,42 @@
+/* C interface to osm_processor_rust (build with `cargo build --release`, link against
+ * target/release/libosm_processor_rust.{so,dylib,dll}). */
+
+#ifndef OSM_PROCESSOR_H
+#define OSM_PROCESSOR_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define OSM_OK 0
+#define OSM_ERR_INVALID_ARGUMENT 1
+#define OSM_ERR_PROCESSING 2
+#define OSM_ERR_PANIC 3
+
+/* phase is one of "find_nodes", "load_nodes", "extract_segments", "grouping";
+ * total is 0 when not known. Called when a phase starts and finishes and, in between, each
+ * time done passes a multiple of 8000 (at most once per PBF block during "load_nodes").
+ * May be called from several threads. */
+typedef void (*osm_progress_callback)(const char *phase, uint64_t done, uint64_t total, void *user_data);
+
+/* Extract streets from pbf_path and write them to output_path as parquet.
+ * feature may be NULL (highways) or "railway", "waterway", "cycling", "paths", "speed".
+ * progress may be NULL. Returns OSM_OK or an OSM_ERR_* code. */
+int osm_process_to_parquet(const char *pbf_path,
+                           const char *state,
+                           const char *output_path,
+                           double distance_threshold_km,
+                           const char *feature,
+                           osm_progress_callback progress,
+                           void *user_data);
+
+/* Description of the last error on the calling thread, or NULL. Do not free. */
+const char *osm_last_error_message(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* OSM_PROCESSOR_H */
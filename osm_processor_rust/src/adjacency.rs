@@ -0,0 +1,52 @@
+use polars::prelude::*;
+use std::collections::HashMap;
+
+use crate::error::Result;
+use crate::StreetSegment;
+
+/// Count, for every pair of distinct street names, the nodes where they meet.
+/// Pairs are ordered so that `name_a < name_b`.
+pub fn street_adjacency(segments: &[StreetSegment]) -> HashMap<(String, String), u32> {
+    // Distinct names touching each node
+    let mut node_names: HashMap<i64, Vec<&str>> = HashMap::new();
+    for seg in segments {
+        for &node_id in &seg.node_ids {
+            let names = node_names.entry(node_id).or_default();
+            if !names.contains(&seg.street_name.as_str()) {
+                names.push(&seg.street_name);
+            }
+        }
+    }
+
+    let mut pairs: HashMap<(String, String), u32> = HashMap::new();
+    for names in node_names.values_mut().filter(|names| names.len() > 1) {
+        names.sort_unstable();
+        for i in 0..names.len() {
+            for j in (i + 1)..names.len() {
+                *pairs
+                    .entry((names[i].to_string(), names[j].to_string()))
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    pairs
+}
+
+/// Street adjacency as a DataFrame, most-connected pairs first
+pub fn adjacency_to_dataframe(
+    pairs: HashMap<(String, String), u32>,
+    state_name: &str,
+) -> Result<DataFrame> {
+    let mut rows: Vec<((String, String), u32)> = pairs.into_iter().collect();
+    rows.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let df = DataFrame::new(vec![
+        Series::new("state", vec![state_name; rows.len()]),
+        Series::new("street_name_a", rows.iter().map(|r| r.0 .0.as_str()).collect::<Vec<_>>()),
+        Series::new("street_name_b", rows.iter().map(|r| r.0 .1.as_str()).collect::<Vec<_>>()),
+        Series::new("intersection_count", rows.iter().map(|r| r.1).collect::<Vec<_>>()),
+    ])?;
+
+    Ok(df)
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use crate::feature::Feature;
+use crate::orientation::orientation;
+use crate::qa::{bbox_diameter_km, raw_component_count};
+use crate::rep_point::{longest_segment, RepPoint};
+use crate::street_id::{street_id, StreetIdMode};
+use crate::{haversine_km, Street, StreetSegment};
+
+/// Turns one grouped component of same-name segments into a [`Street`]: representative point,
+/// tag aggregation and derived metrics. Implement this to change the output schema without
+/// post-processing the parquet; `extra` columns must be the same, in the same order, for
+/// every street of a run.
+pub trait StreetAggregator: Send + Sync {
+    fn aggregate(&self, name: &str, state: &str, segments: &[&StreetSegment], feature: Feature) -> Street;
+}
+
+/// The standard aggregation: representative point per `rep_point` (first segment's first
+/// point by default), summed length, most common type (or the longest segment's type with
+/// [`RepPoint::LongestSegment`]), tags carried by at least half the
+/// segments, plus the feature's extra columns
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultAggregator {
+    pub rep_point: RepPoint,
+    pub street_id: StreetIdMode,
+}
+
+impl StreetAggregator for DefaultAggregator {
+    fn aggregate(&self, name: &str, state: &str, segs: &[&StreetSegment], feature: Feature) -> Street {
+        let (lat, lon) = self.rep_point.locate(segs);
+        let highway_type = match self.rep_point {
+            RepPoint::LongestSegment => longest_segment(segs).highway_type.clone(),
+            _ => most_common_type(segs),
+        };
+        
+        // Sum all segment lengths
+        let total_length_km: f64 = segs.iter().map(|s| s.length_km).sum();
+        let num_components_raw = raw_component_count(segs);
+        
+        Street {
+            street_id: street_id(self.street_id, state, name, segs, (lat, lon)),
+            street_name: name.to_string(),
+            local_name: segs.iter().find_map(|s| s.local_name.clone()),
+            state: state.to_string(),
+            region: segs.first().and_then(|s| s.region.clone()),
+            city: most_common_tag(segs, "addr:city").or_else(|| most_common_tag(segs, "is_in:city")),
+            county_hint: most_common_tag(segs, "tiger:county").map(|value| tiger_county(&value)),
+            wikidata_id: most_common_tag(segs, "wikidata"),
+            etymology_wikidata: most_common_tag(segs, "name:etymology:wikidata"),
+            direction: merged_directions(segs),
+            relation_id: segs.iter().filter_map(|s| s.relation_id).min(),
+            lat,
+            lon,
+            num_segments: segs.len(),
+            num_components_raw,
+            merged_by_distance: num_components_raw > 1,
+            highway_type,
+            tags: common_tags(segs),
+            length_km: total_length_km,
+            extent_km: extent_km(segs),
+            orientation: orientation(segs),
+            endpoints: endpoints(segs),
+            is_loop: is_loop(segs),
+            extra: feature.extra_columns(segs),
+        }
+    }
+}
+
+/// Above this many way ends, [`extent_km`] uses the bounding box instead of comparing every pair
+const MAX_EXTENT_PAIRS_ENDS: usize = 2_000;
+
+/// Greatest Haversine distance between two way ends (first or last points) of the street, the
+/// spread that gives away distinct same-name streets merged into one. Streets with more than
+/// [`MAX_EXTENT_PAIRS_ENDS`] way ends get their bounding box diagonal instead, which is an upper
+/// bound of it.
+pub fn extent_km(segs: &[&StreetSegment]) -> f64 {
+    let ends: Vec<(f64, f64)> = segs
+        .iter()
+        .filter(|seg| !seg.coords.is_empty())
+        .flat_map(|seg| [seg.coords[0], seg.coords[seg.coords.len() - 1]])
+        .collect();
+    if ends.len() > MAX_EXTENT_PAIRS_ENDS {
+        return bbox_diameter_km(segs);
+    }
+
+    ends.iter()
+        .enumerate()
+        .flat_map(|(i, &a)| ends[i + 1..].iter().map(move |&b| haversine_km(a, b)))
+        .fold(0.0, f64::max)
+}
+
+/// Whether the segments close into a ring: every end node (first or last node of a way) is
+/// shared by an even number of way ends, so there is no dead end. A single closed way (a court's
+/// turning circle, a roundabout) qualifies; a loop with a stub road leading into it does not.
+pub fn is_loop(segs: &[&StreetSegment]) -> bool {
+    let mut ends: HashMap<i64, usize> = HashMap::new();
+    for seg in segs {
+        if seg.node_ids.len() < 2 {
+            return false;
+        }
+        *ends.entry(seg.node_ids[0]).or_default() += 1;
+        *ends.entry(seg.node_ids[seg.node_ids.len() - 1]).or_default() += 1;
+    }
+    !ends.is_empty() && ends.values().all(|&n| n % 2 == 0)
+}
+
+/// The two dead ends of the street farthest apart, as `(start, end)` with start the western one
+/// (the southern one if level), so streets read in a consistent direction. A dead end is a way's
+/// first or last node that no other way of the street touches, at either end or in between; a
+/// T-junction of two same-name ways is not one. `None` for loops and streets with a single dead
+/// end.
+pub fn endpoints(segs: &[&StreetSegment]) -> Option<((f64, f64), (f64, f64))> {
+    // Node degree within the street: 1 per way end, 2 per pass through the middle of a way
+    let mut degree: HashMap<i64, usize> = HashMap::new();
+    let mut ends: Vec<(i64, (f64, f64))> = Vec::new();
+    for seg in segs {
+        if seg.node_ids.len() < 2 || seg.coords.is_empty() {
+            continue;
+        }
+        let (first, last) = (seg.node_ids[0], seg.node_ids[seg.node_ids.len() - 1]);
+        for &node_id in &seg.node_ids[1..seg.node_ids.len() - 1] {
+            *degree.entry(node_id).or_default() += 2;
+        }
+        *degree.entry(first).or_default() += 1;
+        *degree.entry(last).or_default() += 1;
+        ends.push((first, seg.coords[0]));
+        ends.push((last, seg.coords[seg.coords.len() - 1]));
+    }
+
+    let dead_ends: Vec<(f64, f64)> = ends.into_iter().filter(|(id, _)| degree[id] == 1).map(|(_, p)| p).collect();
+    let farthest = dead_ends
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &a)| dead_ends[i + 1..].iter().map(move |&b| (haversine_km(a, b), a, b)))
+        .max_by(|x, y| x.0.total_cmp(&y.0));
+
+    farthest.map(|(_, a, b)| if (a.1, a.0) <= (b.1, b.0) { (a, b) } else { (b, a) })
+}
+
+/// Most common non-empty value of `key` among the segments (ties by value), if any carries it
+fn most_common_tag(segs: &[&StreetSegment], key: &str) -> Option<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in segs.iter().filter_map(|s| s.tags.get(key)).map(str::trim).filter(|v| !v.is_empty()) {
+        *counts.entry(value).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+        .map(|(value, _)| value.to_string())
+}
+
+/// County name from a TIGER `tiger:county` value: the first of `;`/`:`-separated counties,
+/// without its state suffix (`Kent, DE:Sussex, DE` -> `Kent`)
+fn tiger_county(value: &str) -> String {
+    let first = value.split([';', ':']).next().unwrap_or(value);
+    first.split(',').next().unwrap_or(first).trim().to_string()
+}
+
+/// Distinct directionals stripped from the segments' names, sorted and comma-separated
+fn merged_directions(segs: &[&StreetSegment]) -> Option<String> {
+    let mut directions: Vec<&str> = segs.iter().filter_map(|s| s.direction).collect();
+    directions.sort_unstable();
+    directions.dedup();
+    (!directions.is_empty()).then(|| directions.join(","))
+}
+
+/// Most common highway (or railway, ...) type among the segments
+pub fn most_common_type(segs: &[&StreetSegment]) -> String {
+    segs.iter()
+        .map(|s| s.highway_type.as_str())
+        .max_by_key(|&ht| segs.iter().filter(|s| s.highway_type == ht).count())
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Tags present on at least half the segments, each with its most common value
+pub fn common_tags(segs: &[&StreetSegment]) -> HashMap<String, String> {
+    let mut tag_counts: HashMap<&str, usize> = HashMap::new();
+    for seg in segs {
+        for key in seg.tags.keys() {
+            *tag_counts.entry(key).or_default() += 1;
+        }
+    }
+    
+    let threshold = segs.len() / 2;
+    let mut common_tags = HashMap::new();
+    for (key, count) in tag_counts {
+        if count >= threshold {
+            // Find most common value for this key
+            let mut value_counts: HashMap<&str, usize> = HashMap::new();
+            for seg in segs {
+                if let Some(value) = seg.tags.get(key) {
+                    *value_counts.entry(value).or_default() += 1;
+                }
+            }
+            if let Some((value, _)) = value_counts.iter().max_by_key(|(_, &c)| c) {
+                common_tags.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+    common_tags
+}
@@ -0,0 +1,202 @@
+use anyhow::Result;
+use polars::prelude::*;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use super::diversity::{diversity, Diversity};
+use super::Input;
+use crate::rng::SplitMix64;
+use osm_processor_rust::write_parquet;
+
+/// Derived statistics of a state's name distribution, as written to `bootstrap_stats.parquet`
+const STATISTICS: [&str; 5] = ["distinct_names", "unique_name_ratio", "shannon_entropy_bits", "evenness", "simpson_index"];
+
+fn statistics(d: &Diversity) -> [f64; 5] {
+    [
+        d.distinct_names as f64,
+        d.distinct_names as f64 / d.streets as f64,
+        d.entropy,
+        d.evenness,
+        d.simpson,
+    ]
+}
+
+/// Point estimate, standard error and percentile interval of one bootstrapped quantity
+struct Interval {
+    estimate: f64,
+    se: f64,
+    lo: f64,
+    hi: f64,
+}
+
+/// Linear-interpolated quantile of sorted values
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let pos = q * (sorted.len() - 1) as f64;
+    let (below, above) = (pos.floor() as usize, pos.ceil() as usize);
+    sorted[below] + (sorted[above] - sorted[below]) * (pos - below as f64)
+}
+
+fn interval(estimate: f64, mut replicates: Vec<f64>, confidence: f64) -> Interval {
+    let n = replicates.len() as f64;
+    let mean = replicates.iter().sum::<f64>() / n;
+    let se = (replicates.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0)).sqrt();
+    replicates.sort_unstable_by(f64::total_cmp);
+    let alpha = 1.0 - confidence;
+    Interval { estimate, se, lo: quantile(&replicates, alpha / 2.0), hi: quantile(&replicates, 1.0 - alpha / 2.0) }
+}
+
+/// Bootstrap results for one state: the `top` most common names' shares, and the statistics
+struct StateResult<'a> {
+    state: &'a str,
+    names: Vec<(&'a str, u64, Interval)>,
+    stats: Vec<Interval>,
+}
+
+/// Resample the state's streets with replacement `replicates` times. `names[i]` is the name
+/// index of street i; names are indexed by descending count, so the first `top` are reported.
+fn bootstrap_state<'a>(
+    state: &'a str,
+    name_list: &[&'a str],
+    names: &[usize],
+    top: usize,
+    replicates: usize,
+    confidence: f64,
+    seed: u64,
+) -> StateResult<'a> {
+    let streets = names.len();
+    let mut counts = vec![0u64; name_list.len()];
+    for &i in names {
+        counts[i] += 1;
+    }
+    let top = top.min(name_list.len());
+
+    let mut rng = SplitMix64::new(seed);
+    let mut shares = vec![Vec::with_capacity(replicates); top];
+    let mut stats = vec![Vec::with_capacity(replicates); STATISTICS.len()];
+    let mut resampled = vec![0u64; name_list.len()];
+    for _ in 0..replicates {
+        resampled.iter_mut().for_each(|n| *n = 0);
+        for _ in 0..streets {
+            resampled[names[rng.below(streets)]] += 1;
+        }
+        for (i, share) in shares.iter_mut().enumerate() {
+            share.push(resampled[i] as f64 / streets as f64);
+        }
+        for (values, value) in stats.iter_mut().zip(statistics(&diversity(&resampled))) {
+            values.push(value);
+        }
+    }
+
+    StateResult {
+        state,
+        names: shares
+            .into_iter()
+            .enumerate()
+            .map(|(i, values)| (name_list[i], counts[i], interval(counts[i] as f64 / streets as f64, values, confidence)))
+            .collect(),
+        stats: stats
+            .into_iter()
+            .zip(statistics(&diversity(&counts)))
+            .map(|(values, estimate)| interval(estimate, values, confidence))
+            .collect(),
+    }
+}
+
+/// `analyze bootstrap <streets.parquet...> [--replicates N] [--seed S] [--confidence X] [--top N] --output-dir DIR`
+///
+/// Resamples streets with replacement within each state to put percentile confidence intervals
+/// on the `top` most common names' shares of the state's streets (`bootstrap_names.parquet`) and
+/// on the diversity statistics of `analyze diversity` (`bootstrap_stats.parquet`). Each state
+/// gets its own stream from `seed`, so results do not depend on which other states are included.
+pub fn run_bootstrap(
+    input: &Input,
+    replicates: usize,
+    seed: u64,
+    confidence: f64,
+    top: usize,
+    output_dir: &Path,
+) -> Result<()> {
+    if replicates < 2 {
+        anyhow::bail!("analyze bootstrap: need at least 2 replicates");
+    }
+    if !(confidence > 0.0 && confidence < 1.0) {
+        anyhow::bail!("analyze bootstrap: confidence must be between 0 and 1, got {}", confidence);
+    }
+
+    println!("\n{}", "=".repeat(70));
+    println!("BOOTSTRAP: NAME FREQUENCIES BY STATE");
+    println!("{}", "=".repeat(70));
+    println!("Input files: {}", input.paths.len());
+    println!("Replicates:  {} (seed {})", replicates, seed);
+    println!("Confidence:  {}", confidence);
+    println!("{}", "=".repeat(70));
+
+    let df = input.load()?;
+    let names = df.column("street_name")?.str()?;
+    let states = df.column("state")?.str()?;
+
+    let mut by_state: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (name, state) in names.into_iter().zip(states) {
+        by_state.entry(state.unwrap_or("")).or_default().push(name.unwrap_or(""));
+    }
+
+    // Per state: names by descending count (ties by name), and each street's index into them
+    let prepared: Vec<(&str, Vec<&str>, Vec<usize>)> = by_state
+        .into_iter()
+        .map(|(state, streets)| {
+            let mut counts: HashMap<&str, u64> = HashMap::new();
+            for &name in &streets {
+                *counts.entry(name).or_default() += 1;
+            }
+            let mut name_list: Vec<&str> = counts.keys().copied().collect();
+            name_list.sort_unstable_by(|a, b| counts[b].cmp(&counts[a]).then(a.cmp(b)));
+            let index: HashMap<&str, usize> = name_list.iter().enumerate().map(|(i, &name)| (name, i)).collect();
+            let indices = streets.iter().map(|name| index[name]).collect();
+            (state, name_list, indices)
+        })
+        .collect();
+
+    let results: Vec<StateResult> = prepared
+        .par_iter()
+        .enumerate()
+        .map(|(i, (state, name_list, indices))| {
+            bootstrap_state(state, name_list, indices, top, replicates, confidence, seed.wrapping_add(i as u64))
+        })
+        .collect();
+
+    let name_rows: Vec<(&str, &(&str, u64, Interval))> =
+        results.iter().flat_map(|r| r.names.iter().map(move |row| (r.state, row))).collect();
+    let mut names_df = DataFrame::new(vec![
+        Series::new("state", name_rows.iter().map(|(state, _)| *state).collect::<Vec<_>>()),
+        Series::new("street_name", name_rows.iter().map(|(_, row)| row.0).collect::<Vec<_>>()),
+        Series::new("count", name_rows.iter().map(|(_, row)| row.1).collect::<Vec<_>>()),
+        Series::new("share", name_rows.iter().map(|(_, row)| row.2.estimate).collect::<Vec<_>>()),
+        Series::new("share_se", name_rows.iter().map(|(_, row)| row.2.se).collect::<Vec<_>>()),
+        Series::new("share_lo", name_rows.iter().map(|(_, row)| row.2.lo).collect::<Vec<_>>()),
+        Series::new("share_hi", name_rows.iter().map(|(_, row)| row.2.hi).collect::<Vec<_>>()),
+    ])?;
+
+    let stat_rows: Vec<(&str, &str, &Interval)> = results
+        .iter()
+        .flat_map(|r| STATISTICS.iter().zip(&r.stats).map(move |(&name, interval)| (r.state, name, interval)))
+        .collect();
+    let mut stats_df = DataFrame::new(vec![
+        Series::new("state", stat_rows.iter().map(|r| r.0).collect::<Vec<_>>()),
+        Series::new("statistic", stat_rows.iter().map(|r| r.1).collect::<Vec<_>>()),
+        Series::new("estimate", stat_rows.iter().map(|r| r.2.estimate).collect::<Vec<_>>()),
+        Series::new("se", stat_rows.iter().map(|r| r.2.se).collect::<Vec<_>>()),
+        Series::new("lo", stat_rows.iter().map(|r| r.2.lo).collect::<Vec<_>>()),
+        Series::new("hi", stat_rows.iter().map(|r| r.2.hi).collect::<Vec<_>>()),
+    ])?;
+
+    println!("\n{}", stats_df);
+
+    std::fs::create_dir_all(output_dir)?;
+    println!("\nSaving to: {}", output_dir.display());
+    write_parquet(&mut names_df, &output_dir.join("bootstrap_names.parquet"))?;
+    write_parquet(&mut stats_df, &output_dir.join("bootstrap_stats.parquet"))?;
+
+    println!("Done!");
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::path::Path;
+
+use super::correction::Correction;
+use super::dist::chi_square_sf;
+use super::{load_name_state_table, Input};
+use osm_processor_rust::write_parquet;
+
+/// `analyze chisq <streets.parquet...> [--min-count N] [--correction bh|bonferroni|none] [--alpha X] --output-dir DIR`
+///
+/// Tests each street name's distribution across states against the overall state distribution.
+/// Names with fewer than `min_count` streets are dropped first, since the chi-square
+/// approximation is unreliable for tiny expected counts. The per-name p-values are adjusted for
+/// the number of names tested, and `significant` compares the adjusted value with `alpha`.
+pub fn run_chisq(input: &Input, min_count: u64, correction: Correction, alpha: f64, output_dir: &Path) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("CHI-SQUARE: STREET NAME vs STATE");
+    println!("{}", "=".repeat(70));
+    println!("Input files: {}", input.paths.len());
+    println!("Min count:   {}", min_count);
+    println!("Correction:  {} (alpha {})", correction.name(), alpha);
+    println!("{}", "=".repeat(70));
+
+    let mut table = load_name_state_table(input)?;
+    let names_before = table.rows.len();
+
+    let keep: Vec<bool> = table.row_totals().iter().map(|&n| n >= min_count).collect();
+    let mut kept = keep.iter();
+    table.rows.retain(|_| *kept.next().unwrap());
+    let mut kept = keep.iter();
+    table.counts.retain(|_| *kept.next().unwrap());
+    // States whose streets all had dropped names would have expected counts of zero
+    let has_streets: Vec<bool> = table.col_totals().iter().map(|&n| n > 0).collect();
+    let mut kept = has_streets.iter();
+    table.cols.retain(|_| *kept.next().unwrap());
+    for counts in &mut table.counts {
+        let mut kept = has_streets.iter();
+        counts.retain(|_| *kept.next().unwrap());
+    }
+    println!("Names tested: {} of {} (>= {} streets)", table.rows.len(), names_before, min_count);
+
+    let row_totals = table.row_totals();
+    let col_totals = table.col_totals();
+    let total = table.total() as f64;
+    if total == 0.0 || table.cols.len() < 2 {
+        anyhow::bail!("analyze chisq: need at least two states with streets");
+    }
+    let dof = (table.cols.len() - 1) as f64;
+
+    let mut by_name = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    let mut cells = (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    let mut overall = 0.0;
+
+    for (r, name) in table.rows.iter().enumerate() {
+        let row_share = row_totals[r] as f64 / total;
+        let mut chi2 = 0.0;
+        for (c, state) in table.cols.iter().enumerate() {
+            let col_share = col_totals[c] as f64 / total;
+            let observed = table.counts[r][c] as f64;
+            let expected = row_totals[r] as f64 * col_totals[c] as f64 / total;
+            let residual = (observed - expected) / expected.sqrt();
+            // Undefined when one name (or one state) holds every street
+            let variance = expected * (1.0 - row_share) * (1.0 - col_share);
+            let adjusted = (variance > 0.0).then(|| (observed - expected) / variance.sqrt());
+            chi2 += residual * residual;
+
+            cells.0.push(name.as_str());
+            cells.1.push(state.as_str());
+            cells.2.push(table.counts[r][c]);
+            cells.3.push(expected);
+            cells.4.push(residual);
+            cells.5.push(adjusted);
+        }
+        overall += chi2;
+
+        by_name.0.push(name.as_str());
+        by_name.1.push(row_totals[r]);
+        by_name.2.push(chi2);
+        by_name.3.push(chi_square_sf(chi2, dof));
+    }
+
+    let overall_dof = dof * (table.rows.len().saturating_sub(1)) as f64;
+    let min_dim = table.rows.len().min(table.cols.len()).saturating_sub(1).max(1) as f64;
+    println!("\nOverall chi-square: {:.2} (dof {}, p = {:.3e})", overall, overall_dof, chi_square_sf(overall, overall_dof));
+    println!("Cramér's V:         {:.4}", (overall / (total * min_dim)).sqrt());
+
+    let p_adjusted = correction.adjust(&by_name.3);
+    let significant: Vec<bool> = p_adjusted.iter().map(|&p| p < alpha).collect();
+    println!(
+        "Significant names:  {} of {} (adjusted p < {})",
+        significant.iter().filter(|&&s| s).count(),
+        significant.len(),
+        alpha
+    );
+
+    let mut by_name_df = DataFrame::new(vec![
+        Series::new("street_name", by_name.0),
+        Series::new("count", by_name.1),
+        Series::new("chi2", by_name.2),
+        Series::new("dof", vec![dof as u32; table.rows.len()]),
+        Series::new("p_value", by_name.3),
+        Series::new("p_adjusted", p_adjusted),
+        Series::new("significant", significant),
+    ])?
+    .sort(["chi2"], SortMultipleOptions::default().with_order_descending(true))?;
+
+    println!("\nMost state-specific names:");
+    println!("{}", by_name_df.head(Some(10)));
+
+    let mut cells_df = DataFrame::new(vec![
+        Series::new("street_name", cells.0),
+        Series::new("state", cells.1),
+        Series::new("observed", cells.2),
+        Series::new("expected", cells.3),
+        Series::new("residual", cells.4),
+        Series::new("adjusted_residual", cells.5),
+    ])?;
+
+    std::fs::create_dir_all(output_dir)?;
+    println!("\nSaving to: {}", output_dir.display());
+    write_parquet(&mut by_name_df, &output_dir.join("chisq_by_name.parquet"))?;
+    write_parquet(&mut cells_df, &output_dir.join("chisq_cells.parquet"))?;
+
+    println!("Done!");
+    Ok(())
+}
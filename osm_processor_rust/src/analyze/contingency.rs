@@ -0,0 +1,79 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::path::Path;
+
+use super::{CrossTab, Input};
+use osm_processor_rust::write_table;
+
+/// One row per name with a count column per category and a `total` column, then a marginal
+/// row with a null `street_name` holding the category totals and the grand total
+fn to_wide(table: &CrossTab) -> Result<DataFrame> {
+    let row_totals = table.row_totals();
+    let col_totals = table.col_totals();
+
+    let mut names: Vec<Option<&str>> = table.rows.iter().map(|name| Some(name.as_str())).collect();
+    names.push(None);
+    let mut columns = vec![Series::new("street_name", names)];
+    for (c, category) in table.cols.iter().enumerate() {
+        let mut counts: Vec<u64> = table.counts.iter().map(|row| row[c]).collect();
+        counts.push(col_totals[c]);
+        columns.push(Series::new(category, counts));
+    }
+    let mut totals = row_totals;
+    totals.push(table.total());
+    columns.push(Series::new("total", totals));
+    Ok(DataFrame::new(columns)?)
+}
+
+/// Non-zero cells as `(street_name, <by>, count)` with the row, column and grand totals
+/// repeated on each row
+fn to_sparse(table: &CrossTab, by: &str) -> Result<DataFrame> {
+    let row_totals = table.row_totals();
+    let col_totals = table.col_totals();
+
+    let mut cells = (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for (r, (name, row)) in table.rows.iter().zip(&table.counts).enumerate() {
+        for (c, (category, &n)) in table.cols.iter().zip(row).enumerate() {
+            if n > 0 {
+                cells.0.push(name.as_str());
+                cells.1.push(category.as_str());
+                cells.2.push(n);
+                cells.3.push(row_totals[r]);
+                cells.4.push(col_totals[c]);
+            }
+        }
+    }
+
+    let rows = cells.0.len();
+    Ok(DataFrame::new(vec![
+        Series::new("street_name", cells.0),
+        Series::new(by, cells.1),
+        Series::new("count", cells.2),
+        Series::new("row_total", cells.3),
+        Series::new("col_total", cells.4),
+        Series::new("grand_total", vec![table.total(); rows]),
+    ])?)
+}
+
+/// `analyze contingency <streets.parquet...> [--by state|highway_type|COLUMN] [--format parquet|csv] --output-dir DIR`
+///
+/// The full street_name × `by` contingency table with marginals, as the canonical input for
+/// external analyses: `contingency_wide` (one column per category plus totals) and
+/// `contingency_sparse` (non-zero cells with their marginals).
+pub fn run_contingency(input: &Input, by: &str, format: &str, output_dir: &Path) -> Result<()> {
+    let df = input.load()?;
+    if !df.get_column_names().contains(&by) {
+        anyhow::bail!("analyze contingency: no {} column in the input", by);
+    }
+    let table = CrossTab::from_columns(&df, "street_name", by)?;
+    println!("{} names × {} {} values ({} streets)", table.rows.len(), table.cols.len(), by, table.total());
+
+    let mut wide = to_wide(&table)?;
+    let mut sparse = to_sparse(&table, by)?;
+
+    std::fs::create_dir_all(output_dir)?;
+    println!("Saving to: {}", output_dir.display());
+    write_table(&mut wide, &output_dir.join(format!("contingency_wide.{}", format)))?;
+    write_table(&mut sparse, &output_dir.join(format!("contingency_sparse.{}", format)))?;
+    Ok(())
+}
@@ -0,0 +1,117 @@
+//! Multiple-testing corrections for per-name tests
+
+use anyhow::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Correction {
+    /// Benjamini-Hochberg: controls the false discovery rate
+    BenjaminiHochberg,
+    /// Bonferroni: controls the family-wise error rate
+    Bonferroni,
+    /// Raw p-values
+    None,
+}
+
+impl Correction {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "bh" => Ok(Correction::BenjaminiHochberg),
+            "bonferroni" => Ok(Correction::Bonferroni),
+            "none" => Ok(Correction::None),
+            other => anyhow::bail!("Unknown correction {} (expected bh, bonferroni or none)", other),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Correction::BenjaminiHochberg => "bh",
+            Correction::Bonferroni => "bonferroni",
+            Correction::None => "none",
+        }
+    }
+
+    /// Adjusted p-values, in the order given; the family is every p-value passed
+    pub fn adjust(self, p_values: &[f64]) -> Vec<f64> {
+        let m = p_values.len() as f64;
+        match self {
+            Correction::None => p_values.to_vec(),
+            Correction::Bonferroni => p_values.iter().map(|p| (p * m).min(1.0)).collect(),
+            Correction::BenjaminiHochberg => {
+                // Step-up: p_(i) * m / i, made monotone from the largest p-value down
+                let mut order: Vec<usize> = (0..p_values.len()).collect();
+                order.sort_unstable_by(|&a, &b| p_values[a].total_cmp(&p_values[b]));
+                let mut adjusted = vec![0.0; p_values.len()];
+                let mut running = 1.0f64;
+                for (rank, &i) in order.iter().enumerate().rev() {
+                    running = running.min(p_values[i] * m / (rank + 1) as f64);
+                    adjusted[i] = running;
+                }
+                adjusted
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_all_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-12, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn benjamini_hochberg_known_vector() {
+        let adjusted = Correction::BenjaminiHochberg.adjust(&[0.005, 0.01, 0.03, 0.04, 0.2]);
+        assert_all_close(&adjusted, &[0.025, 0.025, 0.05, 0.05, 0.2]);
+        let adjusted = Correction::BenjaminiHochberg.adjust(&[0.01, 0.02, 0.03, 0.04, 0.05]);
+        assert_all_close(&adjusted, &[0.05; 5]);
+    }
+
+    #[test]
+    fn benjamini_hochberg_is_monotone_and_keeps_input_order() {
+        // p * m / rank would be 0.06 for 0.04, above the 0.041 of the next larger p-value
+        let adjusted = Correction::BenjaminiHochberg.adjust(&[0.04, 0.01, 0.041]);
+        assert_all_close(&adjusted, &[0.041, 0.03, 0.041]);
+    }
+
+    #[test]
+    fn benjamini_hochberg_ties_share_a_value() {
+        let adjusted = Correction::BenjaminiHochberg.adjust(&[0.02, 0.5, 0.02, 0.02]);
+        assert_all_close(&adjusted, &[0.08 / 3.0, 0.5, 0.08 / 3.0, 0.08 / 3.0]);
+    }
+
+    #[test]
+    fn benjamini_hochberg_stays_at_most_one() {
+        let adjusted = Correction::BenjaminiHochberg.adjust(&[0.9, 0.95, 0.99, 1.0]);
+        assert_all_close(&adjusted, &[1.0; 4]);
+        let adjusted = Correction::BenjaminiHochberg.adjust(&[0.6, 0.7]);
+        assert_all_close(&adjusted, &[0.7, 0.7]);
+    }
+
+    #[test]
+    fn bonferroni_scales_and_caps() {
+        let adjusted = Correction::Bonferroni.adjust(&[0.01, 0.3, 0.04]);
+        assert_all_close(&adjusted, &[0.03, 0.9, 0.12]);
+        let adjusted = Correction::Bonferroni.adjust(&[0.5, 0.01, 0.5]);
+        assert_all_close(&adjusted, &[1.0, 0.03, 1.0]);
+    }
+
+    #[test]
+    fn none_and_empty() {
+        assert_eq!(Correction::None.adjust(&[0.2, 0.01]), vec![0.2, 0.01]);
+        assert!(Correction::BenjaminiHochberg.adjust(&[]).is_empty());
+        assert!(Correction::Bonferroni.adjust(&[]).is_empty());
+    }
+
+    #[test]
+    fn parses_its_own_names() {
+        for correction in [Correction::BenjaminiHochberg, Correction::Bonferroni, Correction::None] {
+            assert_eq!(Correction::parse(correction.name()).unwrap(), correction);
+        }
+        assert!(Correction::parse("holm").is_err());
+    }
+}
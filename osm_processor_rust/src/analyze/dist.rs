@@ -0,0 +1,136 @@
+//! Distribution functions needed for p-values, implemented directly to avoid a stats dependency
+
+/// ln Γ(x) for x > 0 (Lanczos approximation, g = 7)
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let mut sum = COEFFS[0];
+    for (i, &c) in COEFFS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Upper regularized incomplete gamma function Q(a, x)
+pub fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    if x < a + 1.0 {
+        1.0 - gamma_p_series(a, x)
+    } else {
+        gamma_q_continued_fraction(a, x)
+    }
+}
+
+fn gamma_p_series(a: f64, x: f64) -> f64 {
+    let mut term = 1.0 / a;
+    let mut sum = term;
+    let mut n = a;
+    for _ in 0..1000 {
+        n += 1.0;
+        term *= x / n;
+        sum += term;
+        if term.abs() < sum.abs() * 1e-15 {
+            break;
+        }
+    }
+    sum * (-x + a * x.ln() - ln_gamma(a)).exp()
+}
+
+fn gamma_q_continued_fraction(a: f64, x: f64) -> f64 {
+    // Modified Lentz's method
+    const TINY: f64 = 1e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..1000 {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    (-x + a * x.ln() - ln_gamma(a)).exp() * h
+}
+
+/// P(X >= x) for X ~ chi-square with `dof` degrees of freedom
+pub fn chi_square_sf(x: f64, dof: f64) -> f64 {
+    if dof <= 0.0 {
+        return 1.0;
+    }
+    gamma_q(dof / 2.0, x / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        let tolerance = 1e-9 * expected.abs();
+        assert!((actual - expected).abs() <= tolerance, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn chi_square_sf_one_dof() {
+        // Closed form erfc(sqrt(x / 2)); 3.8415 is the 5% critical value
+        assert_close(chi_square_sf(1.0, 1.0), 0.317_310_507_862_914_04);
+        assert_close(chi_square_sf(3.841_458_820_694_124, 1.0), 0.05);
+    }
+
+    #[test]
+    fn chi_square_sf_two_dof() {
+        // Closed form exp(-x / 2)
+        assert_close(chi_square_sf(2.0, 2.0), (-1.0f64).exp());
+        assert_close(chi_square_sf(10.0, 2.0), (-5.0f64).exp());
+    }
+
+    #[test]
+    fn chi_square_sf_ten_dof() {
+        // Closed form exp(-x / 2) * sum_{k<5} (x / 2)^k / k!
+        assert_close(chi_square_sf(10.0, 10.0), 0.440_493_285_065_212_4);
+        assert_close(chi_square_sf(18.307_038_053_275_146, 10.0), 0.05);
+    }
+
+    #[test]
+    fn chi_square_sf_at_zero_is_one() {
+        for dof in [1.0, 2.0, 10.0] {
+            assert_eq!(chi_square_sf(0.0, dof), 1.0);
+        }
+    }
+
+    #[test]
+    fn chi_square_sf_far_tail() {
+        assert_close(chi_square_sf(100.0, 1.0), 1.523_970_604_832_099_5e-23);
+        assert_close(chi_square_sf(200.0, 10.0), 1.613_930_533_697_730_5e-37);
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::path::Path;
+
+use super::{load_name_state_table, CrossTab, Input};
+use osm_processor_rust::write_table;
+
+/// How names are ranked within a state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ranking {
+    /// z-score of the log-odds ratio (state vs rest of country) with an informative Dirichlet prior
+    LogOdds,
+    /// Term frequency in the state × inverse "document" (state) frequency
+    TfIdf,
+}
+
+impl Ranking {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "logodds" => Ok(Ranking::LogOdds),
+            "tfidf" => Ok(Ranking::TfIdf),
+            other => anyhow::bail!("Unknown ranking {} (expected logodds or tfidf)", other),
+        }
+    }
+}
+
+/// One name's scores within one state
+struct Score<'a> {
+    name: &'a str,
+    count: u64,
+    tf_idf: f64,
+    log_odds: f64,
+    z_score: f64,
+}
+
+/// Score every name present in state column `c`.
+///
+/// Log-odds follow Monroe, Colaresi & Quinn (2008): the prior for each name is its national
+/// count scaled by `prior_scale`, so rare names are shrunk toward the national rate.
+fn score_state<'a>(table: &'a CrossTab, c: usize, row_totals: &[u64], prior_scale: f64) -> Vec<Score<'a>> {
+    let num_states = table.cols.len() as f64;
+    let state_total: u64 = table.counts.iter().map(|row| row[c]).sum();
+    let grand_total: u64 = row_totals.iter().sum();
+    let rest_total = grand_total - state_total;
+    let prior_total = grand_total as f64 * prior_scale;
+
+    table
+        .counts
+        .iter()
+        .zip(&table.rows)
+        .zip(row_totals)
+        .filter(|((row, _), _)| row[c] > 0)
+        .map(|((row, name), &national)| {
+            let in_state = row[c] as f64;
+            let in_rest = (national - row[c]) as f64;
+            let prior = national as f64 * prior_scale;
+
+            let states_with_name = row.iter().filter(|&&n| n > 0).count() as f64;
+            let tf_idf = in_state / state_total as f64 * (num_states / states_with_name).ln();
+
+            let odds_state = (in_state + prior) / (state_total as f64 + prior_total - in_state - prior);
+            let odds_rest = (in_rest + prior) / (rest_total as f64 + prior_total - in_rest - prior);
+            let log_odds = odds_state.ln() - odds_rest.ln();
+            let variance = 1.0 / (in_state + prior) + 1.0 / (in_rest + prior);
+
+            Score {
+                name,
+                count: row[c],
+                tf_idf,
+                log_odds,
+                z_score: log_odds / variance.sqrt(),
+            }
+        })
+        .collect()
+}
+
+/// `analyze tfidf <streets.parquet...> [--rank logodds|tfidf] [--top N] [--prior-scale X] [--format parquet|csv] --output-dir DIR`
+///
+/// Writes `<dir>/<state>_distinctive_names.<format>` per state, plus all states combined.
+pub fn run_distinctive(
+    input: &Input,
+    ranking: Ranking,
+    top_n: usize,
+    prior_scale: f64,
+    format: &str,
+    output_dir: &Path,
+) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("DISTINCTIVE STREET NAMES PER STATE");
+    println!("{}", "=".repeat(70));
+    println!("Input files: {}", input.paths.len());
+    println!("Ranking:     {:?} (top {})", ranking, top_n);
+    println!("{}", "=".repeat(70));
+
+    let table = load_name_state_table(input)?;
+    let row_totals = table.row_totals();
+    std::fs::create_dir_all(output_dir)?;
+
+    let mut combined: Option<DataFrame> = None;
+    for (c, state) in table.cols.iter().enumerate() {
+        let mut scores = score_state(&table, c, &row_totals, prior_scale);
+        match ranking {
+            Ranking::LogOdds => scores.sort_by(|a, b| b.z_score.total_cmp(&a.z_score).then(a.name.cmp(b.name))),
+            Ranking::TfIdf => scores.sort_by(|a, b| b.tf_idf.total_cmp(&a.tf_idf).then(a.name.cmp(b.name))),
+        }
+        scores.truncate(top_n);
+
+        let mut ranked = DataFrame::new(vec![
+            Series::new("state", vec![state.as_str(); scores.len()]),
+            Series::new("rank", (1..=scores.len() as u32).collect::<Vec<_>>()),
+            Series::new("street_name", scores.iter().map(|s| s.name).collect::<Vec<_>>()),
+            Series::new("count", scores.iter().map(|s| s.count).collect::<Vec<_>>()),
+            Series::new("tf_idf", scores.iter().map(|s| s.tf_idf).collect::<Vec<_>>()),
+            Series::new("log_odds", scores.iter().map(|s| s.log_odds).collect::<Vec<_>>()),
+            Series::new("z_score", scores.iter().map(|s| s.z_score).collect::<Vec<_>>()),
+        ])?;
+
+        let top: Vec<&str> = scores.iter().take(5).map(|s| s.name).collect();
+        println!("  {:<20} {}", state, top.join(", "));
+
+        write_table(&mut ranked, &output_dir.join(format!("{}_distinctive_names.{}", state, format)))?;
+        combined = Some(match combined {
+            None => ranked,
+            Some(mut acc) => {
+                acc.vstack_mut(&ranked)?;
+                acc
+            }
+        });
+    }
+
+    if let Some(mut all) = combined {
+        write_table(&mut all, &output_dir.join(format!("distinctive_names.{}", format)))?;
+    }
+
+    println!("\nSaved to: {}", output_dir.display());
+    println!("Done!");
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use super::Input;
+use osm_processor_rust::stats::type_column;
+use osm_processor_rust::write_table;
+
+/// Diversity of one group's street-name distribution
+pub(super) struct Diversity {
+    pub streets: u64,
+    pub distinct_names: u64,
+    /// Shannon entropy in bits
+    pub entropy: f64,
+    /// Entropy divided by its maximum (log2 of the distinct-name count)
+    pub evenness: f64,
+    /// Gini-Simpson index: probability two random streets have different names
+    pub simpson: f64,
+}
+
+/// Diversity of a distribution given as per-name street counts; zero counts are ignored
+pub(super) fn diversity(name_counts: &[u64]) -> Diversity {
+    let streets: u64 = name_counts.iter().sum();
+    let distinct_names = name_counts.iter().filter(|&&count| count > 0).count() as u64;
+    let n = streets as f64;
+
+    let mut entropy = 0.0;
+    let mut same_name = 0.0;
+    for &count in name_counts.iter().filter(|&&count| count > 0) {
+        let p = count as f64 / n;
+        entropy -= p * p.log2();
+        same_name += p * p;
+    }
+
+    Diversity {
+        streets,
+        distinct_names,
+        entropy,
+        evenness: if distinct_names > 1 { entropy / (distinct_names as f64).log2() } else { 0.0 },
+        simpson: 1.0 - same_name,
+    }
+}
+
+/// `analyze diversity <streets.parquet...> [--by-type] [--stratify-by COL] [-o out.(csv|parquet)]`
+pub fn run_diversity(input: &Input, by_type: bool, output_path: Option<&Path>) -> Result<()> {
+    let df = input.load()?;
+    let names = df.column("street_name")?.str()?;
+    let states = df.column("state")?.str()?;
+
+    let type_values = match (by_type, type_column(&df)) {
+        (false, _) => None,
+        (true, Some(column)) => Some(df.column(&column)?.cast(&DataType::String)?),
+        (true, None) => anyhow::bail!("analyze diversity: --by-type needs a *_type column"),
+    };
+    let types: Vec<Option<&str>> = match &type_values {
+        Some(values) => values.str()?.into_iter().collect(),
+        None => vec![None; df.height()],
+    };
+
+    // (state, type) -> name -> count; type is "" when not stratifying
+    let mut groups: BTreeMap<(&str, &str), HashMap<&str, u64>> = BTreeMap::new();
+    for ((name, state), street_type) in names.into_iter().zip(states).zip(&types) {
+        let key = (state.unwrap_or(""), if by_type { street_type.unwrap_or("") } else { "" });
+        *groups.entry(key).or_default().entry(name.unwrap_or("")).or_default() += 1;
+    }
+
+    let rows: Vec<((&str, &str), Diversity)> = groups
+        .iter()
+        .map(|(&key, counts)| (key, diversity(&counts.values().copied().collect::<Vec<_>>())))
+        .collect();
+
+    let mut columns = vec![Series::new("state", rows.iter().map(|r| r.0 .0).collect::<Vec<_>>())];
+    if by_type {
+        columns.push(Series::new("street_type", rows.iter().map(|r| r.0 .1).collect::<Vec<_>>()));
+    }
+    columns.extend([
+        Series::new("streets", rows.iter().map(|r| r.1.streets).collect::<Vec<_>>()),
+        Series::new("distinct_names", rows.iter().map(|r| r.1.distinct_names).collect::<Vec<_>>()),
+        Series::new(
+            "unique_name_ratio",
+            rows.iter().map(|r| r.1.distinct_names as f64 / r.1.streets as f64).collect::<Vec<_>>(),
+        ),
+        Series::new("shannon_entropy_bits", rows.iter().map(|r| r.1.entropy).collect::<Vec<_>>()),
+        Series::new("evenness", rows.iter().map(|r| r.1.evenness).collect::<Vec<_>>()),
+        Series::new("simpson_index", rows.iter().map(|r| r.1.simpson).collect::<Vec<_>>()),
+    ]);
+    let mut out = DataFrame::new(columns)?;
+
+    println!("{}", out);
+
+    if let Some(output_path) = output_path {
+        println!("Saving to: {}", output_path.display());
+        write_table(&mut out, output_path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::path::Path;
+
+use super::{load_name_state_table, CrossTab, Input};
+use osm_processor_rust::write_table;
+
+/// Sparse `(street_name, state, count)` rows for the non-zero cells
+fn to_triplets(table: &CrossTab) -> Result<DataFrame> {
+    let mut names = Vec::new();
+    let mut states = Vec::new();
+    let mut counts = Vec::new();
+    for (name, row) in table.rows.iter().zip(&table.counts) {
+        for (state, &n) in table.cols.iter().zip(row) {
+            if n > 0 {
+                names.push(name.as_str());
+                states.push(state.as_str());
+                counts.push(n);
+            }
+        }
+    }
+
+    let df = DataFrame::new(vec![
+        Series::new("street_name", names),
+        Series::new("state", states),
+        Series::new("count", counts),
+    ])?;
+    Ok(df)
+}
+
+/// One row per name, one count column per state
+fn to_wide(table: &CrossTab) -> Result<DataFrame> {
+    let mut columns = vec![Series::new("street_name", &table.rows)];
+    for (c, state) in table.cols.iter().enumerate() {
+        columns.push(Series::new(state, table.counts.iter().map(|row| row[c]).collect::<Vec<_>>()));
+    }
+    Ok(DataFrame::new(columns)?)
+}
+
+/// `analyze matrix <streets.parquet...> [--format triplet|wide] -o out.(csv|parquet)`
+pub fn run_matrix(input: &Input, wide: bool, output_path: &Path) -> Result<()> {
+    let table = load_name_state_table(input)?;
+    let mut out = if wide { to_wide(&table)? } else { to_triplets(&table)? };
+
+    println!("{} names × {} states ({} streets)", table.rows.len(), table.cols.len(), table.total());
+    println!("Saving to: {}", output_path.display());
+    write_table(&mut out, output_path)?;
+    Ok(())
+}
@@ -0,0 +1,350 @@
+//! `analyze <method> ...`: statistical analyses over (merged) street outputs
+
+mod bootstrap;
+mod chisq;
+mod contingency;
+mod correction;
+mod dist;
+mod distinctive;
+mod diversity;
+mod matrix;
+mod permtest;
+mod rates;
+mod similarity;
+
+use anyhow::Result;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli::CliArgs;
+use correction::Correction;
+use osm_processor_rust::stats::{self, load_streets};
+
+/// Counts of streets by two categorical columns (e.g. street_name × state)
+pub struct CrossTab {
+    pub rows: Vec<String>,
+    pub cols: Vec<String>,
+    /// `counts[row][col]`
+    pub counts: Vec<Vec<u64>>,
+}
+
+impl CrossTab {
+    /// Tabulate `row_column` × `col_column`, with rows and columns in sorted order
+    pub fn from_columns(df: &DataFrame, row_column: &str, col_column: &str) -> Result<Self> {
+        let row_values = df.column(row_column)?.cast(&DataType::String)?;
+        let col_values = df.column(col_column)?.cast(&DataType::String)?;
+
+        let mut cells: BTreeMap<&str, BTreeMap<&str, u64>> = BTreeMap::new();
+        for (r, c) in row_values.str()?.into_iter().zip(col_values.str()?) {
+            *cells.entry(r.unwrap_or("")).or_default().entry(c.unwrap_or("")).or_default() += 1;
+        }
+        Ok(Self::from_cells(&cells))
+    }
+
+    /// Dense table from nested row -> col -> count maps
+    fn from_cells(cells: &BTreeMap<&str, BTreeMap<&str, u64>>) -> Self {
+        let mut col_index: BTreeMap<&str, usize> = cells.values().flat_map(|by_col| by_col.keys().map(|&c| (c, 0))).collect();
+        for (i, index) in col_index.values_mut().enumerate() {
+            *index = i;
+        }
+
+        CrossTab {
+            rows: cells.keys().map(|r| r.to_string()).collect(),
+            cols: col_index.keys().map(|c| c.to_string()).collect(),
+            counts: cells
+                .values()
+                .map(|by_col| {
+                    let mut row = vec![0; col_index.len()];
+                    for (c, &n) in by_col {
+                        row[col_index[c]] = n;
+                    }
+                    row
+                })
+                .collect(),
+        }
+    }
+
+    pub fn row_totals(&self) -> Vec<u64> {
+        self.counts.iter().map(|row| row.iter().sum()).collect()
+    }
+
+    pub fn col_totals(&self) -> Vec<u64> {
+        let mut totals = vec![0; self.cols.len()];
+        for row in &self.counts {
+            for (total, &n) in totals.iter_mut().zip(row) {
+                *total += n;
+            }
+        }
+        totals
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().flatten().sum()
+    }
+
+    /// Rebuild a table from sparse `(row, col, count)` triplets
+    pub fn from_triplets(df: &DataFrame, row_column: &str, col_column: &str) -> Result<Self> {
+        let row_values = df.column(row_column)?.str()?;
+        let col_values = df.column(col_column)?.str()?;
+        let counts = df.column("count")?.cast(&DataType::UInt64)?;
+
+        let mut cells: BTreeMap<&str, BTreeMap<&str, u64>> = BTreeMap::new();
+        for ((r, c), n) in row_values.into_iter().zip(col_values).zip(counts.u64()?) {
+            *cells.entry(r.unwrap_or("")).or_default().entry(c.unwrap_or("")).or_default() += n.unwrap_or(0);
+        }
+        Ok(Self::from_cells(&cells))
+    }
+
+    /// Rebuild a table from a wide matrix: `row_column` plus one count column per col value
+    pub fn from_wide(df: &DataFrame, row_column: &str) -> Result<Self> {
+        let rows = df
+            .column(row_column)?
+            .str()?
+            .into_iter()
+            .map(|r| r.unwrap_or("").to_string())
+            .collect();
+        let cols: Vec<String> = df
+            .get_column_names()
+            .into_iter()
+            .filter(|&name| name != row_column)
+            .map(str::to_string)
+            .collect();
+
+        let mut counts = vec![vec![0; cols.len()]; df.height()];
+        for (c, name) in cols.iter().enumerate() {
+            let column = df.column(name)?.cast(&DataType::UInt64)?;
+            for (r, n) in column.u64()?.into_iter().enumerate() {
+                counts[r][c] = n.unwrap_or(0);
+            }
+        }
+
+        Ok(CrossTab { rows, cols, counts })
+    }
+}
+
+/// Street files for one analysis run: all streets, or one stratum of them with `--stratify-by`
+pub struct Input {
+    pub paths: Vec<PathBuf>,
+    /// `(column, value)` the streets are restricted to
+    pub stratum: Option<(String, String)>,
+}
+
+impl Input {
+    pub fn load(&self) -> Result<DataFrame> {
+        match &self.stratum {
+            Some((column, value)) => Ok(stats::load_stratum(&self.paths, column, value)?),
+            None => Ok(load_streets(&self.paths)?),
+        }
+    }
+
+    /// `dir`, or its `<column>=<value>` subdirectory for a stratum
+    pub fn output_dir(&self, dir: &Path) -> PathBuf {
+        match &self.stratum {
+            Some((column, value)) => stats::stratum_dir(dir, column, value),
+            None => dir.to_path_buf(),
+        }
+    }
+
+    /// `path`, or the same file name under a `<column>=<value>` directory next to it for a stratum
+    pub fn output_file(&self, path: &Path) -> PathBuf {
+        match (&self.stratum, path.file_name()) {
+            (Some(_), Some(file_name)) => {
+                self.output_dir(path.parent().unwrap_or_else(|| Path::new(""))).join(file_name)
+            }
+            _ => path.to_path_buf(),
+        }
+    }
+}
+
+/// street_name × state table from street outputs, or from a matrix written by `analyze matrix`
+/// (triplet or wide), so large runs only need to be aggregated once
+pub fn load_name_state_table(input: &Input) -> Result<CrossTab> {
+    let df = input.load()?;
+    let has = |name: &str| df.get_column_names().contains(&name);
+
+    if has("lat") {
+        CrossTab::from_columns(&df, "street_name", "state")
+    } else if has("state") && has("count") {
+        CrossTab::from_triplets(&df, "street_name", "state")
+    } else {
+        CrossTab::from_wide(&df, "street_name")
+    }
+}
+
+/// Positional street files for an analysis, erroring if none were given
+fn input_paths(method: &str, args: &CliArgs) -> Result<Vec<PathBuf>> {
+    let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+    if paths.is_empty() {
+        anyhow::bail!("analyze {}: expected at least one streets parquet file", method);
+    }
+    Ok(paths)
+}
+
+/// One [`Input`] per value of the `--stratify-by` column, or a single unstratified one
+fn inputs(method: &str, args: &CliArgs) -> Result<Vec<Input>> {
+    let paths = input_paths(method, args)?;
+    let Some(column) = args.value("stratify-by") else {
+        return Ok(vec![Input { paths, stratum: None }]);
+    };
+
+    let values = stats::stratum_values(&paths, column)?;
+    if values.is_empty() {
+        anyhow::bail!("analyze {}: no {} values to stratify by", method, column);
+    }
+    println!("Stratifying by {}: {} values", column, values.len());
+    Ok(values
+        .into_iter()
+        .map(|value| Input { paths: paths.clone(), stratum: Some((column.to_string(), value)) })
+        .collect())
+}
+
+/// Run `analysis` once per input, with a header naming the stratum when stratified
+fn for_each_input(inputs: Vec<Input>, mut analysis: impl FnMut(&Input) -> Result<()>) -> Result<()> {
+    for input in &inputs {
+        if let Some((column, value)) = &input.stratum {
+            println!("\n{} {}={} {}", "#".repeat(20), column, value, "#".repeat(20));
+        }
+        analysis(input)?;
+    }
+    Ok(())
+}
+
+/// `--correction` for per-name tests (default: Benjamini-Hochberg)
+fn correction(args: &CliArgs) -> Result<Correction> {
+    match args.value("correction") {
+        Some(value) => Correction::parse(value),
+        None => Ok(Correction::BenjaminiHochberg),
+    }
+}
+
+/// `analyze <method> <streets.parquet...> [options]`
+pub fn run_analyze(args: &[String]) -> Result<()> {
+    let Some((method, rest)) = args.split_first() else {
+        anyhow::bail!("analyze: expected a method (chisq, tfidf, diversity, matrix, contingency, bootstrap, rates, similarity, permtest)");
+    };
+
+    match method.as_str() {
+        "chisq" => {
+            let args = CliArgs::parse(rest, &["min-count", "correction", "alpha", "stratify-by", "output-dir"], &[])?;
+            let inputs = inputs(method, &args)?;
+            let Some(output_dir) = args.value("output-dir").map(PathBuf::from) else {
+                anyhow::bail!("analyze chisq: missing --output-dir");
+            };
+            let (min_count, correction, alpha) =
+                (args.parsed("min-count")?.unwrap_or(5), correction(&args)?, args.parsed("alpha")?.unwrap_or(0.05));
+            for_each_input(inputs, |input| {
+                chisq::run_chisq(input, min_count, correction, alpha, &input.output_dir(&output_dir))
+            })
+        }
+        "tfidf" => {
+            let args = CliArgs::parse(rest, &["rank", "top", "prior-scale", "format", "stratify-by", "output-dir"], &[])?;
+            let inputs = inputs(method, &args)?;
+            let Some(output_dir) = args.value("output-dir").map(PathBuf::from) else {
+                anyhow::bail!("analyze tfidf: missing --output-dir");
+            };
+            let ranking = match args.value("rank") {
+                Some(value) => distinctive::Ranking::parse(value)?,
+                None => distinctive::Ranking::LogOdds,
+            };
+            let format = match args.value("format") {
+                Some(format @ ("parquet" | "csv")) => format,
+                Some(other) => anyhow::bail!("analyze tfidf: unknown format {}", other),
+                None => "parquet",
+            };
+            let (top, prior_scale) = (args.parsed("top")?.unwrap_or(50), args.parsed("prior-scale")?.unwrap_or(0.01));
+            for_each_input(inputs, |input| {
+                distinctive::run_distinctive(input, ranking, top, prior_scale, format, &input.output_dir(&output_dir))
+            })
+        }
+        "diversity" => {
+            let args = CliArgs::parse(rest, &["stratify-by", "output"], &["by-type"])?;
+            let inputs = inputs(method, &args)?;
+            let output_path = args.value("output").map(PathBuf::from);
+            for_each_input(inputs, |input| {
+                let output_path = output_path.as_deref().map(|path| input.output_file(path));
+                diversity::run_diversity(input, args.flag("by-type"), output_path.as_deref())
+            })
+        }
+        "matrix" => {
+            let args = CliArgs::parse(rest, &["format", "stratify-by", "output"], &[])?;
+            let inputs = inputs(method, &args)?;
+            let Some(output_path) = args.value("output").map(PathBuf::from) else {
+                anyhow::bail!("analyze matrix: missing -o/--output");
+            };
+            let wide = match args.value("format") {
+                Some("wide") => true,
+                Some("triplet") | None => false,
+                Some(other) => anyhow::bail!("analyze matrix: unknown format {} (expected triplet or wide)", other),
+            };
+            for_each_input(inputs, |input| matrix::run_matrix(input, wide, &input.output_file(&output_path)))
+        }
+        "contingency" => {
+            let args = CliArgs::parse(rest, &["by", "format", "stratify-by", "output-dir"], &[])?;
+            let inputs = inputs(method, &args)?;
+            let Some(output_dir) = args.value("output-dir").map(PathBuf::from) else {
+                anyhow::bail!("analyze contingency: missing --output-dir");
+            };
+            let format = match args.value("format") {
+                Some(format @ ("parquet" | "csv")) => format,
+                Some(other) => anyhow::bail!("analyze contingency: unknown format {}", other),
+                None => "parquet",
+            };
+            let by = args.value("by").unwrap_or("state");
+            for_each_input(inputs, |input| contingency::run_contingency(input, by, format, &input.output_dir(&output_dir)))
+        }
+        "bootstrap" => {
+            let args = CliArgs::parse(rest, &["replicates", "seed", "confidence", "top", "stratify-by", "output-dir"], &[])?;
+            let inputs = inputs(method, &args)?;
+            let Some(output_dir) = args.value("output-dir").map(PathBuf::from) else {
+                anyhow::bail!("analyze bootstrap: missing --output-dir");
+            };
+            let (replicates, seed) = (args.parsed("replicates")?.unwrap_or(1000), args.parsed("seed")?.unwrap_or(42));
+            let (confidence, top) = (args.parsed("confidence")?.unwrap_or(0.95), args.parsed("top")?.unwrap_or(100));
+            for_each_input(inputs, |input| {
+                bootstrap::run_bootstrap(input, replicates, seed, confidence, top, &input.output_dir(&output_dir))
+            })
+        }
+        "rates" => {
+            let args = CliArgs::parse(rest, &["population", "min-count", "stratify-by", "output"], &[])?;
+            let inputs = inputs(method, &args)?;
+            let Some(output_path) = args.value("output").map(PathBuf::from) else {
+                anyhow::bail!("analyze rates: missing -o/--output");
+            };
+            let population_path = args.value("population").map(PathBuf::from);
+            let min_count = args.parsed("min-count")?.unwrap_or(1);
+            for_each_input(inputs, |input| {
+                rates::run_rates(input, population_path.as_deref(), min_count, &input.output_file(&output_path))
+            })
+        }
+        "similarity" => {
+            let args = CliArgs::parse(rest, &["metric", "stratify-by", "output-dir"], &[])?;
+            let inputs = inputs(method, &args)?;
+            let Some(output_dir) = args.value("output-dir").map(PathBuf::from) else {
+                anyhow::bail!("analyze similarity: missing --output-dir");
+            };
+            let metric = match args.value("metric") {
+                Some(value) => similarity::Metric::parse(value)?,
+                None => similarity::Metric::JensenShannon,
+            };
+            for_each_input(inputs, |input| similarity::run_similarity(input, metric, &input.output_dir(&output_dir)))
+        }
+        "permtest" => {
+            let args = CliArgs::parse(rest, &["name", "states", "permutations", "seed", "correction", "stratify-by", "output"], &[])?;
+            let inputs = inputs(method, &args)?;
+            let Some(name) = args.value("name") else {
+                anyhow::bail!("analyze permtest: missing --name");
+            };
+            let states: Option<Vec<String>> =
+                args.value("states").map(|spec| spec.split(',').map(|s| s.trim().to_string()).collect());
+            let output_path = args.value("output").map(PathBuf::from);
+            let (permutations, seed) = (args.parsed("permutations")?.unwrap_or(10_000), args.parsed("seed")?.unwrap_or(42));
+            let correction = correction(&args)?;
+            for_each_input(inputs, |input| {
+                let output_path = output_path.as_deref().map(|path| input.output_file(path));
+                permtest::run_permtest(input, name, states.as_deref(), permutations, seed, correction, output_path.as_deref())
+            })
+        }
+        other => anyhow::bail!("analyze: unknown method {}", other),
+    }
+}
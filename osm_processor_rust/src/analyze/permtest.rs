@@ -0,0 +1,157 @@
+use anyhow::Result;
+use polars::prelude::*;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::correction::Correction;
+use super::Input;
+use crate::rng::SplitMix64;
+use osm_processor_rust::write_table;
+
+/// Permutations drawn from one seed; chunks run in parallel, so the chunking (not the thread
+/// count) decides which stream each permutation comes from
+const CHUNK: usize = 1000;
+
+/// `analyze permtest <streets.parquet...> --name NAME [--states A,B] [--permutations N] [--seed S] [--correction C] [-o out]`
+///
+/// Tests whether `name` is over- or under-represented in each state by shuffling state labels
+/// across all streets. Only the name's streets matter under a shuffle, so each permutation
+/// draws that many labels without replacement. P-values are empirical with the +1 correction,
+/// `(1 + #{permuted >= observed}) / (1 + N)` and the same for <=; `p_value` is two-sided.
+/// With `--states`, only those states are reported, plus their combined count. Two-sided
+/// p-values are adjusted for the number of rows reported.
+pub fn run_permtest(
+    input: &Input,
+    name: &str,
+    states_filter: Option<&[String]>,
+    permutations: usize,
+    seed: u64,
+    correction: Correction,
+    output_path: Option<&Path>,
+) -> Result<()> {
+    if permutations == 0 {
+        anyhow::bail!("analyze permtest: need at least one permutation");
+    }
+
+    let df = input.load()?;
+    let names = df.column("street_name")?.str()?;
+    let states = df.column("state")?.str()?;
+
+    // Every street's state index, and which streets carry the name
+    let mut state_index: BTreeMap<&str, usize> = states.into_iter().map(|s| (s.unwrap_or(""), 0)).collect();
+    for (i, index) in state_index.values_mut().enumerate() {
+        *index = i;
+    }
+    let state_names: Vec<&str> = state_index.keys().copied().collect();
+    let labels: Vec<usize> = states.into_iter().map(|s| state_index[s.unwrap_or("")]).collect();
+    let streets = labels.len();
+
+    let mut observed = vec![0u64; state_names.len()];
+    let mut with_name = 0;
+    for (n, &label) in names.into_iter().zip(&labels) {
+        if n == Some(name) {
+            observed[label] += 1;
+            with_name += 1;
+        }
+    }
+    if with_name == 0 {
+        anyhow::bail!("analyze permtest: no streets named {:?}", name);
+    }
+
+    // Reported rows: every state, or the requested ones plus their union
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    match states_filter {
+        None => groups.extend(state_names.iter().enumerate().map(|(i, s)| (s.to_string(), vec![i]))),
+        Some(wanted) => {
+            let mut members = Vec::new();
+            for state in wanted {
+                let Some(&i) = state_index.get(state.as_str()) else {
+                    anyhow::bail!("analyze permtest: no streets in state {}", state);
+                };
+                groups.push((state.clone(), vec![i]));
+                members.push(i);
+            }
+            if members.len() > 1 {
+                groups.push((wanted.join("+"), members));
+            }
+        }
+    }
+    let group_count = |counts: &[u64], members: &[usize]| members.iter().map(|&i| counts[i]).sum::<u64>();
+    let group_observed: Vec<u64> = groups.iter().map(|(_, members)| group_count(&observed, members)).collect();
+
+    println!("Permuting {} state labels over {} streets named {:?} ({} permutations)", streets, with_name, name, permutations);
+
+    // Per group: (#permuted >= observed, #permuted <= observed, sum of permuted counts)
+    let chunks: Vec<usize> = (0..permutations).step_by(CHUNK).collect();
+    let tallies: Vec<Vec<(u64, u64, u64)>> = chunks
+        .par_iter()
+        .map(|&start| {
+            let mut rng = SplitMix64::new(seed.wrapping_add((start / CHUNK) as u64));
+            let mut tally = vec![(0, 0, 0); groups.len()];
+            let mut counts = vec![0u64; state_names.len()];
+            for _ in start..(start + CHUNK).min(permutations) {
+                counts.iter_mut().for_each(|n| *n = 0);
+                for i in rng.sample_indices_sparse(streets, with_name) {
+                    counts[labels[i]] += 1;
+                }
+                for ((_, members), (t, &obs)) in groups.iter().zip(tally.iter_mut().zip(&group_observed)) {
+                    let permuted = group_count(&counts, members);
+                    t.0 += u64::from(permuted >= obs);
+                    t.1 += u64::from(permuted <= obs);
+                    t.2 += permuted;
+                }
+            }
+            tally
+        })
+        .collect();
+
+    let mut totals = vec![(0u64, 0u64, 0u64); groups.len()];
+    for tally in tallies {
+        for (total, t) in totals.iter_mut().zip(tally) {
+            total.0 += t.0;
+            total.1 += t.1;
+            total.2 += t.2;
+        }
+    }
+
+    let n = permutations as f64;
+    let state_streets: Vec<u64> = {
+        let mut sizes = vec![0u64; state_names.len()];
+        for &label in &labels {
+            sizes[label] += 1;
+        }
+        sizes
+    };
+    let expected: Vec<f64> = groups
+        .iter()
+        .map(|(_, members)| with_name as f64 * group_count(&state_streets, members) as f64 / streets as f64)
+        .collect();
+    let p_over: Vec<f64> = totals.iter().map(|t| (1.0 + t.0 as f64) / (1.0 + n)).collect();
+    let p_under: Vec<f64> = totals.iter().map(|t| (1.0 + t.1 as f64) / (1.0 + n)).collect();
+    let p_values: Vec<f64> = p_over.iter().zip(&p_under).map(|(o, u)| (2.0 * o.min(*u)).min(1.0)).collect();
+    let p_adjusted = correction.adjust(&p_values);
+
+    let mut out = DataFrame::new(vec![
+        Series::new("street_name", vec![name; groups.len()]),
+        Series::new("state", groups.iter().map(|(state, _)| state.as_str()).collect::<Vec<_>>()),
+        Series::new("observed", group_observed),
+        Series::new("expected", expected),
+        Series::new("permuted_mean", totals.iter().map(|t| t.2 as f64 / n).collect::<Vec<_>>()),
+        Series::new("p_over", p_over),
+        Series::new("p_under", p_under),
+        Series::new("p_value", p_values),
+        Series::new("p_adjusted", p_adjusted),
+        Series::new("permutations", vec![permutations as u64; groups.len()]),
+        Series::new("seed", vec![seed; groups.len()]),
+    ])?;
+
+    println!("{}", out);
+
+    if let Some(output_path) = output_path {
+        println!("Saving to: {}", output_path.display());
+        write_table(&mut out, output_path)?;
+    }
+
+    Ok(())
+}
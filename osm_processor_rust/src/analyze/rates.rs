@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use polars::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use super::Input;
+use osm_processor_rust::write_table;
+
+/// State key as written by streets runs: lowercase, spaces as hyphens (`district-of-columbia`)
+fn state_key(name: &str) -> String {
+    name.trim().to_lowercase().replace(' ', "-")
+}
+
+/// `state` -> `population` from a CSV or parquet table with those two columns
+fn read_population(path: &Path) -> Result<HashMap<String, f64>> {
+    let frame = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("parquet") => LazyFrame::scan_parquet(path, ScanArgsParquet::default())?,
+        _ => LazyCsvReader::new(path).with_has_header(true).finish()?,
+    };
+    let df = frame
+        .select([col("state").cast(DataType::String), col("population").cast(DataType::Float64)])
+        .collect()
+        .with_context(|| format!("Population table {} needs state and population columns", path.display()))?;
+
+    let states = df.column("state")?.str()?;
+    let populations = df.column("population")?.f64()?;
+    Ok(states
+        .into_iter()
+        .zip(populations)
+        .filter_map(|(state, population)| Some((state_key(state?), population?)))
+        .filter(|(_, population)| *population > 0.0)
+        .collect())
+}
+
+/// One name's streets and road length within a state
+#[derive(Default)]
+struct NameTotals {
+    count: u64,
+    length_km: f64,
+}
+
+/// `analyze rates <streets.parquet...> [--population pop.csv] [--min-count N] -o out.(csv|parquet)`
+///
+/// Name counts per state with rates that take the state's size out: per 1,000 streets, per
+/// 1,000 km of named road (summed from `length_km`) and, given a population table, per 100,000
+/// people. States missing from the population table get null population rates.
+pub fn run_rates(input: &Input, population_path: Option<&Path>, min_count: u64, output_path: &Path) -> Result<()> {
+    let population = population_path.map(read_population).transpose()?;
+
+    let df = input.load()?;
+    let names = df.column("street_name")?.str()?;
+    let states = df.column("state")?.str()?;
+    let lengths = df.column("length_km")?.cast(&DataType::Float64)?;
+
+    let mut cells: BTreeMap<&str, HashMap<&str, NameTotals>> = BTreeMap::new();
+    for ((name, state), length_km) in names.into_iter().zip(states).zip(lengths.f64()?) {
+        let totals = cells.entry(state.unwrap_or("")).or_default().entry(name.unwrap_or("")).or_default();
+        totals.count += 1;
+        totals.length_km += length_km.unwrap_or(0.0);
+    }
+
+    if let Some(population) = &population {
+        let missing: Vec<&str> = cells.keys().copied().filter(|state| !population.contains_key(&state_key(state))).collect();
+        if !missing.is_empty() {
+            println!("No population for {} state(s): {}", missing.len(), missing.join(", "));
+        }
+    }
+
+    let mut rows = (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    let mut rates = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for (state, by_name) in &cells {
+        let state_streets: u64 = by_name.values().map(|t| t.count).sum();
+        let state_length_km: f64 = by_name.values().map(|t| t.length_km).sum();
+        let state_population = population.as_ref().and_then(|p| p.get(&state_key(state)).copied());
+
+        let mut named: Vec<(&&str, &NameTotals)> = by_name.iter().filter(|(_, t)| t.count >= min_count).collect();
+        named.sort_unstable_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0)));
+        for (name, totals) in named {
+            rows.0.push(*state);
+            rows.1.push(*name);
+            rows.2.push(totals.count);
+            rows.3.push(totals.length_km);
+            rows.4.push(state_streets);
+            rows.5.push(state_length_km);
+            rates.0.push(totals.count as f64 / state_streets as f64 * 1_000.0);
+            rates.1.push(if state_length_km > 0.0 { Some(totals.count as f64 / state_length_km * 1_000.0) } else { None });
+            rates.2.push(if state_length_km > 0.0 { Some(totals.length_km / state_length_km) } else { None });
+            rates.3.push(state_population.map(|people| totals.count as f64 / people * 100_000.0));
+        }
+    }
+
+    let mut columns = vec![
+        Series::new("state", rows.0),
+        Series::new("street_name", rows.1),
+        Series::new("count", rows.2),
+        Series::new("length_km", rows.3),
+        Series::new("state_streets", rows.4),
+        Series::new("state_length_km", rows.5),
+        Series::new("per_1k_streets", rates.0),
+        Series::new("per_1k_km", rates.1),
+        Series::new("length_share", rates.2),
+    ];
+    if population.is_some() {
+        columns.push(Series::new("per_100k_people", rates.3));
+    }
+    let mut out = DataFrame::new(columns)?;
+
+    println!("{}", out.head(Some(10)));
+    println!("Saving to: {}", output_path.display());
+    write_table(&mut out, output_path)?;
+    Ok(())
+}
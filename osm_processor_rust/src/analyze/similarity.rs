@@ -0,0 +1,101 @@
+use anyhow::Result;
+use polars::prelude::*;
+use rayon::prelude::*;
+use std::path::Path;
+
+use super::{load_name_state_table, Input};
+use osm_processor_rust::write_parquet;
+
+/// Distance between two states' street-name distributions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    /// Square root of the Jensen-Shannon divergence (base 2), a true metric in [0, 1]
+    JensenShannon,
+    /// 1 - cosine similarity of the count vectors
+    Cosine,
+}
+
+impl Metric {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "jsd" => Ok(Metric::JensenShannon),
+            "cosine" => Ok(Metric::Cosine),
+            other => anyhow::bail!("Unknown metric {} (expected jsd or cosine)", other),
+        }
+    }
+
+    fn distance(self, a: &[f64], b: &[f64]) -> f64 {
+        match self {
+            Metric::JensenShannon => {
+                let mut divergence = 0.0;
+                for (&p, &q) in a.iter().zip(b) {
+                    let m = (p + q) / 2.0;
+                    if p > 0.0 {
+                        divergence += p * (p / m).log2() / 2.0;
+                    }
+                    if q > 0.0 {
+                        divergence += q * (q / m).log2() / 2.0;
+                    }
+                }
+                divergence.max(0.0).sqrt()
+            }
+            Metric::Cosine => {
+                let dot: f64 = a.iter().zip(b).map(|(p, q)| p * q).sum();
+                let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+                (1.0 - dot / (norm(a) * norm(b))).max(0.0)
+            }
+        }
+    }
+}
+
+/// `analyze similarity <streets.parquet...> [--metric jsd|cosine] --output-dir DIR`
+///
+/// Distance between every pair of states' name distributions. Writes the full symmetric matrix
+/// (`similarity_matrix.parquet`: `state` plus one column per state) and the upper triangle as
+/// `(state_a, state_b, distance)` rows in matrix order (`similarity_pairs.parquet`), which is
+/// the condensed form hierarchical clustering routines take.
+pub fn run_similarity(input: &Input, metric: Metric, output_dir: &Path) -> Result<()> {
+    let table = load_name_state_table(input)?;
+    if table.cols.len() < 2 {
+        anyhow::bail!("analyze similarity: need at least two states with streets");
+    }
+
+    // Each state's name distribution, as shares of its streets
+    let col_totals = table.col_totals();
+    let shares: Vec<Vec<f64>> = (0..table.cols.len())
+        .map(|c| table.counts.iter().map(|row| row[c] as f64 / col_totals[c].max(1) as f64).collect())
+        .collect();
+
+    let n = table.cols.len();
+    let pairs: Vec<(usize, usize)> = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j))).collect();
+    let distances: Vec<f64> = pairs.par_iter().map(|&(i, j)| metric.distance(&shares[i], &shares[j])).collect();
+
+    let mut matrix = vec![vec![0.0; n]; n];
+    for (&(i, j), &d) in pairs.iter().zip(&distances) {
+        matrix[i][j] = d;
+        matrix[j][i] = d;
+    }
+
+    let mut columns = vec![Series::new("state", &table.cols)];
+    for (c, state) in table.cols.iter().enumerate() {
+        columns.push(Series::new(state, matrix.iter().map(|row| row[c]).collect::<Vec<_>>()));
+    }
+    let mut matrix_df = DataFrame::new(columns)?;
+
+    let mut pairs_df = DataFrame::new(vec![
+        Series::new("state_a", pairs.iter().map(|&(i, _)| table.cols[i].as_str()).collect::<Vec<_>>()),
+        Series::new("state_b", pairs.iter().map(|&(_, j)| table.cols[j].as_str()).collect::<Vec<_>>()),
+        Series::new("distance", distances),
+    ])?;
+
+    println!("Most similar state pairs:");
+    println!("{}", pairs_df.sort(["distance"], SortMultipleOptions::default())?.head(Some(10)));
+
+    std::fs::create_dir_all(output_dir)?;
+    println!("\nSaving to: {}", output_dir.display());
+    write_parquet(&mut matrix_df, &output_dir.join("similarity_matrix.parquet"))?;
+    write_parquet(&mut pairs_df, &output_dir.join("similarity_pairs.parquet"))?;
+
+    println!("Done!");
+    Ok(())
+}
@@ -0,0 +1,135 @@
+//! Async entry points for embedding in a tokio service (cargo feature `async`).
+//!
+//! Only the IO-bound stages are async: fetching a remote PBF and uploading the output to an
+//! object store. The PBF passes and grouping still run on rayon, moved off the runtime with
+//! `spawn_blocking` so they never stall its worker threads.
+
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, WriteMultipart};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use url::Url;
+
+use crate::error::{io_error, ProcessorError, Result};
+use crate::{write_parquet, Feature, OsmStreetProcessor, StreetsResult};
+
+/// Read size for streaming a local file into a multipart upload
+const UPLOAD_CHUNK: usize = 8 * 1024 * 1024;
+
+/// Multipart parts in flight at once; reading waits for one to finish beyond this, so memory
+/// stays near this many parts rather than the whole file
+const UPLOAD_CONCURRENCY: usize = 4;
+
+/// Distinguishes temporary files of concurrent calls within one process
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl OsmStreetProcessor {
+    /// [`run`](Self::run) on tokio's blocking pool
+    pub async fn run_async(&self) -> Result<StreetsResult> {
+        let processor = self.clone();
+        tokio::task::spawn_blocking(move || processor.run())
+            .await
+            .map_err(|_| ProcessorError::WorkerPanic)?
+    }
+}
+
+/// Make `input` available as a local file: `http(s)://` URLs are downloaded into `dir`,
+/// `file://` URLs and plain paths are returned as they are.
+pub async fn fetch_input(input: &str, dir: &Path) -> Result<PathBuf> {
+    let url = match Url::parse(input) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+        Ok(url) if url.scheme() == "file" => {
+            return url
+                .to_file_path()
+                .map_err(|_| ProcessorError::InvalidConfig(format!("Invalid file URL: {}", input)));
+        }
+        _ => return Ok(PathBuf::from(input)),
+    };
+
+    let file_name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("input.osm.pbf");
+    let local = dir.join(file_name);
+
+    let fetch_error = |source| ProcessorError::Fetch { url: input.to_string(), source };
+    let mut response = reqwest::get(url.clone())
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(fetch_error)?;
+
+    let mut file = tokio::fs::File::create(&local).await.map_err(io_error(&local))?;
+    while let Some(chunk) = response.chunk().await.map_err(fetch_error)? {
+        file.write_all(&chunk).await.map_err(io_error(&local))?;
+    }
+    file.flush().await.map_err(io_error(&local))?;
+    Ok(local)
+}
+
+/// Upload a local file to an object store URL (`s3://`, `gs://`, `az://`, `http(s)://`, `file://`).
+/// Credentials and region come from the usual environment variables (`AWS_ACCESS_KEY_ID`, ...).
+pub async fn upload_output(local: &Path, destination: &str) -> Result<()> {
+    let upload_error = |source| ProcessorError::Upload { url: destination.to_string(), source };
+    let url = Url::parse(destination)
+        .map_err(|e| ProcessorError::InvalidConfig(format!("Invalid output URL {}: {}", destination, e)))?;
+    let options = std::env::vars().map(|(key, value)| (key.to_ascii_lowercase(), value));
+    let (store, path): (Box<dyn ObjectStore>, ObjectPath) =
+        object_store::parse_url_opts(&url, options).map_err(upload_error)?;
+
+    let mut file = tokio::fs::File::open(local).await.map_err(io_error(local))?;
+    let mut upload = WriteMultipart::new(store.put_multipart(&path).await.map_err(upload_error)?);
+    let mut buf = vec![0u8; UPLOAD_CHUNK];
+    loop {
+        let n = file.read(&mut buf).await.map_err(io_error(local))?;
+        if n == 0 {
+            break;
+        }
+        upload.wait_for_capacity(UPLOAD_CONCURRENCY).await.map_err(upload_error)?;
+        upload.write(&buf[..n]);
+    }
+    upload.finish().await.map_err(upload_error)?;
+    Ok(())
+}
+
+/// Async counterpart of [`crate::process_osm_to_parquet`] for remote data: fetch `input`
+/// (URL or local path), extract streets, and upload the parquet to `output_url`.
+/// Temporary files go to the system temp directory and are removed afterwards.
+pub async fn process_remote(
+    input: &str,
+    state_name: &str,
+    output_url: &str,
+    distance_threshold_km: f64,
+    feature: Feature,
+) -> Result<()> {
+    let id = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let work_dir = std::env::temp_dir().join(format!("osm_processor_{}_{}", std::process::id(), id));
+    tokio::fs::create_dir_all(&work_dir).await.map_err(io_error(&work_dir))?;
+
+    let result = async {
+        let pbf_path = fetch_input(input, &work_dir).await?;
+        let processor = OsmStreetProcessor::builder()
+            .input(pbf_path)
+            .state(state_name)
+            .distance_threshold_km(distance_threshold_km)
+            .feature(feature)
+            .build()?;
+
+        let local_output = work_dir.join(format!("{}_{}.parquet", state_name, feature.output_kind()));
+        let write_path = local_output.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut df = processor.to_dataframe()?;
+            write_parquet(&mut df, &write_path)
+        })
+        .await
+        .map_err(|_| ProcessorError::WorkerPanic)??;
+
+        upload_output(&local_output, output_url).await
+    }
+    .await;
+
+    // Best effort: a leftover temp dir should not mask the processing result
+    let _ = tokio::fs::remove_dir_all(&work_dir).await;
+    result
+}
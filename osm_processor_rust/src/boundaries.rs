@@ -0,0 +1,114 @@
+use geo::MultiPolygon;
+use polars::prelude::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::multipolygon::{collect_admin_boundary_relations, relation_multipolygon};
+use crate::node_set::NodeIdSet;
+use crate::relations::collect_way_refs;
+use crate::wkb::multipolygon_to_wkb;
+use crate::{default_output_path, load_node_coords, write_parquet, ConsoleProgress};
+
+/// An administrative boundary assembled from its relation
+#[derive(Debug, Clone)]
+pub struct Boundary {
+    pub osm_id: i64,
+    pub name: String,
+    pub admin_level: Option<u8>,
+    pub iso3166_2: Option<String>,
+    pub geometry: MultiPolygon<f64>,
+}
+
+/// Extract `boundary=administrative` relations as polygons
+pub fn extract_boundaries(pbf_path: &Path) -> Result<Vec<Boundary>> {
+    println!("Pass 1: Collecting administrative boundary relations...");
+    let relations = collect_admin_boundary_relations(pbf_path)?;
+    println!("  Found {} boundary relations", relations.len());
+
+    println!("Pass 2: Collecting boundary member ways...");
+    let member_ways: HashSet<i64> = relations.iter().flat_map(|r| r.member_ways()).collect();
+    let way_refs = collect_way_refs(pbf_path, &member_ways)?;
+    println!("  Found {} of {} member ways", way_refs.len(), member_ways.len());
+
+    println!("Pass 3: Loading boundary node coordinates...");
+    let wanted_nodes: NodeIdSet = way_refs.values().flatten().copied().collect();
+    let node_coords = load_node_coords(pbf_path, &wanted_nodes, &ConsoleProgress::default())?;
+
+    let mut boundaries = Vec::new();
+    let mut incomplete = 0;
+
+    for relation in &relations {
+        match relation_multipolygon(relation, &way_refs, &node_coords) {
+            Some(geometry) => boundaries.push(Boundary {
+                osm_id: relation.id,
+                name: relation.tags.get("name").cloned().unwrap_or_default(),
+                admin_level: relation.tags.get("admin_level").and_then(|v| v.parse().ok()),
+                iso3166_2: relation.tags.get("ISO3166-2").cloned(),
+                geometry,
+            }),
+            // Boundaries cut by the extract edge cannot be closed
+            None => incomplete += 1,
+        }
+    }
+
+    println!("  Assembled {} boundaries ({} incomplete, skipped)", boundaries.len(), incomplete);
+    Ok(boundaries)
+}
+
+fn boundaries_to_dataframe(boundaries: &[Boundary], coord_precision: Option<u32>) -> Result<DataFrame> {
+    let geometries: Vec<Vec<u8>> = boundaries
+        .iter()
+        .map(|b| multipolygon_to_wkb(&b.geometry, coord_precision))
+        .collect();
+
+    let df = DataFrame::new(vec![
+        Series::new("osm_id", boundaries.iter().map(|b| b.osm_id).collect::<Vec<_>>()),
+        Series::new("name", boundaries.iter().map(|b| b.name.as_str()).collect::<Vec<_>>()),
+        Series::new("admin_level", boundaries.iter().map(|b| b.admin_level.map(u32::from)).collect::<Vec<_>>()),
+        Series::new("iso3166_2", boundaries.iter().map(|b| b.iso3166_2.as_deref()).collect::<Vec<_>>()),
+        Series::new("geometry", geometries),
+    ])?;
+
+    Ok(df)
+}
+
+/// Boundaries mode: extract administrative boundaries to parquet with WKB geometry
+pub fn process_boundaries_to_parquet(
+    pbf_path: &Path,
+    state_name: &str,
+    output_path: Option<PathBuf>,
+    coord_precision: Option<u32>,
+) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("OSM BOUNDARIES TO PARQUET (Rust)");
+    println!("{}", "=".repeat(70));
+    println!("Input file:  {}", pbf_path.display());
+    println!("State:       {}", state_name);
+    println!("{}", "=".repeat(70));
+
+    let output_path = output_path.unwrap_or_else(|| default_output_path(pbf_path, state_name, "boundaries"));
+
+    let boundaries = extract_boundaries(pbf_path)?;
+    let mut df = boundaries_to_dataframe(&boundaries, coord_precision)?;
+
+    println!("\nBoundaries by admin_level:");
+    let mut levels: Vec<Option<u8>> = boundaries.iter().map(|b| b.admin_level).collect();
+    levels.sort();
+    levels.dedup();
+    for level in levels {
+        let count = boundaries.iter().filter(|b| b.admin_level == level).count();
+        match level {
+            Some(level) => println!("  admin_level={:<3} {}", level, count),
+            None => println!("  admin_level=?   {}", count),
+        }
+    }
+
+    println!("\nSaving to: {}", output_path.display());
+    write_parquet(&mut df, &output_path)?;
+
+    println!("Done!");
+    println!("{}", "=".repeat(70));
+
+    Ok(())
+}
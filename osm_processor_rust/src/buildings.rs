@@ -0,0 +1,182 @@
+use geo::{Centroid, GeodesicArea};
+use osmpbf::{Element, ElementReader};
+use polars::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::error::{pbf_error, Result};
+use crate::multipolygon::{closed_way_polygon, collect_multipolygon_relations, relation_multipolygon};
+use crate::node_set::NodeIdSet;
+use crate::{default_output_path, load_node_coords, round_coord, write_parquet, ConsoleProgress};
+
+/// A building footprint (closed way or multipolygon relation)
+#[derive(Debug)]
+struct Building {
+    osm_type: &'static str,
+    osm_id: i64,
+    building_type: String,
+    lat: f64,
+    lon: f64,
+    area_m2: f64,
+    addr_tags: HashMap<String, String>,
+}
+
+/// A closed building way from the way pass
+struct BuildingWay {
+    id: i64,
+    refs: Vec<i64>,
+    tags: HashMap<String, String>,
+}
+
+fn is_building(tags: &HashMap<String, String>) -> bool {
+    tags.get("building").is_some_and(|v| v != "no")
+}
+
+fn addr_tags(tags: &HashMap<String, String>) -> HashMap<String, String> {
+    tags.iter()
+        .filter(|(k, _)| k.starts_with("addr:"))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+/// Extract building footprints from a PBF file
+fn extract_buildings(pbf_path: &Path) -> Result<Vec<Building>> {
+    println!("Pass 1: Collecting building multipolygon relations...");
+    let relations = collect_multipolygon_relations(pbf_path, is_building)?;
+    println!("  Found {} building relations", relations.len());
+
+    let member_ways: HashSet<i64> = relations.iter().flat_map(|r| r.member_ways()).collect();
+
+    println!("Pass 2: Collecting building ways...");
+    let reader = ElementReader::from_path(pbf_path)
+        .map_err(pbf_error(pbf_path))?;
+
+    let mut building_ways = Vec::new();
+    let mut member_refs: HashMap<i64, Vec<i64>> = HashMap::new();
+
+    reader.for_each(|element| {
+        if let Element::Way(way) = element {
+            let is_member = member_ways.contains(&way.id());
+            let tags: HashMap<String, String> = way
+                .tags()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            let building = is_building(&tags);
+
+            if !is_member && !building {
+                return;
+            }
+
+            let refs: Vec<i64> = way.refs().collect();
+            if is_member {
+                member_refs.insert(way.id(), refs.clone());
+            }
+            if building {
+                building_ways.push(BuildingWay { id: way.id(), refs, tags });
+            }
+        }
+    })
+    .map_err(pbf_error(pbf_path))?;
+    println!("  Found {} building ways, {} relation member ways", building_ways.len(), member_refs.len());
+
+    println!("Pass 3: Loading building node coordinates...");
+    let wanted_nodes: NodeIdSet = building_ways
+        .iter()
+        .flat_map(|w| w.refs.iter().copied())
+        .chain(member_refs.values().flatten().copied())
+        .collect();
+    let node_coords = load_node_coords(pbf_path, &wanted_nodes, &ConsoleProgress::default())?;
+
+    let mut buildings = Vec::new();
+
+    for way in &building_ways {
+        if let Some(polygon) = closed_way_polygon(&way.refs, &node_coords) {
+            if let Some(centroid) = polygon.centroid() {
+                buildings.push(Building {
+                    osm_type: "way",
+                    osm_id: way.id,
+                    building_type: way.tags["building"].clone(),
+                    lat: centroid.y(),
+                    lon: centroid.x(),
+                    area_m2: polygon.geodesic_area_unsigned(),
+                    addr_tags: addr_tags(&way.tags),
+                });
+            }
+        }
+    }
+
+    for relation in &relations {
+        if let Some(multipolygon) = relation_multipolygon(relation, &member_refs, &node_coords) {
+            if let Some(centroid) = multipolygon.centroid() {
+                buildings.push(Building {
+                    osm_type: "relation",
+                    osm_id: relation.id,
+                    building_type: relation.tags["building"].clone(),
+                    lat: centroid.y(),
+                    lon: centroid.x(),
+                    area_m2: multipolygon.geodesic_area_unsigned(),
+                    addr_tags: addr_tags(&relation.tags),
+                });
+            }
+        }
+    }
+
+    println!("  Built {} building footprints", buildings.len());
+    Ok(buildings)
+}
+
+/// Convert buildings to a DataFrame, with one column per `addr:*` key seen
+fn buildings_to_dataframe(buildings: &[Building], state_name: &str, coord_precision: Option<u32>) -> Result<DataFrame> {
+    let addr_keys: BTreeSet<&str> = buildings
+        .iter()
+        .flat_map(|b| b.addr_tags.keys().map(String::as_str))
+        .collect();
+
+    let mut columns = vec![
+        Series::new("osm_type", buildings.iter().map(|b| b.osm_type).collect::<Vec<_>>()),
+        Series::new("osm_id", buildings.iter().map(|b| b.osm_id).collect::<Vec<_>>()),
+        Series::new("state", vec![state_name; buildings.len()]),
+        Series::new("building", buildings.iter().map(|b| b.building_type.as_str()).collect::<Vec<_>>()),
+        Series::new("lat", buildings.iter().map(|b| round_coord(b.lat, coord_precision)).collect::<Vec<_>>()),
+        Series::new("lon", buildings.iter().map(|b| round_coord(b.lon, coord_precision)).collect::<Vec<_>>()),
+        Series::new("area_m2", buildings.iter().map(|b| b.area_m2).collect::<Vec<_>>()),
+    ];
+
+    for key in addr_keys {
+        let values: Vec<Option<&str>> = buildings
+            .iter()
+            .map(|b| b.addr_tags.get(key).map(String::as_str))
+            .collect();
+        columns.push(Series::new(key, values));
+    }
+
+    Ok(DataFrame::new(columns)?)
+}
+
+/// Buildings mode: extract building footprints to parquet
+pub fn process_buildings_to_parquet(
+    pbf_path: &Path,
+    state_name: &str,
+    output_path: Option<PathBuf>,
+    coord_precision: Option<u32>,
+) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("OSM BUILDINGS TO PARQUET (Rust)");
+    println!("{}", "=".repeat(70));
+    println!("Input file:  {}", pbf_path.display());
+    println!("State:       {}", state_name);
+    println!("{}", "=".repeat(70));
+
+    let output_path = output_path.unwrap_or_else(|| default_output_path(pbf_path, state_name, "buildings"));
+
+    let buildings = extract_buildings(pbf_path)?;
+    let mut df = buildings_to_dataframe(&buildings, state_name, coord_precision)?;
+
+    println!("\nSaving to: {}", output_path.display());
+    write_parquet(&mut df, &output_path)?;
+
+    println!("Done!");
+    println!("{}", "=".repeat(70));
+
+    Ok(())
+}
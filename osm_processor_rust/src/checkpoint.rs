@@ -0,0 +1,209 @@
+use osmpbf::{Blob, BlobDecode, BlobReader, ByteOffset};
+use rayon::prelude::*;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::error::{io_error, pbf_error, ProcessorError, Result};
+use crate::fast_hash::NodeMap;
+use crate::feature::Feature;
+use crate::node_set::NodeIdSet;
+use crate::progress::{Phase, ProgressSink};
+use crate::space::ensure_free_space;
+
+const MAGIC: &[u8; 8] = b"OSMNCK\x00\x01";
+/// Blobs decoded between checkpoints (each holds up to 8000 nodes)
+const CHECKPOINT_BLOBS: usize = 1024;
+/// Offset marker of a finished pass
+const COMPLETE: u64 = u64::MAX;
+
+/// Checkpoint file in `temp_dir` for the node pass of `feature` over `pbf_path`
+pub(crate) fn checkpoint_path(temp_dir: &Path, pbf_path: &Path, feature: Feature) -> PathBuf {
+    let stem = pbf_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    temp_dir.join(format!("osm_processor_{}_{}.nodes.ckpt", stem, feature.type_key()))
+}
+
+/// SplitMix64 finalizer, to fold ids and file metadata into one fingerprint
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Identifies the input file (size and modification time) and the wanted node set, so a
+/// checkpoint is only resumed by the same pass over the same file
+fn fingerprint(pbf_path: &Path, wanted: &NodeIdSet) -> Result<u64> {
+    let metadata = std::fs::metadata(pbf_path).map_err(io_error(pbf_path))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_secs());
+    let ids = wanted.iter().fold(0u64, |acc, id| acc ^ mix(id as u64));
+    Ok(mix(metadata.len() ^ mix(modified ^ mix(wanted.len() as u64 ^ ids))))
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// What an earlier pass with the same fingerprint left behind
+struct SavedPass {
+    coords: NodeMap<(f64, f64)>,
+    /// Blob offset to continue from, or [`COMPLETE`]
+    next: u64,
+    /// Length of the file's intact prefix (a chunk cut short by a kill is ignored)
+    valid_len: u64,
+}
+
+/// The checkpoint at `path`, if it was written with `fingerprint`
+fn read_checkpoint(path: &Path, fingerprint: u64) -> Option<SavedPass> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).ok()?;
+    if &magic != MAGIC || read_u64(&mut reader).ok()? != fingerprint {
+        return None;
+    }
+
+    let mut coords = NodeMap::default();
+    let mut next = 0;
+    let mut valid_len = 16;
+    while let Ok(marker) = read_u64(&mut reader) {
+        let Ok(count) = read_u64(&mut reader) else { break };
+        let mut chunk = vec![0u8; count as usize * 24];
+        if reader.read_exact(&mut chunk).is_err() {
+            break;
+        }
+        for entry in chunk.chunks_exact(24) {
+            let id = i64::from_le_bytes(entry[0..8].try_into().unwrap());
+            let lat = f64::from_le_bytes(entry[8..16].try_into().unwrap());
+            let lon = f64::from_le_bytes(entry[16..24].try_into().unwrap());
+            coords.insert(id, (lat, lon));
+        }
+        next = marker;
+        valid_len += 16 + chunk.len() as u64;
+    }
+    Some(SavedPass { coords, next, valid_len })
+}
+
+/// Wanted nodes of one blob as (id, lat, lon)
+fn blob_nodes(blob: &Blob, wanted: &NodeIdSet) -> std::result::Result<Vec<(i64, f64, f64)>, osmpbf::Error> {
+    let mut found = Vec::new();
+    if let BlobDecode::OsmData(block) = blob.decode()? {
+        for group in block.groups() {
+            found.extend(group.nodes().filter(|n| wanted.contains(n.id())).map(|n| (n.id(), n.lat(), n.lon())));
+            found.extend(group.dense_nodes().filter(|n| wanted.contains(n.id())).map(|n| (n.id(), n.lat(), n.lon())));
+        }
+    }
+    Ok(found)
+}
+
+/// Append one chunk: the offset of the next unread blob, then what the batch found
+fn append_chunk(out: &mut BufWriter<File>, marker: u64, found: &[(i64, f64, f64)]) -> std::io::Result<()> {
+    out.write_all(&marker.to_le_bytes())?;
+    out.write_all(&(found.len() as u64).to_le_bytes())?;
+    for (id, lat, lon) in found {
+        out.write_all(&id.to_le_bytes())?;
+        out.write_all(&lat.to_le_bytes())?;
+        out.write_all(&lon.to_le_bytes())?;
+    }
+    out.flush()?;
+    out.get_ref().sync_data()
+}
+
+/// The node coordinate pass, decoding blobs in batches and appending what each batch found to
+/// the checkpoint file at `path` together with the offset of the next blob. A pass interrupted
+/// by a crash or kill resumes from the last complete batch instead of the start of the file; a
+/// finished one is restored without reading the PBF.
+pub(crate) fn load_node_coords_checkpointed(
+    pbf_path: &Path,
+    wanted_nodes: &NodeIdSet,
+    path: &Path,
+    progress: &dyn ProgressSink,
+) -> Result<NodeMap<(f64, f64)>> {
+    let fingerprint = fingerprint(pbf_path, wanted_nodes)?;
+    let (mut coords, mut next, mut out) = match read_checkpoint(path, fingerprint) {
+        Some(SavedPass { coords, next, valid_len }) => {
+            let mut file = OpenOptions::new().write(true).open(path).map_err(io_error(path))?;
+            file.set_len(valid_len).map_err(io_error(path))?;
+            file.seek(SeekFrom::End(0)).map_err(io_error(path))?;
+            (coords, next, BufWriter::new(file))
+        }
+        None => {
+            // Every wanted node found, 24 bytes each, plus room for the batch markers
+            ensure_free_space(path, wanted_nodes.len() as u64 * 24 + (1 << 20))?;
+            let mut out = BufWriter::new(File::create(path).map_err(io_error(path))?);
+            out.write_all(MAGIC).map_err(io_error(path))?;
+            out.write_all(&fingerprint.to_le_bytes()).map_err(io_error(path))?;
+            out.flush().map_err(io_error(path))?;
+            (NodeMap::default(), 0, out)
+        }
+    };
+
+    if next == COMPLETE {
+        progress.message(&format!("  Restored {} node coordinates from {}", coords.len(), path.display()));
+        return Ok(coords);
+    }
+    if next > 0 {
+        progress.message(&format!(
+            "  Resuming node pass at byte {} with {} coordinates from {}",
+            next,
+            coords.len(),
+            path.display()
+        ));
+    } else {
+        progress.message("  Loading node coordinates (checkpointed)...");
+    }
+    progress.phase_started(Phase::LoadNodes, Some(wanted_nodes.len() as u64));
+    progress.advanced(Phase::LoadNodes, coords.len() as u64);
+
+    let mut reader = BlobReader::seekable_from_path(pbf_path).map_err(pbf_error(pbf_path))?;
+    if next > 0 {
+        reader.seek(ByteOffset(next)).map_err(pbf_error(pbf_path))?;
+    }
+    let mut blobs = reader.peekable();
+    loop {
+        let batch: Vec<Blob> = blobs
+            .by_ref()
+            .take(CHECKPOINT_BLOBS)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(pbf_error(pbf_path))?;
+        if batch.is_empty() {
+            break;
+        }
+        let found: Vec<(i64, f64, f64)> = batch
+            .into_par_iter()
+            .map(|blob| blob_nodes(&blob, wanted_nodes))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(pbf_error(pbf_path))?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        // A read error on the next blob surfaces on the next batch; until then no marker is known
+        let marker = match blobs.peek() {
+            None => Some(COMPLETE),
+            Some(Ok(blob)) => blob.offset().map(|offset| offset.0),
+            Some(Err(_)) => None,
+        };
+        if let Some(marker) = marker {
+            append_chunk(&mut out, marker, &found).map_err(io_error(path))?;
+            next = marker;
+        }
+        progress.advanced(Phase::LoadNodes, found.len() as u64);
+        coords.extend(found.into_iter().map(|(id, lat, lon)| (id, (lat, lon))));
+    }
+    if next != COMPLETE {
+        append_chunk(&mut out, COMPLETE, &[]).map_err(io_error(path))?;
+    }
+
+    progress.phase_finished(Phase::LoadNodes);
+    progress.message(&format!("  Loaded {} coordinates", coords.len()));
+    if coords.is_empty() && !wanted_nodes.is_empty() {
+        return Err(ProcessorError::MissingNodes { path: pbf_path.to_path_buf(), wanted: wanted_nodes.len() });
+    }
+    Ok(coords)
+}
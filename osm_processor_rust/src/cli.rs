@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Command-line arguments split into positionals, `--key value` options and `--switch` flags
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    switches: HashSet<String>,
+}
+
+impl CliArgs {
+    /// Parse `args`, accepting only the listed option names (which take a value, either
+    /// `--key value` or `--key=value`) and switch names (which take none)
+    pub fn parse(args: &[String], options: &[&str], switches: &[&str]) -> Result<Self> {
+        let mut parsed = CliArgs::default();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            // `-o` is accepted as the conventional short form of `--output`
+            let arg = if arg == "-o" && options.contains(&"output") { "--output" } else { arg.as_str() };
+
+            let Some(flag) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg.to_string());
+                continue;
+            };
+
+            let (name, inline_value) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (flag, None),
+            };
+
+            if switches.contains(&name) {
+                if inline_value.is_some() {
+                    anyhow::bail!("--{} does not take a value", name);
+                }
+                parsed.switches.insert(name.to_string());
+            } else if options.contains(&name) {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => iter
+                        .next()
+                        .cloned()
+                        .with_context(|| format!("--{} requires a value", name))?,
+                };
+                parsed.options.insert(name.to_string(), value);
+            } else {
+                anyhow::bail!("Unknown option: --{}", name);
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Positional argument `i`, if present
+    pub fn positional(&self, i: usize) -> Option<&String> {
+        self.positional.get(i)
+    }
+
+    /// All positional arguments
+    pub fn positionals(&self) -> &[String] {
+        &self.positional
+    }
+
+    /// Raw value of `--name`, if given
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    /// Parsed value of `--name`, if given
+    pub fn parsed<T>(&self, name: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        self.value(name)
+            .map(|v| v.parse().with_context(|| format!("Invalid value for --{}: {}", name, v)))
+            .transpose()
+    }
+
+    /// Whether `--name` was given
+    pub fn flag(&self, name: &str) -> bool {
+        self.switches.contains(name)
+    }
+}
+
+/// Duration such as `90s`, `45m`, `2h` or `1.5h`; a bare number is seconds
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let (number, unit_s) = match value.char_indices().last() {
+        Some((i, 's')) => (&value[..i], 1.0),
+        Some((i, 'm')) => (&value[..i], 60.0),
+        Some((i, 'h')) => (&value[..i], 3600.0),
+        _ => (value, 1.0),
+    };
+    let amount: f64 = number.parse().with_context(|| format!("Invalid duration: {} (expected e.g. 90s, 45m or 2h)", value))?;
+    if !amount.is_finite() || amount <= 0.0 {
+        anyhow::bail!("Invalid duration: {} (must be positive)", value);
+    }
+    Ok(Duration::from_secs_f64(amount * unit_s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("45m").unwrap(), Duration::from_secs(45 * 60));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(2 * 3600));
+        assert_eq!(parse_duration("1.5h").unwrap(), Duration::from_secs(5400));
+    }
+
+    #[test]
+    fn bare_number_is_seconds() {
+        assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration(" 0.5 ").unwrap(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        for value in ["", "h", "abc", "10d", "0", "-5m", "NaNs", "infh"] {
+            assert!(parse_duration(value).is_err(), "{:?} should be rejected", value);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use anyhow::{Context, Result};
+use polars::prelude::*;
+use rstar::primitives::GeomWithData;
+use rstar::RTree;
+use shapefile::dbase::FieldValue;
+use shapefile::Shape;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::matching::{read_rows, StreetRow};
+use osm_processor_rust::name_parser::match_key;
+use osm_processor_rust::stats::load_streets;
+use osm_processor_rust::write_parquet;
+
+/// Mean Earth radius, for converting km to chord lengths on the unit sphere
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Edge vertices on the unit sphere, tagged with the index of their edge
+type EdgeIndex = RTree<GeomWithData<[f64; 3], usize>>;
+
+/// A named TIGER/Line road edge
+struct TigerEdge {
+    name: String,
+    /// [`match_key`] of `name`, as compared against OSM names
+    key: String,
+    /// State + county FIPS code (`10001`)
+    county: String,
+}
+
+/// How one OSM street fared against the TIGER edges around it
+enum Outcome {
+    /// A same-name edge within the match radius
+    Matched(usize),
+    /// Edges nearby, but none with the same name; the nearest one
+    NameMismatch(usize),
+    /// No edge within the match radius
+    Unmatched,
+}
+
+/// Point on the unit sphere, as in `nearest`
+fn unit_vector(lat: f64, lon: f64) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn text_field(record: &shapefile::dbase::Record, name: &str) -> Option<String> {
+    match record.get(name) {
+        Some(FieldValue::Character(Some(value))) => Some(value.trim().to_string()),
+        _ => None,
+    }
+}
+
+/// Named road edges (MTFCC `S*`) from a TIGER/Line `edges` shapefile, and an R-tree over
+/// their vertices tagged with the edge index
+fn read_tiger(path: &Path) -> Result<(Vec<TigerEdge>, EdgeIndex)> {
+    let mut reader = shapefile::Reader::from_path(path)
+        .with_context(|| format!("Failed to open TIGER shapefile {}", path.display()))?;
+
+    let mut edges = Vec::new();
+    let mut points = Vec::new();
+    for item in reader.iter_shapes_and_records() {
+        let (shape, record) = item.with_context(|| format!("Failed to read {}", path.display()))?;
+        let is_road = text_field(&record, "MTFCC").is_some_and(|mtfcc| mtfcc.starts_with('S'));
+        let Some(name) = text_field(&record, "FULLNAME").filter(|name| is_road && !name.is_empty()) else {
+            continue;
+        };
+        let Shape::Polyline(line) = shape else { continue };
+
+        let index = edges.len();
+        for part in line.parts() {
+            points.extend(part.iter().map(|p| GeomWithData::new(unit_vector(p.y, p.x), index)));
+        }
+        let county = format!(
+            "{}{}",
+            text_field(&record, "STATEFP").unwrap_or_default(),
+            text_field(&record, "COUNTYFP").unwrap_or_default()
+        );
+        edges.push(TigerEdge { key: match_key(&name), name, county });
+    }
+
+    Ok((edges, RTree::bulk_load(points)))
+}
+
+/// Match one street: a same-name edge with a vertex within `max_match_km` of its representative
+/// point, else the nearest edge in that radius as a name mismatch
+fn match_street(
+    row: &StreetRow,
+    edges: &[TigerEdge],
+    tree: &EdgeIndex,
+    max_chord: f64,
+) -> Outcome {
+    if !row.lat.is_finite() || !row.lon.is_finite() {
+        return Outcome::Unmatched;
+    }
+    let key = match_key(&row.street_name);
+    let here = unit_vector(row.lat, row.lon);
+
+    let mut nearest = None;
+    for point in tree.nearest_neighbor_iter(&here) {
+        let d = point.geom();
+        let chord_sq = (d[0] - here[0]).powi(2) + (d[1] - here[1]).powi(2) + (d[2] - here[2]).powi(2);
+        if chord_sq > max_chord * max_chord {
+            break;
+        }
+        if edges[point.data].key == key {
+            return Outcome::Matched(point.data);
+        }
+        nearest.get_or_insert(point.data);
+    }
+    match nearest {
+        Some(edge) => Outcome::NameMismatch(edge),
+        None => Outcome::Unmatched,
+    }
+}
+
+/// Counts for one county (FIPS code, or `unknown` when no TIGER edge was nearby)
+#[derive(Default)]
+struct CountyCounts {
+    streets: u32,
+    matched: u32,
+    name_mismatch: u32,
+    unmatched: u32,
+}
+
+/// `crosscheck <streets.parquet...> --tiger edges.shp [--max-match-km X] [--output-dir DIR]`
+pub fn run_crosscheck(paths: &[PathBuf], tiger_path: &Path, max_match_km: f64, report_dir: Option<&Path>) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("CROSS-CHECK AGAINST TIGER/LINE");
+    println!("{}", "=".repeat(70));
+    println!("TIGER edges: {}", tiger_path.display());
+    println!("Match radius: {} km", max_match_km);
+    println!("{}", "=".repeat(70));
+
+    let rows = read_rows(&load_streets(paths)?)?;
+    let (edges, tree) = read_tiger(tiger_path)?;
+    println!("\nStreets: {}   named TIGER road edges: {}", rows.len(), edges.len());
+
+    let max_chord = 2.0 * (max_match_km / (2.0 * EARTH_RADIUS_KM)).sin();
+    let outcomes: Vec<Outcome> = rows.iter().map(|row| match_street(row, &edges, &tree, max_chord)).collect();
+
+    let mut counties: BTreeMap<&str, CountyCounts> = BTreeMap::new();
+    let mut mismatches: Vec<(usize, usize)> = Vec::new();
+    for (i, outcome) in outcomes.iter().enumerate() {
+        let county = match outcome {
+            Outcome::Matched(edge) | Outcome::NameMismatch(edge) => edges[*edge].county.as_str(),
+            Outcome::Unmatched => "unknown",
+        };
+        let counts = counties.entry(county).or_default();
+        counts.streets += 1;
+        match outcome {
+            Outcome::Matched(_) => counts.matched += 1,
+            Outcome::NameMismatch(edge) => {
+                counts.name_mismatch += 1;
+                mismatches.push((i, *edge));
+            }
+            Outcome::Unmatched => counts.unmatched += 1,
+        }
+    }
+
+    // TIGER names per county that no OSM street matched
+    let matched_keys: HashSet<(&str, &str)> = outcomes
+        .iter()
+        .filter_map(|outcome| match outcome {
+            Outcome::Matched(edge) => Some((edges[*edge].county.as_str(), edges[*edge].key.as_str())),
+            _ => None,
+        })
+        .collect();
+    let mut tiger_names: BTreeMap<&str, HashSet<&str>> = BTreeMap::new();
+    for edge in &edges {
+        tiger_names.entry(edge.county.as_str()).or_default().insert(edge.key.as_str());
+    }
+
+    let rate = |part: u32, whole: u32| if whole == 0 { 0.0 } else { part as f64 / whole as f64 };
+    let total_matched: u32 = counties.values().map(|c| c.matched).sum();
+    println!("Matched:        {} ({:.1}%)", total_matched, 100.0 * rate(total_matched, rows.len() as u32));
+    println!("Name mismatch:  {}", mismatches.len());
+    println!("Unmatched:      {}", counties.values().map(|c| c.unmatched).sum::<u32>());
+
+    let county_names: Vec<&str> = counties.keys().copied().collect();
+    let tiger_only: Vec<u32> = county_names
+        .iter()
+        .map(|county| {
+            tiger_names
+                .get(county)
+                .map(|keys| keys.iter().filter(|key| !matched_keys.contains(&(*county, **key))).count() as u32)
+                .unwrap_or(0)
+        })
+        .collect();
+
+    println!("\n{:<10} {:>8} {:>8} {:>9} {:>9} {:>8} {:>11}", "county", "streets", "matched", "mismatch", "unmatched", "rate", "tiger_only");
+    for (county, missing) in county_names.iter().zip(&tiger_only) {
+        let c = &counties[county];
+        println!(
+            "{:<10} {:>8} {:>8} {:>9} {:>9} {:>7.1}% {:>11}",
+            county, c.streets, c.matched, c.name_mismatch, c.unmatched, 100.0 * rate(c.matched, c.streets), missing
+        );
+    }
+
+    println!("\nSample name mismatches (OSM -> nearest TIGER):");
+    for &(i, edge) in mismatches.iter().take(10) {
+        println!("  {} -> {} ({})", rows[i].street_name, edges[edge].name, edges[edge].county);
+    }
+
+    if let Some(dir) = report_dir {
+        std::fs::create_dir_all(dir)?;
+
+        let counts: Vec<&CountyCounts> = county_names.iter().map(|county| &counties[county]).collect();
+        let mut county_df = DataFrame::new(vec![
+            Series::new("county_fips", county_names.clone()),
+            Series::new("streets", counts.iter().map(|c| c.streets).collect::<Vec<_>>()),
+            Series::new("matched", counts.iter().map(|c| c.matched).collect::<Vec<_>>()),
+            Series::new("name_mismatch", counts.iter().map(|c| c.name_mismatch).collect::<Vec<_>>()),
+            Series::new("unmatched", counts.iter().map(|c| c.unmatched).collect::<Vec<_>>()),
+            Series::new("match_rate", counts.iter().map(|c| rate(c.matched, c.streets)).collect::<Vec<_>>()),
+            Series::new("tiger_only_names", tiger_only),
+        ])?;
+        write_parquet(&mut county_df, &dir.join("crosscheck_by_county.parquet"))?;
+
+        let mut mismatch_df = DataFrame::new(vec![
+            Series::new("street_name", mismatches.iter().map(|m| rows[m.0].street_name.as_str()).collect::<Vec<_>>()),
+            Series::new("state", mismatches.iter().map(|m| rows[m.0].state.as_str()).collect::<Vec<_>>()),
+            Series::new("lat", mismatches.iter().map(|m| rows[m.0].lat).collect::<Vec<_>>()),
+            Series::new("lon", mismatches.iter().map(|m| rows[m.0].lon).collect::<Vec<_>>()),
+            Series::new("tiger_name", mismatches.iter().map(|m| edges[m.1].name.as_str()).collect::<Vec<_>>()),
+            Series::new("county_fips", mismatches.iter().map(|m| edges[m.1].county.as_str()).collect::<Vec<_>>()),
+        ])?;
+        write_parquet(&mut mismatch_df, &dir.join("crosscheck_name_mismatches.parquet"))?;
+
+        println!("Reports saved to: {}", dir.display());
+    }
+
+    Ok(())
+}
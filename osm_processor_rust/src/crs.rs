@@ -0,0 +1,138 @@
+use polars::prelude::*;
+use std::f64::consts::FRAC_PI_4;
+
+use crate::error::{ProcessorError, Result};
+use crate::round_coord;
+
+/// WGS84 semi-major axis in meters
+const WGS84_A: f64 = 6_378_137.0;
+/// WGS84 flattening
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+/// UTM central meridian scale factor
+const UTM_K0: f64 = 0.9996;
+/// Latitude limit of Web Mercator, where the projected map becomes square
+const WEB_MERCATOR_MAX_LAT: f64 = 85.051_128_779_806_59;
+
+/// Coordinate reference system for written coordinates (`--output-crs`). Everything is
+/// extracted in WGS84 lat/lon; other systems are projected from it at write time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputCrs {
+    /// EPSG:4326, lat/lon degrees (default)
+    #[default]
+    Wgs84,
+    /// EPSG:3857, meters
+    WebMercator,
+    /// EPSG:326zz (north) / EPSG:327zz (south), WGS84 UTM zone `zone`, meters
+    Utm { zone: u8, north: bool },
+}
+
+impl OutputCrs {
+    /// Parse an `EPSG:XXXX` code (the `EPSG:` prefix is optional)
+    pub fn parse(value: &str) -> Result<Self> {
+        let code = value.trim();
+        let code = code.strip_prefix("EPSG:").or_else(|| code.strip_prefix("epsg:")).unwrap_or(code);
+        let invalid = || {
+            ProcessorError::InvalidConfig(format!(
+                "Unsupported output CRS: {} (expected EPSG:4326, EPSG:3857 or a WGS84 UTM zone EPSG:326zz/327zz)",
+                value
+            ))
+        };
+        let code: u32 = code.parse().map_err(|_| invalid())?;
+        match code {
+            4326 => Ok(OutputCrs::Wgs84),
+            3857 => Ok(OutputCrs::WebMercator),
+            32601..=32660 => Ok(OutputCrs::Utm { zone: (code - 32600) as u8, north: true }),
+            32701..=32760 => Ok(OutputCrs::Utm { zone: (code - 32700) as u8, north: false }),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// The `EPSG:XXXX` code
+    pub fn name(&self) -> String {
+        match self {
+            OutputCrs::Wgs84 => "EPSG:4326".to_string(),
+            OutputCrs::WebMercator => "EPSG:3857".to_string(),
+            OutputCrs::Utm { zone, north } => format!("EPSG:{}", if *north { 32600 } else { 32700 } + *zone as u32),
+        }
+    }
+
+    /// Whether coordinates stay lat/lon degrees
+    pub fn is_geographic(&self) -> bool {
+        *self == OutputCrs::Wgs84
+    }
+
+    /// Project a WGS84 (lat, lon) point to (x, y) in this system; (lon, lat) for WGS84 itself
+    pub fn project(&self, (lat, lon): (f64, f64)) -> (f64, f64) {
+        match *self {
+            OutputCrs::Wgs84 => (lon, lat),
+            OutputCrs::WebMercator => {
+                let lat = lat.clamp(-WEB_MERCATOR_MAX_LAT, WEB_MERCATOR_MAX_LAT).to_radians();
+                (WGS84_A * lon.to_radians(), WGS84_A * (FRAC_PI_4 + lat / 2.0).tan().ln())
+            }
+            OutputCrs::Utm { zone, north } => {
+                let (x, y) = transverse_mercator(lat, lon, (zone as f64 - 1.0) * 6.0 - 177.0);
+                (x + 500_000.0, if north { y } else { y + 10_000_000.0 })
+            }
+        }
+    }
+}
+
+/// Transverse Mercator on the WGS84 ellipsoid (Snyder's series), scaled by the UTM factor;
+/// x relative to the central meridian `lon0`, y from the equator
+fn transverse_mercator(lat: f64, lon: f64, lon0: f64) -> (f64, f64) {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let (e4, e6) = (e2 * e2, e2 * e2 * e2);
+    let ep2 = e2 / (1.0 - e2);
+    let phi = lat.to_radians();
+    let (sin, cos, tan) = (phi.sin(), phi.cos(), phi.tan());
+
+    let n = WGS84_A / (1.0 - e2 * sin * sin).sqrt();
+    let t = tan * tan;
+    let c = ep2 * cos * cos;
+    let a = cos * (lon - lon0).to_radians();
+    let m = WGS84_A
+        * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * phi
+            - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * phi).sin()
+            + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * phi).sin()
+            - (35.0 * e6 / 3072.0) * (6.0 * phi).sin());
+
+    let x = UTM_K0
+        * n
+        * (a + (1.0 - t + c) * a.powi(3) / 6.0 + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0);
+    let y = UTM_K0
+        * (m + n
+            * tan
+            * (a * a / 2.0
+                + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+                + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * a.powi(6) / 720.0));
+    (x, y)
+}
+
+/// Replace a frame's `lat`/`lon` columns with projected `x`/`y` in their place; frames in
+/// WGS84 are left as they are
+pub fn reproject_point_columns(df: &mut DataFrame, crs: OutputCrs, precision: Option<u32>) -> Result<()> {
+    if crs.is_geographic() {
+        return Ok(());
+    }
+    let lats = df.column("lat")?.f64()?;
+    let lons = df.column("lon")?.f64()?;
+    let (xs, ys): (Vec<Option<f64>>, Vec<Option<f64>>) = lats
+        .into_iter()
+        .zip(lons)
+        .map(|(lat, lon)| match (lat, lon) {
+            (Some(lat), Some(lon)) => {
+                let (x, y) = crs.project((lat, lon));
+                (Some(round_coord(x, precision)), Some(round_coord(y, precision)))
+            }
+            _ => (None, None),
+        })
+        .unzip();
+
+    for (from, to) in [("lon", Series::new("x", xs)), ("lat", Series::new("y", ys))] {
+        let index = df
+            .get_column_index(from)
+            .ok_or_else(|| ProcessorError::InvalidConfig(format!("no {} column to reproject", from)))?;
+        df.replace_column(index, to)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::matching::{match_streets, read_rows, row_distance_km, StreetRow};
+use osm_processor_rust::manifest::{manifest_path, Manifest};
+use osm_processor_rust::stats::load_streets;
+use osm_processor_rust::write_parquet;
+
+/// Relative length change above which a matched street counts as changed
+const LENGTH_CHANGE_RATIO: f64 = 0.01;
+
+/// One row of the detailed diff
+struct DiffRow<'a> {
+    change: &'static str,
+    old: Option<&'a StreetRow>,
+    new: Option<&'a StreetRow>,
+    changed_fields: Vec<&'static str>,
+}
+
+/// Which attributes differ between two matched streets
+fn changed_fields(old: &StreetRow, new: &StreetRow, tolerance_km: f64) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if old.street_name != new.street_name {
+        fields.push("street_name");
+    }
+    if row_distance_km(old, new) > tolerance_km {
+        fields.push("location");
+    }
+    let length_delta = (old.length_km - new.length_km).abs();
+    if length_delta > LENGTH_CHANGE_RATIO * old.length_km.max(new.length_km) {
+        fields.push("length_km");
+    }
+    if old.num_segments != new.num_segments {
+        fields.push("num_segments");
+    }
+    if old.street_type != new.street_type {
+        fields.push("street_type");
+    }
+    fields
+}
+
+fn diff_to_dataframe<'a>(rows: &[DiffRow<'a>]) -> Result<DataFrame> {
+    let pick = |row: &DiffRow<'a>| -> &'a StreetRow { row.new.or(row.old).unwrap() };
+
+    let df = DataFrame::new(vec![
+        Series::new("change", rows.iter().map(|r| r.change).collect::<Vec<_>>()),
+        Series::new("street_name", rows.iter().map(|r| pick(r).street_name.as_str()).collect::<Vec<_>>()),
+        Series::new("state", rows.iter().map(|r| pick(r).state.as_str()).collect::<Vec<_>>()),
+        Series::new("changed_fields", rows.iter().map(|r| r.changed_fields.join(",")).collect::<Vec<_>>()),
+        Series::new("old_lat", rows.iter().map(|r| r.old.map(|o| o.lat)).collect::<Vec<_>>()),
+        Series::new("old_lon", rows.iter().map(|r| r.old.map(|o| o.lon)).collect::<Vec<_>>()),
+        Series::new("new_lat", rows.iter().map(|r| r.new.map(|n| n.lat)).collect::<Vec<_>>()),
+        Series::new("new_lon", rows.iter().map(|r| r.new.map(|n| n.lon)).collect::<Vec<_>>()),
+        Series::new(
+            "distance_km",
+            rows.iter()
+                .map(|r| r.old.zip(r.new).map(|(o, n)| row_distance_km(o, n)))
+                .collect::<Vec<_>>(),
+        ),
+        Series::new("old_length_km", rows.iter().map(|r| r.old.map(|o| o.length_km)).collect::<Vec<_>>()),
+        Series::new("new_length_km", rows.iter().map(|r| r.new.map(|n| n.length_km)).collect::<Vec<_>>()),
+        Series::new("old_num_segments", rows.iter().map(|r| r.old.map(|o| o.num_segments)).collect::<Vec<_>>()),
+        Series::new("new_num_segments", rows.iter().map(|r| r.new.map(|n| n.num_segments)).collect::<Vec<_>>()),
+    ])?;
+
+    Ok(df)
+}
+
+/// `diff <old.parquet> <new.parquet> [--tolerance-km X] [--output diff.parquet]`
+pub fn run_diff(
+    old_path: &Path,
+    new_path: &Path,
+    tolerance_km: f64,
+    output_path: Option<PathBuf>,
+) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("STREET DATASET DIFF");
+    println!("{}", "=".repeat(70));
+    println!("Old:         {}", old_path.display());
+    println!("New:         {}", new_path.display());
+    println!("Tolerance:   {} km", tolerance_km);
+    println!("{}", "=".repeat(70));
+
+    let old_rows = read_rows(&load_streets(&[old_path.to_path_buf()])?)?;
+    let new_rows = read_rows(&load_streets(&[new_path.to_path_buf()])?)?;
+
+    let matches = match_streets(&old_rows, &new_rows, tolerance_km);
+
+    let mut rows: Vec<DiffRow> = Vec::new();
+    for &(i, j) in &matches.matched {
+        let fields = changed_fields(&old_rows[i], &new_rows[j], tolerance_km);
+        if !fields.is_empty() {
+            rows.push(DiffRow { change: "changed", old: Some(&old_rows[i]), new: Some(&new_rows[j]), changed_fields: fields });
+        }
+    }
+    let changed = rows.len();
+    for &i in &matches.left_only {
+        rows.push(DiffRow { change: "removed", old: Some(&old_rows[i]), new: None, changed_fields: Vec::new() });
+    }
+    for &j in &matches.right_only {
+        rows.push(DiffRow { change: "added", old: None, new: Some(&new_rows[j]), changed_fields: Vec::new() });
+    }
+
+    println!("Old streets:      {}", old_rows.len());
+    println!("New streets:      {}", new_rows.len());
+    println!("Matched:          {}", matches.matched.len());
+    println!("  unchanged:      {}", matches.matched.len() - changed);
+    println!("  changed:        {}", changed);
+    println!("Removed:          {}", matches.left_only.len());
+    println!("Added:            {}", matches.right_only.len());
+
+    for (label, path) in [("Old", old_path), ("New", new_path)] {
+        if let Some(source) = Manifest::read_for(path)? {
+            let inputs: Vec<String> = source.inputs.iter().map(|i| i.path.display().to_string()).collect();
+            println!("{} run:          {} {} from {}", label, source.tool, source.tool_version, inputs.join(", "));
+        }
+    }
+
+    if let Some(output_path) = output_path {
+        let mut df = diff_to_dataframe(&rows)?;
+        println!("\nSaving differences to: {}", output_path.display());
+        write_parquet(&mut df, &output_path)?;
+
+        let mut manifest = Manifest::new("diff");
+        manifest.add_source(old_path)?;
+        manifest.add_source(new_path)?;
+        manifest.set_option("tolerance_km", tolerance_km);
+        manifest.add_output(&output_path, df.height());
+        manifest.write(&manifest_path(&output_path))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use polars::prelude::PolarsError;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Errors returned by the library. The CLI wraps these in `anyhow`; library callers can
+/// match on the kind.
+#[derive(Debug, Error)]
+pub enum ProcessorError {
+    #[error("I/O error on {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Failed to read OSM file {}: {source}", path.display())]
+    PbfParse {
+        path: PathBuf,
+        #[source]
+        source: osmpbf::Error,
+    },
+
+    #[error("None of the {wanted} referenced nodes were found in {}; is the file a complete extract?", path.display())]
+    MissingNodes { path: PathBuf, wanted: usize },
+
+    #[error("Failed to write {}: {source}", path.display())]
+    OutputWrite {
+        path: PathBuf,
+        #[source]
+        source: PolarsError,
+    },
+
+    #[error(
+        "Refusing to replace existing output: {} (pass --force to overwrite)",
+        paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    OutputExists { paths: Vec<PathBuf> },
+
+    #[error(
+        "Another run is writing {}{}; wait for it to finish",
+        path.display(),
+        holder.map(|pid| format!(" (process {})", pid)).unwrap_or_default()
+    )]
+    OutputLocked { path: PathBuf, holder: Option<u32> },
+
+    #[error(
+        "Not enough free space for {}: about {} MiB needed, {} MiB available",
+        path.display(),
+        needed >> 20,
+        available >> 20
+    )]
+    InsufficientSpace { path: PathBuf, needed: u64, available: u64 },
+
+    #[error("Failed to read {}: {source}", path.display())]
+    InputRead {
+        path: PathBuf,
+        #[source]
+        source: PolarsError,
+    },
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
+    /// Building or transforming an in-memory DataFrame failed
+    #[error(transparent)]
+    DataFrame(#[from] PolarsError),
+
+    #[error("Street processing thread panicked")]
+    WorkerPanic,
+
+    /// The run reached its maximum runtime and stopped early; the partial outputs and a
+    /// manifest marked `truncated` were still written
+    #[error("Stopped after the maximum runtime of {}s; outputs are partial", max_runtime.as_secs())]
+    Truncated { max_runtime: Duration },
+
+    #[cfg(feature = "async")]
+    #[error("Failed to fetch {url}: {source}")]
+    Fetch {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[cfg(feature = "async")]
+    #[error("Failed to start the async runtime: {0}")]
+    Runtime(#[source] std::io::Error),
+
+    #[cfg(feature = "async")]
+    #[error("Failed to upload to {url}: {source}")]
+    Upload {
+        url: String,
+        #[source]
+        source: object_store::Error,
+    },
+}
+
+pub type Result<T, E = ProcessorError> = std::result::Result<T, E>;
+
+/// `map_err` adapter for osmpbf errors on `path`
+pub(crate) fn pbf_error(path: &Path) -> impl FnOnce(osmpbf::Error) -> ProcessorError + '_ {
+    move |source| ProcessorError::PbfParse { path: path.to_path_buf(), source }
+}
+
+/// `map_err` adapter for filesystem errors on `path`
+pub(crate) fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> ProcessorError + '_ {
+    move |source| ProcessorError::Io { path: path.to_path_buf(), source }
+}
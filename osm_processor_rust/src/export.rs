@@ -0,0 +1,332 @@
+use anyhow::{Context, Result};
+use geo::LineString;
+use polars::prelude::*;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::report::escape;
+use crate::rng::SplitMix64;
+use osm_processor_rust::stats::{load_streets, type_column};
+use osm_processor_rust::wkb::wkb_to_lines;
+
+/// Output formats of `export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Newline-delimited GeoJSON features with tippecanoe hints
+    GeoJsonSeq,
+    /// KML placemarks for Google Earth; zipped into a KMZ when the output ends in `.kmz`
+    Kml,
+    /// CSV with the geometry as a `wkt` column; segments are merged per street unless
+    /// `--per-segment` is given
+    WktCsv,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "geojsonseq" => Ok(ExportFormat::GeoJsonSeq),
+            "kml" => Ok(ExportFormat::Kml),
+            "wkt-csv" => Ok(ExportFormat::WktCsv),
+            other => anyhow::bail!("Unknown export format {} (expected geojsonseq, kml or wkt-csv)", other),
+        }
+    }
+}
+
+/// Geometry of an exported row: the segment polylines when the input has a WKB `geometry`
+/// column (the `--emit-segments` output), otherwise the street's representative point
+pub enum ExportGeometry {
+    Point { lat: f64, lon: f64 },
+    Lines(Vec<LineString<f64>>),
+}
+
+/// One street or segment with the properties every format writes
+pub struct ExportRow {
+    pub street_id: Option<String>,
+    pub street_name: String,
+    pub state: String,
+    pub street_type: Option<String>,
+    pub length_km: Option<f64>,
+    pub num_segments: Option<u32>,
+    pub geometry: ExportGeometry,
+}
+
+/// Rows of a streets or segments frame and the name of its type column; rows without any
+/// geometry are dropped
+pub fn read_export_rows(df: &DataFrame) -> Result<(Vec<ExportRow>, Option<String>)> {
+    let names = df.column("street_name")?.str()?;
+    let states = df.column("state")?.str()?;
+    let ids = match df.column("street_id") {
+        Ok(column) => Some(column.cast(&DataType::String)?),
+        Err(_) => None,
+    };
+    let ids = ids.as_ref().map(|c| c.str()).transpose()?;
+    let type_name = type_column(df);
+    let types = match &type_name {
+        Some(name) => Some(df.column(name)?.str()?),
+        None => None,
+    };
+    let lengths = match df.column("length_km") {
+        Ok(column) => Some(column.f64()?),
+        Err(_) => None,
+    };
+    let segments = match df.column("num_segments") {
+        Ok(column) => Some(column.cast(&DataType::UInt32)?),
+        Err(_) => None,
+    };
+    let segments = segments.as_ref().map(|c| c.u32()).transpose()?;
+
+    let geometries: Vec<Option<ExportGeometry>> = match df.column("geometry") {
+        Ok(column) => column
+            .binary()?
+            .into_iter()
+            .map(|wkb| wkb.and_then(wkb_to_lines).map(ExportGeometry::Lines))
+            .collect(),
+        Err(_) => {
+            let lats = df.column("lat").context("input has neither a geometry column nor lat/lon")?.f64()?;
+            let lons = df.column("lon")?.f64()?;
+            lats.into_iter()
+                .zip(lons)
+                .map(|(lat, lon)| Some(ExportGeometry::Point { lat: lat?, lon: lon? }))
+                .collect()
+        }
+    };
+
+    let rows = geometries
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, geometry)| {
+            Some(ExportRow {
+                street_id: ids.and_then(|c| c.get(i)).map(str::to_string),
+                street_name: names.get(i).unwrap_or("").to_string(),
+                state: states.get(i).unwrap_or("").to_string(),
+                street_type: types.and_then(|c| c.get(i)).map(str::to_string),
+                length_km: lengths.and_then(|c| c.get(i)),
+                num_segments: segments.and_then(|c| c.get(i)),
+                geometry: geometry?,
+            })
+        })
+        .collect();
+    Ok((rows, type_name))
+}
+
+/// Lowest zoom at which tippecanoe should keep a street of this highway type, so national
+/// tiles show the network from the top of the hierarchy down
+pub fn minzoom(street_type: Option<&str>) -> u8 {
+    match street_type.unwrap_or("") {
+        "motorway" | "trunk" | "motorway_link" | "trunk_link" => 4,
+        "primary" | "primary_link" => 6,
+        "secondary" | "secondary_link" => 8,
+        "tertiary" | "tertiary_link" => 10,
+        "residential" | "unclassified" | "living_street" | "road" => 12,
+        _ => 13,
+    }
+}
+
+fn line_coordinates(line: &LineString<f64>) -> Value {
+    Value::Array(line.points().map(|p| json!([p.x(), p.y()])).collect())
+}
+
+fn geojson_geometry(geometry: &ExportGeometry) -> Value {
+    match geometry {
+        ExportGeometry::Point { lat, lon } => json!({ "type": "Point", "coordinates": [lon, lat] }),
+        ExportGeometry::Lines(lines) if lines.len() == 1 => {
+            json!({ "type": "LineString", "coordinates": line_coordinates(&lines[0]) })
+        }
+        ExportGeometry::Lines(lines) => json!({
+            "type": "MultiLineString",
+            "coordinates": lines.iter().map(line_coordinates).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+/// One GeoJSON feature per line. Every feature has the same properties in the same order
+/// (nulls included) and a `tippecanoe` member with the `streets` layer and a minzoom by type.
+fn write_geojsonseq(rows: &[ExportRow], type_name: Option<&str>, out: &mut impl Write) -> Result<()> {
+    let type_name = type_name.unwrap_or("highway_type");
+    for row in rows {
+        let mut properties = Map::new();
+        properties.insert("street_id".into(), json!(row.street_id));
+        properties.insert("street_name".into(), json!(row.street_name));
+        properties.insert("state".into(), json!(row.state));
+        properties.insert(type_name.into(), json!(row.street_type));
+        properties.insert("length_km".into(), json!(row.length_km));
+        properties.insert("num_segments".into(), json!(row.num_segments));
+
+        let feature = json!({
+            "type": "Feature",
+            "tippecanoe": { "layer": "streets", "minzoom": minzoom(row.street_type.as_deref()) },
+            "properties": properties,
+            "geometry": geojson_geometry(&row.geometry),
+        });
+        serde_json::to_writer(&mut *out, &feature)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn kml_coordinates(line: &LineString<f64>) -> String {
+    line.points().map(|p| format!("{},{}", p.x(), p.y())).collect::<Vec<_>>().join(" ")
+}
+
+fn kml_geometry(geometry: &ExportGeometry) -> String {
+    match geometry {
+        ExportGeometry::Point { lat, lon } => format!("<Point><coordinates>{},{}</coordinates></Point>", lon, lat),
+        ExportGeometry::Lines(lines) => {
+            let lines: String = lines
+                .iter()
+                .map(|line| format!("<LineString><coordinates>{}</coordinates></LineString>", kml_coordinates(line)))
+                .collect();
+            format!("<MultiGeometry>{}</MultiGeometry>", lines)
+        }
+    }
+}
+
+/// One placemark per row, named by the street, with name, state and type in the description
+fn write_kml(rows: &[ExportRow], title: &str, out: &mut impl Write) -> Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(out, "<Document><name>{}</name>", escape(title))?;
+    for row in rows {
+        let mut description = format!("{}, {}", row.street_name, row.state);
+        if let Some(street_type) = &row.street_type {
+            description.push_str(&format!(" ({})", street_type));
+        }
+        if let Some(length) = row.length_km {
+            description.push_str(&format!(", {:.3} km", length));
+        }
+        writeln!(
+            out,
+            "<Placemark><name>{}</name><description>{}</description>{}</Placemark>",
+            escape(&row.street_name),
+            escape(&description),
+            kml_geometry(&row.geometry)
+        )?;
+    }
+    writeln!(out, "</Document></kml>")?;
+    Ok(())
+}
+
+fn wkt_coordinates(line: &LineString<f64>) -> String {
+    line.points().map(|p| format!("{} {}", p.x(), p.y())).collect::<Vec<_>>().join(", ")
+}
+
+fn wkt(geometry: &ExportGeometry) -> String {
+    match geometry {
+        ExportGeometry::Point { lat, lon } => format!("POINT ({} {})", lon, lat),
+        ExportGeometry::Lines(lines) if lines.len() == 1 => format!("LINESTRING ({})", wkt_coordinates(&lines[0])),
+        ExportGeometry::Lines(lines) => format!(
+            "MULTILINESTRING ({})",
+            lines.iter().map(|line| format!("({})", wkt_coordinates(line))).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// CSV of the row properties plus a `wkt` geometry column (lon/lat order)
+fn write_wkt_csv(rows: &[ExportRow], type_name: Option<&str>, out: &mut impl Write) -> Result<()> {
+    let mut df = DataFrame::new(vec![
+        Series::new("street_id", rows.iter().map(|r| r.street_id.as_deref()).collect::<Vec<_>>()),
+        Series::new("street_name", rows.iter().map(|r| r.street_name.as_str()).collect::<Vec<_>>()),
+        Series::new("state", rows.iter().map(|r| r.state.as_str()).collect::<Vec<_>>()),
+        Series::new(type_name.unwrap_or("highway_type"), rows.iter().map(|r| r.street_type.as_deref()).collect::<Vec<_>>()),
+        Series::new("length_km", rows.iter().map(|r| r.length_km).collect::<Vec<_>>()),
+        Series::new("num_segments", rows.iter().map(|r| r.num_segments).collect::<Vec<_>>()),
+        Series::new("wkt", rows.iter().map(|r| wkt(&r.geometry)).collect::<Vec<_>>()),
+    ])?;
+    CsvWriter::new(out).finish(&mut df)?;
+    Ok(())
+}
+
+/// Segment rows merged into one row per `street_id`, in order of first appearance: lines
+/// concatenated, lengths summed and segments counted. Rows without an id or line geometry
+/// (streets files) pass through unchanged.
+fn merge_segments(rows: Vec<ExportRow>) -> Vec<ExportRow> {
+    let mut merged: Vec<ExportRow> = Vec::new();
+    let mut by_id: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+        let (Some(id), ExportGeometry::Lines(_)) = (&row.street_id, &row.geometry) else {
+            merged.push(row);
+            continue;
+        };
+        match by_id.get(id) {
+            Some(&i) => {
+                let street = &mut merged[i];
+                if let (ExportGeometry::Lines(lines), ExportGeometry::Lines(more)) = (&mut street.geometry, row.geometry) {
+                    lines.extend(more);
+                }
+                street.length_km = match (street.length_km, row.length_km) {
+                    (Some(a), Some(b)) => Some(a + b),
+                    (a, b) => a.or(b),
+                };
+                street.num_segments = street.num_segments.map(|n| n + 1);
+            }
+            None => {
+                by_id.insert(id.clone(), merged.len());
+                merged.push(ExportRow { num_segments: Some(1), ..row });
+            }
+        }
+    }
+    merged
+}
+
+/// Rows of `state` (any state when `None`), then a seeded sample of `sample` of them
+fn select_rows(rows: Vec<ExportRow>, state: Option<&str>, sample: Option<usize>, seed: u64) -> Vec<ExportRow> {
+    let rows: Vec<ExportRow> = rows
+        .into_iter()
+        .filter(|row| state.is_none_or(|s| s.eq_ignore_ascii_case(&row.state)))
+        .collect();
+    let Some(n) = sample else {
+        return rows;
+    };
+    let mut keep = SplitMix64::new(seed).sample_indices(rows.len(), n);
+    keep.sort_unstable();
+    let mut keep = keep.into_iter().peekable();
+    rows.into_iter()
+        .enumerate()
+        .filter_map(|(i, row)| keep.next_if_eq(&i).map(|_| row))
+        .collect()
+}
+
+/// `export <streets.parquet|segments.parquet...> --format geojsonseq|kml|wkt-csv -o <out> [--state S] [--sample N] [--seed S] [--per-segment]`
+pub fn run_export(
+    paths: &[PathBuf],
+    format: ExportFormat,
+    output_path: &Path,
+    state: Option<&str>,
+    sample: Option<usize>,
+    seed: u64,
+    per_segment: bool,
+) -> Result<()> {
+    if per_segment && format != ExportFormat::WktCsv {
+        anyhow::bail!("export: --per-segment only applies to --format wkt-csv");
+    }
+    let df = load_streets(paths)?;
+    let (rows, type_name) = read_export_rows(&df)?;
+    let rows = if format == ExportFormat::WktCsv && !per_segment { merge_segments(rows) } else { rows };
+    let total = rows.len();
+    let rows = select_rows(rows, state, sample, seed);
+    if rows.len() < total {
+        println!("Selected {} of {} features", rows.len(), total);
+    }
+
+    let file = std::fs::File::create(output_path).with_context(|| format!("Failed to create {}", output_path.display()))?;
+    let mut out = BufWriter::new(file);
+    let title = output_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    match format {
+        ExportFormat::GeoJsonSeq => write_geojsonseq(&rows, type_name.as_deref(), &mut out)?,
+        ExportFormat::Kml if output_path.extension().is_some_and(|e| e.eq_ignore_ascii_case("kmz")) => {
+            let mut zip = ::zip::ZipWriter::new(&mut out);
+            let options = ::zip::write::SimpleFileOptions::default().compression_method(::zip::CompressionMethod::Deflated);
+            zip.start_file("doc.kml", options)?;
+            write_kml(&rows, &title, &mut zip)?;
+            zip.finish()?;
+        }
+        ExportFormat::Kml => write_kml(&rows, &title, &mut out)?,
+        ExportFormat::WktCsv => write_wkt_csv(&rows, type_name.as_deref(), &mut out)?,
+    }
+    out.flush()?;
+
+    println!("Exported {} features to {}", rows.len(), output_path.display());
+    Ok(())
+}
@@ -0,0 +1,67 @@
+//! Fast non-cryptographic hashing for node-ID maps.
+//!
+//! The node-matching passes hash every node reference of every named way, hundreds of millions
+//! on large states, and std's SipHash dominates them. Node IDs come from the input file rather
+//! than an attacker, so HashDoS resistance buys nothing here; [`FxHasher`] (the multiply-rotate
+//! hash rustc uses) is several times faster on integer keys. Use [`NodeMap`] rather than naming
+//! the hasher, so it can be swapped in one place; plain membership sets of node IDs are
+//! [`NodeIdSet`](crate::node_set::NodeIdSet)s.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+/// One multiply and rotate per word written
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    #[inline]
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0u8; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.add(u64::from_le_bytes(word));
+        }
+    }
+
+    #[inline]
+    fn write_u32(&mut self, n: u32) {
+        self.add(n as u64);
+    }
+
+    #[inline]
+    fn write_u64(&mut self, n: u64) {
+        self.add(n);
+    }
+
+    #[inline]
+    fn write_i64(&mut self, n: i64) {
+        self.add(n as u64);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, n: usize) {
+        self.add(n as u64);
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+pub type FastBuildHasher = BuildHasherDefault<FxHasher>;
+
+/// Map keyed by OSM node (or way) ID; create with `NodeMap::default()` or `collect()`
+pub type NodeMap<V> = HashMap<i64, V, FastBuildHasher>;
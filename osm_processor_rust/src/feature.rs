@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use crate::error::{ProcessorError, Result};
+use crate::{speed, StreetSegment};
+
+/// Trail-specific tags carried through to the paths dataset
+const TRAIL_KEYS: [&str; 4] = ["sac_scale", "operator", "surface", "trail_visibility"];
+
+/// Keys that describe bike infrastructure on a road
+const CYCLEWAY_KEYS: [&str; 4] = ["cycleway", "cycleway:both", "cycleway:left", "cycleway:right"];
+
+/// Which kind of named linear feature the segment/grouping pipeline extracts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// Named `highway=*` ways (the default street dataset)
+    Highway,
+    /// Named `railway=rail|light_rail|subway` lines
+    Railway,
+    /// Named `waterway=river|stream|canal` lines
+    Waterway,
+    /// Named `highway=cycleway` ways and roads carrying `cycleway=*` tags
+    Cycling,
+    /// Named trails and footpaths (`highway=footway|path|track`)
+    Paths,
+    /// Named highways with per-street speed limit columns
+    Speed,
+}
+
+/// A feature-specific per-street value, emitted as an extra output column
+#[derive(Debug, Clone)]
+pub enum ExtraValue {
+    Bool(bool),
+    Float(Option<f64>),
+    Text(Option<String>),
+}
+
+impl Feature {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "highway" | "streets" => Ok(Feature::Highway),
+            "railway" => Ok(Feature::Railway),
+            "waterway" => Ok(Feature::Waterway),
+            "cycling" => Ok(Feature::Cycling),
+            "paths" => Ok(Feature::Paths),
+            "speed" => Ok(Feature::Speed),
+            other => Err(ProcessorError::InvalidConfig(format!(
+                "Unknown feature: {} (expected highway, railway, waterway, cycling, paths or speed)",
+                other
+            ))),
+        }
+    }
+
+    /// The OSM key whose value classifies a way
+    pub fn type_key(&self) -> &'static str {
+        match self {
+            Feature::Highway | Feature::Cycling | Feature::Paths | Feature::Speed => "highway",
+            Feature::Railway => "railway",
+            Feature::Waterway => "waterway",
+        }
+    }
+
+    /// The way's type value if it belongs to this feature; `tag` looks up a tag value by key
+    pub fn way_type<'a>(&self, tag: impl Fn(&str) -> Option<&'a str>) -> Option<&'a str> {
+        let value = tag(self.type_key())?;
+        let accepted = match self {
+            Feature::Highway | Feature::Speed => true,
+            Feature::Railway => matches!(value, "rail" | "light_rail" | "subway"),
+            Feature::Waterway => matches!(value, "river" | "stream" | "canal"),
+            Feature::Paths => matches!(value, "footway" | "path" | "track"),
+            Feature::Cycling => {
+                value == "cycleway" || CYCLEWAY_KEYS.iter().any(|key| tag(key).is_some())
+            }
+        };
+        accepted.then_some(value)
+    }
+
+    /// Name of the type column in the output
+    pub fn type_column(&self) -> &'static str {
+        match self {
+            Feature::Highway | Feature::Cycling | Feature::Paths | Feature::Speed => "highway_type",
+            Feature::Railway => "railway_type",
+            Feature::Waterway => "waterway_type",
+        }
+    }
+
+    /// Suffix used in default output file names (`<state>_<suffix>.parquet`)
+    pub fn output_kind(&self) -> &'static str {
+        match self {
+            Feature::Highway => "streets",
+            Feature::Railway => "railways",
+            Feature::Waterway => "waterways",
+            Feature::Cycling => "cycling",
+            Feature::Paths => "paths",
+            Feature::Speed => "speeds",
+        }
+    }
+
+    /// Feature-specific columns computed from a street's segments
+    pub fn extra_columns(&self, segs: &[&StreetSegment]) -> Vec<(&'static str, ExtraValue)> {
+        match self {
+            Feature::Cycling => cycling_columns(segs),
+            Feature::Paths => trail_columns(segs),
+            Feature::Speed => speed::speed_columns(segs),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Kind of bike infrastructure on a segment: "cycleway" for dedicated ways,
+/// otherwise the first meaningful `cycleway*=*` value (lane, track, shared_lane, ...)
+fn cycle_infra(seg: &StreetSegment) -> Option<&str> {
+    if seg.highway_type == "cycleway" {
+        return Some("cycleway");
+    }
+    CYCLEWAY_KEYS
+        .iter()
+        .filter_map(|key| seg.tags.get(key))
+        .find(|value| !matches!(*value, "no" | "none" | "separate"))
+}
+
+fn cycling_columns(segs: &[&StreetSegment]) -> Vec<(&'static str, ExtraValue)> {
+    let total_km: f64 = segs.iter().map(|s| s.length_km).sum();
+    let infra: Vec<(Option<&str>, f64)> = segs.iter().map(|s| (cycle_infra(s), s.length_km)).collect();
+
+    let infra_km: f64 = infra.iter().filter(|(kind, _)| kind.is_some()).map(|(_, km)| km).sum();
+    let has = |wanted: &str| infra.iter().any(|(kind, _)| *kind == Some(wanted));
+
+    vec![
+        ("cycle_infra_km", ExtraValue::Float(Some(infra_km))),
+        ("cycle_infra_share", ExtraValue::Float(Some(if total_km > 0.0 { infra_km / total_km } else { 0.0 }))),
+        ("has_cycleway", ExtraValue::Bool(has("cycleway"))),
+        ("has_bike_lane", ExtraValue::Bool(has("lane"))),
+        ("has_cycle_track", ExtraValue::Bool(has("track"))),
+    ]
+}
+
+/// Most common value of `key` across the segments, if any segment carries it
+fn most_common_tag(segs: &[&StreetSegment], key: &str) -> Option<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for seg in segs {
+        if let Some(value) = seg.tags.get(key) {
+            *counts.entry(value).or_default() += 1;
+        }
+    }
+    // Break ties by value so the result doesn't depend on hash order
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(value, _)| value.to_string())
+}
+
+fn trail_columns(segs: &[&StreetSegment]) -> Vec<(&'static str, ExtraValue)> {
+    TRAIL_KEYS
+        .iter()
+        .map(|&key| (key, ExtraValue::Text(most_common_tag(segs, key))))
+        .collect()
+}
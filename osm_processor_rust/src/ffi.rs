@@ -0,0 +1,165 @@
+//! Minimal C ABI for driving the processor from other languages (e.g. an R package via C).
+//! The matching declarations are in `include/osm_processor.h`.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::progress::{Phase, ProgressSink};
+use crate::{write_parquet, Feature, OsmStreetProcessor, ProcessorError};
+
+pub const OSM_OK: c_int = 0;
+pub const OSM_ERR_INVALID_ARGUMENT: c_int = 1;
+pub const OSM_ERR_PROCESSING: c_int = 2;
+pub const OSM_ERR_PANIC: c_int = 3;
+
+/// `(phase, done, total, user_data)`; `total` is 0 when unknown
+pub type OsmProgressCallback = Option<extern "C" fn(*const c_char, u64, u64, *mut c_void)>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|slot| *slot.borrow_mut() = Some(message));
+}
+
+fn phase_name(phase: Phase) -> &'static CStr {
+    match phase {
+        Phase::FindNodes => c"find_nodes",
+        Phase::LoadNodes => c"load_nodes",
+        Phase::ExtractSegments => c"extract_segments",
+        Phase::Grouping => c"grouping",
+    }
+}
+
+/// Progress between callbacks within a phase: about one PBF block of nodes, so the node pass
+/// calls back at most once per block and the per-way phases every few thousand ways
+const CALLBACK_STEP: u64 = 8000;
+
+/// Forwards progress to a C callback, at phase start and end and each time `done` passes a
+/// multiple of [`CALLBACK_STEP`]. The caller guarantees `user_data` may be used from
+/// any thread for the duration of the call.
+struct CallbackProgress {
+    callback: extern "C" fn(*const c_char, u64, u64, *mut c_void),
+    user_data: *mut c_void,
+    done: AtomicU64,
+    total: AtomicU64,
+}
+
+unsafe impl Send for CallbackProgress {}
+unsafe impl Sync for CallbackProgress {}
+
+impl ProgressSink for CallbackProgress {
+    fn phase_started(&self, phase: Phase, total: Option<u64>) {
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total.unwrap_or(0), Ordering::Relaxed);
+        (self.callback)(phase_name(phase).as_ptr(), 0, total.unwrap_or(0), self.user_data);
+    }
+
+    fn advanced(&self, phase: Phase, n: u64) {
+        let done = self.done.fetch_add(n, Ordering::Relaxed) + n;
+        if done / CALLBACK_STEP == (done - n) / CALLBACK_STEP {
+            return;
+        }
+        (self.callback)(phase_name(phase).as_ptr(), done, self.total.load(Ordering::Relaxed), self.user_data);
+    }
+
+    fn phase_finished(&self, phase: Phase) {
+        let total = self.total.load(Ordering::Relaxed);
+        let done = self.done.load(Ordering::Relaxed);
+        (self.callback)(phase_name(phase).as_ptr(), done.max(total), total, self.user_data);
+    }
+}
+
+/// Borrow a required UTF-8 string argument
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, String> {
+    if ptr.is_null() {
+        return Err(format!("{} must not be NULL", name));
+    }
+    CStr::from_ptr(ptr).to_str().map_err(|_| format!("{} is not valid UTF-8", name))
+}
+
+/// Extract streets from `pbf_path` and write them to `output_path` as parquet.
+///
+/// `feature` may be NULL (highways) or one of the `--feature` names. `progress` may be NULL.
+/// Returns `OSM_OK` or an `OSM_ERR_*` code; on error, `osm_last_error_message` describes it.
+///
+/// # Safety
+///
+/// String arguments must be NULL or valid NUL-terminated strings. If `progress` is given,
+/// it and `user_data` must be safe to call/use from multiple threads until this returns.
+#[no_mangle]
+pub unsafe extern "C" fn osm_process_to_parquet(
+    pbf_path: *const c_char,
+    state: *const c_char,
+    output_path: *const c_char,
+    distance_threshold_km: f64,
+    feature: *const c_char,
+    progress: OsmProgressCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    let args = (|| {
+        let pbf_path = str_arg(pbf_path, "pbf_path")?;
+        let state = str_arg(state, "state")?;
+        let output_path = str_arg(output_path, "output_path")?;
+        let feature = if feature.is_null() {
+            Feature::Highway
+        } else {
+            Feature::parse(str_arg(feature, "feature")?).map_err(|e| e.to_string())?
+        };
+        Ok::<_, String>((pbf_path, state, output_path, feature))
+    })();
+    let (pbf_path, state, output_path, feature) = match args {
+        Ok(args) => args,
+        Err(message) => {
+            set_last_error(message);
+            return OSM_ERR_INVALID_ARGUMENT;
+        }
+    };
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let mut builder = OsmStreetProcessor::builder()
+            .input(pbf_path)
+            .state(state)
+            .distance_threshold_km(distance_threshold_km)
+            .feature(feature);
+        if let Some(callback) = progress {
+            builder = builder.progress(Arc::new(CallbackProgress {
+                callback,
+                user_data,
+                done: AtomicU64::new(0),
+                total: AtomicU64::new(0),
+            }));
+        }
+        let mut df = builder.build()?.to_dataframe()?;
+        write_parquet(&mut df, Path::new(output_path))
+    }));
+
+    match result {
+        Ok(Ok(())) => OSM_OK,
+        Ok(Err(err)) => {
+            let code = match err {
+                ProcessorError::InvalidConfig(_) => OSM_ERR_INVALID_ARGUMENT,
+                _ => OSM_ERR_PROCESSING,
+            };
+            set_last_error(err.to_string());
+            code
+        }
+        Err(_) => {
+            set_last_error("panic during processing".to_string());
+            OSM_ERR_PANIC
+        }
+    }
+}
+
+/// Message for the last error on this thread, or NULL. Valid until the next call on the
+/// same thread; do not free.
+#[no_mangle]
+pub extern "C" fn osm_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|slot| slot.borrow().as_ref().map_or(std::ptr::null(), |message| message.as_ptr()))
+}
@@ -0,0 +1,314 @@
+use anyhow::{Context, Result};
+use polars::prelude::*;
+use rstar::primitives::GeomWithData;
+use rstar::RTree;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::matching::read_rows;
+use osm_processor_rust::haversine_km;
+use osm_processor_rust::name_parser::match_key;
+use osm_processor_rust::stats::load_streets;
+
+/// File signature and format version of a geocoding index
+const MAGIC: &[u8; 8] = b"OSMGEO\x00\x01";
+
+/// One street in the index, pointing into the index's string tables
+#[derive(Debug, Clone, Copy)]
+pub struct IndexedStreet {
+    /// Position in the sorted [`match_key`] table
+    pub key: u32,
+    /// Position in the street name table
+    pub name: u32,
+    /// Position in the state table
+    pub state: u16,
+    pub lat: f64,
+    pub lon: f64,
+    pub length_km: f32,
+}
+
+/// Street-level geocoding index: sorted, front-coded name keys (a flattened trie, so exact and
+/// prefix lookups are binary searches) over streets ordered by key, plus an R-tree over their
+/// representative points that is bulk-loaded when the file is read
+pub struct GeocodeIndex {
+    keys: Vec<String>,
+    names: Vec<String>,
+    states: Vec<String>,
+    streets: Vec<IndexedStreet>,
+    tree: RTree<GeomWithData<[f64; 3], usize>>,
+}
+
+/// Point on the unit sphere, as in `nearest`
+fn unit_vector(lat: f64, lon: f64) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// Sorted distinct values
+fn string_table<'a>(values: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut table: Vec<String> = values.map(str::to_string).collect();
+    table.sort_unstable();
+    table.dedup();
+    table
+}
+
+fn position(table: &[String], value: &str) -> usize {
+    table.binary_search_by(|probe| probe.as_str().cmp(value)).expect("value is in its table")
+}
+
+/// Write a sorted string table with front coding: each entry stores the length of the prefix
+/// it shares with the previous one and the remaining bytes
+fn write_table(out: &mut impl Write, table: &[String]) -> std::io::Result<()> {
+    out.write_all(&(table.len() as u32).to_le_bytes())?;
+    let mut previous: &[u8] = &[];
+    for value in table {
+        let bytes = value.as_bytes();
+        let shared = previous.iter().zip(bytes).take_while(|(a, b)| a == b).count().min(u16::MAX as usize);
+        let suffix = &bytes[shared..];
+        out.write_all(&(shared as u16).to_le_bytes())?;
+        out.write_all(&(suffix.len() as u32).to_le_bytes())?;
+        out.write_all(suffix)?;
+        previous = bytes;
+    }
+    Ok(())
+}
+
+fn read_u16(input: &mut impl Read) -> std::io::Result<u16> {
+    let mut buf = [0; 2];
+    input.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32(input: &mut impl Read) -> std::io::Result<u32> {
+    let mut buf = [0; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_f64(input: &mut impl Read) -> std::io::Result<f64> {
+    let mut buf = [0; 8];
+    input.read_exact(&mut buf)?;
+    Ok(f64::from_le_bytes(buf))
+}
+
+fn read_table(input: &mut impl Read) -> Result<Vec<String>> {
+    let len = read_u32(input)? as usize;
+    let mut table = Vec::with_capacity(len);
+    let mut previous: Vec<u8> = Vec::new();
+    for _ in 0..len {
+        let shared = read_u16(input)? as usize;
+        let mut bytes = previous.get(..shared).context("Corrupt geocoding index: bad prefix length")?.to_vec();
+        let mut suffix = vec![0; read_u32(input)? as usize];
+        input.read_exact(&mut suffix)?;
+        bytes.extend_from_slice(&suffix);
+        table.push(String::from_utf8(bytes.clone()).context("Corrupt geocoding index: invalid UTF-8")?);
+        previous = bytes;
+    }
+    Ok(table)
+}
+
+impl GeocodeIndex {
+    /// Index the streets of one or more streets parquet files
+    pub fn from_dataframe(df: &DataFrame) -> Result<Self> {
+        let rows = read_rows(df)?;
+        let row_keys: Vec<String> = rows.iter().map(|row| match_key(&row.street_name)).collect();
+        let keys = string_table(row_keys.iter().map(String::as_str));
+        let names = string_table(rows.iter().map(|row| row.street_name.as_str()));
+        let states = string_table(rows.iter().map(|row| row.state.as_str()));
+        if states.len() > u16::MAX as usize {
+            anyhow::bail!("Too many states for a geocoding index: {}", states.len());
+        }
+
+        let mut streets: Vec<IndexedStreet> = rows
+            .iter()
+            .zip(&row_keys)
+            .filter(|(row, _)| row.lat.is_finite() && row.lon.is_finite())
+            .map(|(row, key)| IndexedStreet {
+                key: position(&keys, key) as u32,
+                name: position(&names, &row.street_name) as u32,
+                state: position(&states, &row.state) as u16,
+                lat: row.lat,
+                lon: row.lon,
+                length_km: row.length_km as f32,
+            })
+            .collect();
+        streets.sort_by_key(|street| (street.key, street.state, street.name));
+
+        Ok(Self::assemble(keys, names, states, streets))
+    }
+
+    fn assemble(keys: Vec<String>, names: Vec<String>, states: Vec<String>, streets: Vec<IndexedStreet>) -> Self {
+        let points = streets
+            .iter()
+            .enumerate()
+            .map(|(i, street)| GeomWithData::new(unit_vector(street.lat, street.lon), i))
+            .collect();
+        GeocodeIndex { keys, names, states, streets, tree: RTree::bulk_load(points) }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        out.write_all(MAGIC)?;
+        write_table(&mut out, &self.keys)?;
+        write_table(&mut out, &self.names)?;
+        write_table(&mut out, &self.states)?;
+        out.write_all(&(self.streets.len() as u32).to_le_bytes())?;
+        for street in &self.streets {
+            out.write_all(&street.key.to_le_bytes())?;
+            out.write_all(&street.name.to_le_bytes())?;
+            out.write_all(&street.state.to_le_bytes())?;
+            out.write_all(&street.lat.to_le_bytes())?;
+            out.write_all(&street.lon.to_le_bytes())?;
+            out.write_all(&street.length_km.to_le_bytes())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut input = BufReader::new(file);
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            anyhow::bail!("{} is not a geocoding index (build one with geocode-index)", path.display());
+        }
+        let keys = read_table(&mut input)?;
+        let names = read_table(&mut input)?;
+        let states = read_table(&mut input)?;
+
+        let len = read_u32(&mut input)? as usize;
+        let mut streets = Vec::with_capacity(len);
+        for _ in 0..len {
+            let key = read_u32(&mut input)?;
+            let name = read_u32(&mut input)?;
+            let state = read_u16(&mut input)?;
+            let lat = read_f64(&mut input)?;
+            let lon = read_f64(&mut input)?;
+            let length_km = f32::from_bits(read_u32(&mut input)?);
+            if key as usize >= keys.len() || name as usize >= names.len() || state as usize >= states.len() {
+                anyhow::bail!("Corrupt geocoding index: street entry out of range");
+            }
+            streets.push(IndexedStreet { key, name, state, lat, lon, length_km });
+        }
+
+        Ok(Self::assemble(keys, names, states, streets))
+    }
+
+    pub fn name(&self, street: &IndexedStreet) -> &str {
+        &self.names[street.name as usize]
+    }
+
+    pub fn state(&self, street: &IndexedStreet) -> &str {
+        &self.states[street.state as usize]
+    }
+
+    /// Keys equal to `name`'s [`match_key`] or, if there is none, starting with it, as a range of
+    /// positions in the key table; the flag is whether the match is exact
+    fn key_range(&self, name: &str) -> (Range<usize>, bool) {
+        let key = match_key(name);
+        let start = self.keys.partition_point(|k| k.as_str() < key.as_str());
+        let exact = self.keys.get(start).is_some_and(|k| *k == key);
+        let end = if exact {
+            start + 1
+        } else {
+            start + self.keys[start..].partition_point(|k| k.starts_with(&key))
+        };
+        (start..end, exact)
+    }
+
+    fn in_state(&self, street: &IndexedStreet, state: Option<&str>) -> bool {
+        state.is_none_or(|s| self.state(street).eq_ignore_ascii_case(s))
+    }
+
+    /// Streets whose name matches `name` exactly after [`match_key`] normalization or, if none
+    /// does, whose normalized name starts with it; optionally limited to one state
+    /// (case-insensitive). The flag is whether the matches are exact.
+    pub fn lookup(&self, name: &str, state: Option<&str>) -> (Vec<&IndexedStreet>, bool) {
+        let (keys, exact) = self.key_range(name);
+        // Streets are ordered by key, so each key range is one contiguous run
+        let first = self.streets.partition_point(|s| (s.key as usize) < keys.start);
+        let last = self.streets.partition_point(|s| (s.key as usize) < keys.end);
+        let hits = self.streets[first..last].iter().filter(|street| self.in_state(street, state)).collect();
+        (hits, exact)
+    }
+
+    /// Like [`lookup`](Self::lookup), but the `limit` matches closest to (lat, lon), nearest
+    /// first, with distances in km
+    pub fn lookup_near(
+        &self,
+        name: &str,
+        state: Option<&str>,
+        (lat, lon): (f64, f64),
+        limit: usize,
+    ) -> (Vec<(&IndexedStreet, f64)>, bool) {
+        let (keys, exact) = self.key_range(name);
+        let hits = self
+            .tree
+            .nearest_neighbor_iter(&unit_vector(lat, lon))
+            .map(|point| &self.streets[point.data])
+            .filter(|street| keys.contains(&(street.key as usize)) && self.in_state(street, state))
+            .take(limit)
+            .map(|street| (street, haversine_km((lat, lon), (street.lat, street.lon))))
+            .collect();
+        (hits, exact)
+    }
+}
+
+/// `geocode-index <streets.parquet...> -o <index.geo>`
+pub fn run_geocode_index(paths: &[PathBuf], output_path: &Path) -> Result<()> {
+    let index = GeocodeIndex::from_dataframe(&load_streets(paths)?)?;
+    index.write(output_path)?;
+    let size = std::fs::metadata(output_path)?.len();
+    println!(
+        "Indexed {} streets ({} distinct names) into {} ({:.1} MB)",
+        index.streets.len(),
+        index.keys.len(),
+        output_path.display(),
+        size as f64 / 1_000_000.0
+    );
+    Ok(())
+}
+
+/// `geocode "<name>, <state>" --index <index.geo> [--near LAT,LON] [--limit N]`
+///
+/// Candidates are listed longest first, or nearest first with `near`.
+pub fn run_geocode(query: &str, index_path: &Path, near: Option<(f64, f64)>, limit: usize) -> Result<()> {
+    let index = GeocodeIndex::read(index_path)?;
+    let (name, state) = match query.rsplit_once(',') {
+        Some((name, state)) => (name.trim(), Some(state.trim()).filter(|s| !s.is_empty())),
+        None => (query.trim(), None),
+    };
+
+    let (hits, exact): (Vec<(&IndexedStreet, Option<f64>)>, bool) = match near {
+        Some(point) => {
+            let (hits, exact) = index.lookup_near(name, state, point, limit);
+            (hits.into_iter().map(|(street, km)| (street, Some(km))).collect(), exact)
+        }
+        None => {
+            let (mut hits, exact) = index.lookup(name, state);
+            hits.sort_by(|a, b| b.length_km.total_cmp(&a.length_km));
+            hits.truncate(limit);
+            (hits.into_iter().map(|street| (street, None)).collect(), exact)
+        }
+    };
+
+    let mut out = DataFrame::new(vec![
+        Series::new("street_name", hits.iter().map(|h| index.name(h.0)).collect::<Vec<_>>()),
+        Series::new("state", hits.iter().map(|h| index.state(h.0)).collect::<Vec<_>>()),
+        Series::new("lat", hits.iter().map(|h| h.0.lat).collect::<Vec<_>>()),
+        Series::new("lon", hits.iter().map(|h| h.0.lon).collect::<Vec<_>>()),
+        Series::new("length_km", hits.iter().map(|h| h.0.length_km as f64).collect::<Vec<_>>()),
+    ])?;
+    if near.is_some() {
+        out.with_column(Series::new("distance_km", hits.iter().map(|h| h.1).collect::<Vec<_>>()))?;
+    }
+
+    let kind = if exact { "exact" } else { "prefix" };
+    let order = if near.is_some() { "nearest" } else { "longest" };
+    println!("{} candidates for \"{}\" ({} match, {} first):", hits.len(), query, kind, order);
+    println!("{}", out);
+    Ok(())
+}
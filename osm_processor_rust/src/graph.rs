@@ -0,0 +1,240 @@
+use osmpbf::{Element, ElementReader};
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::{io_error, pbf_error, Result};
+use crate::fast_hash::NodeMap;
+use crate::node_set::NodeIdSet;
+use crate::{haversine_km, load_node_coords, round_coord, write_parquet, ConsoleProgress};
+
+/// A highway way as needed for the routing graph
+struct GraphWay {
+    id: i64,
+    refs: Vec<i64>,
+    highway_type: String,
+    name: Option<String>,
+    oneway: Oneway,
+    maxspeed: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Oneway {
+    No,
+    Forward,
+    Reverse,
+}
+
+impl Oneway {
+    /// Interpret `oneway=*`, falling back to the implicit oneway of motorways and roundabouts
+    fn from_tags(tags: &HashMap<&str, &str>) -> Self {
+        match tags.get("oneway").copied() {
+            Some("yes") | Some("1") | Some("true") => Oneway::Forward,
+            Some("-1") | Some("reverse") => Oneway::Reverse,
+            Some(_) => Oneway::No,
+            None => {
+                let implicit = tags.get("highway") == Some(&"motorway")
+                    || tags.get("junction") == Some(&"roundabout");
+                if implicit { Oneway::Forward } else { Oneway::No }
+            }
+        }
+    }
+}
+
+/// A routable edge between two graph nodes
+struct Edge {
+    way_id: i64,
+    from_node: i64,
+    to_node: i64,
+    length_km: f64,
+    highway_type: String,
+    name: Option<String>,
+    oneway: bool,
+    maxspeed: Option<String>,
+}
+
+/// Pass 1: collect all highway ways and count how many ways use each node
+fn collect_graph_ways(pbf_path: &Path) -> Result<(Vec<GraphWay>, HashMap<i64, u32>)> {
+    println!("Pass 1: Collecting highway ways...");
+    let reader = ElementReader::from_path(pbf_path)
+        .map_err(pbf_error(pbf_path))?;
+
+    let mut ways = Vec::new();
+    let mut node_use: HashMap<i64, u32> = HashMap::new();
+
+    reader.for_each(|element| {
+        if let Element::Way(way) = element {
+            let tags: HashMap<&str, &str> = way.tags().collect();
+
+            if let Some(&highway_type) = tags.get("highway") {
+                let refs: Vec<i64> = way.refs().collect();
+                if refs.len() < 2 {
+                    return;
+                }
+                for &node_id in &refs {
+                    *node_use.entry(node_id).or_default() += 1;
+                }
+                // Way endpoints are always graph nodes
+                *node_use.entry(refs[0]).or_default() += 1;
+                *node_use.entry(refs[refs.len() - 1]).or_default() += 1;
+
+                ways.push(GraphWay {
+                    id: way.id(),
+                    refs,
+                    highway_type: highway_type.to_string(),
+                    name: tags.get("name").map(|s| s.to_string()),
+                    oneway: Oneway::from_tags(&tags),
+                    maxspeed: tags.get("maxspeed").map(|s| s.to_string()),
+                });
+            }
+        }
+    })
+    .map_err(pbf_error(pbf_path))?;
+
+    println!("  Found {} highway ways using {} nodes", ways.len(), node_use.len());
+    Ok((ways, node_use))
+}
+
+/// Split ways into edges at graph nodes (nodes used more than once).
+/// Nodes missing from the extract also break an edge, since its length would be unknown.
+fn build_edges(
+    ways: &[GraphWay],
+    node_use: &HashMap<i64, u32>,
+    node_coords: &NodeMap<(f64, f64)>,
+) -> Vec<Edge> {
+    let mut edges = Vec::new();
+
+    for way in ways {
+        let mut start: Option<i64> = None;
+        let mut length_km = 0.0;
+        let mut prev: Option<(f64, f64)> = None;
+
+        for &node_id in &way.refs {
+            let Some(&coord) = node_coords.get(&node_id) else {
+                start = None;
+                prev = None;
+                length_km = 0.0;
+                continue;
+            };
+
+            if let Some(p) = prev {
+                length_km += haversine_km(p, coord);
+            }
+            prev = Some(coord);
+
+            let is_graph_node = node_use.get(&node_id).copied().unwrap_or(0) > 1;
+            if !is_graph_node {
+                continue;
+            }
+
+            if let Some(from) = start {
+                let (from_node, to_node) = match way.oneway {
+                    Oneway::Reverse => (node_id, from),
+                    _ => (from, node_id),
+                };
+                edges.push(Edge {
+                    way_id: way.id,
+                    from_node,
+                    to_node,
+                    length_km,
+                    highway_type: way.highway_type.clone(),
+                    name: way.name.clone(),
+                    oneway: way.oneway != Oneway::No,
+                    maxspeed: way.maxspeed.clone(),
+                });
+            }
+
+            start = Some(node_id);
+            length_km = 0.0;
+        }
+    }
+
+    edges
+}
+
+fn nodes_to_dataframe(
+    edges: &[Edge],
+    node_coords: &NodeMap<(f64, f64)>,
+    coord_precision: Option<u32>,
+) -> Result<DataFrame> {
+    let mut degree: HashMap<i64, u32> = HashMap::new();
+    for edge in edges {
+        *degree.entry(edge.from_node).or_default() += 1;
+        *degree.entry(edge.to_node).or_default() += 1;
+    }
+
+    let mut node_ids: Vec<i64> = degree.keys().copied().collect();
+    node_ids.sort_unstable();
+
+    let df = DataFrame::new(vec![
+        Series::new("node_id", &node_ids),
+        Series::new("lat", node_ids.iter().map(|id| round_coord(node_coords[id].0, coord_precision)).collect::<Vec<_>>()),
+        Series::new("lon", node_ids.iter().map(|id| round_coord(node_coords[id].1, coord_precision)).collect::<Vec<_>>()),
+        Series::new("degree", node_ids.iter().map(|id| degree[id]).collect::<Vec<_>>()),
+    ])?;
+
+    Ok(df)
+}
+
+fn edges_to_dataframe(edges: &[Edge]) -> Result<DataFrame> {
+    let df = DataFrame::new(vec![
+        Series::new("edge_id", (0..edges.len() as u64).collect::<Vec<_>>()),
+        Series::new("way_id", edges.iter().map(|e| e.way_id).collect::<Vec<_>>()),
+        Series::new("from_node", edges.iter().map(|e| e.from_node).collect::<Vec<_>>()),
+        Series::new("to_node", edges.iter().map(|e| e.to_node).collect::<Vec<_>>()),
+        Series::new("length_km", edges.iter().map(|e| e.length_km).collect::<Vec<_>>()),
+        Series::new("highway_type", edges.iter().map(|e| e.highway_type.as_str()).collect::<Vec<_>>()),
+        Series::new("name", edges.iter().map(|e| e.name.as_deref()).collect::<Vec<_>>()),
+        Series::new("oneway", edges.iter().map(|e| e.oneway).collect::<Vec<_>>()),
+        Series::new("maxspeed", edges.iter().map(|e| e.maxspeed.as_deref()).collect::<Vec<_>>()),
+    ])?;
+
+    Ok(df)
+}
+
+/// Graph mode: export a routable network as nodes and edges parquet files
+pub fn process_graph_to_parquet(
+    pbf_path: &Path,
+    state_name: &str,
+    output_dir: Option<PathBuf>,
+    coord_precision: Option<u32>,
+) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("OSM ROUTING GRAPH TO PARQUET (Rust)");
+    println!("{}", "=".repeat(70));
+    println!("Input file:  {}", pbf_path.display());
+    println!("State:       {}", state_name);
+    println!("{}", "=".repeat(70));
+
+    let output_dir = output_dir.unwrap_or_else(|| {
+        let mut path = pbf_path.parent().unwrap().parent().unwrap().to_path_buf();
+        path.push("graphs");
+        path
+    });
+    std::fs::create_dir_all(&output_dir).map_err(io_error(&output_dir))?;
+
+    let (ways, node_use) = collect_graph_ways(pbf_path)?;
+
+    println!("Pass 2: Loading highway node coordinates...");
+    let wanted_nodes: NodeIdSet = node_use.keys().copied().collect();
+    let node_coords = load_node_coords(pbf_path, &wanted_nodes, &ConsoleProgress::default())?;
+
+    println!("Building edges...");
+    let edges = build_edges(&ways, &node_use, &node_coords);
+    let mut nodes_df = nodes_to_dataframe(&edges, &node_coords, coord_precision)?;
+    let mut edges_df = edges_to_dataframe(&edges)?;
+    println!("  {} nodes, {} edges", nodes_df.height(), edges_df.height());
+
+    let nodes_path = output_dir.join(format!("{}_graph_nodes.parquet", state_name));
+    let edges_path = output_dir.join(format!("{}_graph_edges.parquet", state_name));
+
+    println!("\nSaving to: {}", nodes_path.display());
+    write_parquet(&mut nodes_df, &nodes_path)?;
+    println!("Saving to: {}", edges_path.display());
+    write_parquet(&mut edges_df, &edges_path)?;
+
+    println!("Done!");
+    println!("{}", "=".repeat(70));
+
+    Ok(())
+}
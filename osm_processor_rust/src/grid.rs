@@ -0,0 +1,148 @@
+use polars::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::error::{io_error, ProcessorError, Result};
+use crate::feature::Feature;
+use crate::names::NamePolicy;
+use crate::{
+    collect_highway_nodes, default_output_path, extract_street_segments, haversine_km, write_parquet,
+    ConsoleProgress,
+};
+
+/// Degrees of latitude per km
+const DEG_LAT_PER_KM: f64 = 1.0 / 111.32;
+
+/// Roughly equal-area grid: rows are `cell_km` tall, and each row is split into cells
+/// `cell_km` wide at the row's centre latitude
+struct Grid {
+    cell_km: f64,
+}
+
+impl Grid {
+    fn row_height_deg(&self) -> f64 {
+        self.cell_km * DEG_LAT_PER_KM
+    }
+
+    fn col_width_deg(&self, row: i64) -> f64 {
+        let center_lat = (row as f64 + 0.5) * self.row_height_deg();
+        self.cell_km * DEG_LAT_PER_KM / center_lat.to_radians().cos().max(0.01)
+    }
+
+    fn cell(&self, (lat, lon): (f64, f64)) -> (i64, i64) {
+        let row = (lat / self.row_height_deg()).floor() as i64;
+        let col = (lon / self.col_width_deg(row)).floor() as i64;
+        (row, col)
+    }
+
+    /// (min_lat, min_lon, max_lat, max_lon)
+    fn bounds(&self, (row, col): (i64, i64)) -> (f64, f64, f64, f64) {
+        let (height, width) = (self.row_height_deg(), self.col_width_deg(row));
+        (row as f64 * height, col as f64 * width, (row + 1) as f64 * height, (col + 1) as f64 * width)
+    }
+}
+
+#[derive(Default)]
+struct CellStats<'a> {
+    segments: u32,
+    names: HashSet<&'a str>,
+    length_km: f64,
+}
+
+fn write_geojson(grid: &Grid, cells: &BTreeMap<(i64, i64), CellStats>, path: &Path) -> Result<()> {
+    let mut out = String::from("{\"type\":\"FeatureCollection\",\"features\":[\n");
+    for (i, (&cell, stats)) in cells.iter().enumerate() {
+        let (min_lat, min_lon, max_lat, max_lon) = grid.bounds(cell);
+        if i > 0 {
+            out.push_str(",\n");
+        }
+        write!(
+            out,
+            "{{\"type\":\"Feature\",\"properties\":{{\"row\":{},\"col\":{},\"segments\":{},\"street_names\":{},\"length_km\":{:.4}}},\
+             \"geometry\":{{\"type\":\"Polygon\",\"coordinates\":[[[{min_lon},{min_lat}],[{max_lon},{min_lat}],[{max_lon},{max_lat}],[{min_lon},{max_lat}],[{min_lon},{min_lat}]]]}}}}",
+            cell.0, cell.1, stats.segments, stats.names.len(), stats.length_km,
+        )
+        .expect("writing to a String cannot fail");
+    }
+    out.push_str("\n]}\n");
+    std::fs::write(path, out).map_err(io_error(path))?;
+    Ok(())
+}
+
+/// `grid <state_name> [pbf_file] [output.(parquet|geojson)] [--cell-km X] [--feature F]`
+///
+/// Each edge of a segment's polyline adds its length to the cell containing its midpoint;
+/// a segment counts once towards every cell it passes through.
+pub fn process_grid(
+    pbf_path: &Path,
+    state_name: &str,
+    output_path: Option<PathBuf>,
+    cell_km: f64,
+    feature: Feature,
+) -> Result<()> {
+    if !cell_km.is_finite() || cell_km <= 0.0 {
+        return Err(ProcessorError::InvalidConfig(format!("invalid cell size {} km", cell_km)));
+    }
+    println!("\n{}", "=".repeat(70));
+    println!("OSM STREET DENSITY GRID (Rust)");
+    println!("{}", "=".repeat(70));
+    println!("Input file:  {}", pbf_path.display());
+    println!("State:       {}", state_name);
+    println!("Cell size:   {} km", cell_km);
+    println!("Feature:     {}", feature.type_key());
+    println!("{}", "=".repeat(70));
+
+    let output_path = output_path.unwrap_or_else(|| default_output_path(pbf_path, state_name, &format!("{}_grid", feature.output_kind())));
+
+    let progress = ConsoleProgress::default();
+    let names = NamePolicy::default();
+    let highway_nodes = collect_highway_nodes(pbf_path, feature, &names, false, &progress)?;
+    let segments = extract_street_segments(pbf_path, state_name, feature, &names, &highway_nodes, &progress, None)?;
+
+    println!("Binning segments into {} km cells...", cell_km);
+    let grid = Grid { cell_km };
+    let mut cells: BTreeMap<(i64, i64), CellStats> = BTreeMap::new();
+    for segment in &segments {
+        let mut touched = HashSet::new();
+        if let [only] = segment.coords.as_slice() {
+            touched.insert(grid.cell(*only));
+        }
+        for edge in segment.coords.windows(2) {
+            let midpoint = ((edge[0].0 + edge[1].0) / 2.0, (edge[0].1 + edge[1].1) / 2.0);
+            let cell = grid.cell(midpoint);
+            cells.entry(cell).or_default().length_km += haversine_km(edge[0], edge[1]);
+            touched.insert(cell);
+        }
+        for cell in touched {
+            let stats = cells.entry(cell).or_default();
+            stats.segments += 1;
+            stats.names.insert(segment.street_name.as_str());
+        }
+    }
+    println!("  {} non-empty cells", cells.len());
+
+    println!("\nSaving to: {}", output_path.display());
+    if output_path.extension().and_then(|ext| ext.to_str()) == Some("geojson") {
+        write_geojson(&grid, &cells, &output_path)?;
+    } else {
+        let bounds: Vec<_> = cells.keys().map(|&cell| grid.bounds(cell)).collect();
+        let mut df = DataFrame::new(vec![
+            Series::new("state", vec![state_name; cells.len()]),
+            Series::new("row", cells.keys().map(|c| c.0).collect::<Vec<_>>()),
+            Series::new("col", cells.keys().map(|c| c.1).collect::<Vec<_>>()),
+            Series::new("min_lat", bounds.iter().map(|b| b.0).collect::<Vec<_>>()),
+            Series::new("min_lon", bounds.iter().map(|b| b.1).collect::<Vec<_>>()),
+            Series::new("max_lat", bounds.iter().map(|b| b.2).collect::<Vec<_>>()),
+            Series::new("max_lon", bounds.iter().map(|b| b.3).collect::<Vec<_>>()),
+            Series::new("segments", cells.values().map(|s| s.segments).collect::<Vec<_>>()),
+            Series::new("street_names", cells.values().map(|s| s.names.len() as u32).collect::<Vec<_>>()),
+            Series::new("length_km", cells.values().map(|s| s.length_km).collect::<Vec<_>>()),
+        ])?;
+        write_parquet(&mut df, &output_path)?;
+    }
+
+    println!("Done!");
+    println!("{}", "=".repeat(70));
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use geo::{Distance, Haversine, Point};
+use std::collections::HashMap;
+
+use crate::error::{ProcessorError, Result};
+use crate::fast_hash::NodeMap;
+use crate::StreetSegment;
+
+/// Decides which segments of one street name belong to the same street.
+///
+/// `group` receives every segment sharing a (name, state) and returns components as index
+/// lists into that slice; each component becomes one [`crate::Street`]. Every segment
+/// should appear in exactly one component.
+pub trait GroupingStrategy: Send + Sync {
+    fn group(&self, segments: &[StreetSegment]) -> Vec<Vec<usize>>;
+}
+
+/// The default strategy: segments sharing a node are connected, then components whose
+/// endpoints are closer than `distance_threshold_km` are merged (0 disables merging).
+///
+/// `class_thresholds_km` overrides the distance per highway type. A component uses the
+/// largest threshold among its segments' types, and a pair of components is merged within
+/// the larger of their two thresholds, so a split arterial can join across a long gap while
+/// residential streets stay apart.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectivityDistance {
+    pub distance_threshold_km: f64,
+    pub class_thresholds_km: HashMap<String, f64>,
+}
+
+impl ConnectivityDistance {
+    pub fn new(distance_threshold_km: f64) -> Self {
+        ConnectivityDistance { distance_threshold_km, class_thresholds_km: HashMap::new() }
+    }
+
+    /// Merge threshold for one segment's highway type
+    fn threshold_for(&self, segment: &StreetSegment) -> f64 {
+        self.class_thresholds_km
+            .get(&segment.highway_type)
+            .copied()
+            .unwrap_or(self.distance_threshold_km)
+    }
+}
+
+impl GroupingStrategy for ConnectivityDistance {
+    fn group(&self, segments: &[StreetSegment]) -> Vec<Vec<usize>> {
+        // Find connected components
+        let components = find_connected_components(segments);
+        
+        if self.class_thresholds_km.is_empty() {
+            // Optionally merge nearby components
+            return if self.distance_threshold_km > 0.0 {
+                group_nearby_components(segments, components, self.distance_threshold_km)
+            } else {
+                components
+            };
+        }
+        
+        let thresholds: Vec<f64> = components
+            .iter()
+            .map(|component| {
+                component
+                    .iter()
+                    .map(|&i| self.threshold_for(&segments[i]))
+                    .fold(0.0, f64::max)
+            })
+            .collect();
+        if thresholds.iter().all(|&km| km <= 0.0) {
+            return components;
+        }
+        merge_components_within(segments, components, |i, j| thresholds[i].max(thresholds[j]))
+    }
+}
+
+/// Parse `--threshold-by-class` values: `motorway=1,primary=1,residential=0.1` (km)
+pub fn parse_class_thresholds(spec: &str) -> Result<HashMap<String, f64>> {
+    spec.split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let invalid = || ProcessorError::InvalidConfig(format!("Invalid class threshold: {}", entry));
+            let (class, km) = entry.split_once('=').ok_or_else(invalid)?;
+            let km: f64 = km.trim().parse().map_err(|_| invalid())?;
+            if !km.is_finite() || km < 0.0 {
+                return Err(invalid());
+            }
+            Ok((class.trim().to_string(), km))
+        })
+        .collect()
+}
+
+/// Group segments into connected components using node sharing
+pub fn find_connected_components(segments: &[StreetSegment]) -> Vec<Vec<usize>> {
+    if segments.is_empty() {
+        return Vec::new();
+    }
+    
+    let n = segments.len();
+    
+    // Build adjacency list based on shared nodes
+    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); n];
+    
+    // For efficiency, build a map of node_id -> segment indices
+    let mut node_to_segments: NodeMap<Vec<usize>> = NodeMap::default();
+    for (i, seg) in segments.iter().enumerate() {
+        for &node_id in &seg.node_ids {
+            node_to_segments.entry(node_id).or_default().push(i);
+        }
+    }
+    
+    // Connect segments that share nodes
+    for segment_indices in node_to_segments.values() {
+        for i in 0..segment_indices.len() {
+            for j in (i + 1)..segment_indices.len() {
+                let idx_i = segment_indices[i];
+                let idx_j = segment_indices[j];
+                adj[idx_i].push(idx_j);
+                adj[idx_j].push(idx_i);
+            }
+        }
+    }
+    
+    // Find connected components using BFS
+    let mut visited = vec![false; n];
+    let mut components = Vec::new();
+    
+    for start in 0..n {
+        if !visited[start] {
+            let mut component = Vec::new();
+            let mut queue = vec![start];
+            visited[start] = true;
+            
+            while let Some(current) = queue.pop() {
+                component.push(current);
+                for &neighbor in &adj[current] {
+                    if !visited[neighbor] {
+                        visited[neighbor] = true;
+                        queue.push(neighbor);
+                    }
+                }
+            }
+            
+            components.push(component);
+        }
+    }
+    
+    components
+}
+
+/// Join components whose segments belong to the same street relation, whatever the distance
+/// between them; components without relation members are left as they are
+pub fn merge_by_relation(segments: &[StreetSegment], components: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+    if components.len() <= 1 || segments.iter().all(|s| s.relation_id.is_none()) {
+        return components;
+    }
+    
+    let mut merged: Vec<Vec<usize>> = Vec::new();
+    let mut by_relation: HashMap<i64, usize> = HashMap::new();
+    for component in components {
+        // Every relation this component touches, and the merged components already holding them
+        let mut relations: Vec<i64> = component.iter().filter_map(|&i| segments[i].relation_id).collect();
+        relations.sort_unstable();
+        relations.dedup();
+        let mut targets: Vec<usize> = relations.iter().filter_map(|id| by_relation.get(id).copied()).collect();
+        targets.sort_unstable();
+        targets.dedup();
+        
+        let target = match targets.first() {
+            Some(&first) => {
+                // Fold any other holders into the first one
+                for &other in targets[1..].iter().rev() {
+                    let members = std::mem::take(&mut merged[other]);
+                    merged[first].extend(members);
+                    for slot in by_relation.values_mut() {
+                        if *slot == other {
+                            *slot = first;
+                        }
+                    }
+                }
+                merged[first].extend(component);
+                first
+            }
+            None => {
+                merged.push(component);
+                merged.len() - 1
+            }
+        };
+        for id in relations {
+            by_relation.insert(id, target);
+        }
+    }
+    merged.retain(|component| !component.is_empty());
+    merged
+}
+
+/// Group segments with same name using spatial proximity (for disconnected segments)
+/// Optimized: checks minimum distance between endpoints (first/last nodes) of segments
+pub fn group_nearby_components(
+    segments: &[StreetSegment],
+    components: Vec<Vec<usize>>,
+    distance_threshold_km: f64,
+) -> Vec<Vec<usize>> {
+    merge_components_within(segments, components, |_, _| distance_threshold_km)
+}
+
+/// [`group_nearby_components`] with a threshold chosen per pair of component indices
+fn merge_components_within(
+    segments: &[StreetSegment],
+    components: Vec<Vec<usize>>,
+    threshold_km: impl Fn(usize, usize) -> f64,
+) -> Vec<Vec<usize>> {
+    if components.len() <= 1 {
+        return components;
+    }
+    
+    // Build connectivity graph based on distance threshold
+    // This matches the Python algorithm exactly
+    let n = components.len();
+    let mut connections: Vec<Vec<usize>> = vec![Vec::new(); n];
+    
+    // Check all pairs of components (quadratic, like Python)
+    // Optimized to only check first and last nodes of each segment
+    for i in 0..n {
+        for j in (i + 1)..n {
+            // Check minimum distance between endpoints (first/last nodes) of segments
+            let mut min_dist = f64::INFINITY;
+            
+            for &seg_i in &components[i] {
+                let (start_i, end_i) = segments[seg_i].endpoints();
+                let p1_start = Point::new(start_i.1, start_i.0); // (lon, lat)
+                let p1_end = Point::new(end_i.1, end_i.0); // (lon, lat)
+                
+                for &seg_j in &components[j] {
+                    let (start_j, end_j) = segments[seg_j].endpoints();
+                    let p2_start = Point::new(start_j.1, start_j.0); // (lon, lat)
+                    let p2_end = Point::new(end_j.1, end_j.0); // (lon, lat)
+                    
+                    // Check all endpoint combinations
+                    let dists = [
+                        Haversine.distance(p1_start, p2_start) / 1000.0, // Convert meters to km
+                        Haversine.distance(p1_start, p2_end) / 1000.0,
+                        Haversine.distance(p1_end, p2_start) / 1000.0,
+                        Haversine.distance(p1_end, p2_end) / 1000.0,
+                    ];
+                    
+                    min_dist = min_dist.min(dists[0].min(dists[1].min(dists[2].min(dists[3]))));
+                }
+            }
+            
+            if min_dist < threshold_km(i, j) {
+                connections[i].push(j);
+                connections[j].push(i);
+            }
+        }
+    }
+    
+    // Find connected components using BFS (same as Python)
+    let mut visited = vec![false; n];
+    let mut final_components = Vec::new();
+    
+    for start in 0..n {
+        if !visited[start] {
+            let mut merged_component = Vec::new();
+            let mut queue = vec![start];
+            visited[start] = true;
+            
+            while let Some(current) = queue.pop() {
+                // Add all segments from this component
+                merged_component.extend(&components[current]);
+                
+                for &neighbor in &connections[current] {
+                    if !visited[neighbor] {
+                        visited[neighbor] = true;
+                        queue.push(neighbor);
+                    }
+                }
+            }
+            
+            final_components.push(merged_component);
+        }
+    }
+    
+    final_components
+}
@@ -0,0 +1,1011 @@
+//! Extract named streets (or railways, waterways, ...) from an OpenStreetMap PBF file and group
+//! their way segments into unique streets.
+//!
+//! [`OsmStreetProcessor`] is the entry point for embedding; the `osm_processor_rust` binary is a
+//! thin CLI over this crate.
+
+use geo::{Distance, Haversine, Point};
+use osmpbf::{BlobDecode, BlobReader, Element, ElementReader};
+use polars::prelude::*;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::error::{io_error, pbf_error};
+use crate::fast_hash::NodeMap;
+use crate::grouping::merge_by_relation;
+use crate::manifest::Manifest;
+use crate::names::NamePolicy;
+use crate::node_set::{NodeIdSet, NodeIdSetBuilder};
+use crate::qa::{QaConfig, QaRecord};
+use crate::sanitize::{sanitize_way, SanitizeCounts};
+use crate::skipped::{SkipLog, SkipReason, SkippedWay};
+use crate::tags::Tags;
+
+pub mod adjacency;
+pub mod aggregation;
+#[cfg(feature = "async")]
+pub mod async_api;
+pub mod boundaries;
+pub mod buildings;
+mod checkpoint;
+pub mod crs;
+pub mod error;
+pub mod fast_hash;
+pub mod feature;
+pub mod ffi;
+pub mod graph;
+pub mod grid;
+pub mod grouping;
+mod lock;
+pub mod manifest;
+mod multipolygon;
+pub mod name_parser;
+pub mod names;
+pub mod natural_earth;
+pub mod node_set;
+pub mod orientation;
+pub mod overture;
+pub mod places;
+mod processor;
+pub mod progress;
+pub mod qa;
+pub mod regions;
+mod relations;
+pub mod rep_point;
+pub mod sanitize;
+mod segment_artifact;
+pub mod segments;
+mod sharding;
+pub mod simplify;
+pub mod skipped;
+mod space;
+mod speed;
+pub mod stats;
+pub mod street_id;
+pub mod structures;
+pub mod tags;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+pub mod transit;
+pub mod wikidata;
+pub mod wkb;
+
+pub use aggregation::{DefaultAggregator, StreetAggregator};
+pub use error::{ProcessorError, Result};
+pub use feature::{ExtraValue, Feature};
+pub use grouping::{ConnectivityDistance, GroupingStrategy};
+pub use progress::{ConsoleProgress, JsonProgress, NoProgress, Phase, ProgressSink};
+pub use rep_point::RepPoint;
+pub use sharding::ShardConfig;
+pub use street_id::StreetIdMode;
+pub use processor::{
+    OsmStreetProcessor, OsmStreetProcessorBuilder, StreetStream, StreetsResult, DEFAULT_DISTANCE_THRESHOLD_KM,
+};
+
+/// A street segment from OSM
+#[derive(Debug, Clone)]
+pub struct StreetSegment {
+    pub street_name: String,
+    /// The plain `name` tag, kept when `--name-lang` may pick a different one
+    pub local_name: Option<String>,
+    pub state: String,
+    /// County or city with `--region-level`, from boundary assignment
+    pub region: Option<String>,
+    /// Directional removed from `street_name` by `--merge-directionals`
+    pub direction: Option<&'static str>,
+    /// associatedStreet, street or road route relation the way belongs to, with `--relations`
+    pub relation_id: Option<i64>,
+    pub way_id: i64,
+    pub node_ids: Vec<i64>,
+    pub coords: Vec<(f64, f64)>, // (lat, lon)
+    pub highway_type: String,
+    pub tags: Tags,
+    pub length_km: f64,
+    /// What hardened parsing repaired or noticed in this way (`lossy_tags`, `repeated_nodes`,
+    /// `self_intersecting`)
+    pub sanitized: Vec<&'static str>,
+}
+
+impl StreetSegment {
+    /// Get representative coordinates (first point)
+    fn rep_coords(&self) -> (f64, f64) {
+        self.coords[0]
+    }
+    
+    /// Get first and last coordinates for distance checking
+    fn endpoints(&self) -> ((f64, f64), (f64, f64)) {
+        let first = self.coords[0];
+        let last = self.coords[self.coords.len() - 1];
+        (first, last)
+    }
+}
+
+/// A unique street (potentially multiple segments grouped together)
+#[derive(Debug)]
+pub struct Street {
+    /// Stable across runs; see [`street_id::street_id`]
+    pub street_id: String,
+    pub street_name: String,
+    /// The plain `name` tag when `street_name` was chosen by a `--name-lang` policy
+    pub local_name: Option<String>,
+    pub state: String,
+    /// County or city the street was grouped within, if grouping below state level
+    pub region: Option<String>,
+    /// Most common `addr:city` (or `is_in:city`) tag among the segments
+    pub city: Option<String>,
+    /// County from the segments' `tiger:county` tags, without the state suffix
+    pub county_hint: Option<String>,
+    /// Most common `wikidata` tag among the segments, the street's own item
+    pub wikidata_id: Option<String>,
+    /// Most common `name:etymology:wikidata` tag, the item(s) the street is named after; see
+    /// [`wikidata::resolve_etymology`]
+    pub etymology_wikidata: Option<String>,
+    /// Directionals removed from the segments' names by `--merge-directionals`, comma-separated
+    /// (`N,S`); without merging the `direction` column is parsed from `street_name` instead
+    pub direction: Option<String>,
+    /// Street relation that held the segments together; `None` when grouped by the heuristic alone
+    pub relation_id: Option<i64>,
+    pub lat: f64,
+    pub lon: f64,
+    pub num_segments: usize,
+    pub highway_type: String,
+    pub tags: HashMap<String, String>,
+    pub length_km: f64,
+    /// Greatest distance between two of the street's way ends; see [`aggregation::extent_km`]
+    pub extent_km: f64,
+    /// Dominant bearing and orientation entropy; `None` for a street without length
+    pub orientation: Option<orientation::Orientation>,
+    /// Node-connected pieces the street was assembled from, before distance merging
+    pub num_components_raw: usize,
+    /// At least two pieces were joined by the distance threshold rather than shared nodes or a
+    /// street relation
+    pub merged_by_distance: bool,
+    /// `(start, end)` (lat, lon) of the street's farthest-apart dead ends; see
+    /// [`aggregation::endpoints`]
+    pub endpoints: Option<((f64, f64), (f64, f64))>,
+    /// The segments join up into closed rings with no dead ends; see [`aggregation::is_loop`]
+    pub is_loop: bool,
+    pub extra: Vec<(&'static str, ExtraValue)>,
+}
+
+/// Haversine distance in km between two (lat, lon) points
+pub fn haversine_km(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let p1 = Point::new(a.1, a.0); // (lon, lat)
+    let p2 = Point::new(b.1, b.0); // (lon, lat)
+    Haversine.distance(p1, p2) / 1000.0 // Convert meters to km
+}
+
+/// Round a coordinate to `precision` decimal places; `None` leaves it unchanged
+pub fn round_coord(value: f64, precision: Option<u32>) -> f64 {
+    match precision {
+        Some(digits) => {
+            let scale = 10f64.powi(digits as i32);
+            (value * scale).round() / scale
+        }
+        None => value,
+    }
+}
+
+/// Total Haversine length in km of a polyline of (lat, lon) points
+pub fn polyline_length_km(coords: &[(f64, f64)]) -> f64 {
+    coords.windows(2).map(|w| haversine_km(w[0], w[1])).sum()
+}
+
+/// First pass: collect which nodes are used by named ways of the requested feature; `hardened`
+/// reads tags that are not valid UTF-8 lossily, as the second pass then does
+fn collect_highway_nodes(
+    pbf_path: &Path,
+    feature: Feature,
+    names: &NamePolicy,
+    hardened: bool,
+    progress: &dyn ProgressSink,
+) -> Result<NodeIdSet> {
+    progress.message(&format!("Pass 1: Identifying nodes used by named {} ways...", feature.type_key()));
+    progress.phase_started(Phase::FindNodes, None);
+    
+    let reader = ElementReader::from_path(pbf_path)
+        .map_err(pbf_error(pbf_path))?;
+    
+    let mut highway_nodes = NodeIdSetBuilder::default();
+    let mut way_count = 0;
+    
+    reader.for_each(|element| {
+        if let Element::Way(way) = element {
+            let owned;
+            let tags: HashMap<&str, &str> = if hardened {
+                owned = sanitize::lossy_tags(&way).0;
+                owned.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect()
+            } else {
+                way.tags().collect()
+            };
+            
+            // Check if this way has both a name and a matching type tag (e.g. highway)
+            let matches_feature = feature.way_type(|key| tags.get(key).copied()).is_some();
+            if names.choose(|key| tags.get(key).copied()).is_some() && matches_feature {
+                way_count += 1;
+                progress.advanced(Phase::FindNodes, 1);
+                for node_id in way.refs() {
+                    highway_nodes.insert(node_id);
+                }
+            }
+        }
+    })
+    .map_err(pbf_error(pbf_path))?;
+    
+    let highway_nodes = highway_nodes.build();
+    progress.phase_finished(Phase::FindNodes);
+    progress.message(&format!(
+        "  Found {} named {} ways using {} nodes ({:.1} MB)",
+        way_count,
+        feature.type_key(),
+        highway_nodes.len(),
+        highway_nodes.heap_bytes() as f64 / 1e6
+    ));
+    Ok(highway_nodes)
+}
+
+/// Coordinates of the wanted nodes in one blob, with the number of nodes it held and how many
+/// of them matched
+type BlobNodes = (NodeMap<(f64, f64)>, u64, u64);
+
+/// Load coordinates for the given set of node IDs
+fn load_node_coords(
+    pbf_path: &Path,
+    wanted_nodes: &NodeIdSet,
+    progress: &dyn ProgressSink,
+) -> Result<NodeMap<(f64, f64)>> {
+    progress.message("  Loading node coordinates...");
+    progress.phase_started(Phase::LoadNodes, Some(wanted_nodes.len() as u64));
+    let reader = BlobReader::from_path(pbf_path)
+        .map_err(pbf_error(pbf_path))?;
+    
+    // Blobs are decoded in parallel, and progress is reported once per blob (up to 8000 nodes)
+    // that matched anything rather than per node, so workers don't contend on the sink
+    let (node_coords, node_count, matched_count) = reader
+        .par_bridge()
+        .map(|blob| -> std::result::Result<BlobNodes, osmpbf::Error> {
+            let mut coords = NodeMap::default();
+            let mut total = 0;
+            if let BlobDecode::OsmData(block) = blob?.decode()? {
+                for group in block.groups() {
+                    for node in group.nodes() {
+                        total += 1;
+                        if wanted_nodes.contains(node.id()) {
+                            coords.insert(node.id(), (node.lat(), node.lon()));
+                        }
+                    }
+                    for node in group.dense_nodes() {
+                        total += 1;
+                        if wanted_nodes.contains(node.id()) {
+                            coords.insert(node.id(), (node.lat(), node.lon()));
+                        }
+                    }
+                }
+            }
+            let matched = coords.len() as u64;
+            if matched > 0 {
+                progress.advanced(Phase::LoadNodes, matched);
+            }
+            Ok((coords, total, matched))
+        })
+        .try_reduce(
+            || (NodeMap::default(), 0, 0),
+            |mut a, b| {
+                a.0.extend(b.0);
+                a.1 += b.1;
+                a.2 += b.2;
+                Ok(a)
+            },
+        )
+        .map_err(pbf_error(pbf_path))?;
+    
+    progress.phase_finished(Phase::LoadNodes);
+    progress.message(&format!("  Scanned {} nodes, matched {} wanted nodes, loaded {} coordinates", 
+                              node_count, matched_count, node_coords.len()));
+    if node_coords.is_empty() && !wanted_nodes.is_empty() {
+        return Err(ProcessorError::MissingNodes { path: pbf_path.to_path_buf(), wanted: wanted_nodes.len() });
+    }
+    Ok(node_coords)
+}
+
+/// Second pass: extract street segments with coordinates, recording candidate ways that were
+/// dropped in `skipped` if given
+fn extract_street_segments(
+    pbf_path: &Path,
+    state_name: &str,
+    feature: Feature,
+    names: &NamePolicy,
+    highway_nodes: &NodeIdSet,
+    progress: &dyn ProgressSink,
+    skipped: Option<&mut SkipLog>,
+) -> Result<Vec<StreetSegment>> {
+    progress.message("Pass 2: Extracting street segments...");
+    
+    // First pass through file: collect node coordinates
+    let node_coords = load_node_coords(pbf_path, highway_nodes, progress)?;
+    
+    // Second pass through file: extract ways
+    segments_from_ways(pbf_path, state_name, feature, names, &node_coords, progress, skipped, None)
+}
+
+/// Read the named ways of `feature` into segments using whatever coordinates `node_coords`
+/// has; ways with none of their nodes there are dropped. With `sanitize` (hardened parsing),
+/// tags are decoded lossily and degenerate ways repaired or dropped, tallied there.
+#[allow(clippy::too_many_arguments)]
+fn segments_from_ways(
+    pbf_path: &Path,
+    state_name: &str,
+    feature: Feature,
+    names: &NamePolicy,
+    node_coords: &NodeMap<(f64, f64)>,
+    progress: &dyn ProgressSink,
+    mut skipped: Option<&mut SkipLog>,
+    mut sanitize: Option<&mut SanitizeCounts>,
+) -> Result<Vec<StreetSegment>> {
+    progress.message("  Extracting ways...");
+    progress.phase_started(Phase::ExtractSegments, None);
+    let reader = ElementReader::from_path(pbf_path)
+        .map_err(pbf_error(pbf_path))?;
+    
+    let mut segments = Vec::new();
+    
+    reader.for_each(|element| {
+        if let Element::Way(way) = element {
+            // Borrowed until the way turns out to be wanted; only kept segments intern their tags
+            let owned;
+            let (tags, lossy): (HashMap<&str, &str>, bool) = if sanitize.is_some() {
+                let (decoded, lossy) = sanitize::lossy_tags(&way);
+                owned = decoded;
+                (owned.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect(), lossy)
+            } else {
+                (way.tags().collect(), false)
+            };
+            
+            let highway_type = feature
+                .way_type(|key| tags.get(key).copied())
+                .map(str::to_string);
+            
+            let name = names.choose(|key| tags.get(key).copied());
+            
+            if let Some(log) = skipped.as_deref_mut() {
+                let value = tags.get(feature.type_key()).copied();
+                let reason = match (name, &highway_type) {
+                    (None, Some(_)) => Some(SkipReason::NoName),
+                    (Some(_), None) if value.is_some() => Some(SkipReason::FilteredOut),
+                    (Some(_), Some(_)) if !way.refs().any(|id| node_coords.contains_key(&id)) => {
+                        Some(SkipReason::NoCoordinates)
+                    }
+                    _ => None,
+                };
+                if let Some(reason) = reason {
+                    log.record(&SkippedWay {
+                        way_id: way.id(),
+                        reason,
+                        name,
+                        key: feature.type_key(),
+                        value,
+                        first_node: way.refs().next(),
+                        num_nodes: way.raw_refs().len(),
+                    });
+                }
+            }
+            
+            if let (Some(name), Some(highway_type)) = (name, highway_type) {
+                // Collect coordinates for this way
+                let mut node_ids: Vec<i64> = way.refs().collect();
+                let mut coords: Vec<(f64, f64)> = node_ids
+                    .iter()
+                    .filter_map(|node_id| node_coords.get(node_id).copied())
+                    .collect();
+                
+                let sanitized = match sanitize.as_deref_mut() {
+                    Some(counts) if !coords.is_empty() => sanitize_way(counts, lossy, &mut node_ids, &mut coords),
+                    _ => Some(Vec::new()),
+                };
+                let Some(sanitized) = sanitized else {
+                    if let Some(log) = skipped.as_deref_mut() {
+                        log.record(&SkippedWay {
+                            way_id: way.id(),
+                            reason: SkipReason::TooShort,
+                            name: Some(name),
+                            key: feature.type_key(),
+                            value: Some(highway_type.as_str()),
+                            first_node: way.refs().next(),
+                            num_nodes: way.raw_refs().len(),
+                        });
+                    }
+                    return;
+                };
+                
+                if !coords.is_empty() {
+                    // Calculate segment length using Haversine distance
+                    let length_km = polyline_length_km(&coords);
+                    progress.advanced(Phase::ExtractSegments, 1);
+                    
+                    segments.push(StreetSegment {
+                        street_name: name.to_string(),
+                        local_name: if names.is_local() { None } else { tags.get("name").map(|n| n.to_string()) },
+                        state: state_name.to_string(),
+                        region: None,
+                        direction: None,
+                        relation_id: None,
+                        way_id: way.id(),
+                        node_ids,
+                        coords,
+                        highway_type,
+                        tags: Tags::from_pairs(tags),
+                        length_km,
+                        sanitized,
+                    });
+                }
+            }
+        }
+    })
+    .map_err(pbf_error(pbf_path))?;
+    
+    progress.phase_finished(Phase::ExtractSegments);
+    progress.message(&format!("  Found {} street segments", segments.len()));
+    Ok(segments)
+}
+
+/// Segment indices grouped by (name, state, region)
+fn segments_by_name(segments: &[StreetSegment]) -> HashMap<(String, String, Option<String>), Vec<usize>> {
+    let mut by_name_state: HashMap<(String, String, Option<String>), Vec<usize>> = HashMap::new();
+    for (i, seg) in segments.iter().enumerate() {
+        let key = (seg.street_name.clone(), seg.state.clone(), seg.region.clone());
+        by_name_state.entry(key).or_default().push(i);
+    }
+    by_name_state
+}
+
+/// One (name, state, region) group of segments, ready to be grouped on its own
+struct NameGroup {
+    key: (String, String, Option<String>),
+    indices: Vec<usize>,
+    /// The segments' node IDs, moved out of `segments` unless they are needed after grouping
+    node_ids: Vec<Vec<i64>>,
+}
+
+/// [`segments_by_name`], moving each segment's node IDs into its group unless `keep_node_ids`.
+/// Connectivity is all they are needed for, so a group frees them once it is done instead of the
+/// full segment list holding a copy through the whole grouping phase.
+fn name_groups(segments: &mut [StreetSegment], keep_node_ids: bool) -> Vec<NameGroup> {
+    segments_by_name(segments)
+        .into_iter()
+        .map(|(key, indices)| {
+            let node_ids = if keep_node_ids {
+                Vec::new()
+            } else {
+                indices.iter().map(|&i| std::mem::take(&mut segments[i].node_ids)).collect()
+            };
+            NameGroup { key, indices, node_ids }
+        })
+        .collect()
+}
+
+/// Copies of a group's segments, given back the node IDs [`name_groups`] moved out
+fn group_segments(segments: &[StreetSegment], indices: &[usize], node_ids: Vec<Vec<i64>>) -> Vec<StreetSegment> {
+    let mut name_segments: Vec<StreetSegment> = indices.iter().map(|&i| segments[i].clone()).collect();
+    for (seg, node_ids) in name_segments.iter_mut().zip(node_ids) {
+        seg.node_ids = node_ids;
+    }
+    name_segments
+}
+
+/// Streets made from a set of segments
+#[derive(Default)]
+struct Grouped {
+    streets: Vec<Street>,
+    /// Indices of the segments making up each street, parallel to `streets`
+    members: Vec<Vec<usize>>,
+    /// Streets flagged by the QA checks, if enabled
+    flagged: Vec<QaRecord>,
+    /// Name groups left ungrouped because the deadline passed
+    skipped_names: usize,
+}
+
+/// Whether a run's deadline (if any) has passed
+pub(crate) fn deadline_passed(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// Group the segments of one (name, state, region) into streets
+fn streets_for_name(
+    name: &str,
+    state: &str,
+    name_segments: &[StreetSegment],
+    grouping: &dyn GroupingStrategy,
+    aggregator: &dyn StreetAggregator,
+    feature: Feature,
+    qa: Option<&QaConfig>,
+) -> Grouped {
+    let mut grouped = Grouped::default();
+    let components = grouping.group(name_segments);
+    
+    // Segments of a component that the grouping strategy assembled from several pieces, so
+    // streets joined by relation afterwards only count as distance merged if one of these is in them
+    let mut distance_merged = vec![false; name_segments.len()];
+    for component in &components {
+        let segs: Vec<_> = component.iter().map(|&i| &name_segments[i]).collect();
+        if qa::raw_component_count(&segs) > 1 {
+            for &i in component {
+                distance_merged[i] = true;
+            }
+        }
+    }
+    
+    // Create one street per component, joining components held together by a street relation
+    for component_indices in merge_by_relation(name_segments, components) {
+        let segs: Vec<_> = component_indices
+            .iter()
+            .map(|&i| &name_segments[i])
+            .collect();
+        
+        let mut street = aggregator.aggregate(name, state, &segs, feature);
+        street.merged_by_distance = component_indices.iter().any(|&i| distance_merged[i]);
+        if let Some(record) = qa.and_then(|config| qa::inspect(config, &street, &segs)) {
+            grouped.flagged.push(record);
+        }
+        grouped.streets.push(street);
+        grouped.members.push(component_indices);
+    }
+    grouped
+}
+
+/// Group segments into unique streets. Segments lose their node IDs to the grouping unless
+/// `keep_node_ids` (for node output afterwards).
+#[allow(clippy::too_many_arguments)]
+fn group_segments_into_streets(
+    segments: &mut [StreetSegment],
+    keep_node_ids: bool,
+    grouping: &dyn GroupingStrategy,
+    aggregator: &dyn StreetAggregator,
+    feature: Feature,
+    qa: Option<&QaConfig>,
+    deadline: Option<Instant>,
+    progress: &dyn ProgressSink,
+) -> Grouped {
+    progress.message("Grouping segments into unique streets...");
+    
+    // Group by (name, state, region)
+    let name_groups = name_groups(segments, keep_node_ids);
+    let segments = &*segments;
+    
+    progress.message(&format!("  Found {} unique street names", name_groups.len()));
+    progress.phase_started(Phase::Grouping, Some(name_groups.len() as u64));
+    
+    // Process each name group in parallel
+    let groups: Vec<Grouped> = name_groups
+        .into_par_iter()
+        .map(|NameGroup { key: (name, state, _), indices, node_ids }| {
+            // Past the deadline the remaining names are skipped, so the run can stop cleanly
+            if deadline_passed(deadline) {
+                progress.advanced(Phase::Grouping, 1);
+                return Grouped { skipped_names: 1, ..Grouped::default() };
+            }
+            let name_segments = group_segments(segments, &indices, node_ids);
+            let mut result = streets_for_name(&name, &state, &name_segments, grouping, aggregator, feature, qa);
+            // Back from positions within the name group to positions in `segments`
+            for members in &mut result.members {
+                for member in members.iter_mut() {
+                    *member = indices[*member];
+                }
+            }
+            progress.advanced(Phase::Grouping, 1);
+            result
+        })
+        .collect();
+    
+    let mut grouped = Grouped::default();
+    for group in groups {
+        grouped.streets.extend(group.streets);
+        grouped.members.extend(group.members);
+        grouped.flagged.extend(group.flagged);
+        grouped.skipped_names += group.skipped_names;
+    }
+    
+    progress.phase_finished(Phase::Grouping);
+    progress.message(&format!("  Created {} unique streets", grouped.streets.len()));
+    if grouped.skipped_names > 0 {
+        progress.message(&format!("  Deadline reached: {} street names were not grouped", grouped.skipped_names));
+    }
+    grouped
+}
+
+/// Convert streets to Polars DataFrame
+pub fn streets_to_dataframe(streets: Vec<Street>, feature: Feature) -> Result<DataFrame> {
+    let street_ids: Vec<String> = streets.iter().map(|s| s.street_id.clone()).collect();
+    let street_names: Vec<String> = streets.iter().map(|s| s.street_name.clone()).collect();
+    let local_names: Vec<Option<String>> = streets.iter().map(|s| s.local_name.clone()).collect();
+    let has_local_names = local_names.iter().any(Option::is_some);
+    let states: Vec<String> = streets.iter().map(|s| s.state.clone()).collect();
+    let regions: Vec<Option<String>> = streets.iter().map(|s| s.region.clone()).collect();
+    let has_regions = regions.iter().any(Option::is_some);
+    let cities: Vec<Option<&str>> = streets.iter().map(|s| s.city.as_deref()).collect();
+    let county_hints: Vec<Option<&str>> = streets.iter().map(|s| s.county_hint.as_deref()).collect();
+    let has_locality_hints = cities.iter().chain(&county_hints).any(Option::is_some);
+    let wikidata_ids: Vec<Option<&str>> = streets.iter().map(|s| s.wikidata_id.as_deref()).collect();
+    let etymology_ids: Vec<Option<&str>> = streets.iter().map(|s| s.etymology_wikidata.as_deref()).collect();
+    let has_wikidata = wikidata_ids.iter().chain(&etymology_ids).any(Option::is_some);
+    let lats: Vec<f64> = streets.iter().map(|s| s.lat).collect();
+    let lons: Vec<f64> = streets.iter().map(|s| s.lon).collect();
+    let num_segments: Vec<u32> = streets.iter().map(|s| s.num_segments as u32).collect();
+    let highway_types: Vec<String> = streets.iter().map(|s| s.highway_type.clone()).collect();
+    let lengths_km: Vec<f64> = streets.iter().map(|s| s.length_km).collect();
+    let extents_km: Vec<f64> = streets.iter().map(|s| s.extent_km).collect();
+    let bearings: Vec<Option<f64>> = streets.iter().map(|s| s.orientation.map(|o| o.bearing_deg)).collect();
+    let entropies: Vec<Option<f64>> = streets.iter().map(|s| s.orientation.map(|o| o.entropy)).collect();
+    let parsed_names: Vec<name_parser::ParsedName> =
+        streets.iter().map(|s| name_parser::parse(&s.street_name)).collect();
+    let street_types_norm: Vec<Option<&str>> = parsed_names.iter().map(|p| p.street_type).collect();
+    let street_numbers: Vec<Option<u32>> = parsed_names.iter().map(|p| p.number).collect();
+    let is_numbered: Vec<bool> = street_numbers.iter().map(Option::is_some).collect();
+    let is_loop: Vec<bool> = streets.iter().map(|s| s.is_loop).collect();
+    let start_lats: Vec<Option<f64>> = streets.iter().map(|s| s.endpoints.map(|(start, _)| start.0)).collect();
+    let start_lons: Vec<Option<f64>> = streets.iter().map(|s| s.endpoints.map(|(start, _)| start.1)).collect();
+    let end_lats: Vec<Option<f64>> = streets.iter().map(|s| s.endpoints.map(|(_, end)| end.0)).collect();
+    let end_lons: Vec<Option<f64>> = streets.iter().map(|s| s.endpoints.map(|(_, end)| end.1)).collect();
+    let num_components_raw: Vec<u32> = streets.iter().map(|s| s.num_components_raw as u32).collect();
+    let merged_by_distance: Vec<bool> = streets.iter().map(|s| s.merged_by_distance).collect();
+    let relation_ids: Vec<Option<i64>> = streets.iter().map(|s| s.relation_id).collect();
+    let has_relations = relation_ids.iter().any(Option::is_some);
+    let directions: Vec<Option<&str>> = streets
+        .iter()
+        .zip(&parsed_names)
+        .map(|(s, p)| s.direction.as_deref().or(p.direction))
+        .collect();
+    
+    let mut df = DataFrame::new(vec![
+        Series::new("street_id", street_ids),
+        Series::new("street_name", street_names),
+        Series::new("state", states),
+        Series::new("lat", lats),
+        Series::new("lon", lons),
+        Series::new("start_lat", start_lats),
+        Series::new("start_lon", start_lons),
+        Series::new("end_lat", end_lats),
+        Series::new("end_lon", end_lons),
+        Series::new("num_segments", num_segments),
+        Series::new("num_components_raw", num_components_raw),
+        Series::new("merged_by_distance", merged_by_distance),
+        Series::new(feature.type_column(), highway_types),
+        Series::new("length_km", lengths_km),
+        Series::new("extent_km", extents_km),
+        Series::new("bearing_deg", bearings),
+        Series::new("orientation_entropy", entropies),
+        Series::new("street_type_norm", street_types_norm),
+        Series::new("direction", directions),
+        Series::new("street_number", street_numbers),
+        Series::new("is_numbered", is_numbered),
+        Series::new("is_loop", is_loop),
+    ])?;
+    if has_relations {
+        let grouped_by: Vec<&str> = relation_ids
+            .iter()
+            .map(|id| if id.is_some() { "relation" } else { "heuristic" })
+            .collect();
+        df.with_column(Series::new("grouped_by", grouped_by))?;
+        df.with_column(Series::new("relation_id", relation_ids))?;
+    }
+    if has_wikidata {
+        df.with_column(Series::new("wikidata_id", wikidata_ids))?;
+        df.with_column(Series::new("etymology_wikidata", etymology_ids))?;
+    }
+    if has_locality_hints {
+        df.insert_column(3, Series::new("county_hint", county_hints))?;
+        df.insert_column(3, Series::new("city", cities))?;
+    }
+    if has_regions {
+        df.insert_column(3, Series::new("region", regions))?;
+    }
+    if has_local_names {
+        df.insert_column(2, Series::new("name_local", local_names))?;
+    }
+    
+    // Feature-specific columns (all streets of a run carry the same extras, in the same order)
+    if let Some(first) = streets.first() {
+        for (idx, (name, _)) in first.extra.iter().enumerate() {
+            let values: Vec<&ExtraValue> = streets.iter().map(|s| &s.extra[idx].1).collect();
+            df.with_column(extra_series(name, &values))?;
+        }
+    }
+    
+    Ok(df)
+}
+
+/// Build a column from feature-specific values, typed by the first value
+fn extra_series(name: &str, values: &[&ExtraValue]) -> Series {
+    match values.first() {
+        Some(ExtraValue::Bool(_)) => Series::new(
+            name,
+            values
+                .iter()
+                .map(|v| match v {
+                    ExtraValue::Bool(b) => Some(*b),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Some(ExtraValue::Text(_)) => Series::new(
+            name,
+            values
+                .iter()
+                .map(|v| match v {
+                    ExtraValue::Text(t) => t.as_deref(),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+        ),
+        _ => Series::new(
+            name,
+            values
+                .iter()
+                .map(|v| match v {
+                    ExtraValue::Float(f) => *f,
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+/// Default output location: `<pbf dir>/../streetdfs/<state>_<kind>.parquet`
+pub fn default_output_path(pbf_path: &Path, state_name: &str, kind: &str) -> PathBuf {
+    let mut path = pbf_path.parent().unwrap().parent().unwrap().to_path_buf();
+    path.push("streetdfs");
+    std::fs::create_dir_all(&path).ok();
+    path.push(format!("{}_{}.parquet", state_name, kind));
+    path
+}
+
+/// Write to `<path>.tmp` and rename it over `path` only once `write` has succeeded and the data
+/// is on disk, so a crash or full disk never leaves a truncated file at `path`
+fn write_atomically(path: &Path, write: impl FnOnce(&mut std::fs::File) -> Result<()>) -> Result<()> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".tmp");
+    let tmp_path = PathBuf::from(name);
+    
+    let mut file = std::fs::File::create(&tmp_path).map_err(io_error(&tmp_path))?;
+    let written = write(&mut file).and_then(|()| file.sync_all().map_err(io_error(&tmp_path)));
+    drop(file);
+    match written {
+        Ok(()) => std::fs::rename(&tmp_path, path).map_err(io_error(path)),
+        Err(err) => {
+            let _ = std::fs::remove_file(&tmp_path);
+            Err(err)
+        }
+    }
+}
+
+/// Rows per parquet row group. A nationwide streets file is millions of rows, and a single row
+/// group is encoded on one thread; groups of this size are compressed in parallel and still
+/// large enough for column statistics and compression to work well.
+pub const ROW_GROUP_ROWS: usize = 128 * 1024;
+
+/// Write a DataFrame to a parquet file in [`ROW_GROUP_ROWS`] row groups encoded in parallel,
+/// first checking the target has room for it; written atomically through `<path>.tmp`
+pub fn write_parquet(df: &mut DataFrame, path: &Path) -> Result<()> {
+    space::ensure_free_space(path, space::estimate_output_bytes(df))?;
+    write_atomically(path, |file| {
+        ParquetWriter::new(file)
+            .with_row_group_size(Some(ROW_GROUP_ROWS))
+            .set_parallel(true)
+            .finish(df)
+            .map_err(|source| ProcessorError::OutputWrite { path: path.to_path_buf(), source })?;
+        Ok(())
+    })
+}
+
+/// Write a DataFrame as CSV or parquet, depending on the file extension
+pub fn write_table(df: &mut DataFrame, path: &Path) -> Result<()> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => {
+            space::ensure_free_space(path, space::estimate_output_bytes(df))?;
+            write_atomically(path, |file| {
+                CsvWriter::new(file)
+                    .finish(df)
+                    .map_err(|source| ProcessorError::OutputWrite { path: path.to_path_buf(), source })
+            })
+        }
+        _ => write_parquet(df, path),
+    }
+}
+
+/// `<state>_street_adjacency.parquet` next to the streets output
+fn adjacency_output_path(output_path: &Path, state_name: &str) -> PathBuf {
+    output_path.with_file_name(format!("{}_street_adjacency.parquet", state_name))
+}
+
+/// Every file a streets run with these settings writes for `output_path`
+fn planned_outputs(processor: &OsmStreetProcessor, output_path: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![output_path.to_path_buf(), manifest::manifest_path(output_path)];
+    if processor.qa().is_some() {
+        paths.push(qa::qa_output_path(output_path));
+    }
+    if processor.adjacency() {
+        paths.push(adjacency_output_path(output_path, processor.state()));
+    }
+    if processor.emit_segments() {
+        paths.push(segments::segments_output_path(output_path));
+    }
+    if processor.emit_nodes() {
+        paths.push(segments::nodes_output_path(output_path));
+    }
+    if let Some(path) = processor.skipped_log() {
+        paths.push(path.to_path_buf());
+    }
+    if let Some(path) = processor.save_segments() {
+        paths.push(path.to_path_buf());
+    }
+    paths
+}
+
+/// Optional outputs of a streets run besides the streets file itself
+struct SideOutputs {
+    qa: Option<Vec<QaRecord>>,
+    adjacency: Option<HashMap<(String, String), u32>>,
+    segments: Option<Vec<segments::SegmentRecord>>,
+    nodes: Option<Vec<segments::NodeRecord>>,
+}
+
+impl SideOutputs {
+    /// Write each output present next to `output_path`, returning the files and their row counts
+    fn write(self, processor: &OsmStreetProcessor, output_path: &Path) -> Result<Vec<(PathBuf, usize)>> {
+        let mut written = Vec::new();
+        
+        if let Some(records) = self.qa {
+            let qa_path = qa::qa_output_path(output_path);
+            println!("QA: {} streets flagged for review", records.len());
+            println!("Saving QA report to: {}", qa_path.display());
+            let mut qa_df = qa::qa_to_dataframe(records)?;
+            write_parquet(&mut qa_df, &qa_path)?;
+            written.push((qa_path, qa_df.height()));
+        }
+        
+        if let Some(pairs) = self.adjacency {
+            let mut adjacency_df = adjacency::adjacency_to_dataframe(pairs, processor.state())?;
+            let adjacency_path = adjacency_output_path(output_path, processor.state());
+            println!("Saving street adjacency to: {}", adjacency_path.display());
+            write_parquet(&mut adjacency_df, &adjacency_path)?;
+            written.push((adjacency_path, adjacency_df.height()));
+        }
+        
+        if let Some(records) = self.segments {
+            let segments_path = segments::segments_output_path(output_path);
+            let mut segments_df = segments::segments_to_dataframe(
+                &records,
+                processor.feature(),
+                processor.coord_precision(),
+                processor.simplify_tolerance_m(),
+                processor.output_crs(),
+            )?;
+            println!("Saving {} segments to: {}", segments_df.height(), segments_path.display());
+            write_parquet(&mut segments_df, &segments_path)?;
+            written.push((segments_path, segments_df.height()));
+        }
+        
+        if let Some(records) = self.nodes {
+            let nodes_path = segments::nodes_output_path(output_path);
+            let mut nodes_df = segments::nodes_to_dataframe(&records)?;
+            crs::reproject_point_columns(&mut nodes_df, processor.output_crs(), processor.coord_precision())?;
+            println!("Saving {} street nodes to: {}", nodes_df.height(), nodes_path.display());
+            write_parquet(&mut nodes_df, &nodes_path)?;
+            written.push((nodes_path, nodes_df.height()));
+        }
+        
+        Ok(written)
+    }
+}
+
+/// Process a PBF straight to the streets DataFrame without writing any files; the in-memory
+/// equivalent of [`process_osm_to_parquet`]
+pub fn process_to_dataframe(
+    pbf_path: &Path,
+    state_name: &str,
+    distance_threshold_km: f64,
+    feature: Feature,
+) -> Result<DataFrame> {
+    OsmStreetProcessor::builder()
+        .input(pbf_path)
+        .state(state_name)
+        .distance_threshold_km(distance_threshold_km)
+        .feature(feature)
+        .build()?
+        .to_dataframe()
+}
+
+/// Main processing function: run `processor` and write its streets (and street adjacency,
+/// if enabled) to parquet plus a `.manifest.json`, printing a summary. Progress goes wherever
+/// the processor's sink sends it; the CLI configures [`ConsoleProgress`].
+pub fn process_osm_to_parquet(processor: &OsmStreetProcessor, output_path: Option<PathBuf>) -> Result<()> {
+    let (pbf_path, state_name, feature) = (processor.input(), processor.state(), processor.feature());
+    let started = Instant::now();
+    let mut manifest = Manifest::new("streets");
+    manifest.set_option("state", state_name);
+    manifest.options.extend(processor.options().clone());
+    
+    println!("\n{}", "=".repeat(70));
+    println!("OSM TO PARQUET PROCESSOR (Rust)");
+    println!("{}", "=".repeat(70));
+    println!("Input file:  {}", pbf_path.display());
+    println!("State:       {}", state_name);
+    println!("Distance threshold: {} km", processor.distance_threshold_km());
+    println!("Feature:     {}", feature.type_key());
+    println!("{}", "=".repeat(70));
+    
+    // Determine output path
+    let output_path = output_path.unwrap_or_else(|| default_output_path(pbf_path, state_name, feature.output_kind()));
+    
+    // Held until every output is written, so concurrent runs for the same output cannot interleave
+    let _lock = lock::OutputLock::acquire(&output_path)?;
+    
+    // A mistyped state name should not silently replace an earlier dataset
+    let existing: Vec<PathBuf> =
+        planned_outputs(processor, &output_path).into_iter().filter(|path| path.exists()).collect();
+    if !existing.is_empty() {
+        if !processor.overwrite() {
+            return Err(ProcessorError::OutputExists { paths: existing });
+        }
+        for path in &existing {
+            println!("Replacing existing {}", path.display());
+        }
+    }
+    
+    // Two-pass processing and grouping
+    let mut result = processor.run()?;
+    manifest.truncated = result.truncated;
+    manifest.sanitized = result.sanitized;
+    if result.truncated {
+        println!("Maximum runtime reached: writing partial outputs");
+    }
+    manifest.timings_s.insert("process".to_string(), started.elapsed().as_secs_f64());
+    let writing = Instant::now();
+    
+    let side_outputs = SideOutputs {
+        qa: result.qa.take(),
+        adjacency: result.adjacency.take(),
+        segments: result.segments.take(),
+        nodes: result.nodes.take(),
+    };
+    
+    // Convert to DataFrame
+    println!("Creating DataFrame...");
+    let mut df = result.into_dataframe()?;
+    
+    // Show statistics
+    stats::print_run_summary(&df)?;
+    crs::reproject_point_columns(&mut df, processor.output_crs(), processor.coord_precision())?;
+    
+    // Save to parquet: the streets file is encoded on its own thread while the side outputs are
+    // built and written on this one
+    println!("\nSaving to: {}", output_path.display());
+    let (streets_written, side_written) = std::thread::scope(|scope| {
+        let streets = scope.spawn(|| write_parquet(&mut df, &output_path));
+        let side_written = side_outputs.write(processor, &output_path);
+        (streets.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)), side_written)
+    });
+    for (path, rows) in side_written? {
+        manifest.add_output(&path, rows);
+    }
+    streets_written?;
+    manifest.add_output(&output_path, df.height());
+    manifest.timings_s.insert("write".to_string(), writing.elapsed().as_secs_f64());
+    
+    // Provenance: checksum last so it doesn't hold up the outputs
+    manifest.add_input(processor.from_segments().unwrap_or(pbf_path))?;
+    manifest.timings_s.insert("total".to_string(), started.elapsed().as_secs_f64());
+    let manifest_path = manifest::manifest_path(&output_path);
+    println!("Saving manifest to: {}", manifest_path.display());
+    manifest.write(&manifest_path)?;
+    
+    if let (true, Some(max_runtime)) = (manifest.truncated, processor.max_runtime()) {
+        return Err(ProcessorError::Truncated { max_runtime });
+    }
+    println!("Done!");
+    println!("{}", "=".repeat(70));
+    
+    Ok(())
+}
+
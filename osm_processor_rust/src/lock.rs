@@ -0,0 +1,51 @@
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{io_error, ProcessorError, Result};
+
+/// Advisory lock on an output path, held through a sidecar `<output>.lock` file that records the
+/// holder's process ID. Released (and the sidecar removed) when dropped.
+pub(crate) struct OutputLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl OutputLock {
+    /// Lock `output`, or fail with [`ProcessorError::OutputLocked`] if another process holds it
+    pub(crate) fn acquire(output: &Path) -> Result<Self> {
+        let mut name = output.as_os_str().to_owned();
+        name.push(".lock");
+        let path = PathBuf::from(name);
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(io_error(&path))?;
+        if file.try_lock_exclusive().is_err() {
+            let mut holder = String::new();
+            let _ = file.read_to_string(&mut holder);
+            return Err(ProcessorError::OutputLocked {
+                path: output.to_path_buf(),
+                holder: holder.trim().parse().ok(),
+            });
+        }
+
+        file.set_len(0).map_err(io_error(&path))?;
+        file.seek(SeekFrom::Start(0)).map_err(io_error(&path))?;
+        writeln!(file, "{}", std::process::id()).map_err(io_error(&path))?;
+        Ok(OutputLock { file, path })
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        // Remove while still holding the lock, so no other run can lock the file being deleted
+        let _ = std::fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
@@ -1,580 +1,797 @@
 use anyhow::{Context, Result};
-use geo::{HaversineDistance, Point};
-use indicatif::{ProgressBar, ProgressStyle};
-use osmpbf::{Element, ElementReader};
-use polars::prelude::*;
-use rayon::prelude::*;
-use std::collections::{HashMap, HashSet};
+use osm_processor_rust::crs::OutputCrs;
+use osm_processor_rust::grouping::parse_class_thresholds;
+use osm_processor_rust::names::NamePolicy;
+use osm_processor_rust::natural_earth;
+use osm_processor_rust::overture::OvertureConfig;
+use osm_processor_rust::qa::QaConfig;
+use osm_processor_rust::regions::{RegionIndex, RegionLevel, STATE_ADMIN_LEVEL};
+use osm_processor_rust::wikidata::{WikidataConfig, WikidataSource};
+use osm_processor_rust::{
+    boundaries, buildings, graph, grid, places, process_osm_to_parquet, stats, structures, transit,
+    ConsoleProgress, Feature, JsonProgress, OsmStreetProcessor, ProcessorError, ProgressSink,
+    RepPoint, ShardConfig, StreetIdMode, DEFAULT_DISTANCE_THRESHOLD_KM,
+};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-/// A street segment from OSM
-#[derive(Debug, Clone)]
-struct StreetSegment {
-    street_name: String,
-    state: String,
-    way_id: i64,
-    node_ids: Vec<i64>,
-    coords: Vec<(f64, f64)>, // (lat, lon)
-    highway_type: String,
-    tags: HashMap<String, String>,
-    length_km: f64,
+mod analyze;
+mod cli;
+mod crosscheck;
+mod diff;
+mod export;
+mod geocode;
+mod matching;
+mod merge;
+mod nearest;
+#[cfg(feature = "async")]
+mod nominatim;
+mod query;
+mod report;
+mod revgeocode;
+mod rng;
+mod sample;
+mod search;
+mod spatial_join;
+mod tidy;
+mod tiles;
+mod validate;
+
+use cli::CliArgs;
+
+/// Today's UTC date as `YYYY-MM-DD`
+fn utc_date() -> String {
+    let secs = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    civil_date(secs)
+}
+
+/// UTC date of a Unix timestamp as `YYYY-MM-DD` (days-to-civil conversion, proleptic Gregorian)
+fn civil_date(secs: u64) -> String {
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
 }
 
-impl StreetSegment {
-    /// Get representative coordinates (first point)
-    fn rep_coords(&self) -> (f64, f64) {
-        self.coords[0]
+/// Output path from `--output-template`, filling in `{state}`, `{date}` (UTC run date,
+/// YYYY-MM-DD), `{threshold}` (km), `{feature}` (`streets`, `railways`, ...) and `{version}`
+fn expand_output_template(template: &str, state_name: &str, threshold_km: f64, feature: Feature) -> Result<PathBuf> {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        expanded.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}').map(|i| open + i) else {
+            anyhow::bail!("Unclosed {{ in output template {}", template);
+        };
+        match &rest[open + 1..close] {
+            "state" => expanded.push_str(state_name),
+            "date" => expanded.push_str(&utc_date()),
+            "threshold" => expanded.push_str(&threshold_km.to_string()),
+            "feature" => expanded.push_str(feature.output_kind()),
+            "version" => expanded.push_str(env!("CARGO_PKG_VERSION")),
+            other => anyhow::bail!(
+                "Unknown output template variable {{{}}} (expected state, date, threshold, feature or version)",
+                other
+            ),
+        }
+        rest = &rest[close + 1..];
     }
+    expanded.push_str(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+/// Resolve the input PBF path: explicit argument, or `data/osm/<state>-latest.osm.pbf`
+fn resolve_pbf_path(state_name: &str, arg: Option<&String>) -> Result<PathBuf> {
+    let pbf_path = match arg {
+        Some(path) => PathBuf::from(path),
+        None => {
+            // Default: look in data/osm directory
+            let mut path = std::env::current_dir()?;
+            path.push("data");
+            path.push("osm");
+            path.push(format!("{}-latest.osm.pbf", state_name));
+            path
+        }
+    };
     
-    /// Get first and last coordinates for distance checking
-    fn endpoints(&self) -> ((f64, f64), (f64, f64)) {
-        let first = self.coords[0];
-        let last = self.coords[self.coords.len() - 1];
-        (first, last)
+    if !pbf_path.exists() {
+        anyhow::bail!("File not found: {}", pbf_path.display());
     }
+    
+    Ok(pbf_path)
 }
 
-/// A unique street (potentially multiple segments grouped together)
-#[derive(Debug)]
-struct Street {
-    street_name: String,
-    state: String,
-    lat: f64,
-    lon: f64,
-    num_segments: usize,
-    highway_type: String,
-    tags: HashMap<String, String>,
-    length_km: f64,
+fn print_usage(program: &str) {
+    eprintln!("Usage: {} <state_name> [pbf_file] [distance_threshold_km] [output_path] [--feature highway|railway|waterway|cycling|paths|speed] [--emit-adjacency] [--emit-segments] [--emit-nodes] [--simplify-tolerance-m M] [--output-crs EPSG:XXXX] [--coord-precision N] [--threshold-by-class CLASS=KM,...] [--rep-point first|midpoint|longest|centroid] [--street-id ways|point] [--skipped-log FILE.ndjson] [--name-lang LANG,...|local] [--merge-directionals] [--relations] [--hardened] [--checkpoint-nodes] [--save-segments FILE | --from-segments FILE] [--overture [--overture-connectors FILE]] [--wikidata DUMP.json|api|URL [--wikidata-lang LANG]] [--progress console|json] [--max-runtime 2h] [--temp-dir DIR] [--force] [--output-template TEMPLATE] [--states all|NAME,...] [--admin-level N] [--region-level state|county|city] [--boundaries pbf|natural-earth [--boundary-cache DIR]] [--shard-deg X [--shard-halo-deg X] [--jobs N]] [--qa [--qa-max-diameter-km X] [--qa-max-components N]]", program);
+    eprintln!("       {} buildings <state_name> [pbf_file] [output_path] [--coord-precision N]", program);
+    eprintln!("       {} places <state_name> [pbf_file] [output_path] [--coord-precision N]", program);
+    eprintln!("       {} boundaries <state_name> [pbf_file] [output_path] [--coord-precision N]", program);
+    eprintln!("       {} graph <state_name> [pbf_file] [output_dir] [--coord-precision N]", program);
+    eprintln!("       {} grid <state_name> [pbf_file] [output.parquet|output.geojson] [--cell-km X] [--feature F]", program);
+    eprintln!("       {} routes <state_name> [pbf_file] [output_path] [--coord-precision N]", program);
+    eprintln!("       {} structures <state_name> [pbf_file] [output_path] [--coord-precision N]", program);
+    eprintln!("       {} stats <streets.parquet...> [--top N] [--stratify-by highway_type] [--output-dir DIR]", program);
+    eprintln!("       {} diff <old.parquet> <new.parquet> [--tolerance-km X] [--output diff.parquet]", program);
+    eprintln!("       {} merge <in.parquet...> -o <out> [--dedup-border-km X] [--partition-by state]", program);
+    eprintln!("       {} analyze chisq <streets.parquet...> [--min-count N] [--correction bh|bonferroni|none] [--alpha X] [--stratify-by highway_type] --output-dir DIR", program);
+    eprintln!("       {} analyze tfidf <streets.parquet...> [--rank logodds|tfidf] [--top N] [--prior-scale X] [--format parquet|csv] [--stratify-by highway_type] --output-dir DIR", program);
+    eprintln!("       {} analyze diversity <streets.parquet...> [--by-type] [--stratify-by highway_type] [-o <out.csv|out.parquet>]", program);
+    eprintln!("       {} analyze matrix <streets.parquet...> [--format triplet|wide] [--stratify-by highway_type] -o <out.csv|out.parquet>", program);
+    eprintln!("       {} analyze contingency <streets.parquet...> [--by state|highway_type] [--format parquet|csv] [--stratify-by highway_type] --output-dir DIR", program);
+    eprintln!("       {} analyze bootstrap <streets.parquet...> [--replicates N] [--seed S] [--confidence X] [--top N] [--stratify-by highway_type] --output-dir DIR", program);
+    eprintln!("       {} analyze rates <streets.parquet...> [--population pop.csv] [--min-count N] [--stratify-by highway_type] -o <out.csv|out.parquet>", program);
+    eprintln!("       {} analyze similarity <streets.parquet...> [--metric jsd|cosine] [--stratify-by highway_type] --output-dir DIR", program);
+    eprintln!("       {} analyze permtest <streets.parquet...> --name NAME [--states A,B] [--permutations N] [--seed S] [--correction bh|bonferroni|none] [--stratify-by highway_type] [-o <out.csv|out.parquet>]", program);
+    eprintln!("       {} nearest <streets.parquet...> --lat LAT --lon LON [--k N]", program);
+    eprintln!("       {} search \"<name>\" <streets.parquet...> [--state S] [--limit N] [--min-similarity X]", program);
+    eprintln!("       {} report <streets.parquet...> [--state S] [--format md|html] [--top N] [--maps N] --output-dir DIR", program);
+    eprintln!("       {} geocode-index <streets.parquet...> -o <index.geo>", program);
+    eprintln!("       {} geocode \"<name>, <state>\" --index <index.geo> [--near LAT,LON] [--limit N]", program);
+    eprintln!("       {} revgeocode <streets.parquet|segments.parquet...> --lat LAT --lon LON [--k N]", program);
+    eprintln!("       {} spatial-join <streets.parquet...> <zones.geojson> --key KEY[,KEY...] -o <out.csv|out.parquet>", program);
+    eprintln!("       {} export <streets.parquet|segments.parquet...> --format geojsonseq|kml|wkt-csv -o <out> [--state S] [--sample N] [--seed S] [--per-segment]", program);
+    eprintln!("       {} tiles <streets.parquet|segments.parquet...> -o <out.pmtiles> [--min-zoom Z] [--max-zoom Z] [--temp-dir DIR]", program);
+    eprintln!("       {} tidy <streets.parquet...> -o <counts.csv|counts.parquet> [--streets <streets.csv|streets.parquet>]", program);
+    eprintln!("       {} query \"<SQL>\" <streets.parquet...> [-o <out.csv|out.parquet>]", program);
+    eprintln!("       {} sample <streets.parquet...> --n N [--stratify-by state|highway_type] [--seed S] -o <out.csv|out.parquet>", program);
+    eprintln!("       {} crosscheck <streets.parquet...> --tiger <edges.shp> [--max-match-km X] [--output-dir DIR]", program);
+    eprintln!("       {} crosscheck <streets.parquet...> --nominatim <url> [--n N] [--seed S] [--max-match-km X] [--delay-ms MS] [--output-dir DIR]", program);
+    eprintln!("       {} validate <output.parquet> --reference <reference.parquet> [--tolerance-km X] [--max-match-km X] [--output-dir DIR]", program);
+    eprintln!("Example: {} delaware", program);
+    eprintln!("Example: {} california /path/to/california-latest.osm.pbf 0.1", program);
+    eprintln!("Example: {} delaware data/osm/delaware-latest.osm.pbf 1.609 data/streetdfs_1mi/delaware_streets.parquet", program);
+    eprintln!("Example: {} delaware --feature railway", program);
+    eprintln!("Example: {} delaware --feature waterway", program);
+    eprintln!("Example: {} delaware --feature cycling", program);
+    eprintln!("Example: {} delaware --feature paths", program);
+    eprintln!("Example: {} delaware --feature speed", program);
+    eprintln!("Example: {} delaware --emit-adjacency", program);
+    eprintln!("Example: {} delaware --emit-segments", program);
+    eprintln!("Example: {} delaware --emit-segments --emit-nodes", program);
+    eprintln!("Example: {} delaware --emit-segments --simplify-tolerance-m 5", program);
+    eprintln!("Example: {} delaware --emit-segments --output-crs EPSG:32618", program);
+    eprintln!("Example: {} delaware --coord-precision 6", program);
+    eprintln!("Example: {} delaware --threshold-by-class motorway=1,trunk=1,primary=1,residential=0.1", program);
+    eprintln!("Example: {} delaware --rep-point midpoint", program);
+    eprintln!("Example: {} us data/osm/us-latest.osm.pbf --states all", program);
+    eprintln!("Example: {} planet data/osm/planet-latest.osm.pbf --shard-deg 5 --jobs 4", program);
+    eprintln!("Example: {} us data/osm/us-latest.osm.pbf --states all --checkpoint-nodes", program);
+    eprintln!("Example: {} us data/osm/us-latest.osm.pbf --states all --checkpoint-nodes --temp-dir /scratch", program);
+    eprintln!("Example: {} delaware --progress json 2> progress.ndjson", program);
+    eprintln!("Example: {} delaware --output-template \"out/{{state}}/{{date}}/{{state}}_streets_{{threshold}}km.parquet\"", program);
+    eprintln!("Example: {} us data/osm/us-latest.osm.pbf --states all --max-runtime 2h", program);
+    eprintln!("Example: {} delaware --region-level city", program);
+    eprintln!("Example: {} germany data/osm/germany-latest.osm.pbf --admin-level 4", program);
+    eprintln!("Example: {} quebec --name-lang en,local", program);
+    eprintln!("Example: {} district-of-columbia --merge-directionals", program);
+    eprintln!("Example: {} berlin --relations", program);
+    eprintln!("Example: {} berlin --wikidata api --wikidata-lang de", program);
+    eprintln!("Example: {} buildings delaware", program);
+    eprintln!("Example: {} geocode-index data/streetdfs/delaware_streets.parquet -o delaware.geo", program);
+    eprintln!("Example: {} geocode \"N Main St, delaware\" --index delaware.geo --near 39.16,-75.52", program);
+    eprintln!("Example: {} revgeocode data/streetdfs/delaware_streets_segments.parquet --lat 39.7391 --lon -75.5398", program);
+    eprintln!("Example: {} spatial-join data/streetdfs/delaware_streets.parquet zones.geojson --key zone_id -o zoned.parquet", program);
+    eprintln!("Example: {} export data/streetdfs/delaware_streets_segments.parquet --format geojsonseq -o delaware.geojsons", program);
+    eprintln!("Example: {} export data/streetdfs/delaware_streets.parquet --format kml --sample 500 -o delaware_review.kmz", program);
+    eprintln!("Example: {} export data/streetdfs/delaware_streets_segments.parquet --format wkt-csv -o delaware_wkt.csv", program);
+    eprintln!("Example: {} tiles data/streetdfs/delaware_streets_segments.parquet -o delaware.pmtiles --max-zoom 14", program);
+    eprintln!("Example: {} tidy data/streetdfs/*_streets.parquet -o name_counts.csv --streets streets_covariates.csv", program);
+    eprintln!("Example: {} crosscheck data/streetdfs/delaware_streets.parquet --tiger tl_2023_10001_edges.shp --output-dir crosscheck", program);
+    eprintln!("Example: {} crosscheck data/streetdfs/delaware_streets.parquet --nominatim http://localhost:8080 --n 500 --delay-ms 0", program);
 }
 
-/// First pass: collect which nodes are used by named highways
-fn collect_highway_nodes(pbf_path: &Path) -> Result<HashSet<i64>> {
-    println!("Pass 1: Identifying nodes used by named highways...");
-    
-    let reader = ElementReader::from_path(pbf_path)
-        .context("Failed to open OSM file")?;
-    
-    let mut highway_nodes = HashSet::new();
-    let mut way_count = 0;
-    
-    reader.for_each(|element| {
-        if let Element::Way(way) = element {
-            let tags: HashMap<_, _> = way.tags().collect();
-            
-            // Check if this way has both a name and is a highway
-            if tags.contains_key("name") && tags.contains_key("highway") {
-                way_count += 1;
-                for node_id in way.refs() {
-                    highway_nodes.insert(node_id);
-                }
-            }
-        }
-    })?;
-    
-    println!("  Found {} named highways using {} nodes", way_count, highway_nodes.len());
-    Ok(highway_nodes)
+/// Exit code of a streets run stopped by `--max-runtime` after writing partial outputs, so
+/// schedulers can tell it from a failure (1)
+const EXIT_TRUNCATED: i32 = 3;
+
+/// Progress display for `--progress console|json` (console when not given)
+fn progress_sink(value: Option<&str>) -> Result<Arc<dyn ProgressSink>> {
+    match value.unwrap_or("console") {
+        "console" => Ok(Arc::new(ConsoleProgress::default())),
+        "json" => Ok(Arc::new(JsonProgress::default())),
+        other => anyhow::bail!("Unknown progress format {} (expected console or json)", other),
+    }
 }
 
-/// Second pass: extract street segments with coordinates
-fn extract_street_segments(
-    pbf_path: &Path,
-    state_name: &str,
-    highway_nodes: &HashSet<i64>,
-) -> Result<Vec<StreetSegment>> {
-    println!("Pass 2: Extracting street segments...");
-    
-    // First pass through file: collect node coordinates
-    println!("  Loading node coordinates...");
-    let reader = ElementReader::from_path(pbf_path)
-        .context("Failed to open OSM file")?;
-    
-    // Use par_map_reduce to collect nodes in parallel
-    let (node_coords, node_count, matched_count) = reader.par_map_reduce(
-        |element| {
-            let mut coords = HashMap::new();
-            let mut total = 0;
-            let mut matched = 0;
-            
-            match element {
-                Element::Node(node) => {
-                    total = 1;
-                    if highway_nodes.contains(&node.id()) {
-                        matched = 1;
-                        coords.insert(node.id(), (node.lat(), node.lon()));
-                    }
-                }
-                Element::DenseNode(node) => {
-                    total = 1;
-                    if highway_nodes.contains(&node.id()) {
-                        matched = 1;
-                        coords.insert(node.id(), (node.lat(), node.lon()));
-                    }
-                }
-                _ => {}
-            }
-            
-            (coords, total, matched)
-        },
-        || (HashMap::new(), 0, 0),
-        |mut a, b| {
-            a.0.extend(b.0);
-            a.1 += b.1;
-            a.2 += b.2;
-            a
-        },
+/// Streets mode (default): `<state_name> [pbf_file] [distance_threshold_km] [output_path] [options]`
+fn run_streets(args: &[String]) -> Result<()> {
+    let args = CliArgs::parse(
+        args,
+        &[
+            "feature",
+            "coord-precision",
+            "simplify-tolerance-m",
+            "output-crs",
+            "threshold-by-class",
+            "rep-point",
+            "skipped-log",
+            "street-id",
+            "qa-max-diameter-km",
+            "qa-max-components",
+            "name-lang",
+            "states",
+            "admin-level",
+            "region-level",
+            "shard-deg",
+            "shard-halo-deg",
+            "jobs",
+            "progress",
+            "max-runtime",
+            "temp-dir",
+            "output-template",
+            "save-segments",
+            "from-segments",
+            "overture-connectors",
+            "boundaries",
+            "boundary-cache",
+            "wikidata",
+            "wikidata-lang",
+        ],
+        &["emit-adjacency", "emit-segments", "emit-nodes", "qa", "merge-directionals", "relations", "hardened", "checkpoint-nodes", "force", "overture"],
     )?;
     
-    println!("  Scanned {} nodes, matched {} highway nodes, loaded {} coordinates", 
-             node_count, matched_count, node_coords.len());
+    let Some(state_name) = args.positional(0).map(|s| s.to_lowercase()) else {
+        anyhow::bail!("missing <state_name>");
+    };
+    let pbf_path = resolve_pbf_path(&state_name, args.positional(1))?;
     
-    // Second pass through file: extract ways
-    println!("  Extracting ways...");
-    let reader = ElementReader::from_path(pbf_path)
-        .context("Failed to open OSM file")?;
+    let distance_threshold_km = match args.positional(2) {
+        Some(value) => value.parse().context("Invalid distance threshold")?,
+        None => DEFAULT_DISTANCE_THRESHOLD_KM, // 200m
+    };
     
-    let mut segments = Vec::new();
+    let feature = match args.value("feature") {
+        Some(value) => Feature::parse(value)?,
+        None => Feature::Highway,
+    };
     
-    reader.for_each(|element| {
-        if let Element::Way(way) = element {
-            let tags: HashMap<String, String> = way
-                .tags()
-                .map(|(k, v)| (k.to_string(), v.to_string()))
-                .collect();
-            
-            if let (Some(name), Some(highway_type)) = (tags.get("name"), tags.get("highway")) {
-                // Collect coordinates for this way
-                let coords: Vec<(f64, f64)> = way
-                    .refs()
-                    .filter_map(|node_id| node_coords.get(&node_id).copied())
-                    .collect();
-                
-                if !coords.is_empty() {
-                    // Calculate segment length using Haversine distance
-                    let mut length_km = 0.0;
-                    for i in 0..(coords.len() - 1) {
-                        let p1 = Point::new(coords[i].1, coords[i].0); // (lon, lat)
-                        let p2 = Point::new(coords[i + 1].1, coords[i + 1].0); // (lon, lat)
-                        length_km += p1.haversine_distance(&p2) / 1000.0; // Convert meters to km
-                    }
-                    
-                    segments.push(StreetSegment {
-                        street_name: name.clone(),
-                        state: state_name.to_string(),
-                        way_id: way.id(),
-                        node_ids: way.refs().collect(),
-                        coords,
-                        highway_type: highway_type.clone(),
-                        tags,
-                        length_km,
-                    });
-                }
+    let output_path = match (args.positional(3), args.value("output-template")) {
+        (Some(_), Some(_)) => anyhow::bail!("give either [output_path] or --output-template, not both"),
+        (Some(path), None) => Some(PathBuf::from(path)),
+        (None, Some(template)) => {
+            let path = expand_output_template(template, &state_name, distance_threshold_km, feature)?;
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
             }
+            Some(path)
         }
-    })?;
-    
-    println!("  Found {} street segments", segments.len());
-    Ok(segments)
-}
-
-/// Group segments into connected components using node sharing
-fn find_connected_components(segments: &[StreetSegment]) -> Vec<Vec<usize>> {
-    if segments.is_empty() {
-        return Vec::new();
-    }
+        (None, None) => None,
+    };
     
-    let n = segments.len();
+    let region_level = match args.value("region-level") {
+        Some(value) => RegionLevel::parse(value)?,
+        None => RegionLevel::State,
+    };
     
-    // Build adjacency list based on shared nodes
-    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); n];
+    // Outside the US, pick which admin_level is the "state" (4 = Bundesländer, 6 = départements);
+    // giving one implies --states all
+    let admin_level: Option<u8> = args.parsed("admin-level")?;
+    let states = args.value("states").or(admin_level.map(|_| "all"));
+    
+    // State/county/city polygons come from the boundary relations in the input itself, or from
+    // Natural Earth (downloaded once into the cache dir) with --boundaries natural-earth
+    let natural_earth = match args.value("boundaries") {
+        None | Some("pbf") => false,
+        Some("natural-earth") => true,
+        Some(other) => anyhow::bail!("Unknown boundary source {} (expected pbf or natural-earth)", other),
+    };
+    if natural_earth && admin_level.is_some() {
+        anyhow::bail!("--admin-level picks among PBF boundaries; Natural Earth only has states and counties");
+    }
     
-    // For efficiency, build a map of node_id -> segment indices
-    let mut node_to_segments: HashMap<i64, Vec<usize>> = HashMap::new();
-    for (i, seg) in segments.iter().enumerate() {
-        for &node_id in &seg.node_ids {
-            node_to_segments.entry(node_id).or_default().push(i);
-        }
+    let overture = args.flag("overture") || args.value("overture-connectors").is_some();
+    if overture && !natural_earth && (states.is_some() || region_level != RegionLevel::State) {
+        anyhow::bail!("--states and --region-level with --overture need --boundaries natural-earth");
     }
     
-    // Connect segments that share nodes
-    for segment_indices in node_to_segments.values() {
-        for i in 0..segment_indices.len() {
-            for j in (i + 1)..segment_indices.len() {
-                let idx_i = segment_indices[i];
-                let idx_j = segment_indices[j];
-                adj[idx_i].push(idx_j);
-                adj[idx_j].push(idx_i);
+    let state_names: Option<Vec<String>> = states.map(|spec| spec.split(',').map(|s| s.trim().to_string()).collect());
+    let state_names = state_names.as_deref().filter(|_| states != Some("all"));
+    let (regions, sub_regions) = if natural_earth {
+        let cache_dir = args.value("boundary-cache").map(PathBuf::from).unwrap_or_else(natural_earth::default_cache_dir);
+        let regions = match states {
+            Some(_) => Some(natural_earth::regions(RegionLevel::State, state_names, &cache_dir)?),
+            None => None,
+        };
+        let sub_regions = match region_level {
+            RegionLevel::State => None,
+            level => Some(natural_earth::regions(level, None, &cache_dir)?),
+        };
+        (regions, sub_regions)
+    } else {
+        let boundaries = if states.is_some() || region_level != RegionLevel::State {
+            boundaries::extract_boundaries(&pbf_path)?
+        } else {
+            Vec::new()
+        };
+        
+        // Multi-state file: assign segments to the state containing them
+        let regions = match states {
+            Some(_) => {
+                let level = admin_level.unwrap_or(STATE_ADMIN_LEVEL);
+                Some(RegionIndex::from_boundaries(boundaries.clone(), level, state_names)?)
             }
-        }
-    }
+            None => None,
+        };
+        let sub_regions = match region_level {
+            RegionLevel::State => None,
+            level => Some(RegionIndex::from_boundaries(boundaries, level.admin_level(), None)?),
+        };
+        (regions, sub_regions)
+    };
     
-    // Find connected components using BFS
-    let mut visited = vec![false; n];
-    let mut components = Vec::new();
+    let mut builder = OsmStreetProcessor::builder()
+        .input(pbf_path)
+        .state(state_name)
+        .distance_threshold_km(distance_threshold_km)
+        .feature(feature)
+        .merge_directionals(args.flag("merge-directionals"))
+        .relations(args.flag("relations"))
+        .hardened(args.flag("hardened"))
+        .checkpoint_nodes(args.flag("checkpoint-nodes"))
+        .overwrite(args.flag("force"))
+        .adjacency(args.flag("emit-adjacency"))
+        .segments(args.flag("emit-segments"))
+        .nodes(args.flag("emit-nodes"))
+        .progress(progress_sink(args.value("progress"))?);
+    if let Some(digits) = args.parsed("coord-precision")? {
+        builder = builder.coord_precision(digits);
+    }
+    if let Some(dir) = args.value("temp-dir") {
+        builder = builder.temp_dir(dir);
+    }
+    if let Some(path) = args.value("save-segments") {
+        builder = builder.save_segments(path);
+    }
+    if let Some(path) = args.value("from-segments") {
+        builder = builder.from_segments(path);
+    }
+    if overture {
+        builder = builder.overture(OvertureConfig { connectors: args.value("overture-connectors").map(PathBuf::from) });
+    }
+    if let Some(source) = args.value("wikidata") {
+        let lang = args.value("wikidata-lang").unwrap_or("en").to_string();
+        builder = builder.wikidata(WikidataConfig { source: WikidataSource::parse(source), lang });
+    } else if args.value("wikidata-lang").is_some() {
+        anyhow::bail!("--wikidata-lang needs --wikidata");
+    }
+    if let Some(value) = args.value("max-runtime") {
+        builder = builder.max_runtime(cli::parse_duration(value)?);
+    }
+    if let Some(meters) = args.parsed("simplify-tolerance-m")? {
+        builder = builder.simplify_tolerance_m(meters);
+    }
+    if let Some(value) = args.value("output-crs") {
+        builder = builder.output_crs(OutputCrs::parse(value)?);
+    }
+    if let Some(spec) = args.value("threshold-by-class") {
+        builder = builder.threshold_by_class(parse_class_thresholds(spec)?);
+    }
+    if let Some(value) = args.value("rep-point") {
+        builder = builder.rep_point(RepPoint::parse(value)?);
+    }
+    if let Some(value) = args.value("street-id") {
+        builder = builder.street_id_mode(StreetIdMode::parse(value)?);
+    }
+    if let Some(spec) = args.value("name-lang") {
+        builder = builder.name_lang(NamePolicy::parse(spec)?);
+    }
+    if let Some(path) = args.value("skipped-log") {
+        builder = builder.skipped_log(path);
+    }
+    if let Some(regions) = regions {
+        builder = builder.regions(Arc::new(regions));
+    }
+    if let Some(sub_regions) = sub_regions {
+        builder = builder.region_level(region_level, Arc::new(sub_regions));
+    }
+    if let Some(shard_deg) = args.parsed("shard-deg")? {
+        let defaults = ShardConfig::default();
+        builder = builder.shards(ShardConfig {
+            shard_deg,
+            halo_deg: args.parsed("shard-halo-deg")?.unwrap_or(defaults.halo_deg),
+            jobs: args.parsed("jobs")?.unwrap_or(defaults.jobs),
+        });
+    }
+    if args.flag("qa") {
+        let defaults = QaConfig::default();
+        builder = builder.qa(QaConfig {
+            max_diameter_km: args.parsed("qa-max-diameter-km")?.unwrap_or(defaults.max_diameter_km),
+            max_components: args.parsed("qa-max-components")?.unwrap_or(defaults.max_components),
+        });
+    }
     
-    for start in 0..n {
-        if !visited[start] {
-            let mut component = Vec::new();
-            let mut queue = vec![start];
-            visited[start] = true;
-            
-            while let Some(current) = queue.pop() {
-                component.push(current);
-                for &neighbor in &adj[current] {
-                    if !visited[neighbor] {
-                        visited[neighbor] = true;
-                        queue.push(neighbor);
-                    }
-                }
-            }
-            
-            components.push(component);
+    match process_osm_to_parquet(&builder.build()?, output_path) {
+        Err(err @ ProcessorError::Truncated { .. }) => {
+            eprintln!("{}", err);
+            std::process::exit(EXIT_TRUNCATED);
         }
+        result => Ok(result?),
     }
-    
-    components
 }
 
-/// Group segments with same name using spatial proximity (for disconnected segments)
-/// Optimized: checks minimum distance between endpoints (first/last nodes) of segments
-fn group_nearby_components(
-    segments: &[StreetSegment],
-    components: Vec<Vec<usize>>,
-    distance_threshold_km: f64,
-) -> Vec<Vec<usize>> {
-    if components.len() <= 1 {
-        return components;
-    }
+/// Arguments shared by the extraction modes:
+/// `<state_name> [pbf_file] [output_path] [--coord-precision N]`
+fn parse_extract_args(mode: &str, args: &[String]) -> Result<(String, PathBuf, Option<PathBuf>, Option<u32>)> {
+    let args = CliArgs::parse(args, &["coord-precision"], &[])?;
+    let Some(state_name) = args.positional(0).map(|s| s.to_lowercase()) else {
+        anyhow::bail!("{}: missing <state_name>", mode);
+    };
+    let pbf_path = resolve_pbf_path(&state_name, args.positional(1))?;
+    let output_path = args.positional(2).map(PathBuf::from);
     
-    // Build connectivity graph based on distance threshold
-    // This matches the Python algorithm exactly
-    let n = components.len();
-    let mut connections: Vec<Vec<usize>> = vec![Vec::new(); n];
+    Ok((state_name, pbf_path, output_path, args.parsed("coord-precision")?))
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
     
-    // Progress bar for distance checks
-    let pb = ProgressBar::new((n * (n - 1) / 2) as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("  [{bar:40}] {pos}/{len} pairs ({eta})")
-            .unwrap()
-            .progress_chars("=>-"),
-    );
+    if args.len() < 2 {
+        print_usage(&args[0]);
+        std::process::exit(1);
+    }
     
-    // Check all pairs of components (quadratic, like Python)
-    // Optimized to only check first and last nodes of each segment
-    for i in 0..n {
-        for j in (i + 1)..n {
-            pb.inc(1);
-            // Check minimum distance between endpoints (first/last nodes) of segments
-            let mut min_dist = f64::INFINITY;
-            
-            for &seg_i in &components[i] {
-                let (start_i, end_i) = segments[seg_i].endpoints();
-                let p1_start = Point::new(start_i.1, start_i.0); // (lon, lat)
-                let p1_end = Point::new(end_i.1, end_i.0); // (lon, lat)
-                
-                for &seg_j in &components[j] {
-                    let (start_j, end_j) = segments[seg_j].endpoints();
-                    let p2_start = Point::new(start_j.1, start_j.0); // (lon, lat)
-                    let p2_end = Point::new(end_j.1, end_j.0); // (lon, lat)
-                    
-                    // Check all endpoint combinations
-                    let dists = [
-                        p1_start.haversine_distance(&p2_start) / 1000.0, // Convert meters to km
-                        p1_start.haversine_distance(&p2_end) / 1000.0,
-                        p1_end.haversine_distance(&p2_start) / 1000.0,
-                        p1_end.haversine_distance(&p2_end) / 1000.0,
-                    ];
-                    
-                    min_dist = min_dist.min(dists[0].min(dists[1].min(dists[2].min(dists[3]))));
+    match args[1].as_str() {
+        "buildings" => {
+            let (state_name, pbf_path, output_path, precision) = parse_extract_args("buildings", &args[2..])?;
+            Ok(buildings::process_buildings_to_parquet(&pbf_path, &state_name, output_path, precision)?)
+        }
+        "boundaries" => {
+            let (state_name, pbf_path, output_path, precision) = parse_extract_args("boundaries", &args[2..])?;
+            Ok(boundaries::process_boundaries_to_parquet(&pbf_path, &state_name, output_path, precision)?)
+        }
+        "graph" => {
+            let (state_name, pbf_path, output_dir, precision) = parse_extract_args("graph", &args[2..])?;
+            Ok(graph::process_graph_to_parquet(&pbf_path, &state_name, output_dir, precision)?)
+        }
+        "grid" => {
+            let args = CliArgs::parse(&args[2..], &["cell-km", "feature"], &[])?;
+            let (state_name, pbf_path, output_path, _) = parse_extract_args("grid", args.positionals())?;
+            let feature = match args.value("feature") {
+                Some(value) => Feature::parse(value)?,
+                None => Feature::Highway,
+            };
+            let cell_km = args.parsed("cell-km")?.unwrap_or(1.0);
+            Ok(grid::process_grid(&pbf_path, &state_name, output_path, cell_km, feature)?)
+        }
+        "routes" => {
+            let (state_name, pbf_path, output_path, precision) = parse_extract_args("routes", &args[2..])?;
+            Ok(transit::process_routes_to_parquet(&pbf_path, &state_name, output_path, precision)?)
+        }
+        "structures" => {
+            let (state_name, pbf_path, output_path, precision) = parse_extract_args("structures", &args[2..])?;
+            Ok(structures::process_structures_to_parquet(&pbf_path, &state_name, output_path, precision)?)
+        }
+        "stats" => {
+            let args = CliArgs::parse(&args[2..], &["top", "stratify-by", "output-dir"], &[])?;
+            let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                anyhow::bail!("stats: expected at least one streets parquet file");
+            }
+            let top_n = args.parsed("top")?.unwrap_or(20);
+            let output_dir = args.value("output-dir").map(PathBuf::from);
+            Ok(stats::run_stats(&paths, top_n, args.value("stratify-by"), output_dir.as_deref())?)
+        }
+        "diff" => {
+            let args = CliArgs::parse(&args[2..], &["tolerance-km", "output"], &[])?;
+            let (Some(old_path), Some(new_path)) = (args.positional(0), args.positional(1)) else {
+                anyhow::bail!("diff: expected <old.parquet> <new.parquet>");
+            };
+            let tolerance_km = args.parsed("tolerance-km")?.unwrap_or(0.5);
+            let output_path = args.value("output").map(PathBuf::from);
+            diff::run_diff(Path::new(old_path), Path::new(new_path), tolerance_km, output_path)
+        }
+        "merge" => {
+            let args = CliArgs::parse(&args[2..], &["output", "dedup-border-km", "partition-by"], &[])?;
+            let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                anyhow::bail!("merge: expected at least one parquet file");
+            }
+            let Some(output_path) = args.value("output").map(PathBuf::from) else {
+                anyhow::bail!("merge: missing -o/--output");
+            };
+            let partition_by_state = match args.value("partition-by") {
+                Some("state") => true,
+                Some(other) => anyhow::bail!("merge: can only partition by state, not {}", other),
+                None => false,
+            };
+            merge::run_merge(&paths, &output_path, args.parsed("dedup-border-km")?, partition_by_state)
+        }
+        "validate" => {
+            let args = CliArgs::parse(&args[2..], &["reference", "tolerance-km", "max-match-km", "output-dir"], &[])?;
+            let (Some(output_path), Some(reference_path)) = (args.positional(0), args.value("reference")) else {
+                anyhow::bail!("validate: expected <output.parquet> --reference <reference.parquet>");
+            };
+            let options = validate::ValidateOptions {
+                tolerance_km: args.parsed("tolerance-km")?.unwrap_or(0.001),
+                max_match_km: args.parsed("max-match-km")?.unwrap_or(5.0),
+            };
+            let report_dir = args.value("output-dir").map(PathBuf::from);
+            validate::run_validate(Path::new(output_path), Path::new(reference_path), &options, report_dir.as_deref())
+        }
+        "crosscheck" => {
+            let args = CliArgs::parse(
+                &args[2..],
+                &["tiger", "nominatim", "n", "seed", "delay-ms", "max-match-km", "output-dir"],
+                &[],
+            )?;
+            let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                anyhow::bail!("crosscheck: expected at least one streets parquet file");
+            }
+            let report_dir = args.value("output-dir").map(PathBuf::from);
+            if let Some(base_url) = args.value("nominatim") {
+                if args.value("tiger").is_some() {
+                    anyhow::bail!("crosscheck: --tiger and --nominatim are separate checks; run one at a time");
                 }
+                #[cfg(feature = "async")]
+                {
+                    let options = nominatim::NominatimOptions {
+                        base_url,
+                        n: args.parsed("n")?.unwrap_or(100),
+                        seed: args.parsed("seed")?.unwrap_or(42),
+                        // Nominatim places a street at one of its ways, not its representative point
+                        max_match_km: args.parsed("max-match-km")?.unwrap_or(1.0),
+                        delay: std::time::Duration::from_millis(args.parsed("delay-ms")?.unwrap_or(1000)),
+                    };
+                    return nominatim::run_nominatim_crosscheck(&paths, &options, report_dir.as_deref());
+                }
+                #[cfg(not(feature = "async"))]
+                anyhow::bail!(
+                    "crosscheck: querying {} needs the `async` feature (cargo build --release --features async)",
+                    base_url
+                );
             }
-            
-            if min_dist < distance_threshold_km {
-                connections[i].push(j);
-                connections[j].push(i);
+            let Some(tiger_path) = args.value("tiger") else {
+                anyhow::bail!("crosscheck: missing --tiger <edges.shp> or --nominatim <url>");
+            };
+            crosscheck::run_crosscheck(
+                &paths,
+                Path::new(tiger_path),
+                args.parsed("max-match-km")?.unwrap_or(0.5),
+                report_dir.as_deref(),
+            )
+        }
+        "sample" => {
+            let args = CliArgs::parse(&args[2..], &["n", "stratify-by", "seed", "output"], &[])?;
+            let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                anyhow::bail!("sample: expected at least one streets parquet file");
             }
+            let Some(n) = args.parsed("n")? else {
+                anyhow::bail!("sample: missing --n");
+            };
+            let Some(output_path) = args.value("output").map(PathBuf::from) else {
+                anyhow::bail!("sample: missing -o/--output");
+            };
+            let seed = args.parsed("seed")?.unwrap_or(42);
+            sample::run_sample(&paths, n, args.value("stratify-by"), seed, &output_path)
         }
-    }
-    
-    pb.finish_and_clear();
-    
-    // Find connected components using BFS (same as Python)
-    let mut visited = vec![false; n];
-    let mut final_components = Vec::new();
-    
-    for start in 0..n {
-        if !visited[start] {
-            let mut merged_component = Vec::new();
-            let mut queue = vec![start];
-            visited[start] = true;
-            
-            while let Some(current) = queue.pop() {
-                // Add all segments from this component
-                merged_component.extend(&components[current]);
-                
-                for &neighbor in &connections[current] {
-                    if !visited[neighbor] {
-                        visited[neighbor] = true;
-                        queue.push(neighbor);
-                    }
+        "nearest" => {
+            let args = CliArgs::parse(&args[2..], &["lat", "lon", "k"], &[])?;
+            let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                anyhow::bail!("nearest: expected at least one streets parquet file");
+            }
+            let (Some(lat), Some(lon)) = (args.parsed("lat")?, args.parsed("lon")?) else {
+                anyhow::bail!("nearest: missing --lat/--lon");
+            };
+            nearest::run_nearest(&paths, lat, lon, args.parsed("k")?.unwrap_or(5))
+        }
+        "search" => {
+            let args = CliArgs::parse(&args[2..], &["state", "limit", "min-similarity"], &[])?;
+            let Some((query, files)) = args.positionals().split_first() else {
+                anyhow::bail!("search: expected \"<name>\" <streets.parquet...>");
+            };
+            if files.is_empty() {
+                anyhow::bail!("search: expected at least one streets parquet file");
+            }
+            let paths: Vec<PathBuf> = files.iter().map(PathBuf::from).collect();
+            search::run_search(
+                query,
+                &paths,
+                args.value("state"),
+                args.parsed("limit")?.unwrap_or(20),
+                args.parsed("min-similarity")?.unwrap_or(0.2),
+            )
+        }
+        "report" => {
+            let args = CliArgs::parse(&args[2..], &["state", "format", "top", "maps", "output-dir"], &[])?;
+            let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                anyhow::bail!("report: expected at least one streets parquet file");
+            }
+            let Some(output_dir) = args.value("output-dir").map(PathBuf::from) else {
+                anyhow::bail!("report: missing --output-dir");
+            };
+            let format = match args.value("format") {
+                Some(value) => report::ReportFormat::parse(value)?,
+                None => report::ReportFormat::Html,
+            };
+            report::run_report(
+                &paths,
+                args.value("state"),
+                format,
+                args.parsed("top")?.unwrap_or(20),
+                args.parsed("maps")?.unwrap_or(5),
+                &output_dir,
+            )
+        }
+        "geocode-index" => {
+            let args = CliArgs::parse(&args[2..], &["output"], &[])?;
+            let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                anyhow::bail!("geocode-index: expected at least one streets parquet file");
+            }
+            let Some(output_path) = args.value("output").map(PathBuf::from) else {
+                anyhow::bail!("geocode-index: missing -o/--output");
+            };
+            geocode::run_geocode_index(&paths, &output_path)
+        }
+        "geocode" => {
+            let args = CliArgs::parse(&args[2..], &["index", "near", "limit"], &[])?;
+            let (Some(query), Some(index_path)) = (args.positional(0), args.value("index")) else {
+                anyhow::bail!("geocode: expected \"<name>, <state>\" --index <index.geo>");
+            };
+            let near = match args.value("near") {
+                Some(value) => {
+                    let parsed = value
+                        .split_once(',')
+                        .and_then(|(lat, lon)| Some((lat.trim().parse().ok()?, lon.trim().parse().ok()?)));
+                    Some(parsed.with_context(|| format!("Invalid value for --near: {} (expected LAT,LON)", value))?)
                 }
+                None => None,
+            };
+            geocode::run_geocode(query, Path::new(index_path), near, args.parsed("limit")?.unwrap_or(10))
+        }
+        "revgeocode" => {
+            let args = CliArgs::parse(&args[2..], &["lat", "lon", "k"], &[])?;
+            let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                anyhow::bail!("revgeocode: expected at least one streets or segments parquet file");
+            }
+            let (Some(lat), Some(lon)) = (args.parsed("lat")?, args.parsed("lon")?) else {
+                anyhow::bail!("revgeocode: missing --lat/--lon");
+            };
+            revgeocode::run_revgeocode(&paths, lat, lon, args.parsed("k")?.unwrap_or(1))
+        }
+        "spatial-join" => {
+            let args = CliArgs::parse(&args[2..], &["key", "output"], &[])?;
+            let Some((zones_path, files)) = args.positionals().split_last() else {
+                anyhow::bail!("spatial-join: expected <streets.parquet...> <zones.geojson>");
+            };
+            if files.is_empty() {
+                anyhow::bail!("spatial-join: expected at least one streets parquet file");
             }
-            
-            final_components.push(merged_component);
+            let Some(keys) = args.value("key") else {
+                anyhow::bail!("spatial-join: missing --key");
+            };
+            let keys: Vec<String> = keys.split(',').map(|key| key.trim().to_string()).filter(|key| !key.is_empty()).collect();
+            let Some(output_path) = args.value("output").map(PathBuf::from) else {
+                anyhow::bail!("spatial-join: missing -o/--output");
+            };
+            let paths: Vec<PathBuf> = files.iter().map(PathBuf::from).collect();
+            spatial_join::run_spatial_join(&paths, Path::new(zones_path), &keys, &output_path)
+        }
+        "export" => {
+            let args = CliArgs::parse(&args[2..], &["format", "output", "state", "sample", "seed"], &["per-segment"])?;
+            let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                anyhow::bail!("export: expected at least one streets or segments parquet file");
+            }
+            let Some(format) = args.value("format") else {
+                anyhow::bail!("export: missing --format");
+            };
+            let Some(output_path) = args.value("output").map(PathBuf::from) else {
+                anyhow::bail!("export: missing -o/--output");
+            };
+            export::run_export(
+                &paths,
+                export::ExportFormat::parse(format)?,
+                &output_path,
+                args.value("state"),
+                args.parsed("sample")?,
+                args.parsed("seed")?.unwrap_or(42),
+                args.flag("per-segment"),
+            )
         }
+        "tiles" => {
+            let args = CliArgs::parse(&args[2..], &["output", "min-zoom", "max-zoom", "temp-dir"], &[])?;
+            let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                anyhow::bail!("tiles: expected at least one streets or segments parquet file");
+            }
+            let Some(output_path) = args.value("output").map(PathBuf::from) else {
+                anyhow::bail!("tiles: missing -o/--output");
+            };
+            tiles::run_tiles(
+                &paths,
+                &output_path,
+                args.parsed("min-zoom")?.unwrap_or(4),
+                args.parsed("max-zoom")?.unwrap_or(14),
+                &args.value("temp-dir").map_or_else(std::env::temp_dir, PathBuf::from),
+            )
+        }
+        "analyze" => analyze::run_analyze(&args[2..]),
+        "tidy" => {
+            let args = CliArgs::parse(&args[2..], &["output", "streets"], &[])?;
+            let paths: Vec<PathBuf> = args.positionals().iter().map(PathBuf::from).collect();
+            if paths.is_empty() {
+                anyhow::bail!("tidy: expected at least one streets parquet file");
+            }
+            let Some(output_path) = args.value("output").map(PathBuf::from) else {
+                anyhow::bail!("tidy: missing -o/--output");
+            };
+            let streets_path = args.value("streets").map(PathBuf::from);
+            tidy::run_tidy(&paths, &output_path, streets_path.as_deref())
+        }
+        "query" => {
+            let args = CliArgs::parse(&args[2..], &["output"], &[])?;
+            let Some((sql, files)) = args.positionals().split_first() else {
+                anyhow::bail!("query: expected \"<SQL>\" <streets.parquet...>");
+            };
+            if files.is_empty() {
+                anyhow::bail!("query: expected at least one streets parquet file");
+            }
+            let paths: Vec<PathBuf> = files.iter().map(PathBuf::from).collect();
+            let output_path = args.value("output").map(PathBuf::from);
+            query::run_query(sql, &paths, output_path.as_deref())
+        }
+        "places" => {
+            let (state_name, pbf_path, output_path, precision) = parse_extract_args("places", &args[2..])?;
+            Ok(places::process_places_to_parquet(&pbf_path, &state_name, output_path, precision)?)
+        }
+        _ => run_streets(&args[1..]),
     }
-    
-    final_components
 }
 
-/// Group segments into unique streets
-fn group_segments_into_streets(
-    segments: Vec<StreetSegment>,
-    distance_threshold_km: f64,
-) -> Vec<Street> {
-    println!("Grouping segments into unique streets...");
-    
-    // Group by (name, state)
-    let mut by_name_state: HashMap<(String, String), Vec<usize>> = HashMap::new();
-    for (i, seg) in segments.iter().enumerate() {
-        let key = (seg.street_name.clone(), seg.state.clone());
-        by_name_state.entry(key).or_default().push(i);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(86_399), "1970-01-01");
+        assert_eq!(civil_date(86_400), "1970-01-02");
+        assert_eq!(civil_date(951_782_400), "2000-02-29");
+        assert_eq!(civil_date(1_709_251_199), "2024-02-29");
+        assert_eq!(civil_date(1_709_251_200), "2024-03-01");
+        assert_eq!(civil_date(4_102_444_799), "2099-12-31");
     }
-    
-    println!("  Found {} unique street names", by_name_state.len());
-    
-    // Progress bar for processing street names
-    let pb = ProgressBar::new(by_name_state.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("  Processing: [{bar:40}] {pos}/{len} street names ({eta})")
-            .unwrap()
-            .progress_chars("=>-"),
-    );
-    
-    // Process each name group in parallel
-    let streets: Vec<Street> = by_name_state
-        .into_par_iter()
-        .flat_map(|((name, state), indices)| {
-            let name_segments: Vec<_> = indices.iter().map(|&i| segments[i].clone()).collect();
-            
-            // Find connected components
-            let components = find_connected_components(&name_segments);
-            
-            // Optionally merge nearby components
-            let final_components = if distance_threshold_km > 0.0 {
-                group_nearby_components(&name_segments, components, distance_threshold_km)
-            } else {
-                components
-            };
-            
-            pb.inc(1);
-            
-            // Create one street per component
-            final_components
-                .into_iter()
-                .map(|component_indices| {
-                    let segs: Vec<_> = component_indices
-                        .iter()
-                        .map(|&i| &name_segments[i])
-                        .collect();
-                    
-                    // Use first segment's coordinates
-                    let (lat, lon) = segs[0].rep_coords();
-                    
-                    // Sum all segment lengths
-                    let total_length_km: f64 = segs.iter().map(|s| s.length_km).sum();
-                    
-                    // Most common highway type
-                    let highway_type = segs
-                        .iter()
-                        .map(|s| s.highway_type.as_str())
-                        .max_by_key(|&ht| segs.iter().filter(|s| s.highway_type == ht).count())
-                        .unwrap_or("")
-                        .to_string();
-                    
-                    // Collect common tags (appear in >50% of segments)
-                    let mut tag_counts: HashMap<String, usize> = HashMap::new();
-                    for seg in &segs {
-                        for key in seg.tags.keys() {
-                            *tag_counts.entry(key.clone()).or_default() += 1;
-                        }
-                    }
-                    
-                    let threshold = segs.len() / 2;
-                    let mut common_tags = HashMap::new();
-                    for (key, count) in tag_counts {
-                        if count >= threshold {
-                            // Find most common value for this key
-                            let mut value_counts: HashMap<String, usize> = HashMap::new();
-                            for seg in &segs {
-                                if let Some(value) = seg.tags.get(&key) {
-                                    *value_counts.entry(value.clone()).or_default() += 1;
-                                }
-                            }
-                            if let Some((value, _)) = value_counts.iter().max_by_key(|(_, &c)| c) {
-                                common_tags.insert(key, value.clone());
-                            }
-                        }
-                    }
-                    
-                    Street {
-                        street_name: name.clone(),
-                        state: state.clone(),
-                        lat,
-                        lon,
-                        num_segments: segs.len(),
-                        highway_type,
-                        tags: common_tags,
-                        length_km: total_length_km,
-                    }
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect();
-    
-    pb.finish_and_clear();
-    
-    println!("  Created {} unique streets", streets.len());
-    streets
-}
 
-/// Convert streets to Polars DataFrame
-fn streets_to_dataframe(streets: Vec<Street>) -> Result<DataFrame> {
-    let street_names: Vec<String> = streets.iter().map(|s| s.street_name.clone()).collect();
-    let states: Vec<String> = streets.iter().map(|s| s.state.clone()).collect();
-    let lats: Vec<f64> = streets.iter().map(|s| s.lat).collect();
-    let lons: Vec<f64> = streets.iter().map(|s| s.lon).collect();
-    let num_segments: Vec<u32> = streets.iter().map(|s| s.num_segments as u32).collect();
-    let highway_types: Vec<String> = streets.iter().map(|s| s.highway_type.clone()).collect();
-    let lengths_km: Vec<f64> = streets.iter().map(|s| s.length_km).collect();
-    
-    let df = DataFrame::new(vec![
-        Series::new("street_name", street_names),
-        Series::new("state", states),
-        Series::new("lat", lats),
-        Series::new("lon", lons),
-        Series::new("num_segments", num_segments),
-        Series::new("highway_type", highway_types),
-        Series::new("length_km", lengths_km),
-    ])?;
-    
-    Ok(df)
-}
+    #[test]
+    fn utc_date_is_iso() {
+        let date = utc_date();
+        assert_eq!(date.len(), 10);
+        assert_eq!((&date[4..5], &date[7..8]), ("-", "-"));
+        assert!(date.as_str() >= "2024-01-01");
+    }
 
-/// Main processing function
-fn process_osm_to_parquet(
-    pbf_path: &Path,
-    state_name: &str,
-    output_path: Option<PathBuf>,
-    distance_threshold_km: f64,
-) -> Result<()> {
-    println!("\n{}", "=".repeat(70));
-    println!("OSM TO PARQUET PROCESSOR (Rust)");
-    println!("{}", "=".repeat(70));
-    println!("Input file:  {}", pbf_path.display());
-    println!("State:       {}", state_name);
-    println!("Distance threshold: {} km", distance_threshold_km);
-    println!("{}", "=".repeat(70));
-    
-    // Determine output path
-    let output_path = output_path.unwrap_or_else(|| {
-        let mut path = pbf_path.parent().unwrap().parent().unwrap().to_path_buf();
-        path.push("streetdfs");
-        std::fs::create_dir_all(&path).ok();
-        path.push(format!("{}_streets.parquet", state_name));
-        path
-    });
-    
-    // Two-pass processing
-    let highway_nodes = collect_highway_nodes(pbf_path)?;
-    let segments = extract_street_segments(pbf_path, state_name, &highway_nodes)?;
-    
-    // Group into streets
-    let streets = group_segments_into_streets(segments, distance_threshold_km);
-    
-    // Convert to DataFrame
-    println!("Creating DataFrame...");
-    let df = streets_to_dataframe(streets)?;
-    
-    // Show statistics
-    println!("\n{}", "=".repeat(70));
-    println!("SUMMARY STATISTICS");
-    println!("{}", "=".repeat(70));
-    println!("Total unique streets: {}", df.height());
-    
-    let multi_segment = df
-        .clone()
-        .lazy()
-        .filter(col("num_segments").gt(lit(1)))
-        .collect()?;
-    println!("Streets with multiple segments: {}", multi_segment.height());
-    
-    // Top street names
-    println!("\nTop 10 street names:");
-    let name_counts = df
-        .clone()
-        .lazy()
-        .group_by([col("street_name")])
-        .agg([len().alias("count")])
-        .sort(["count"], Default::default())
-        .reverse()
-        .limit(10)
-        .collect()?;
-    println!("{}", name_counts);
-    
-    // Save to parquet
-    println!("\nSaving to: {}", output_path.display());
-    let mut file = std::fs::File::create(&output_path)?;
-    ParquetWriter::new(&mut file).finish(&mut df.clone())?;
-    
-    println!("Done!");
-    println!("{}", "=".repeat(70));
-    
-    Ok(())
-}
+    #[test]
+    fn expands_template_variables() {
+        let path = expand_output_template("out/{state}_{feature}_{threshold}km.parquet", "delaware", 0.2, Feature::Highway)
+            .unwrap();
+        assert_eq!(path, PathBuf::from("out/delaware_streets_0.2km.parquet"));
 
-fn main() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
-    
-    if args.len() < 2 {
-        eprintln!("Usage: {} <state_name> [pbf_file] [distance_threshold_km] [output_path]", args[0]);
-        eprintln!("Example: {} delaware", args[0]);
-        eprintln!("Example: {} california /path/to/california-latest.osm.pbf 0.1", args[0]);
-        eprintln!("Example: {} delaware data/osm/delaware-latest.osm.pbf 1.609 data/streetdfs_1mi/delaware_streets.parquet", args[0]);
-        std::process::exit(1);
+        let path = expand_output_template("{state}-{date}-v{version}", "ohio", 0.2, Feature::Railway).unwrap();
+        let path = path.to_str().unwrap();
+        let version = format!("-v{}", env!("CARGO_PKG_VERSION"));
+        assert!(path.starts_with("ohio-") && path.ends_with(&version), "{}", path);
+        assert_eq!(path.len(), "ohio-".len() + "YYYY-MM-DD".len() + version.len());
+
+        let path = expand_output_template("plain.parquet", "ohio", 0.2, Feature::Highway).unwrap();
+        assert_eq!(path, PathBuf::from("plain.parquet"));
     }
-    
-    let state_name = args[1].to_lowercase();
-    
-    let pbf_path = if args.len() > 2 {
-        PathBuf::from(&args[2])
-    } else {
-        // Default: look in data/osm directory
-        let mut path = std::env::current_dir()?;
-        path.push("data");
-        path.push("osm");
-        path.push(format!("{}-latest.osm.pbf", state_name));
-        path
-    };
-    
-    let distance_threshold_km = if args.len() > 3 {
-        args[3].parse().context("Invalid distance threshold")?
-    } else {
-        0.2 // Default 200m
-    };
-    
-    let output_path = if args.len() > 4 {
-        Some(PathBuf::from(&args[4]))
-    } else {
-        None
-    };
-    
-    if !pbf_path.exists() {
-        anyhow::bail!("File not found: {}", pbf_path.display());
+
+    #[test]
+    fn rejects_bad_templates() {
+        assert!(expand_output_template("{county}.parquet", "ohio", 0.2, Feature::Highway).is_err());
+        assert!(expand_output_template("{state.parquet", "ohio", 0.2, Feature::Highway).is_err());
     }
-    
-    process_osm_to_parquet(&pbf_path, &state_name, output_path, distance_threshold_km)?;
-    
-    Ok(())
 }
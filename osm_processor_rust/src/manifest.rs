@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{io_error, ProcessorError, Result};
+use crate::sanitize::SanitizeCounts;
+
+/// Bumped whenever the streets output columns change incompatibly
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// An input file and its SHA-256
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputFile {
+    pub path: PathBuf,
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+/// An output file and the number of rows written to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputFile {
+    pub path: PathBuf,
+    pub rows: usize,
+}
+
+/// Provenance for one run, written as `<output stem>.manifest.json` next to the output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub tool: String,
+    pub tool_version: String,
+    pub schema_version: u32,
+    /// Subcommand that produced the outputs (`streets`, `merge`, `diff`, ...)
+    pub command: String,
+    pub created_unix: u64,
+    pub inputs: Vec<InputFile>,
+    /// Effective options, including defaults that were not given explicitly
+    pub options: BTreeMap<String, String>,
+    pub outputs: Vec<OutputFile>,
+    pub timings_s: BTreeMap<String, f64>,
+    /// The run stopped at its maximum runtime, so the outputs hold only what was finished
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// What hardened parsing repaired or dropped, when enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sanitized: Option<SanitizeCounts>,
+    /// Manifests of input files that had one (e.g. the per-state runs behind a merge)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<Manifest>,
+}
+
+impl Manifest {
+    pub fn new(command: &str) -> Self {
+        Manifest {
+            tool: env!("CARGO_PKG_NAME").to_string(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version: SCHEMA_VERSION,
+            command: command.to_string(),
+            created_unix: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            inputs: Vec::new(),
+            options: BTreeMap::new(),
+            outputs: Vec::new(),
+            timings_s: BTreeMap::new(),
+            truncated: false,
+            sanitized: None,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Record an input file with its checksum
+    pub fn add_input(&mut self, path: &Path) -> Result<()> {
+        let (sha256, bytes) = sha256_file(path)?;
+        self.inputs.push(InputFile { path: path.to_path_buf(), sha256, bytes });
+        Ok(())
+    }
+
+    pub fn add_output(&mut self, path: &Path, rows: usize) {
+        self.outputs.push(OutputFile { path: path.to_path_buf(), rows });
+    }
+
+    pub fn set_option(&mut self, name: &str, value: impl ToString) {
+        self.options.insert(name.to_string(), value.to_string());
+    }
+
+    /// Record an input and, if it has a manifest of its own, carry that along as a source
+    pub fn add_source(&mut self, path: &Path) -> Result<()> {
+        self.add_input(path)?;
+        if let Some(source) = Manifest::read_for(path)? {
+            self.sources.push(source);
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).expect("manifest serializes");
+        std::fs::write(path, json + "\n").map_err(io_error(path))
+    }
+
+    /// The manifest written alongside `output`, if there is one
+    pub fn read_for(output: &Path) -> Result<Option<Manifest>> {
+        let path = manifest_path(output);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path).map_err(io_error(&path))?;
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| ProcessorError::InvalidConfig(format!("Invalid manifest {}: {}", path.display(), e)))
+    }
+}
+
+/// `<output stem>.manifest.json` next to a file output, `<dir>/manifest.json` for a directory
+pub fn manifest_path(output: &Path) -> PathBuf {
+    if output.is_dir() {
+        return output.join("manifest.json");
+    }
+    let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    output.with_file_name(format!("{}.manifest.json", stem))
+}
+
+/// Hex SHA-256 and size of a file, read in chunks
+pub fn sha256_file(path: &Path) -> Result<(String, u64)> {
+    let mut file = File::open(path).map_err(io_error(path))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    let mut bytes = 0u64;
+    loop {
+        let n = file.read(&mut buf).map_err(io_error(path))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        bytes += n as u64;
+    }
+    let digest = hasher.finalize();
+    Ok((digest.iter().map(|b| format!("{:02x}", b)).collect(), bytes))
+}
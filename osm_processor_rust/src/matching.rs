@@ -0,0 +1,146 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::collections::HashMap;
+
+use osm_processor_rust::haversine_km;
+use osm_processor_rust::stats::type_column;
+
+/// The columns of a street row needed to compare two datasets
+#[derive(Debug, Clone)]
+pub struct StreetRow {
+    pub street_id: Option<String>,
+    pub street_name: String,
+    pub state: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub length_km: f64,
+    pub num_segments: u32,
+    pub street_type: Option<String>,
+}
+
+/// Read comparable rows from a streets DataFrame
+pub fn read_rows(df: &DataFrame) -> Result<Vec<StreetRow>> {
+    let names = df.column("street_name")?.str()?;
+    let states = df.column("state")?.str()?;
+    let lats = df.column("lat")?.f64()?;
+    let lons = df.column("lon")?.f64()?;
+    let lengths = df.column("length_km")?.f64()?;
+    let segments = df.column("num_segments")?.cast(&DataType::UInt32)?;
+    let segments = segments.u32()?;
+    let ids = match df.column("street_id") {
+        Ok(column) => Some(column.cast(&DataType::String)?),
+        Err(_) => None,
+    };
+    let ids = ids.as_ref().map(|c| c.str()).transpose()?;
+    let types = match type_column(df) {
+        Some(column) => Some(df.column(&column)?.str()?.clone()),
+        None => None,
+    };
+
+    let rows = (0..df.height())
+        .map(|i| StreetRow {
+            street_id: ids.and_then(|c| c.get(i)).map(str::to_string),
+            street_name: names.get(i).unwrap_or("").to_string(),
+            state: states.get(i).unwrap_or("").to_string(),
+            lat: lats.get(i).unwrap_or(f64::NAN),
+            lon: lons.get(i).unwrap_or(f64::NAN),
+            length_km: lengths.get(i).unwrap_or(0.0),
+            num_segments: segments.get(i).unwrap_or(0),
+            street_type: types.as_ref().and_then(|c| c.get(i)).map(str::to_string),
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+/// Distance in km between the representative points of two rows
+pub fn row_distance_km(a: &StreetRow, b: &StreetRow) -> f64 {
+    haversine_km((a.lat, a.lon), (b.lat, b.lon))
+}
+
+/// Result of matching two street tables
+#[derive(Debug, Default)]
+pub struct MatchResult {
+    /// (left index, right index) pairs
+    pub matched: Vec<(usize, usize)>,
+    pub left_only: Vec<usize>,
+    pub right_only: Vec<usize>,
+}
+
+/// Row indices of the left and right table per (name, state)
+type NameGroups<'a> = HashMap<(&'a str, &'a str), (Vec<usize>, Vec<usize>)>;
+
+/// Match rows between two tables. When both sides carry `street_id`, rows are matched by ID;
+/// otherwise streets with the same (name, state) are paired greedily, closest first, as long
+/// as their representative points are within `tolerance_km`.
+pub fn match_streets(left: &[StreetRow], right: &[StreetRow], tolerance_km: f64) -> MatchResult {
+    let has_ids = |rows: &[StreetRow]| !rows.is_empty() && rows.iter().all(|r| r.street_id.is_some());
+    if has_ids(left) && has_ids(right) {
+        return match_by_id(left, right);
+    }
+
+    let mut groups = NameGroups::new();
+    for (i, row) in left.iter().enumerate() {
+        groups.entry((row.street_name.as_str(), row.state.as_str())).or_default().0.push(i);
+    }
+    for (j, row) in right.iter().enumerate() {
+        groups.entry((row.street_name.as_str(), row.state.as_str())).or_default().1.push(j);
+    }
+
+    let mut result = MatchResult::default();
+    // Indexed by row, shared by all groups since each row is in exactly one
+    let mut used_left = vec![false; left.len()];
+    let mut used_right = vec![false; right.len()];
+
+    for (lefts, rights) in groups.into_values() {
+        let mut candidates: Vec<(f64, usize, usize)> = Vec::new();
+        for &i in &lefts {
+            for &j in &rights {
+                let dist = row_distance_km(&left[i], &right[j]);
+                if dist <= tolerance_km {
+                    candidates.push((dist, i, j));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (_, i, j) in candidates {
+            if !used_left[i] && !used_right[j] {
+                used_left[i] = true;
+                used_right[j] = true;
+                result.matched.push((i, j));
+            }
+        }
+
+        result.left_only.extend(lefts.into_iter().filter(|&i| !used_left[i]));
+        result.right_only.extend(rights.into_iter().filter(|&j| !used_right[j]));
+    }
+
+    result.matched.sort_unstable();
+    result.left_only.sort_unstable();
+    result.right_only.sort_unstable();
+    result
+}
+
+fn match_by_id(left: &[StreetRow], right: &[StreetRow]) -> MatchResult {
+    let right_by_id: HashMap<&str, usize> = right
+        .iter()
+        .enumerate()
+        .filter_map(|(j, r)| r.street_id.as_deref().map(|id| (id, j)))
+        .collect();
+
+    let mut result = MatchResult::default();
+    let mut matched_right = vec![false; right.len()];
+
+    for (i, row) in left.iter().enumerate() {
+        match row.street_id.as_deref().and_then(|id| right_by_id.get(id)) {
+            Some(&j) if !matched_right[j] => {
+                matched_right[j] = true;
+                result.matched.push((i, j));
+            }
+            _ => result.left_only.push(i),
+        }
+    }
+    result.right_only = (0..right.len()).filter(|&j| !matched_right[j]).collect();
+    result
+}
@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::matching::{read_rows, row_distance_km};
+use osm_processor_rust::manifest::{manifest_path, Manifest};
+use osm_processor_rust::write_parquet;
+
+/// Degrees of latitude per km, used to bound the border-duplicate sweep
+const DEG_LAT_PER_KM: f64 = 1.0 / 111.0;
+
+/// Read per-state outputs and check they share one schema (column order may differ)
+fn read_compatible(paths: &[PathBuf]) -> Result<DataFrame> {
+    let mut combined: Option<DataFrame> = None;
+
+    for path in paths {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let df = ParquetReader::new(file)
+            .finish()
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        combined = Some(match combined {
+            None => df,
+            Some(mut acc) => {
+                let expected = acc.schema();
+                let actual = df.schema();
+                if expected.len() != actual.len() || expected.iter().any(|(name, dtype)| actual.get(name) != Some(dtype)) {
+                    anyhow::bail!(
+                        "Schema mismatch in {}:\n  expected: {:?}\n  found:    {:?}",
+                        path.display(),
+                        expected,
+                        actual
+                    );
+                }
+                let aligned = df.select(acc.get_column_names())?;
+                acc.vstack_mut(&aligned)?;
+                acc
+            }
+        });
+    }
+
+    combined.context("No input files")
+}
+
+/// Mark streets duplicated across a state border: same name, different state, representative
+/// points within `threshold_km`. The longer copy is kept.
+fn border_duplicates(df: &DataFrame, threshold_km: f64) -> Result<Vec<bool>> {
+    let rows = read_rows(df)?;
+    let mut keep = vec![true; rows.len()];
+
+    let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        by_name.entry(&row.street_name).or_default().push(i);
+    }
+
+    let lat_window = threshold_km * DEG_LAT_PER_KM;
+    for mut indices in by_name.into_values() {
+        indices.sort_by(|&a, &b| rows[a].lat.total_cmp(&rows[b].lat));
+
+        for (pos, &i) in indices.iter().enumerate() {
+            for &j in &indices[pos + 1..] {
+                if rows[j].lat - rows[i].lat > lat_window {
+                    break;
+                }
+                if rows[i].state == rows[j].state || !keep[i] || !keep[j] {
+                    continue;
+                }
+                if row_distance_km(&rows[i], &rows[j]) <= threshold_km {
+                    let drop = if rows[i].length_km >= rows[j].length_km { j } else { i };
+                    keep[drop] = false;
+                }
+            }
+        }
+    }
+
+    Ok(keep)
+}
+
+/// Write one file per state under `<dir>/state=<state>/part-0.parquet`
+fn write_partitioned(df: &DataFrame, dir: &Path) -> Result<()> {
+    let mut states: Vec<String> = df
+        .column("state")?
+        .str()?
+        .into_iter()
+        .flatten()
+        .map(str::to_string)
+        .collect();
+    states.sort_unstable();
+    states.dedup();
+
+    for state in states {
+        let mut part = df
+            .clone()
+            .lazy()
+            .filter(col("state").eq(lit(state.as_str())))
+            .collect()?;
+        let part_dir = dir.join(format!("state={}", state));
+        std::fs::create_dir_all(&part_dir)?;
+        write_parquet(&mut part, &part_dir.join("part-0.parquet"))?;
+    }
+
+    Ok(())
+}
+
+/// `merge <in.parquet...> -o <out> [--dedup-border-km X] [--partition-by state]`
+pub fn run_merge(
+    paths: &[PathBuf],
+    output_path: &Path,
+    dedup_border_km: Option<f64>,
+    partition_by_state: bool,
+) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("MERGE STREET DATASETS");
+    println!("{}", "=".repeat(70));
+    println!("Input files: {}", paths.len());
+    println!("Output:      {}", output_path.display());
+    println!("{}", "=".repeat(70));
+
+    let started = std::time::Instant::now();
+    let mut manifest = Manifest::new("merge");
+    for path in paths {
+        manifest.add_source(path)?;
+    }
+    let mut schema_versions: Vec<u32> = manifest.sources.iter().map(|m| m.schema_version).collect();
+    schema_versions.sort_unstable();
+    schema_versions.dedup();
+    if schema_versions.len() > 1 {
+        println!("Warning: inputs were written with different schema versions {:?}", schema_versions);
+    }
+
+    let mut df = read_compatible(paths)?;
+    println!("Combined rows: {}", df.height());
+
+    if let Some(threshold_km) = dedup_border_km {
+        let keep = border_duplicates(&df, threshold_km)?;
+        let removed = keep.iter().filter(|k| !**k).count();
+        df = df.filter(&BooleanChunked::from_slice("keep", &keep))?;
+        println!("Removed {} border duplicates (within {} km)", removed, threshold_km);
+    }
+
+    if partition_by_state {
+        println!("\nSaving partitioned dataset to: {}", output_path.display());
+        write_partitioned(&df, output_path)?;
+    } else {
+        println!("\nSaving to: {}", output_path.display());
+        write_parquet(&mut df, output_path)?;
+    }
+
+    if let Some(threshold_km) = dedup_border_km {
+        manifest.set_option("dedup_border_km", threshold_km);
+    }
+    manifest.set_option("partition_by", if partition_by_state { "state" } else { "none" });
+    manifest.add_output(output_path, df.height());
+    manifest.timings_s.insert("total".to_string(), started.elapsed().as_secs_f64());
+    manifest.write(&manifest_path(output_path))?;
+
+    println!("Done!");
+    Ok(())
+}
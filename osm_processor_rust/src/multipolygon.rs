@@ -0,0 +1,177 @@
+use geo::{Contains, LineString, MultiPolygon, Point, Polygon};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::fast_hash::NodeMap;
+use crate::relations::collect_relations;
+
+/// A relation whose way members describe an area (multipolygon, boundary)
+#[derive(Debug, Clone)]
+pub struct AreaRelation {
+    pub id: i64,
+    pub tags: HashMap<String, String>,
+    pub outer_ways: Vec<i64>,
+    pub inner_ways: Vec<i64>,
+}
+
+impl AreaRelation {
+    /// All way IDs referenced by this relation
+    pub fn member_ways(&self) -> impl Iterator<Item = i64> + '_ {
+        self.outer_ways.iter().chain(&self.inner_ways).copied()
+    }
+}
+
+/// Collect relations whose tags pass `keep`, splitting way members by role
+fn collect_area_relations<F>(pbf_path: &Path, keep: F) -> Result<Vec<AreaRelation>>
+where
+    F: Fn(&HashMap<String, String>) -> bool,
+{
+    let relations = collect_relations(pbf_path, keep)?
+        .into_iter()
+        .map(|rel| {
+            let mut outer_ways = Vec::new();
+            let mut inner_ways = Vec::new();
+            for member in rel.way_members() {
+                // An empty role is treated as outer, as most consumers do
+                match member.role.as_str() {
+                    "inner" => inner_ways.push(member.id),
+                    "outer" | "" => outer_ways.push(member.id),
+                    _ => {}
+                }
+            }
+
+            AreaRelation {
+                id: rel.id,
+                tags: rel.tags,
+                outer_ways,
+                inner_ways,
+            }
+        })
+        .collect();
+
+    Ok(relations)
+}
+
+/// Collect `type=multipolygon` relations whose tags pass `keep`
+pub fn collect_multipolygon_relations<F>(pbf_path: &Path, keep: F) -> Result<Vec<AreaRelation>>
+where
+    F: Fn(&HashMap<String, String>) -> bool,
+{
+    collect_area_relations(pbf_path, |tags| {
+        tags.get("type").map(String::as_str) == Some("multipolygon") && keep(tags)
+    })
+}
+
+/// Collect `boundary=administrative` relations (tagged `type=boundary` or `type=multipolygon`)
+pub fn collect_admin_boundary_relations(pbf_path: &Path) -> Result<Vec<AreaRelation>> {
+    collect_area_relations(pbf_path, |tags| {
+        matches!(tags.get("type").map(String::as_str), Some("boundary") | Some("multipolygon"))
+            && tags.get("boundary").map(String::as_str) == Some("administrative")
+    })
+}
+
+/// Join way node lists end-to-end into closed rings.
+/// Member ways may appear in any order and direction; pieces that never close are dropped.
+pub fn assemble_rings(way_ids: &[i64], way_refs: &HashMap<i64, Vec<i64>>) -> Vec<Vec<i64>> {
+    let mut pieces: Vec<Vec<i64>> = way_ids
+        .iter()
+        .filter_map(|id| way_refs.get(id))
+        .filter(|refs| refs.len() >= 2)
+        .cloned()
+        .collect();
+
+    let mut rings = Vec::new();
+
+    while let Some(mut ring) = pieces.pop() {
+        while ring.first() != ring.last() {
+            let tail = ring[ring.len() - 1];
+            let next = pieces
+                .iter()
+                .position(|p| p[0] == tail || p[p.len() - 1] == tail);
+
+            match next {
+                Some(idx) => {
+                    let mut piece = pieces.swap_remove(idx);
+                    if piece[0] != tail {
+                        piece.reverse();
+                    }
+                    ring.extend_from_slice(&piece[1..]);
+                }
+                None => break,
+            }
+        }
+
+        if ring.len() >= 4 && ring.first() == ring.last() {
+            rings.push(ring);
+        }
+    }
+
+    rings
+}
+
+/// Convert a ring of node IDs to a (lon, lat) LineString; None if any node is missing
+pub fn ring_to_linestring(
+    ring: &[i64],
+    node_coords: &NodeMap<(f64, f64)>,
+) -> Option<LineString<f64>> {
+    let coords: Option<Vec<(f64, f64)>> = ring
+        .iter()
+        .map(|id| node_coords.get(id).map(|&(lat, lon)| (lon, lat)))
+        .collect();
+    coords.map(LineString::from)
+}
+
+/// Polygon for a closed way, if it is closed and all its nodes are known
+pub fn closed_way_polygon(
+    refs: &[i64],
+    node_coords: &NodeMap<(f64, f64)>,
+) -> Option<Polygon<f64>> {
+    if refs.len() < 4 || refs.first() != refs.last() {
+        return None;
+    }
+    ring_to_linestring(refs, node_coords).map(|exterior| Polygon::new(exterior, Vec::new()))
+}
+
+/// Build a MultiPolygon from an area relation, assigning each inner ring to the
+/// outer ring that contains it
+pub fn relation_multipolygon(
+    relation: &AreaRelation,
+    way_refs: &HashMap<i64, Vec<i64>>,
+    node_coords: &NodeMap<(f64, f64)>,
+) -> Option<MultiPolygon<f64>> {
+    let outers: Vec<LineString<f64>> = assemble_rings(&relation.outer_ways, way_refs)
+        .iter()
+        .filter_map(|ring| ring_to_linestring(ring, node_coords))
+        .collect();
+
+    if outers.is_empty() {
+        return None;
+    }
+
+    let inners: Vec<LineString<f64>> = assemble_rings(&relation.inner_ways, way_refs)
+        .iter()
+        .filter_map(|ring| ring_to_linestring(ring, node_coords))
+        .collect();
+
+    let shells: Vec<Polygon<f64>> = outers
+        .iter()
+        .map(|outer| Polygon::new(outer.clone(), Vec::new()))
+        .collect();
+    let mut holes: Vec<Vec<LineString<f64>>> = vec![Vec::new(); outers.len()];
+
+    for inner in inners {
+        let probe = Point::from(inner.0[0]);
+        if let Some(idx) = shells.iter().position(|shell| shell.contains(&probe)) {
+            holes[idx].push(inner);
+        }
+    }
+
+    let polygons = outers
+        .into_iter()
+        .zip(holes)
+        .map(|(outer, inner)| Polygon::new(outer, inner))
+        .collect();
+
+    Some(MultiPolygon::new(polygons))
+}
@@ -0,0 +1,217 @@
+/// Canonical street types and the spellings (full or abbreviated, lowercase, without the
+/// trailing period) that map to them
+const STREET_TYPES: &[(&str, &[&str])] = &[
+    ("street", &["street", "st", "str", "strasse", "straße"]),
+    ("avenue", &["avenue", "ave", "av", "avn", "aven"]),
+    ("boulevard", &["boulevard", "blvd", "boul", "blv"]),
+    ("road", &["road", "rd"]),
+    ("drive", &["drive", "dr", "drv"]),
+    ("lane", &["lane", "ln"]),
+    ("court", &["court", "ct", "crt"]),
+    ("place", &["place", "pl"]),
+    ("way", &["way", "wy"]),
+    ("circle", &["circle", "cir", "circ"]),
+    ("terrace", &["terrace", "ter", "terr"]),
+    ("parkway", &["parkway", "pkwy", "pky"]),
+    ("highway", &["highway", "hwy"]),
+    ("freeway", &["freeway", "fwy"]),
+    ("expressway", &["expressway", "expy"]),
+    ("turnpike", &["turnpike", "tpke"]),
+    ("pike", &["pike"]),
+    ("trail", &["trail", "trl"]),
+    ("path", &["path"]),
+    ("alley", &["alley", "aly"]),
+    ("square", &["square", "sq"]),
+    ("plaza", &["plaza", "plz"]),
+    ("loop", &["loop"]),
+    ("run", &["run"]),
+    ("row", &["row"]),
+    ("cove", &["cove", "cv"]),
+    ("crossing", &["crossing", "xing"]),
+    ("ridge", &["ridge", "rdg"]),
+    ("crescent", &["crescent", "cres"]),
+    ("close", &["close"]),
+    ("walk", &["walk"]),
+];
+
+/// Directional words and abbreviations, by canonical abbreviation
+const DIRECTIONS: &[(&str, &[&str])] = &[
+    ("N", &["n", "north"]),
+    ("S", &["s", "south"]),
+    ("E", &["e", "east"]),
+    ("W", &["w", "west"]),
+    ("NE", &["ne", "northeast"]),
+    ("NW", &["nw", "northwest"]),
+    ("SE", &["se", "southeast"]),
+    ("SW", &["sw", "southwest"]),
+];
+
+/// Spelled-out ordinals, in order from `first`
+const ORDINAL_WORDS: &[&str] = &[
+    "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth",
+    "eleventh", "twelfth", "thirteenth", "fourteenth", "fifteenth", "sixteenth", "seventeenth",
+    "eighteenth", "nineteenth", "twentieth",
+];
+
+/// German compound suffixes that mean "street" (`Hauptstraße`, `Bahnhofstr.`)
+const COMPOUND_STREET_SUFFIXES: &[&str] = &["straße", "strasse", "str"];
+
+/// The parts of a street name the analyses care about
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedName {
+    /// The name without its directional prefix/suffix (`N Main St` -> `Main St`)
+    pub base: String,
+    /// Canonical directional (`N`, `SW`, ...) from a prefix or, failing that, a suffix
+    pub direction: Option<&'static str>,
+    /// Canonical type from the name's suffix (`St.` -> `street`), if recognized
+    pub street_type: Option<&'static str>,
+    /// The ordinal of a numbered street (`3rd Street` -> 3, `Third Avenue` -> 3), if any
+    pub number: Option<u32>,
+}
+
+/// Lowercase a token and drop a trailing period (`St.` -> `st`)
+fn clean_token(token: &str) -> String {
+    token.trim_end_matches('.').to_lowercase()
+}
+
+/// Canonical street type for one token (`Ave`, `Blvd.`, `Straße`), if it is one
+pub fn normalize_street_type(token: &str) -> Option<&'static str> {
+    let token = clean_token(token);
+    STREET_TYPES
+        .iter()
+        .find(|(_, spellings)| spellings.contains(&token.as_str()))
+        .map(|(canonical, _)| *canonical)
+}
+
+/// Canonical directional for one token (`N`, `N.`, `North`), if it is one
+pub fn normalize_direction(token: &str) -> Option<&'static str> {
+    let token = clean_token(token);
+    DIRECTIONS
+        .iter()
+        .find(|(_, spellings)| spellings.contains(&token.as_str()))
+        .map(|(canonical, _)| *canonical)
+}
+
+/// Key for comparing names across spellings: lowercase, with directionals and street types
+/// reduced to one form (`North Main Street` and `N Main St.` -> `N main street`)
+pub fn match_key(name: &str) -> String {
+    let tokens: Vec<String> = name
+        .split_whitespace()
+        .map(|token| {
+            normalize_direction(token)
+                .or_else(|| normalize_street_type(token))
+                .map(str::to_string)
+                .unwrap_or_else(|| token.to_lowercase())
+        })
+        .collect();
+    tokens.join(" ")
+}
+
+/// Street type of a German-style compound word (`Hauptstraße` -> `street`)
+fn compound_street_type(word: &str) -> Option<&'static str> {
+    let word = clean_token(word);
+    COMPOUND_STREET_SUFFIXES
+        .iter()
+        .any(|suffix| word.len() > suffix.len() && word.ends_with(suffix))
+        .then_some("street")
+}
+
+/// Value of an ordinal token: `3rd`, `42nd`, `101st` or a spelled-out `first` to `twentieth`
+pub fn parse_ordinal(token: &str) -> Option<u32> {
+    let token = clean_token(token);
+    if let Some(position) = ORDINAL_WORDS.iter().position(|word| *word == token) {
+        return Some(position as u32 + 1);
+    }
+    let digits_end = token.find(|c: char| !c.is_ascii_digit())?;
+    let (digits, suffix) = token.split_at(digits_end);
+    let number: u32 = digits.parse().ok()?;
+    // The suffix has to agree with the number: 1st, 2nd, 3rd, 11th-13th, 4th..
+    let expected = match (number % 100, number % 10) {
+        (11..=13, _) => "th",
+        (_, 1) => "st",
+        (_, 2) => "nd",
+        (_, 3) => "rd",
+        _ => "th",
+    };
+    (suffix == expected).then_some(number)
+}
+
+/// Whether what is left after removing a directional is still a name: `North Ave` and
+/// `West Street` keep their directional, which is the name itself there
+fn is_name_without(rest: &[&str]) -> bool {
+    match rest {
+        [] => false,
+        [word] => normalize_street_type(word).is_none(),
+        _ => true,
+    }
+}
+
+/// Split a street name into the parts used for derived columns
+pub fn parse(name: &str) -> ParsedName {
+    let mut tokens: Vec<&str> = name.split_whitespace().collect();
+
+    let mut direction = None;
+    if let Some(dir) = tokens.first().and_then(|t| normalize_direction(t)) {
+        if is_name_without(&tokens[1..]) {
+            direction = Some(dir);
+            tokens.remove(0);
+        }
+    }
+    if let Some(dir) = tokens.last().and_then(|t| normalize_direction(t)) {
+        if is_name_without(&tokens[..tokens.len() - 1]) {
+            direction = direction.or(Some(dir));
+            tokens.pop();
+        }
+    }
+
+    let street_type = match tokens.as_slice() {
+        [] => None,
+        // A lone word is the name itself ("Broadway"), unless it is a compound like Hauptstraße
+        [word] => compound_street_type(word),
+        [.., last] => normalize_street_type(last).or_else(|| compound_street_type(last)),
+    };
+    // `3rd Street`, `W 42nd St`: an ordinal followed by the rest of the name
+    let number = match tokens.as_slice() {
+        [first, _, ..] => parse_ordinal(first),
+        _ => None,
+    };
+    ParsedName { base: tokens.join(" "), direction, street_type, number }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_abbreviations_and_periods() {
+        assert_eq!(normalize_street_type("St."), Some("street"));
+        assert_eq!(normalize_street_type("AVE"), Some("avenue"));
+        assert_eq!(normalize_street_type("Blvd"), Some("boulevard"));
+        assert_eq!(normalize_street_type("Pkwy."), Some("parkway"));
+        assert_eq!(normalize_street_type("Main"), None);
+    }
+
+    #[test]
+    fn street_type_comes_from_the_last_token() {
+        assert_eq!(parse("Main Street").street_type, Some("street"));
+        assert_eq!(parse("Martin Luther King Jr. Blvd.").street_type, Some("boulevard"));
+        assert_eq!(parse("Street of Dreams").street_type, None);
+        assert_eq!(parse("  Elm   Ct  ").street_type, Some("court"));
+    }
+
+    #[test]
+    fn lone_word_is_the_name_itself() {
+        assert_eq!(parse("Broadway").street_type, None);
+        assert_eq!(parse("Street").street_type, None);
+        assert_eq!(parse("").street_type, None);
+    }
+
+    #[test]
+    fn german_compounds() {
+        assert_eq!(parse("Hauptstraße").street_type, Some("street"));
+        assert_eq!(parse("Bahnhofstr.").street_type, Some("street"));
+        assert_eq!(parse("Obere Bahnhofstrasse").street_type, Some("street"));
+        assert_eq!(parse("Alte Straße").street_type, Some("street"));
+        assert_eq!(parse("Str.").street_type, None);
+    }
+}
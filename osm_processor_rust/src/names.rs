@@ -0,0 +1,57 @@
+use crate::error::{ProcessorError, Result};
+
+/// Which name tag becomes `street_name` when a way has several: an ordered list of languages,
+/// each `name:<lang>`, with `local` standing for the plain `name` tag. The first one present
+/// wins, e.g. `en,local` uses `name:en` where mapped and falls back to `name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamePolicy {
+    langs: Vec<Option<String>>,
+}
+
+impl Default for NamePolicy {
+    /// `local`: the plain `name` tag only
+    fn default() -> Self {
+        NamePolicy { langs: vec![None] }
+    }
+}
+
+impl NamePolicy {
+    /// Parse a `--name-lang` value such as `en,local`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut langs = Vec::new();
+        for lang in spec.split(',').map(str::trim) {
+            match lang {
+                "local" => langs.push(None),
+                code if !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') => {
+                    langs.push(Some(code.to_string()))
+                }
+                other => {
+                    return Err(ProcessorError::InvalidConfig(format!(
+                        "Invalid language in --name-lang: {:?} (expected codes like en or local)",
+                        other
+                    )))
+                }
+            }
+        }
+        Ok(NamePolicy { langs })
+    }
+
+    /// The `--name-lang` value for this policy
+    pub fn name(&self) -> String {
+        let langs: Vec<&str> = self.langs.iter().map(|lang| lang.as_deref().unwrap_or("local")).collect();
+        langs.join(",")
+    }
+
+    /// Whether only the plain `name` tag is used, in which case no separate local name is kept
+    pub fn is_local(&self) -> bool {
+        self.langs == [None]
+    }
+
+    /// The preferred name among a way's tags, if it has any of the listed ones
+    pub fn choose<'a>(&self, tag: impl Fn(&str) -> Option<&'a str>) -> Option<&'a str> {
+        self.langs.iter().find_map(|lang| match lang {
+            Some(code) => tag(&format!("name:{}", code)),
+            None => tag("name"),
+        })
+    }
+}
@@ -0,0 +1,166 @@
+//! Natural Earth admin boundaries, downloaded on first use and cached, for assigning segments to
+//! states or counties without a PBF that carries its own boundary relations (a city extract cut
+//! inside its state, Overture input) or sourcing boundary files by hand.
+//!
+//! States come from the 1:10m admin-1 layer (first-level subdivisions worldwide), counties from
+//! the 1:10m US counties layer; there is no city layer. The zipped shapefiles are kept in the
+//! cache dir and unpacked next to themselves. Downloading needs the `async` feature; without it,
+//! place the zip files in the cache dir yourself.
+
+use geo::{LineString, MultiPolygon, Polygon};
+use shapefile::dbase::{FieldValue, Record};
+use shapefile::{PolygonRing, Shape};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::error::{io_error, ProcessorError, Result};
+use crate::regions::{region_key, Region, RegionIndex, RegionLevel};
+
+const BASE_URL: &str = "https://naciscdn.org/naturalearth/10m/cultural";
+
+/// A Natural Earth layer and the attribute holding each feature's name
+struct Dataset {
+    stem: &'static str,
+    name_field: &'static str,
+}
+
+const STATES: Dataset = Dataset { stem: "ne_10m_admin_1_states_provinces", name_field: "name" };
+const COUNTIES: Dataset = Dataset { stem: "ne_10m_admin_2_counties", name_field: "NAME" };
+
+/// `$XDG_CACHE_HOME/osm_processor`, else `~/.cache/osm_processor`, else under the temp dir
+pub fn default_cache_dir() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("osm_processor")
+}
+
+/// Regions for `level` from Natural Earth, named by [`region_key`]. With `names`, only those
+/// regions are kept and each must be found.
+pub fn regions(level: RegionLevel, names: Option<&[String]>, cache_dir: &Path) -> Result<RegionIndex> {
+    let dataset = match level {
+        RegionLevel::State => STATES,
+        RegionLevel::County => COUNTIES,
+        RegionLevel::City => {
+            return Err(ProcessorError::InvalidConfig(
+                "Natural Earth has no city boundaries; use boundaries from the PBF for --region-level city".into(),
+            ))
+        }
+    };
+    let shp_path = ensure_unpacked(&dataset, cache_dir)?;
+    println!("Reading Natural Earth boundaries from {}...", shp_path.display());
+    let mut regions = read_regions(&shp_path, dataset.name_field)?;
+    println!("  Found {} {} boundaries", regions.len(), level.name());
+
+    if let Some(names) = names {
+        let wanted: Vec<String> = names.iter().map(|name| region_key(name)).collect();
+        if let Some(missing) = wanted.iter().find(|name| !regions.iter().any(|r| &r.name == *name)) {
+            return Err(ProcessorError::InvalidConfig(format!(
+                "no Natural Earth {} boundary named {}",
+                level.name(),
+                missing
+            )));
+        }
+        regions.retain(|r| wanted.contains(&r.name));
+    }
+    Ok(RegionIndex::new(regions))
+}
+
+/// The layer's `.shp`, downloading and unpacking its zip into `cache_dir` if not already there
+fn ensure_unpacked(dataset: &Dataset, cache_dir: &Path) -> Result<PathBuf> {
+    let dir = cache_dir.join(dataset.stem);
+    let shp_path = dir.join(format!("{}.shp", dataset.stem));
+    if shp_path.exists() {
+        return Ok(shp_path);
+    }
+    std::fs::create_dir_all(cache_dir).map_err(io_error(cache_dir))?;
+
+    let zip_path = cache_dir.join(format!("{}.zip", dataset.stem));
+    if !zip_path.exists() {
+        download(&format!("{}/{}.zip", BASE_URL, dataset.stem), &zip_path)?;
+    }
+
+    // Unpacked into a staging dir renamed into place at the end, so an interrupted run does not
+    // leave a partial layer behind
+    let staging = cache_dir.join(format!("{}.unpacking", dataset.stem));
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir_all(&staging).map_err(io_error(&staging))?;
+    let file = File::open(&zip_path).map_err(io_error(&zip_path))?;
+    let zip_error = |err: zip::result::ZipError| ProcessorError::Io { path: zip_path.clone(), source: err.into() };
+    let mut archive = zip::ZipArchive::new(file).map_err(zip_error)?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(zip_error)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(name) = entry.enclosed_name().and_then(|path| path.file_name().map(PathBuf::from)) else {
+            continue;
+        };
+        let path = staging.join(name);
+        let mut out = File::create(&path).map_err(io_error(&path))?;
+        std::io::copy(&mut entry, &mut out).map_err(io_error(&path))?;
+    }
+    std::fs::rename(&staging, &dir).map_err(io_error(&dir))?;
+    if !shp_path.exists() {
+        return Err(ProcessorError::InvalidConfig(format!("{} has no {}.shp", zip_path.display(), dataset.stem)));
+    }
+    Ok(shp_path)
+}
+
+/// Fetch `url` to `path` through a temporary file
+#[cfg(feature = "async")]
+fn download(url: &str, path: &Path) -> Result<()> {
+    println!("Downloading {}...", url);
+    let staging = path.with_extension("partial");
+    std::fs::create_dir_all(&staging).map_err(io_error(&staging))?;
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(io_error(path))?;
+    let fetched = runtime.block_on(crate::async_api::fetch_input(url, &staging));
+    let renamed = fetched.and_then(|local| std::fs::rename(&local, path).map_err(io_error(path)));
+    let _ = std::fs::remove_dir_all(&staging);
+    renamed
+}
+
+#[cfg(not(feature = "async"))]
+fn download(url: &str, path: &Path) -> Result<()> {
+    Err(ProcessorError::InvalidConfig(format!(
+        "downloading boundaries needs the `async` feature; fetch {} to {} yourself",
+        url,
+        path.display()
+    )))
+}
+
+fn text_field(record: &Record, name: &str) -> Option<String> {
+    match record.get(name) {
+        Some(FieldValue::Character(Some(value))) => Some(value.trim().to_string()),
+        _ => None,
+    }
+}
+
+/// Named polygons of a shapefile; each outer ring starts a polygon and the inner rings after it
+/// are its holes
+fn read_regions(shp_path: &Path, name_field: &str) -> Result<Vec<Region>> {
+    let shapefile_error = |err: shapefile::Error| ProcessorError::Io {
+        path: shp_path.to_path_buf(),
+        source: std::io::Error::other(err),
+    };
+    let mut reader = shapefile::Reader::from_path(shp_path).map_err(shapefile_error)?;
+
+    let mut regions = Vec::new();
+    for item in reader.iter_shapes_and_records() {
+        let (shape, record) = item.map_err(shapefile_error)?;
+        let Some(name) = text_field(&record, name_field).filter(|name| !name.is_empty()) else { continue };
+        let Shape::Polygon(polygon) = shape else { continue };
+
+        let mut polygons: Vec<Polygon<f64>> = Vec::new();
+        for ring in polygon.rings() {
+            let line: LineString<f64> = ring.points().iter().map(|p| (p.x, p.y)).collect();
+            match (ring, polygons.last_mut()) {
+                (PolygonRing::Inner(_), Some(last)) => last.interiors_push(line),
+                _ => polygons.push(Polygon::new(line, Vec::new())),
+            }
+        }
+        regions.push(Region { name: region_key(&name), geometry: MultiPolygon(polygons) });
+    }
+    Ok(regions)
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use polars::prelude::*;
+use rstar::primitives::GeomWithData;
+use rstar::RTree;
+use std::path::PathBuf;
+
+use crate::matching::{read_rows, StreetRow};
+use osm_processor_rust::haversine_km;
+use osm_processor_rust::stats::load_streets;
+
+/// Point on the unit sphere; chord length grows monotonically with great-circle distance,
+/// so nearest neighbours in this space are nearest on the globe
+fn unit_vector(lat: f64, lon: f64) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// R-tree over street representative points
+pub struct StreetIndex {
+    rows: Vec<StreetRow>,
+    tree: RTree<GeomWithData<[f64; 3], usize>>,
+}
+
+impl StreetIndex {
+    pub fn new(rows: Vec<StreetRow>) -> Self {
+        let points = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.lat.is_finite() && row.lon.is_finite())
+            .map(|(i, row)| GeomWithData::new(unit_vector(row.lat, row.lon), i))
+            .collect();
+        StreetIndex { rows, tree: RTree::bulk_load(points) }
+    }
+
+    pub fn from_dataframe(df: &DataFrame) -> Result<Self> {
+        Ok(Self::new(read_rows(df)?))
+    }
+
+    /// The `k` streets closest to (lat, lon), nearest first, with distances in km
+    pub fn nearest(&self, lat: f64, lon: f64, k: usize) -> Vec<(&StreetRow, f64)> {
+        self.tree
+            .nearest_neighbor_iter(&unit_vector(lat, lon))
+            .take(k)
+            .map(|point| {
+                let row = &self.rows[point.data];
+                (row, haversine_km((lat, lon), (row.lat, row.lon)))
+            })
+            .collect()
+    }
+}
+
+/// `nearest <streets.parquet...> --lat LAT --lon LON [--k N]`
+pub fn run_nearest(paths: &[PathBuf], lat: f64, lon: f64, k: usize) -> Result<()> {
+    let index = StreetIndex::from_dataframe(&load_streets(paths)?)?;
+    let hits = index.nearest(lat, lon, k);
+
+    let out = DataFrame::new(vec![
+        Series::new("street_name", hits.iter().map(|h| h.0.street_name.as_str()).collect::<Vec<_>>()),
+        Series::new("state", hits.iter().map(|h| h.0.state.as_str()).collect::<Vec<_>>()),
+        Series::new("street_type", hits.iter().map(|h| h.0.street_type.as_deref()).collect::<Vec<_>>()),
+        Series::new("lat", hits.iter().map(|h| h.0.lat).collect::<Vec<_>>()),
+        Series::new("lon", hits.iter().map(|h| h.0.lon).collect::<Vec<_>>()),
+        Series::new("num_segments", hits.iter().map(|h| h.0.num_segments).collect::<Vec<_>>()),
+        Series::new("length_km", hits.iter().map(|h| h.0.length_km).collect::<Vec<_>>()),
+        Series::new("distance_km", hits.iter().map(|h| h.1).collect::<Vec<_>>()),
+    ])?;
+
+    println!("{} nearest streets to ({}, {}):", hits.len(), lat, lon);
+    println!("{}", out);
+    Ok(())
+}
@@ -0,0 +1,225 @@
+//! Compact membership set of OSM node IDs.
+//!
+//! Pass 1 collects every node of the named ways, tens of millions on large states, and the node
+//! pass then tests every node of the file against them. A hash set costs well over ten bytes per
+//! ID and a hash per lookup. [`NodeIdSet`] instead splits IDs into chunks of 65,536 consecutive
+//! IDs, Roaring-bitmap style, and stores each chunk as a sorted array of 16-bit offsets (two
+//! bytes per ID) or, once it holds more than 4,096 IDs, as an 8 KiB bitmap. Nodes of an area are
+//! created together and get nearby IDs, so the dense chunks of a state's street nodes cost under
+//! a byte per ID, and a lookup is a binary search over chunk keys plus a bit test.
+
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+
+const CHUNK_BITS: u32 = 16;
+const BITMAP_WORDS: usize = 1 << (CHUNK_BITS - 6);
+/// Past this many IDs a sorted array takes more room than a bitmap
+const ARRAY_MAX_IDS: usize = BITMAP_WORDS * 4;
+/// IDs inserted between merges into the chunks
+const BUFFER_IDS: usize = 1 << 22;
+
+/// Chunk key and offset within the chunk
+fn split(id: i64) -> (i64, u16) {
+    (id >> CHUNK_BITS, id as u16)
+}
+
+#[derive(Debug, Clone)]
+enum Chunk {
+    Array(Vec<u16>),
+    Bitmap(Box<[u64; BITMAP_WORDS]>),
+}
+
+impl Chunk {
+    fn contains(&self, offset: u16) -> bool {
+        match self {
+            Chunk::Array(offsets) => offsets.binary_search(&offset).is_ok(),
+            Chunk::Bitmap(words) => words[offset as usize >> 6] & (1 << (offset & 63)) != 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Chunk::Array(offsets) => offsets.len(),
+            Chunk::Bitmap(words) => words.iter().map(|word| word.count_ones() as usize).sum(),
+        }
+    }
+
+    fn heap_bytes(&self) -> usize {
+        match self {
+            Chunk::Array(offsets) => offsets.capacity() * 2,
+            Chunk::Bitmap(_) => BITMAP_WORDS * 8,
+        }
+    }
+
+    /// Add sorted, distinct offsets, switching to a bitmap once an array grows past
+    /// [`ARRAY_MAX_IDS`]
+    fn extend(&mut self, new: &[u16]) {
+        if let Chunk::Array(offsets) = self {
+            offsets.extend_from_slice(new);
+            offsets.sort_unstable();
+            offsets.dedup();
+            if offsets.len() <= ARRAY_MAX_IDS {
+                return;
+            }
+            let mut words = Box::new([0u64; BITMAP_WORDS]);
+            for &offset in offsets.iter() {
+                words[offset as usize >> 6] |= 1 << (offset & 63);
+            }
+            *self = Chunk::Bitmap(words);
+            return;
+        }
+        if let Chunk::Bitmap(words) = self {
+            for &offset in new {
+                words[offset as usize >> 6] |= 1 << (offset & 63);
+            }
+        }
+    }
+
+    /// Offsets in ascending order
+    fn offsets(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Chunk::Array(offsets) => Box::new(offsets.iter().copied()),
+            Chunk::Bitmap(words) => Box::new(words.iter().enumerate().flat_map(|(i, &word)| {
+                let mut rest = word;
+                std::iter::from_fn(move || {
+                    if rest == 0 {
+                        return None;
+                    }
+                    let bit = rest.trailing_zeros() as usize;
+                    rest &= rest - 1;
+                    Some((i * 64 + bit) as u16)
+                })
+            })),
+        }
+    }
+}
+
+/// Immutable set of node IDs; build with [`NodeIdSetBuilder`] or `collect()`
+#[derive(Debug, Clone, Default)]
+pub struct NodeIdSet {
+    /// Sorted chunk keys, parallel to `chunks`
+    keys: Vec<i64>,
+    chunks: Vec<Chunk>,
+    len: usize,
+}
+
+impl NodeIdSet {
+    pub fn contains(&self, id: i64) -> bool {
+        let (key, offset) = split(id);
+        self.keys.binary_search(&key).is_ok_and(|i| self.chunks[i].contains(offset))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// IDs in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = i64> + '_ {
+        self.keys
+            .iter()
+            .zip(&self.chunks)
+            .flat_map(|(&key, chunk)| chunk.offsets().map(move |offset| (key << CHUNK_BITS) | offset as i64))
+    }
+
+    /// Approximate memory held, for progress messages
+    pub fn heap_bytes(&self) -> usize {
+        self.keys.capacity() * 8
+            + self.chunks.capacity() * std::mem::size_of::<Chunk>()
+            + self.chunks.iter().map(Chunk::heap_bytes).sum::<usize>()
+    }
+}
+
+impl FromIterator<i64> for NodeIdSet {
+    fn from_iter<I: IntoIterator<Item = i64>>(ids: I) -> Self {
+        let mut builder = NodeIdSetBuilder::default();
+        for id in ids {
+            builder.insert(id);
+        }
+        builder.build()
+    }
+}
+
+/// Accumulates IDs in any order and with repeats; inserts are buffered and merged into the
+/// chunks a few million at a time, so peak memory stays close to that of the finished set
+#[derive(Debug, Default)]
+pub struct NodeIdSetBuilder {
+    chunks: BTreeMap<i64, Chunk>,
+    buffer: Vec<i64>,
+}
+
+impl NodeIdSetBuilder {
+    pub fn insert(&mut self, id: i64) {
+        self.buffer.push(id);
+        if self.buffer.len() >= BUFFER_IDS {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        self.buffer.par_sort_unstable();
+        self.buffer.dedup();
+        for run in self.buffer.chunk_by(|&a, &b| split(a).0 == split(b).0) {
+            let key = split(run[0]).0;
+            let offsets: Vec<u16> = run.iter().map(|&id| split(id).1).collect();
+            self.chunks.entry(key).or_insert_with(|| Chunk::Array(Vec::new())).extend(&offsets);
+        }
+        self.buffer.clear();
+    }
+
+    pub fn build(mut self) -> NodeIdSet {
+        self.flush();
+        let mut set = NodeIdSet::default();
+        for (key, mut chunk) in self.chunks {
+            if let Chunk::Array(offsets) = &mut chunk {
+                offsets.shrink_to_fit();
+            }
+            set.len += chunk.len();
+            set.keys.push(key);
+            set.chunks.push(chunk);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sparse_chunk_stays_an_array() {
+        let set: NodeIdSet = [5, 3, 70_000, 3].into_iter().collect();
+        assert_eq!(set.len(), 3);
+        assert!(set.chunks.iter().all(|chunk| matches!(chunk, Chunk::Array(_))));
+        assert!(set.contains(3) && set.contains(5) && set.contains(70_000));
+        assert!(!set.contains(4) && !set.contains(70_001));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3, 5, 70_000]);
+    }
+
+    #[test]
+    fn dense_chunk_switches_to_a_bitmap() {
+        let at_limit: NodeIdSet = (0..ARRAY_MAX_IDS as i64).map(|i| i * 2).collect();
+        assert!(matches!(at_limit.chunks[..], [Chunk::Array(_)]));
+
+        let ids: Vec<i64> = (0..=ARRAY_MAX_IDS as i64).map(|i| i * 2).collect();
+        let set: NodeIdSet = ids.iter().copied().collect();
+        assert!(matches!(set.chunks[..], [Chunk::Bitmap(_)]));
+        assert_eq!(set.len(), ids.len());
+        assert!(ids.iter().all(|&id| set.contains(id)));
+        assert!(!set.contains(1) && !set.contains(ids[ids.len() - 1] + 2));
+        assert_eq!(set.iter().collect::<Vec<_>>(), ids);
+    }
+
+    #[test]
+    fn negative_ids_keep_their_own_chunks() {
+        let ids = [-70_000, -65_536, -1, 0, 65_535];
+        let set: NodeIdSet = ids.iter().rev().copied().collect();
+        assert_eq!(set.len(), ids.len());
+        assert!(ids.iter().all(|&id| set.contains(id)));
+        assert!(!set.contains(-2) && !set.contains(1) && !set.contains(-65_535));
+        assert_eq!(set.iter().collect::<Vec<_>>(), ids);
+    }
+}
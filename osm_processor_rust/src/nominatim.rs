@@ -0,0 +1,192 @@
+//! `crosscheck --nominatim`: an external check of the grouping against a geocoder, for areas
+//! without TIGER coverage. Needs the `async` feature for its HTTP client.
+
+use anyhow::{Context, Result};
+use polars::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::matching::{read_rows, StreetRow};
+use crate::rng::SplitMix64;
+use osm_processor_rust::stats::load_streets;
+use osm_processor_rust::{haversine_km, write_parquet};
+
+/// Settings for `crosscheck --nominatim`
+pub struct NominatimOptions<'a> {
+    /// Base URL of the Nominatim instance (`https://nominatim.openstreetmap.org`)
+    pub base_url: &'a str,
+    /// Streets to sample
+    pub n: usize,
+    pub seed: u64,
+    /// Largest distance between a street's point and a Nominatim result that still agrees
+    pub max_match_km: f64,
+    /// Pause between requests; the public instance allows one per second
+    pub delay: Duration,
+}
+
+/// Nominatim's answer for one sampled street
+enum Lookup {
+    /// Highway results; the nearest one to the street's point and its distance from it
+    Found { lat: f64, lon: f64, distance_km: f64 },
+    /// No highway of that name in the state
+    NotFound,
+    /// The request failed; counted apart from both
+    Failed(String),
+}
+
+/// Search `<base_url>/search` for a street by name and state, returning the (lat, lon) of the
+/// highway results
+async fn search_street(client: &reqwest::Client, endpoint: &str, row: &StreetRow) -> Result<Vec<(f64, f64)>> {
+    let state = row.state.replace('-', " ");
+    let body = client
+        .get(endpoint)
+        .query(&[("street", row.street_name.as_str()), ("state", state.as_str()), ("format", "jsonv2"), ("limit", "10")])
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())?
+        .text()
+        .await?;
+    let results: Vec<serde_json::Value> = serde_json::from_str(&body).context("Unexpected Nominatim response")?;
+    Ok(results
+        .iter()
+        .filter(|result| result["category"] == "highway")
+        .filter_map(|result| Some((result["lat"].as_str()?.parse().ok()?, result["lon"].as_str()?.parse().ok()?)))
+        .collect())
+}
+
+fn lookup_streets(rows: &[&StreetRow], options: &NominatimOptions) -> Result<Vec<Lookup>> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("osm_processor_rust/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let endpoint = format!("{}/search", options.base_url.trim_end_matches('/'));
+
+    let mut lookups = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            std::thread::sleep(options.delay);
+        }
+        let lookup = match runtime.block_on(search_street(&client, &endpoint, row)) {
+            Ok(points) => points
+                .into_iter()
+                .map(|(lat, lon)| (lat, lon, haversine_km((row.lat, row.lon), (lat, lon))))
+                .min_by(|a, b| a.2.total_cmp(&b.2))
+                .map_or(Lookup::NotFound, |(lat, lon, distance_km)| Lookup::Found { lat, lon, distance_km }),
+            Err(err) => Lookup::Failed(format!("{:#}", err)),
+        };
+        if (i + 1) % 50 == 0 {
+            println!("  {} of {} looked up", i + 1, rows.len());
+        }
+        lookups.push(lookup);
+    }
+    Ok(lookups)
+}
+
+/// `crosscheck <streets.parquet...> --nominatim URL [--n N] [--seed S] [--max-match-km X]
+/// [--delay-ms MS] [--output-dir DIR]`: look up a random sample of streets by name and state and
+/// report how many Nominatim knows (existence) and how many of those it places within
+/// `max_match_km` of the street's point (location)
+pub fn run_nominatim_crosscheck(paths: &[PathBuf], options: &NominatimOptions, report_dir: Option<&Path>) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("CROSS-CHECK AGAINST NOMINATIM");
+    println!("{}", "=".repeat(70));
+    println!("Nominatim:    {}", options.base_url);
+    println!("Sample:       {} streets (seed {})", options.n, options.seed);
+    println!("Match radius: {} km", options.max_match_km);
+    println!("{}", "=".repeat(70));
+
+    let all_rows = read_rows(&load_streets(paths)?)?;
+    let sample = SplitMix64::new(options.seed).sample_indices(all_rows.len(), options.n);
+    let rows: Vec<&StreetRow> = sample.iter().map(|&i| &all_rows[i]).collect();
+    println!("\nLooking up {} of {} streets...", rows.len(), all_rows.len());
+    let lookups = lookup_streets(&rows, options)?;
+
+    let mut found: Vec<f64> = lookups
+        .iter()
+        .filter_map(|lookup| match lookup {
+            Lookup::Found { distance_km, .. } => Some(*distance_km),
+            _ => None,
+        })
+        .collect();
+    let failed = lookups.iter().filter(|lookup| matches!(lookup, Lookup::Failed(_))).count();
+    let answered = lookups.len() - failed;
+    let located = found.iter().filter(|&&d| d <= options.max_match_km).count();
+    found.sort_by(f64::total_cmp);
+
+    let pct = |part: usize, whole: usize| if whole == 0 { 0.0 } else { 100.0 * part as f64 / whole as f64 };
+    println!("Found:          {} of {} ({:.1}%)", found.len(), answered, pct(found.len(), answered));
+    println!("Located:        {} of found ({:.1}%) within {} km", located, pct(located, found.len()), options.max_match_km);
+    if let Some(median) = found.get(found.len() / 2) {
+        println!("Median distance: {:.3} km", median);
+    }
+    if failed > 0 {
+        println!("Failed lookups: {}", failed);
+    }
+
+    let disagreements: Vec<String> = rows
+        .iter()
+        .zip(&lookups)
+        .filter_map(|(row, lookup)| match lookup {
+            Lookup::NotFound => Some(format!("{} ({}): not found", row.street_name, row.state)),
+            Lookup::Found { distance_km, .. } if *distance_km > options.max_match_km => {
+                Some(format!("{} ({}): nearest result {:.2} km away", row.street_name, row.state, distance_km))
+            }
+            _ => None,
+        })
+        .collect();
+    if !disagreements.is_empty() {
+        println!("\nSample disagreements:");
+        for line in disagreements.iter().take(20) {
+            println!("  {}", line);
+        }
+    }
+
+    if let Some(dir) = report_dir {
+        std::fs::create_dir_all(dir)?;
+        let result = |f: fn(&Lookup) -> Option<f64>| lookups.iter().map(f).collect::<Vec<_>>();
+        let mut df = DataFrame::new(vec![
+            Series::new("street_id", rows.iter().map(|r| r.street_id.as_deref()).collect::<Vec<_>>()),
+            Series::new("street_name", rows.iter().map(|r| r.street_name.as_str()).collect::<Vec<_>>()),
+            Series::new("state", rows.iter().map(|r| r.state.as_str()).collect::<Vec<_>>()),
+            Series::new("lat", rows.iter().map(|r| r.lat).collect::<Vec<_>>()),
+            Series::new("lon", rows.iter().map(|r| r.lon).collect::<Vec<_>>()),
+            Series::new(
+                "found",
+                lookups
+                    .iter()
+                    .map(|lookup| match lookup {
+                        Lookup::Found { .. } => Some(true),
+                        Lookup::NotFound => Some(false),
+                        Lookup::Failed(_) => None,
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            Series::new("nominatim_lat", result(|lookup| match lookup {
+                Lookup::Found { lat, .. } => Some(*lat),
+                _ => None,
+            })),
+            Series::new("nominatim_lon", result(|lookup| match lookup {
+                Lookup::Found { lon, .. } => Some(*lon),
+                _ => None,
+            })),
+            Series::new("distance_km", result(|lookup| match lookup {
+                Lookup::Found { distance_km, .. } => Some(*distance_km),
+                _ => None,
+            })),
+            Series::new(
+                "error",
+                lookups
+                    .iter()
+                    .map(|lookup| match lookup {
+                        Lookup::Failed(err) => Some(err.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+        ])?;
+        write_parquet(&mut df, &dir.join("crosscheck_nominatim.parquet"))?;
+        println!("Report saved to: {}", dir.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Street orientation: the dominant compass bearing of a street's geometry and how spread out
+//! its bearings are, for grid-orientation analyses in the style of street-network orientation
+//! studies (polar histograms of edge bearings, orientation entropy).
+
+use crate::{haversine_km, StreetSegment};
+
+/// Histogram bins over the full compass; each edge counts in both directions, so a bin and the
+/// one opposite it always hold the same weight
+pub const BEARING_BINS: usize = 36;
+
+/// Length-weighted orientation of a street's segments
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orientation {
+    /// Dominant axis in degrees clockwise from north, in [0, 180): 0 runs north-south, 90
+    /// east-west. The axial mean, so bearings 179° and 1° average to 0° rather than 90°.
+    pub bearing_deg: f64,
+    /// Shannon entropy (nats) of the bearing histogram over [`BEARING_BINS`] bins: ln 2 for a
+    /// straight street, ln 4 for a perfect grid, up to ln 36 when all directions are equally common
+    pub entropy: f64,
+}
+
+/// Initial great-circle bearing from `a` to `b`, both (lat, lon), in degrees in [0, 360)
+pub fn bearing_deg(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (a.0.to_radians(), b.0.to_radians());
+    let dlon = (b.1 - a.1).to_radians();
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Orientation of the segments' edges (consecutive points), weighted by edge length; `None` if
+/// the street has no length
+pub fn orientation(segs: &[&StreetSegment]) -> Option<Orientation> {
+    let bin_width = 360.0 / BEARING_BINS as f64;
+    let mut histogram = [0.0; BEARING_BINS];
+    let (mut cos_sum, mut sin_sum, mut total) = (0.0, 0.0, 0.0);
+
+    for edge in segs.iter().flat_map(|seg| seg.coords.windows(2)) {
+        let length = haversine_km(edge[0], edge[1]);
+        if length <= 0.0 {
+            continue;
+        }
+        let bearing = bearing_deg(edge[0], edge[1]);
+
+        // Doubling the angle makes opposite directions coincide, for the axial mean
+        let doubled = (2.0 * bearing).to_radians();
+        cos_sum += length * doubled.cos();
+        sin_sum += length * doubled.sin();
+        total += length;
+
+        // Bins are centred on multiples of the bin width (0° is the north bin)
+        let bin = ((bearing / bin_width).round() as usize) % BEARING_BINS;
+        histogram[bin] += length / 2.0;
+        histogram[(bin + BEARING_BINS / 2) % BEARING_BINS] += length / 2.0;
+    }
+    if total <= 0.0 {
+        return None;
+    }
+
+    let entropy = -histogram
+        .iter()
+        .filter(|&&w| w > 0.0)
+        .map(|&w| {
+            let p = w / total;
+            p * p.ln()
+        })
+        .sum::<f64>();
+    let bearing_deg = (sin_sum.atan2(cos_sum) / 2.0).to_degrees().rem_euclid(180.0);
+
+    Some(Orientation { bearing_deg, entropy })
+}
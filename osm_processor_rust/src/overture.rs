@@ -0,0 +1,187 @@
+//! Overture Maps transportation input.
+//!
+//! Overture's transportation theme publishes roads, railways and waterways as `segment` features
+//! (GeoParquet with WKB LineString geometry) joined at `connector` points. Reading those into
+//! [`StreetSegment`]s runs the same grouping and aggregation as for OSM, for comparable street
+//! datasets to cross-validate against each other. Overture IDs are GERS ID strings, so segments
+//! and connectors get 64-bit FNV-1a hashes of them as way and node IDs; connectors shared by two
+//! segments are the shared nodes the grouping connects them by.
+
+use polars::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::error::{ProcessorError, Result};
+use crate::fast_hash::NodeMap;
+use crate::feature::Feature;
+use crate::names::NamePolicy;
+use crate::progress::ProgressSink;
+use crate::street_id::fnv1a;
+use crate::tags::Tags;
+use crate::wkb::{wkb_to_lines, wkb_to_point};
+use crate::{polyline_length_km, StreetSegment};
+
+/// Overture input besides the segments file, which is the processor's input
+#[derive(Debug, Clone, Default)]
+pub struct OvertureConfig {
+    /// Connectors GeoParquet of the same area; its points become node coordinates, which node
+    /// output needs
+    pub connectors: Option<PathBuf>,
+}
+
+/// Stable way or node ID for a GERS ID
+pub fn gers_id(id: &str) -> i64 {
+    fnv1a(id.as_bytes()) as i64
+}
+
+/// Overture `subtype` of `feature`'s segments
+fn subtype(feature: Feature) -> &'static str {
+    match feature {
+        Feature::Railway => "rail",
+        Feature::Waterway => "water",
+        Feature::Highway | Feature::Cycling | Feature::Paths | Feature::Speed => "road",
+    }
+}
+
+fn read_error(path: &Path) -> impl FnOnce(PolarsError) -> ProcessorError + '_ {
+    move |source| ProcessorError::InputRead { path: path.to_path_buf(), source }
+}
+
+/// Read the named segments of `feature` from an Overture segments GeoParquet file; `class` is
+/// the type (`residential`, `primary`, ...), `names.primary` the name and `names.common` the
+/// `name:<lang>` variants
+pub fn read_segments(
+    path: &Path,
+    state_name: &str,
+    feature: Feature,
+    names: &NamePolicy,
+    progress: &dyn ProgressSink,
+) -> Result<Vec<StreetSegment>> {
+    progress.message(&format!("Reading Overture segments from {}...", path.display()));
+    let mut scan = LazyFrame::scan_parquet(path, ScanArgsParquet::default()).map_err(read_error(path))?;
+    let schema = scan.schema().map_err(read_error(path))?;
+    // Releases before mid-2024 list plain `connector_ids`; later ones `connectors` with positions
+    let connectors = ["connectors", "connector_ids"].into_iter().find(|name| schema.contains(name));
+    let mut columns = vec![
+        col("id"),
+        col("class"),
+        col("names").struct_().field_by_name("primary").alias("name"),
+        col("names").struct_().field_by_name("common").alias("common_names"),
+        col("geometry"),
+    ];
+    columns.extend(connectors.map(col));
+    let df = scan
+        .filter(col("subtype").eq(lit(subtype(feature))))
+        .select(columns)
+        .collect()
+        .map_err(read_error(path))?;
+
+    let segments = segments_from_frame(&df, connectors, state_name, feature, names).map_err(read_error(path))?;
+    progress.message(&format!("  Found {} street segments", segments.len()));
+    Ok(segments)
+}
+
+fn segments_from_frame(
+    df: &DataFrame,
+    connectors: Option<&str>,
+    state_name: &str,
+    feature: Feature,
+    names: &NamePolicy,
+) -> PolarsResult<Vec<StreetSegment>> {
+    let ids = df.column("id")?.str()?;
+    let classes = df.column("class")?.str()?;
+    let primary_names = df.column("name")?.str()?;
+    let common_names = df.column("common_names")?.list()?;
+    let geometries = df.column("geometry")?.binary()?;
+    let connector_lists = connectors.map(|name| df.column(name)?.list()).transpose()?;
+
+    let mut segments = Vec::new();
+    for i in 0..df.height() {
+        let (Some(id), Some(class)) = (ids.get(i), classes.get(i)) else { continue };
+        let common = match common_names.get_as_series(i) {
+            Some(map) => map_entries(&map)?,
+            None => Vec::new(),
+        };
+        let tag = |key: &str| match key.strip_prefix("name:") {
+            Some(lang) => common.iter().find(|(l, _)| l == lang).map(|(_, name)| name.as_str()),
+            None if key == "name" => primary_names.get(i),
+            None => (key == feature.type_key()).then_some(class),
+        };
+        let (Some(name), Some(highway_type)) = (names.choose(tag), feature.way_type(tag)) else { continue };
+
+        let coords: Vec<(f64, f64)> = geometries
+            .get(i)
+            .and_then(wkb_to_lines)
+            .and_then(|lines| lines.into_iter().next())
+            .map(|line| line.0.iter().map(|c| (c.y, c.x)).collect())
+            .unwrap_or_default();
+        if coords.is_empty() {
+            continue;
+        }
+        let node_ids = match (connectors, connector_lists.and_then(|lists| lists.get_as_series(i))) {
+            (Some("connectors"), Some(list)) => {
+                let ids = list.struct_()?.field_by_name("connector_id")?;
+                ids.str()?.into_iter().flatten().map(gers_id).collect()
+            }
+            (_, Some(list)) => list.str()?.into_iter().flatten().map(gers_id).collect(),
+            (_, None) => Vec::new(),
+        };
+
+        let mut pairs = vec![(feature.type_key().to_string(), class.to_string())];
+        pairs.extend(primary_names.get(i).map(|primary| ("name".to_string(), primary.to_string())));
+        pairs.extend(common.iter().map(|(lang, name)| (format!("name:{}", lang), name.clone())));
+
+        segments.push(StreetSegment {
+            street_name: name.to_string(),
+            local_name: if names.is_local() { None } else { primary_names.get(i).map(str::to_string) },
+            state: state_name.to_string(),
+            region: None,
+            direction: None,
+            relation_id: None,
+            way_id: gers_id(id),
+            node_ids,
+            length_km: polyline_length_km(&coords),
+            coords,
+            highway_type: highway_type.to_string(),
+            tags: Tags::from_pairs(pairs),
+            sanitized: Vec::new(),
+        });
+    }
+    Ok(segments)
+}
+
+/// (key, value) entries of one row of a parquet map column, which reads as a list of structs
+fn map_entries(map: &Series) -> PolarsResult<Vec<(String, String)>> {
+    let entries = map.struct_()?;
+    let keys = entries.field_by_name("key")?;
+    let values = entries.field_by_name("value")?;
+    Ok(keys
+        .str()?
+        .into_iter()
+        .zip(values.str()?)
+        .filter_map(|(key, value)| Some((key?.to_string(), value?.to_string())))
+        .collect())
+}
+
+/// Connector coordinates `(lat, lon)` by node ID from an Overture connectors GeoParquet file
+pub fn read_connectors(path: &Path, progress: &dyn ProgressSink) -> Result<NodeMap<(f64, f64)>> {
+    progress.message(&format!("Reading Overture connectors from {}...", path.display()));
+    let df = LazyFrame::scan_parquet(path, ScanArgsParquet::default())
+        .and_then(|scan| scan.select([col("id"), col("geometry")]).collect())
+        .map_err(read_error(path))?;
+    let coords = connectors_from_frame(&df).map_err(read_error(path))?;
+    progress.message(&format!("  Loaded {} connector coordinates", coords.len()));
+    Ok(coords)
+}
+
+fn connectors_from_frame(df: &DataFrame) -> PolarsResult<NodeMap<(f64, f64)>> {
+    let ids = df.column("id")?.str()?;
+    let geometries = df.column("geometry")?.binary()?;
+    Ok(ids
+        .into_iter()
+        .zip(geometries)
+        .filter_map(|(id, geometry)| {
+            let point = wkb_to_point(geometry?)?;
+            Some((gers_id(id?), (point.y(), point.x())))
+        })
+        .collect())
+}
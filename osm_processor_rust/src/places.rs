@@ -0,0 +1,134 @@
+use osmpbf::{Element, ElementReader};
+use polars::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::error::{pbf_error, Result};
+use crate::{default_output_path, round_coord, write_parquet};
+
+/// Settlement types kept by the places extractor
+const PLACE_TYPES: [&str; 4] = ["city", "town", "village", "hamlet"];
+
+/// A named settlement node
+#[derive(Debug)]
+struct Place {
+    osm_id: i64,
+    name: String,
+    place_type: String,
+    population: Option<i64>,
+    lat: f64,
+    lon: f64,
+}
+
+/// Parse a `population` tag, tolerating thousands separators ("12,345")
+fn parse_population(value: &str) -> Option<i64> {
+    let digits: String = value
+        .chars()
+        .filter(|c| !matches!(c, ',' | ' ' | '_'))
+        .collect();
+    digits.parse().ok()
+}
+
+/// Build a Place from a node's id, coordinates and tags, if it is a named settlement
+fn place_from_tags<'a>(
+    osm_id: i64,
+    lat: f64,
+    lon: f64,
+    tags: impl Iterator<Item = (&'a str, &'a str)>,
+) -> Option<Place> {
+    let mut name = None;
+    let mut place_type = None;
+    let mut population = None;
+
+    for (k, v) in tags {
+        match k {
+            "name" => name = Some(v),
+            "place" if PLACE_TYPES.contains(&v) => place_type = Some(v),
+            "population" => population = parse_population(v),
+            _ => {}
+        }
+    }
+
+    Some(Place {
+        osm_id,
+        name: name?.to_string(),
+        place_type: place_type?.to_string(),
+        population,
+        lat,
+        lon,
+    })
+}
+
+/// Single pass over nodes collecting `place=city|town|village|hamlet`
+fn extract_places(pbf_path: &Path) -> Result<Vec<Place>> {
+    println!("Extracting place nodes...");
+    let reader = ElementReader::from_path(pbf_path)
+        .map_err(pbf_error(pbf_path))?;
+
+    let places = reader.par_map_reduce(
+        |element| match element {
+            Element::Node(node) => place_from_tags(node.id(), node.lat(), node.lon(), node.tags())
+                .into_iter()
+                .collect(),
+            Element::DenseNode(node) => place_from_tags(node.id(), node.lat(), node.lon(), node.tags())
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        },
+        Vec::new,
+        |mut a, b| {
+            a.extend(b);
+            a
+        },
+    )
+    .map_err(pbf_error(pbf_path))?;
+
+    println!("  Found {} places", places.len());
+    Ok(places)
+}
+
+fn places_to_dataframe(places: &[Place], state_name: &str, coord_precision: Option<u32>) -> Result<DataFrame> {
+    let df = DataFrame::new(vec![
+        Series::new("osm_id", places.iter().map(|p| p.osm_id).collect::<Vec<_>>()),
+        Series::new("state", vec![state_name; places.len()]),
+        Series::new("name", places.iter().map(|p| p.name.as_str()).collect::<Vec<_>>()),
+        Series::new("place", places.iter().map(|p| p.place_type.as_str()).collect::<Vec<_>>()),
+        Series::new("population", places.iter().map(|p| p.population).collect::<Vec<_>>()),
+        Series::new("lat", places.iter().map(|p| round_coord(p.lat, coord_precision)).collect::<Vec<_>>()),
+        Series::new("lon", places.iter().map(|p| round_coord(p.lon, coord_precision)).collect::<Vec<_>>()),
+    ])?;
+
+    Ok(df)
+}
+
+/// Places mode: extract settlement nodes to parquet
+pub fn process_places_to_parquet(
+    pbf_path: &Path,
+    state_name: &str,
+    output_path: Option<PathBuf>,
+    coord_precision: Option<u32>,
+) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("OSM PLACES TO PARQUET (Rust)");
+    println!("{}", "=".repeat(70));
+    println!("Input file:  {}", pbf_path.display());
+    println!("State:       {}", state_name);
+    println!("{}", "=".repeat(70));
+
+    let output_path = output_path.unwrap_or_else(|| default_output_path(pbf_path, state_name, "places"));
+
+    let places = extract_places(pbf_path)?;
+    let mut df = places_to_dataframe(&places, state_name, coord_precision)?;
+
+    for place_type in PLACE_TYPES {
+        let count = places.iter().filter(|p| p.place_type == place_type).count();
+        println!("  {:<8} {}", place_type, count);
+    }
+
+    println!("\nSaving to: {}", output_path.display());
+    write_parquet(&mut df, &output_path)?;
+
+    println!("Done!");
+    println!("{}", "=".repeat(70));
+
+    Ok(())
+}
@@ -0,0 +1,1021 @@
+use polars::prelude::*;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::aggregation::{DefaultAggregator, StreetAggregator};
+use crate::checkpoint::{checkpoint_path, load_node_coords_checkpointed};
+use crate::crs::OutputCrs;
+use crate::error::{io_error, ProcessorError, Result};
+use crate::fast_hash::NodeMap;
+use crate::feature::Feature;
+use crate::grouping::{ConnectivityDistance, GroupingStrategy};
+use crate::name_parser;
+use crate::names::NamePolicy;
+use crate::overture::{self, OvertureConfig};
+use crate::progress::{NoProgress, Phase, ProgressSink};
+use crate::qa::{QaConfig, QaRecord};
+use crate::relations::street_relations;
+use crate::regions::{assign_regions, assign_sub_regions, RegionIndex, RegionLevel};
+use crate::segment_artifact::{load_segments, save_segments};
+use crate::segments::{node_records, segment_records, NodeRecord, SegmentRecord};
+use crate::sharding::{run_sharded, ShardConfig};
+use crate::rep_point::RepPoint;
+use crate::sanitize::SanitizeCounts;
+use crate::skipped::SkipLog;
+use crate::street_id::StreetIdMode;
+use crate::wikidata::{self, WikidataConfig};
+use crate::{
+    adjacency, collect_highway_nodes, deadline_passed, group_segments, group_segments_into_streets, load_node_coords,
+    name_groups, round_coord, segments_from_ways, streets_for_name, streets_to_dataframe, NameGroup, Street,
+    StreetSegment,
+};
+
+/// Default distance for merging disconnected same-name components (200 m)
+pub const DEFAULT_DISTANCE_THRESHOLD_KM: f64 = 0.2;
+
+/// Streets buffered between the grouping workers and a [`StreetStream`] consumer
+const STREAM_BUFFER: usize = 1024;
+
+/// Coordinates (lat, lon) of the nodes loaded by the coordinate pass, by node ID
+type NodeCoords = NodeMap<(f64, f64)>;
+
+/// Streets extracted from one PBF file
+#[derive(Debug)]
+pub struct StreetsResult {
+    pub state: String,
+    pub feature: Feature,
+    pub streets: Vec<Street>,
+    /// Named way segments found before grouping
+    pub num_segments: usize,
+    /// Number of distinct shared nodes per intersecting street-name pair, if requested
+    pub adjacency: Option<HashMap<(String, String), u32>>,
+    /// Streets flagged by the QA checks, if enabled
+    pub qa: Option<Vec<QaRecord>>,
+    /// Every grouped segment with its street's `street_id`, if requested
+    pub segments: Option<Vec<SegmentRecord>>,
+    /// Every node of a grouped segment with its coordinates and street, if requested
+    pub nodes: Option<Vec<NodeRecord>>,
+    /// The max runtime was reached and the run stopped early: `streets` (and the other outputs)
+    /// hold only the street names grouped before then
+    pub truncated: bool,
+    /// What hardened parsing repaired or dropped, if enabled
+    pub sanitized: Option<SanitizeCounts>,
+    /// Etymology lookup that [`into_dataframe`](Self::into_dataframe) applies, if configured
+    pub wikidata: Option<WikidataConfig>,
+}
+
+impl StreetsResult {
+    /// The streets in the same layout as the parquet output, with the etymology columns
+    /// resolved when Wikidata lookup is configured
+    pub fn into_dataframe(self) -> Result<DataFrame> {
+        let mut df = streets_to_dataframe(self.streets, self.feature)?;
+        if let Some(config) = &self.wikidata {
+            wikidata::resolve_etymology(&mut df, config)?;
+        }
+        Ok(df)
+    }
+}
+
+/// Street extraction for one PBF file, configured through [`OsmStreetProcessor::builder`]:
+///
+/// ```ignore
+/// let result = OsmStreetProcessor::builder()
+///     .input("data/osm/delaware-latest.osm.pbf")
+///     .state("delaware")
+///     .distance_threshold_km(0.2)
+///     .run()?;
+/// ```
+#[derive(Clone)]
+pub struct OsmStreetProcessor {
+    input: PathBuf,
+    state: String,
+    distance_threshold_km: f64,
+    /// Largest of `distance_threshold_km` and the per-class thresholds
+    max_merge_km: f64,
+    feature: Feature,
+    names: NamePolicy,
+    merge_directionals: bool,
+    relations: bool,
+    adjacency: bool,
+    emit_segments: bool,
+    emit_nodes: bool,
+    coord_precision: Option<u32>,
+    simplify_tolerance_m: Option<f64>,
+    output_crs: OutputCrs,
+    skipped_log: Option<PathBuf>,
+    hardened: bool,
+    checkpoint_nodes: bool,
+    save_segments: Option<PathBuf>,
+    from_segments: Option<PathBuf>,
+    overture: Option<OvertureConfig>,
+    wikidata: Option<WikidataConfig>,
+    overwrite: bool,
+    temp_dir: PathBuf,
+    max_runtime: Option<Duration>,
+    qa: Option<QaConfig>,
+    regions: Option<Arc<RegionIndex>>,
+    sub_regions: Option<(RegionLevel, Arc<RegionIndex>)>,
+    shards: Option<ShardConfig>,
+    options: BTreeMap<String, String>,
+    grouping: Arc<dyn GroupingStrategy>,
+    aggregator: Arc<dyn StreetAggregator>,
+    progress: Arc<dyn ProgressSink>,
+}
+
+/// Builder for [`OsmStreetProcessor`]; `input` and `state` are required
+#[derive(Clone, Default)]
+pub struct OsmStreetProcessorBuilder {
+    input: Option<PathBuf>,
+    state: Option<String>,
+    distance_threshold_km: Option<f64>,
+    class_thresholds_km: HashMap<String, f64>,
+    feature: Option<Feature>,
+    names: NamePolicy,
+    merge_directionals: bool,
+    relations: bool,
+    adjacency: bool,
+    emit_segments: bool,
+    emit_nodes: bool,
+    coord_precision: Option<u32>,
+    simplify_tolerance_m: Option<f64>,
+    output_crs: OutputCrs,
+    rep_point: RepPoint,
+    street_id: StreetIdMode,
+    skipped_log: Option<PathBuf>,
+    hardened: bool,
+    checkpoint_nodes: bool,
+    save_segments: Option<PathBuf>,
+    from_segments: Option<PathBuf>,
+    overture: Option<OvertureConfig>,
+    wikidata: Option<WikidataConfig>,
+    overwrite: bool,
+    temp_dir: Option<PathBuf>,
+    max_runtime: Option<Duration>,
+    qa: Option<QaConfig>,
+    regions: Option<Arc<RegionIndex>>,
+    sub_regions: Option<(RegionLevel, Arc<RegionIndex>)>,
+    shards: Option<ShardConfig>,
+    grouping: Option<Arc<dyn GroupingStrategy>>,
+    aggregator: Option<Arc<dyn StreetAggregator>>,
+    progress: Option<Arc<dyn ProgressSink>>,
+}
+
+impl OsmStreetProcessorBuilder {
+    /// PBF file to read
+    pub fn input(mut self, path: impl Into<PathBuf>) -> Self {
+        self.input = Some(path.into());
+        self
+    }
+
+    /// State name written to every row
+    pub fn state(mut self, state: impl Into<String>) -> Self {
+        self.state = Some(state.into());
+        self
+    }
+
+    /// Merge disconnected same-name components closer than this; 0 disables merging.
+    /// Only used by the default [`ConnectivityDistance`] grouping.
+    pub fn distance_threshold_km(mut self, km: f64) -> Self {
+        self.distance_threshold_km = Some(km);
+        self
+    }
+
+    /// Per-highway-type merge thresholds overriding `distance_threshold_km`, e.g. 1 km for
+    /// motorways and 0.1 km for residential streets. Only used by the default grouping.
+    pub fn threshold_by_class(mut self, thresholds_km: HashMap<String, f64>) -> Self {
+        self.class_thresholds_km = thresholds_km;
+        self
+    }
+
+    /// Which ways to extract (default: named highways)
+    pub fn feature(mut self, feature: Feature) -> Self {
+        self.feature = Some(feature);
+        self
+    }
+
+    /// Which `name:*` tag becomes `street_name` (default: the plain `name` tag). Any other policy
+    /// also keeps the plain `name` as `name_local`.
+    pub fn name_lang(mut self, policy: NamePolicy) -> Self {
+        self.names = policy;
+        self
+    }
+
+    /// Group `N Main St` and `S Main St` as one `Main St`, listing the directionals in the
+    /// `direction` column, instead of as separate streets (default)
+    pub fn merge_directionals(mut self, enabled: bool) -> Self {
+        self.merge_directionals = enabled;
+        self
+    }
+
+    /// Read associatedStreet, street and road route relations and group same-name segments on
+    /// one relation into one street, however far apart; such streets get `grouped_by =
+    /// relation` and their `relation_id` in the output, the rest `grouped_by = heuristic`
+    pub fn relations(mut self, enabled: bool) -> Self {
+        self.relations = enabled;
+        self
+    }
+
+    /// Tolerate malformed input instead of passing it through: tags that are not valid UTF-8 are
+    /// decoded lossily, consecutive repeated nodes collapsed, ways left with fewer than two
+    /// distinct points dropped and self-intersecting ones flagged. Counts end up in
+    /// [`StreetsResult::sanitized`] and the manifest, affected streets in the QA report.
+    pub fn hardened(mut self, enabled: bool) -> Self {
+        self.hardened = enabled;
+        self
+    }
+
+    /// Let [`process_osm_to_parquet`](crate::process_osm_to_parquet) replace output files that
+    /// already exist; without it the run refuses to start and lists them
+    pub fn overwrite(mut self, enabled: bool) -> Self {
+        self.overwrite = enabled;
+        self
+    }
+
+    /// Directory for spill and checkpoint files (default: the system temp dir)
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// Checkpoint the node coordinate pass to a file in the temp dir as it goes, so a run
+    /// killed during that pass resumes where it stopped; the file is removed once segments are
+    /// extracted
+    pub fn checkpoint_nodes(mut self, enabled: bool) -> Self {
+        self.checkpoint_nodes = enabled;
+        self
+    }
+
+    /// Save the extracted segments to this parquet file before grouping, for re-running the
+    /// grouping with [`from_segments`](Self::from_segments) without reading the PBF again
+    pub fn save_segments(mut self, path: impl Into<PathBuf>) -> Self {
+        self.save_segments = Some(path.into());
+        self
+    }
+
+    /// Read segments saved by [`save_segments`](Self::save_segments) instead of extracting them
+    /// from the input. The feature must match the saved one; name language, relations and
+    /// hardened parsing are whatever they were when saving. Not combined with node output, node
+    /// checkpoints, the skipped-ways log or sharding.
+    pub fn from_segments(mut self, path: impl Into<PathBuf>) -> Self {
+        self.from_segments = Some(path.into());
+        self
+    }
+
+    /// Read `input` as an Overture Maps transportation segments GeoParquet file instead of an
+    /// OSM PBF: `class` becomes the type and `names.primary` the name, and segments connect at
+    /// shared connectors. Node output needs the connectors file in `config`. Not combined with
+    /// relations, hardened parsing, node checkpoints, the skipped-ways log, saved segments input or
+    /// sharding.
+    pub fn overture(mut self, config: OvertureConfig) -> Self {
+        self.overture = Some(config);
+        self
+    }
+
+    /// Look up the items in the `etymology_wikidata` column when the streets become a DataFrame
+    /// ([`StreetsResult::into_dataframe`], [`OsmStreetProcessor::to_dataframe`] and the parquet
+    /// output) and add their label, description, kind and (for people) gender and life years;
+    /// see [`wikidata::resolve_etymology`](crate::wikidata::resolve_etymology). Streamed streets
+    /// carry only the item IDs.
+    pub fn wikidata(mut self, config: WikidataConfig) -> Self {
+        self.wikidata = Some(config);
+        self
+    }
+
+    /// Stop at the next safe point once the run has taken this long: between the PBF passes,
+    /// or between street names while grouping. The result is then marked
+    /// [`truncated`](StreetsResult::truncated) and holds what was finished.
+    pub fn max_runtime(mut self, limit: Duration) -> Self {
+        self.max_runtime = Some(limit);
+        self
+    }
+
+    /// Also count street-name pairs meeting at shared nodes
+    pub fn adjacency(mut self, enabled: bool) -> Self {
+        self.adjacency = enabled;
+        self
+    }
+
+    /// Also keep every grouped segment with the `street_id` of its street, in
+    /// [`StreetsResult::segments`]; not applied by [`OsmStreetProcessor::stream`]
+    pub fn segments(mut self, enabled: bool) -> Self {
+        self.emit_segments = enabled;
+        self
+    }
+
+    /// Also keep the coordinates of every node of a grouped segment, once per street it belongs
+    /// to, in [`StreetsResult::nodes`]; not applied by [`OsmStreetProcessor::stream`]
+    pub fn nodes(mut self, enabled: bool) -> Self {
+        self.emit_nodes = enabled;
+        self
+    }
+
+    /// Round output lat/lon to this many decimal places (default: full precision)
+    pub fn coord_precision(mut self, digits: u32) -> Self {
+        self.coord_precision = Some(digits);
+        self
+    }
+
+    /// Douglas-Peucker simplify segment geometries to this many meters when writing them
+    /// (default: every node kept)
+    pub fn simplify_tolerance_m(mut self, meters: f64) -> Self {
+        self.simplify_tolerance_m = Some(meters);
+        self
+    }
+
+    /// Coordinate system of the written files (default: WGS84 lat/lon). Projected systems
+    /// replace the `lat`/`lon` columns with `x`/`y` and project segment geometries; results
+    /// returned by [`OsmStreetProcessor::run`] stay in lat/lon.
+    pub fn output_crs(mut self, crs: OutputCrs) -> Self {
+        self.output_crs = crs;
+        self
+    }
+
+    /// Replace the default connectivity + distance grouping of same-name segments
+    pub fn grouping(mut self, strategy: Arc<dyn GroupingStrategy>) -> Self {
+        self.grouping = Some(strategy);
+        self
+    }
+
+    /// Write every candidate way that was skipped (no name, no coordinates, filtered out) to
+    /// this NDJSON file, one object per line with the reason
+    pub fn skipped_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.skipped_log = Some(path.into());
+        self
+    }
+
+    /// Flag suspicious streets (large extent, many raw components, distance-merged) into
+    /// [`StreetsResult::qa`]; not applied by [`OsmStreetProcessor::stream`]
+    pub fn qa(mut self, config: QaConfig) -> Self {
+        self.qa = Some(config);
+        self
+    }
+
+    /// Assign each segment to the region containing it and write that as its state, instead of
+    /// `state`, so one pass over a multi-state file yields every state. Segments outside all
+    /// regions are dropped; `state` then only names the run.
+    pub fn regions(mut self, regions: Arc<RegionIndex>) -> Self {
+        self.regions = Some(regions);
+        self
+    }
+
+    /// Group within counties or cities instead of whole states: segments are assigned to the
+    /// `regions` boundary containing them, which is written to a `region` column. Segments in
+    /// none of them are grouped together with a null region.
+    pub fn region_level(mut self, level: RegionLevel, regions: Arc<RegionIndex>) -> Self {
+        self.sub_regions = (level != RegionLevel::State).then_some((level, regions));
+        self
+    }
+
+    /// Process the input in spatial shards, so planet-sized files never need every node's
+    /// coordinates in memory at once (see [`ShardConfig`] for what is still held). Not combined
+    /// with adjacency, relations, QA or the skipped-ways log, and not applied by
+    /// [`OsmStreetProcessor::stream`].
+    pub fn shards(mut self, config: ShardConfig) -> Self {
+        self.shards = Some(config);
+        self
+    }
+
+    /// How the default aggregator picks each street's lat/lon (default: first node)
+    pub fn rep_point(mut self, rep_point: RepPoint) -> Self {
+        self.rep_point = rep_point;
+        self
+    }
+
+    /// What the default aggregator hashes into `street_id` besides state and name (default: way IDs)
+    pub fn street_id_mode(mut self, mode: StreetIdMode) -> Self {
+        self.street_id = mode;
+        self
+    }
+
+    /// Replace how a grouped component becomes a [`Street`] (default: [`DefaultAggregator`])
+    pub fn aggregator(mut self, aggregator: Arc<dyn StreetAggregator>) -> Self {
+        self.aggregator = Some(aggregator);
+        self
+    }
+
+    /// Where progress is reported (default: nowhere; the CLI uses [`crate::ConsoleProgress`])
+    pub fn progress(mut self, sink: Arc<dyn ProgressSink>) -> Self {
+        self.progress = Some(sink);
+        self
+    }
+
+    /// Every setting as a string, defaults included, for run manifests
+    fn effective_options(&self) -> BTreeMap<String, String> {
+        let mut options = BTreeMap::new();
+        let mut set = |name: &str, value: String| {
+            options.insert(name.to_string(), value);
+        };
+        set(
+            "distance_threshold_km",
+            self.distance_threshold_km.unwrap_or(DEFAULT_DISTANCE_THRESHOLD_KM).to_string(),
+        );
+        if !self.class_thresholds_km.is_empty() {
+            let classes: BTreeMap<_, _> = self.class_thresholds_km.iter().collect();
+            let spec: Vec<String> = classes.iter().map(|(class, km)| format!("{}={}", class, km)).collect();
+            set("threshold_by_class", spec.join(","));
+        }
+        set("feature", format!("{:?}", self.feature.unwrap_or(Feature::Highway)).to_lowercase());
+        set("name_lang", self.names.name());
+        set("merge_directionals", self.merge_directionals.to_string());
+        set("relations", self.relations.to_string());
+        set("hardened", self.hardened.to_string());
+        set("emit_adjacency", self.adjacency.to_string());
+        set("emit_segments", self.emit_segments.to_string());
+        set("emit_nodes", self.emit_nodes.to_string());
+        if let Some(path) = &self.save_segments {
+            set("save_segments", path.display().to_string());
+        }
+        if let Some(path) = &self.from_segments {
+            set("from_segments", path.display().to_string());
+        }
+        if let Some(overture) = &self.overture {
+            set("input_format", "overture".to_string());
+            if let Some(path) = &overture.connectors {
+                set("overture_connectors", path.display().to_string());
+            }
+        }
+        if let Some(wikidata) = &self.wikidata {
+            set("wikidata", wikidata.source.name());
+            set("wikidata_lang", wikidata.lang.clone());
+        }
+        if let Some(limit) = self.max_runtime {
+            set("max_runtime_s", limit.as_secs().to_string());
+        }
+        if let Some(digits) = self.coord_precision {
+            set("coord_precision", digits.to_string());
+        }
+        if let Some(meters) = self.simplify_tolerance_m {
+            set("simplify_tolerance_m", meters.to_string());
+        }
+        set("output_crs", self.output_crs.name());
+        set("rep_point", self.rep_point.name().to_string());
+        set("street_id", self.street_id.name().to_string());
+        if let Some(qa) = &self.qa {
+            set("qa_max_diameter_km", qa.max_diameter_km.to_string());
+            set("qa_max_components", qa.max_components.to_string());
+        }
+        if let Some(regions) = &self.regions {
+            set("regions", regions.names().join(","));
+        }
+        if let Some((level, _)) = &self.sub_regions {
+            set("region_level", level.name().to_string());
+        }
+        if let Some(shards) = &self.shards {
+            set("shard_deg", shards.shard_deg.to_string());
+            set("shard_halo_deg", shards.halo_deg.to_string());
+            set("shard_jobs", shards.jobs.to_string());
+        }
+        if self.grouping.is_some() {
+            set("grouping", "custom".to_string());
+        }
+        if self.aggregator.is_some() {
+            set("aggregator", "custom".to_string());
+        }
+        options
+    }
+
+    pub fn build(self) -> Result<OsmStreetProcessor> {
+        let options = self.effective_options();
+        let Some(input) = self.input else {
+            return Err(ProcessorError::InvalidConfig("input path is required".into()));
+        };
+        let Some(state) = self.state else {
+            return Err(ProcessorError::InvalidConfig("state is required".into()));
+        };
+        let distance_threshold_km = self.distance_threshold_km.unwrap_or(DEFAULT_DISTANCE_THRESHOLD_KM);
+        if !distance_threshold_km.is_finite() || distance_threshold_km < 0.0 {
+            return Err(ProcessorError::InvalidConfig(format!(
+                "invalid distance threshold {}",
+                distance_threshold_km
+            )));
+        }
+        if let Some((class, km)) = self.class_thresholds_km.iter().find(|(_, km)| !km.is_finite() || **km < 0.0) {
+            return Err(ProcessorError::InvalidConfig(format!("invalid distance threshold {} for {}", km, class)));
+        }
+        let class_thresholds_km = self.class_thresholds_km;
+        let max_merge_km = class_thresholds_km.values().copied().fold(distance_threshold_km, f64::max);
+        let temp_dir = self.temp_dir.unwrap_or_else(std::env::temp_dir);
+        if !temp_dir.is_dir() {
+            return Err(ProcessorError::InvalidConfig(format!(
+                "temp dir {} does not exist or is not a directory",
+                temp_dir.display()
+            )));
+        }
+        if let Some(meters) = self.simplify_tolerance_m.filter(|m| !m.is_finite() || *m < 0.0) {
+            return Err(ProcessorError::InvalidConfig(format!("invalid simplify tolerance {} m", meters)));
+        }
+        if let Some(shards) = &self.shards {
+            let valid = shards.shard_deg.is_finite()
+                && shards.shard_deg > 0.0
+                && shards.halo_deg.is_finite()
+                && shards.halo_deg >= 0.0
+                && shards.jobs > 0;
+            if !valid {
+                return Err(ProcessorError::InvalidConfig(format!("invalid shard settings {:?}", shards)));
+            }
+            if self.adjacency
+                || self.emit_segments
+                || self.emit_nodes
+                || self.relations
+                || self.qa.is_some()
+                || self.skipped_log.is_some()
+                || self.hardened
+                || self.checkpoint_nodes
+                || self.max_runtime.is_some()
+                || self.save_segments.is_some()
+                || self.from_segments.is_some()
+                || self.overture.is_some()
+            {
+                return Err(ProcessorError::InvalidConfig(
+                    "sharding cannot be combined with adjacency, segment/node output, relations, QA, the \
+                     skipped-ways log, hardened parsing, node checkpoints, saved segments, Overture input or a \
+                     max runtime"
+                        .into(),
+                ));
+            }
+        }
+        if let Some(overture) = &self.overture {
+            if self.relations
+                || self.hardened
+                || self.checkpoint_nodes
+                || self.skipped_log.is_some()
+                || self.from_segments.is_some()
+            {
+                return Err(ProcessorError::InvalidConfig(
+                    "Overture input cannot be combined with relations, hardened parsing, node checkpoints, the \
+                     skipped-ways log or saved segments input"
+                        .into(),
+                ));
+            }
+            if self.emit_nodes && overture.connectors.is_none() {
+                return Err(ProcessorError::InvalidConfig(
+                    "node output from Overture input needs the connectors file".into(),
+                ));
+            }
+        }
+        if self.wikidata.as_ref().is_some_and(|wikidata| wikidata.lang.trim().is_empty()) {
+            return Err(ProcessorError::InvalidConfig("Wikidata label language is empty".into()));
+        }
+        if self.from_segments.is_some() {
+            if self.save_segments.is_some() {
+                return Err(ProcessorError::InvalidConfig("cannot both save and read saved segments".into()));
+            }
+            if self.emit_nodes
+                || self.checkpoint_nodes
+                || self.skipped_log.is_some()
+                || self.relations
+                || self.hardened
+            {
+                return Err(ProcessorError::InvalidConfig(
+                    "saved segments cannot be combined with node output, node checkpoints, the skipped-ways log, \
+                     relations or hardened parsing; relations and hardened parsing apply when saving"
+                        .into(),
+                ));
+            }
+        }
+
+        Ok(OsmStreetProcessor {
+            input,
+            state,
+            distance_threshold_km,
+            max_merge_km,
+            feature: self.feature.unwrap_or(Feature::Highway),
+            names: self.names,
+            merge_directionals: self.merge_directionals,
+            relations: self.relations,
+            adjacency: self.adjacency,
+            emit_segments: self.emit_segments,
+            emit_nodes: self.emit_nodes,
+            coord_precision: self.coord_precision,
+            simplify_tolerance_m: self.simplify_tolerance_m,
+            output_crs: self.output_crs,
+            skipped_log: self.skipped_log,
+            hardened: self.hardened,
+            checkpoint_nodes: self.checkpoint_nodes,
+            save_segments: self.save_segments,
+            from_segments: self.from_segments,
+            overture: self.overture,
+            wikidata: self.wikidata,
+            overwrite: self.overwrite,
+            temp_dir,
+            max_runtime: self.max_runtime,
+            qa: self.qa,
+            regions: self.regions,
+            sub_regions: self.sub_regions,
+            shards: self.shards,
+            options,
+            grouping: self
+                .grouping
+                .unwrap_or_else(|| Arc::new(ConnectivityDistance { distance_threshold_km, class_thresholds_km })),
+            aggregator: self
+                .aggregator
+                .unwrap_or_else(|| {
+                    Arc::new(DefaultAggregator { rep_point: self.rep_point, street_id: self.street_id })
+                }),
+            progress: self.progress.unwrap_or_else(|| Arc::new(NoProgress)),
+        })
+    }
+
+    /// Shorthand for `build()?.run()`
+    pub fn run(self) -> Result<StreetsResult> {
+        self.build()?.run()
+    }
+}
+
+impl OsmStreetProcessor {
+    pub fn builder() -> OsmStreetProcessorBuilder {
+        OsmStreetProcessorBuilder::default()
+    }
+
+    pub fn input(&self) -> &Path {
+        &self.input
+    }
+
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    pub fn distance_threshold_km(&self) -> f64 {
+        self.distance_threshold_km
+    }
+
+    pub fn feature(&self) -> Feature {
+        self.feature
+    }
+
+    pub fn adjacency(&self) -> bool {
+        self.adjacency
+    }
+
+    pub fn emit_segments(&self) -> bool {
+        self.emit_segments
+    }
+
+    pub fn emit_nodes(&self) -> bool {
+        self.emit_nodes
+    }
+
+    pub fn qa(&self) -> Option<&QaConfig> {
+        self.qa.as_ref()
+    }
+
+    pub fn skipped_log(&self) -> Option<&Path> {
+        self.skipped_log.as_deref()
+    }
+
+    pub fn save_segments(&self) -> Option<&Path> {
+        self.save_segments.as_deref()
+    }
+
+    pub fn overwrite(&self) -> bool {
+        self.overwrite
+    }
+
+    pub fn coord_precision(&self) -> Option<u32> {
+        self.coord_precision
+    }
+
+    pub fn simplify_tolerance_m(&self) -> Option<f64> {
+        self.simplify_tolerance_m
+    }
+
+    pub fn output_crs(&self) -> OutputCrs {
+        self.output_crs
+    }
+
+    pub fn max_runtime(&self) -> Option<Duration> {
+        self.max_runtime
+    }
+
+    pub fn from_segments(&self) -> Option<&Path> {
+        self.from_segments.as_deref()
+    }
+
+    pub fn wikidata(&self) -> Option<&WikidataConfig> {
+        self.wikidata.as_ref()
+    }
+
+    pub fn temp_dir(&self) -> &Path {
+        &self.temp_dir
+    }
+
+    /// Effective settings, defaults included, as recorded in run manifests
+    pub fn options(&self) -> &BTreeMap<String, String> {
+        &self.options
+    }
+
+    /// Adjustments between extraction and grouping: state and region from the configured
+    /// boundaries, and directionals split off names when merging them
+    pub(crate) fn prepare_segments(&self, segments: &mut Vec<StreetSegment>, progress: &dyn ProgressSink) {
+        self.assign_regions(segments, progress);
+        self.split_directionals(segments);
+    }
+
+    /// Replace each segment's state and set its region from the configured boundaries
+    pub(crate) fn assign_regions(&self, segments: &mut Vec<StreetSegment>, progress: &dyn ProgressSink) {
+        if let Some(regions) = &self.regions {
+            assign_regions(segments, regions, progress);
+        }
+        if let Some((level, regions)) = &self.sub_regions {
+            assign_sub_regions(segments, regions, *level, progress);
+        }
+    }
+
+    /// When merging directionals, move each name's directional into `direction`
+    pub(crate) fn split_directionals(&self, segments: &mut [StreetSegment]) {
+        if self.merge_directionals {
+            for seg in segments.iter_mut() {
+                let parsed = name_parser::parse(&seg.street_name);
+                if parsed.direction.is_some() {
+                    seg.street_name = parsed.base;
+                    seg.direction = parsed.direction;
+                }
+            }
+        }
+    }
+
+    /// Largest distance across which ways of any class are grouped
+    pub(crate) fn max_merge_km(&self) -> f64 {
+        self.max_merge_km
+    }
+
+    pub(crate) fn names(&self) -> &NamePolicy {
+        &self.names
+    }
+
+    pub(crate) fn grouping(&self) -> &dyn GroupingStrategy {
+        self.grouping.as_ref()
+    }
+
+    pub(crate) fn aggregator(&self) -> &dyn StreetAggregator {
+        self.aggregator.as_ref()
+    }
+
+    pub(crate) fn progress(&self) -> &dyn ProgressSink {
+        self.progress.as_ref()
+    }
+
+    /// Both PBF passes, writing the skipped-ways log if configured; also returns the loaded node
+    /// coordinates and, when hardened, what was sanitized. Past `deadline` between passes, stops
+    /// with no segments and `true`.
+    #[allow(clippy::type_complexity)]
+    fn extract_segments(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<(Vec<StreetSegment>, NodeCoords, Option<SanitizeCounts>, bool)> {
+        let progress = self.progress.as_ref();
+        if let Some(path) = &self.from_segments {
+            progress.message(&format!("Reading saved segments from {}...", path.display()));
+            let mut segments = load_segments(path, self.feature)?;
+            progress.message(&format!("  Found {} street segments", segments.len()));
+            self.prepare_segments(&mut segments, progress);
+            return Ok((segments, NodeMap::default(), None, false));
+        }
+        if let Some(config) = &self.overture {
+            let mut segments = overture::read_segments(&self.input, &self.state, self.feature, &self.names, progress)?;
+            let node_coords = match &config.connectors {
+                Some(path) => overture::read_connectors(path, progress)?,
+                None => NodeMap::default(),
+            };
+            if let Some(path) = &self.save_segments {
+                progress.message(&format!("Saving {} segments to {}", segments.len(), path.display()));
+                save_segments(&segments, self.feature, path)?;
+            }
+            self.prepare_segments(&mut segments, progress);
+            return Ok((segments, node_coords, None, false));
+        }
+        let mut sanitized = self.hardened.then(SanitizeCounts::default);
+        let highway_nodes = collect_highway_nodes(&self.input, self.feature, &self.names, self.hardened, progress)?;
+        if deadline_passed(deadline) {
+            progress.message("Deadline reached after pass 1; no segments extracted");
+            return Ok((Vec::new(), NodeMap::default(), sanitized, true));
+        }
+        let mut skipped = self.skipped_log.as_deref().map(SkipLog::create).transpose()?;
+        progress.message("Pass 2: Extracting street segments...");
+        let checkpoint = self.checkpoint_nodes.then(|| checkpoint_path(&self.temp_dir, &self.input, self.feature));
+        let node_coords = match &checkpoint {
+            Some(path) => load_node_coords_checkpointed(&self.input, &highway_nodes, path, progress)?,
+            None => load_node_coords(&self.input, &highway_nodes, progress)?,
+        };
+        drop(highway_nodes);
+        if deadline_passed(deadline) {
+            progress.message("Deadline reached after loading node coordinates; no segments extracted");
+            return Ok((Vec::new(), NodeMap::default(), sanitized, true));
+        }
+        let mut segments = segments_from_ways(
+            &self.input,
+            &self.state,
+            self.feature,
+            &self.names,
+            &node_coords,
+            progress,
+            skipped.as_mut(),
+            sanitized.as_mut(),
+        )?;
+        if let Some(path) = checkpoint {
+            std::fs::remove_file(&path).map_err(io_error(&path))?;
+        }
+        if self.relations {
+            progress.message("Reading street relations...");
+            let by_way = street_relations(&self.input)?;
+            for seg in &mut segments {
+                seg.relation_id = by_way.get(&seg.way_id).copied();
+            }
+            let members = segments.iter().filter(|s| s.relation_id.is_some()).count();
+            progress.message(&format!("  {} segments are street relation members", members));
+        }
+        if let Some(path) = &self.save_segments {
+            progress.message(&format!("Saving {} segments to {}", segments.len(), path.display()));
+            save_segments(&segments, self.feature, path)?;
+        }
+        self.prepare_segments(&mut segments, progress);
+        if let Some(log) = skipped {
+            let counts = log.finish()?;
+            let summary: Vec<String> = counts.iter().map(|(reason, n)| format!("{} {}", n, reason.name())).collect();
+            if !summary.is_empty() {
+                progress.message(&format!("  Skipped ways: {}", summary.join(", ")));
+            }
+        }
+        if let Some(counts) = sanitized.filter(|counts| !counts.is_empty()) {
+            progress.message(&format!("  Sanitized ways: {}", counts.summary()));
+        }
+        Ok((segments, node_coords, sanitized, false))
+    }
+
+    /// Read the PBF, extract named segments and group them into streets
+    pub fn run(&self) -> Result<StreetsResult> {
+        if let Some(config) = &self.shards {
+            return self.run_sharded(config);
+        }
+        let progress = self.progress.as_ref();
+        let deadline = self.max_runtime.map(|limit| Instant::now() + limit);
+        let (mut segments, node_coords, sanitized, extract_truncated) = self.extract_segments(deadline)?;
+        // Only kept through grouping when node output needs it
+        let node_coords = self.emit_nodes.then_some(node_coords);
+        let num_segments = segments.len();
+
+        // Street-name co-occurrence at shared nodes
+        let adjacency = self.adjacency.then(|| {
+            progress.message("Computing street adjacency...");
+            let pairs = adjacency::street_adjacency(&segments);
+            progress.message(&format!("  Found {} intersecting street-name pairs", pairs.len()));
+            pairs
+        });
+
+        let mut grouped = group_segments_into_streets(
+            &mut segments,
+            self.emit_nodes,
+            self.grouping.as_ref(),
+            self.aggregator.as_ref(),
+            self.feature,
+            self.qa.as_ref(),
+            deadline,
+            progress,
+        );
+        let nodes = node_coords
+            .map(|coords| node_records(&segments, &grouped.streets, &grouped.members, &coords, self.coord_precision));
+        let segments = self
+            .emit_segments
+            .then(|| segment_records(segments, &grouped.streets, &grouped.members));
+        for street in &mut grouped.streets {
+            self.round_coords(street);
+        }
+
+        Ok(StreetsResult {
+            state: self.state.clone(),
+            feature: self.feature,
+            streets: grouped.streets,
+            num_segments,
+            adjacency,
+            qa: self.qa.map(|_| grouped.flagged),
+            segments,
+            nodes,
+            truncated: extract_truncated || grouped.skipped_names > 0,
+            sanitized,
+            wikidata: self.wikidata.clone(),
+        })
+    }
+
+    fn run_sharded(&self, config: &ShardConfig) -> Result<StreetsResult> {
+        let mut streets = run_sharded(self, config)?;
+        for street in &mut streets {
+            self.round_coords(street);
+        }
+        let num_segments = streets.iter().map(|s| s.num_segments).sum();
+        self.progress.message(&format!("  Created {} unique streets", streets.len()));
+
+        Ok(StreetsResult {
+            state: self.state.clone(),
+            feature: self.feature,
+            streets,
+            num_segments,
+            adjacency: None,
+            qa: None,
+            segments: None,
+            nodes: None,
+            truncated: false,
+            sanitized: None,
+            wikidata: self.wikidata.clone(),
+        })
+    }
+
+    /// Apply the configured coordinate precision to a finished street
+    fn round_coords(&self, street: &mut Street) {
+        street.lat = round_coord(street.lat, self.coord_precision);
+        street.lon = round_coord(street.lon, self.coord_precision);
+        if let Some((start, end)) = &mut street.endpoints {
+            for point in [start, end] {
+                *point = (round_coord(point.0, self.coord_precision), round_coord(point.1, self.coord_precision));
+            }
+        }
+    }
+
+    /// [`run`](Self::run) and convert to the output DataFrame, skipping the parquet round trip
+    pub fn to_dataframe(&self) -> Result<DataFrame> {
+        self.run()?.into_dataframe()
+    }
+}
+
+impl OsmStreetProcessor {
+    /// Process on a background thread, yielding streets as each name group is finished.
+    ///
+    /// Segments still have to be read in full before grouping can start (a street's ways may
+    /// be anywhere in the file), but finished streets are handed over through a bounded
+    /// channel instead of being collected, so the consumer controls how many are held.
+    /// Dropping the stream stops the remaining grouping work.
+    pub fn stream(&self) -> StreetStream {
+        let (sender, receiver) = sync_channel(STREAM_BUFFER);
+        let processor = self.clone();
+
+        let handle = std::thread::spawn(move || {
+            let progress = processor.progress.as_ref();
+            let mut segments = match processor.extract_segments(None) {
+                Ok((segments, _, _, _)) => segments,
+                Err(err) => {
+                    let _ = sender.send(Err(err));
+                    return;
+                }
+            };
+
+            let name_groups = name_groups(&mut segments, false);
+            progress.phase_started(Phase::Grouping, Some(name_groups.len() as u64));
+
+            // Send errors only mean the consumer has gone away, which ends the stream early; the
+            // unsent street is dropped right there rather than carried back up
+            let _ = name_groups.into_par_iter().try_for_each_with(
+                sender,
+                |sender, NameGroup { key: (name, state, _), indices, node_ids }| {
+                    let name_segments = group_segments(&segments, &indices, node_ids);
+                    progress.advanced(Phase::Grouping, 1);
+                    streets_for_name(
+                        &name,
+                        &state,
+                        &name_segments,
+                        processor.grouping.as_ref(),
+                        processor.aggregator.as_ref(),
+                        processor.feature,
+                        None,
+                    )
+                    .streets
+                    .into_iter()
+                    .try_for_each(|mut street| {
+                        processor.round_coords(&mut street);
+                        sender.send(Ok(street)).map_err(drop)
+                    })
+                },
+            );
+            progress.phase_finished(Phase::Grouping);
+        });
+
+        StreetStream { receiver, handle: Some(handle) }
+    }
+}
+
+/// Streets produced by [`OsmStreetProcessor::stream`], in no particular order
+pub struct StreetStream {
+    receiver: Receiver<Result<Street>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Iterator for StreetStream {
+    type Item = Result<Street>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receiver.recv() {
+            Ok(item) => Some(item),
+            Err(_) => {
+                // Channel closed: the worker has finished, or panicked
+                let handle = self.handle.take()?;
+                match handle.join() {
+                    Ok(()) => None,
+                    Err(_) => Some(Err(ProcessorError::WorkerPanic)),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Shortest interval between two `progress` ticks of one phase from [`JsonProgress`]
+const JSON_TICK: Duration = Duration::from_secs(1);
+/// Units [`JsonProgress`] counts without locking before folding them into its phase state,
+/// about one PBF block of nodes
+const JSON_BATCH: u64 = 8000;
+
+/// Stages of street processing reported to a [`ProgressSink`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Pass 1: scanning ways for the nodes of named features
+    FindNodes,
+    /// Loading coordinates for those nodes
+    LoadNodes,
+    /// Pass 2: building segments from matching ways
+    ExtractSegments,
+    /// Grouping segments into streets, one unit per (name, state)
+    Grouping,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::FindNodes => "find_nodes",
+            Phase::LoadNodes => "load_nodes",
+            Phase::ExtractSegments => "extract_segments",
+            Phase::Grouping => "grouping",
+        }
+    }
+}
+
+/// Receives progress from the processing pipeline. Implementations must be thread-safe:
+/// grouping advances from rayon workers.
+pub trait ProgressSink: Send + Sync {
+    /// A phase began; `total` is the number of units when known up front
+    fn phase_started(&self, phase: Phase, total: Option<u64>);
+
+    /// `n` more units of `phase` are done
+    fn advanced(&self, phase: Phase, n: u64);
+
+    fn phase_finished(&self, phase: Phase);
+
+    /// Human-readable status line (counts found, ...)
+    fn message(&self, _text: &str) {}
+}
+
+/// Discards all progress; the default for library use
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn phase_started(&self, _phase: Phase, _total: Option<u64>) {}
+    fn advanced(&self, _phase: Phase, _n: u64) {}
+    fn phase_finished(&self, _phase: Phase) {}
+}
+
+/// Status lines on stdout and indicatif bars for phases with a known total, as the CLI shows
+#[derive(Debug, Default)]
+pub struct ConsoleProgress {
+    bars: Mutex<HashMap<Phase, ProgressBar>>,
+}
+
+impl ProgressSink for ConsoleProgress {
+    fn phase_started(&self, phase: Phase, total: Option<u64>) {
+        let Some(total) = total else { return };
+        let unit = match phase {
+            Phase::Grouping => "street names",
+            _ => "items",
+        };
+        let pb = ProgressBar::new(total);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template(&format!("  Processing: [{{bar:40}}] {{pos}}/{{len}} {} ({{eta}})", unit))
+                .unwrap()
+                .progress_chars("=>-"),
+        );
+        self.bars.lock().unwrap().insert(phase, pb);
+    }
+
+    fn advanced(&self, phase: Phase, n: u64) {
+        if let Some(pb) = self.bars.lock().unwrap().get(&phase) {
+            pb.inc(n);
+        }
+    }
+
+    fn phase_finished(&self, phase: Phase) {
+        if let Some(pb) = self.bars.lock().unwrap().remove(&phase) {
+            pb.finish_and_clear();
+        }
+    }
+
+    fn message(&self, text: &str) {
+        println!("{}", text);
+    }
+}
+
+/// Counters of one running phase for [`JsonProgress`]
+#[derive(Debug)]
+struct JsonPhase {
+    total: Option<u64>,
+    done: u64,
+    started: Instant,
+    last_tick: Instant,
+}
+
+/// One JSON object per line on stderr, for wrapper scripts and web UIs: `phase_started`,
+/// `progress` ticks (at most one a second per phase) with `done`, `total`, `rate` per second
+/// and `eta_s` when the total is known, `phase_finished`, and `message` for status lines.
+/// Advances are counted lock-free and only every [`JSON_BATCH`] units checked for a tick.
+#[derive(Debug, Default)]
+pub struct JsonProgress {
+    phases: Mutex<HashMap<Phase, JsonPhase>>,
+    /// Units advanced per phase (by `Phase as usize`) not yet added to its `done`
+    unreported: [AtomicU64; 4],
+}
+
+impl JsonProgress {
+    fn emit(&self, event: serde_json::Value) {
+        let mut stderr = std::io::stderr().lock();
+        let _ = writeln!(stderr, "{}", event);
+    }
+}
+
+impl ProgressSink for JsonProgress {
+    fn phase_started(&self, phase: Phase, total: Option<u64>) {
+        let now = Instant::now();
+        self.phases
+            .lock()
+            .unwrap()
+            .insert(phase, JsonPhase { total, done: 0, started: now, last_tick: now });
+        self.unreported[phase as usize].store(0, Ordering::Relaxed);
+        self.emit(json!({ "event": "phase_started", "phase": phase.name(), "total": total }));
+    }
+
+    fn advanced(&self, phase: Phase, n: u64) {
+        let unreported = &self.unreported[phase as usize];
+        if unreported.fetch_add(n, Ordering::Relaxed) + n < JSON_BATCH {
+            return;
+        }
+        let mut phases = self.phases.lock().unwrap();
+        let Some(state) = phases.get_mut(&phase) else { return };
+        state.done += unreported.swap(0, Ordering::Relaxed);
+        let now = Instant::now();
+        if now.duration_since(state.last_tick) < JSON_TICK {
+            return;
+        }
+        state.last_tick = now;
+        let rate = state.done as f64 / now.duration_since(state.started).as_secs_f64();
+        let eta_s = state
+            .total
+            .filter(|_| rate > 0.0)
+            .map(|total| total.saturating_sub(state.done) as f64 / rate);
+        let event = json!({
+            "event": "progress",
+            "phase": phase.name(),
+            "done": state.done,
+            "total": state.total,
+            "rate": rate,
+            "eta_s": eta_s,
+        });
+        drop(phases);
+        self.emit(event);
+    }
+
+    fn phase_finished(&self, phase: Phase) {
+        let Some(state) = self.phases.lock().unwrap().remove(&phase) else { return };
+        let done = state.done + self.unreported[phase as usize].swap(0, Ordering::Relaxed);
+        self.emit(json!({
+            "event": "phase_finished",
+            "phase": phase.name(),
+            "done": done,
+            "elapsed_s": state.started.elapsed().as_secs_f64(),
+        }));
+    }
+
+    fn message(&self, text: &str) {
+        self.emit(json!({ "event": "message", "text": text.trim() }));
+    }
+}
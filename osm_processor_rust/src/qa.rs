@@ -0,0 +1,132 @@
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::{haversine_km, Street, StreetSegment};
+
+/// Limits above which a street is flagged for manual review
+#[derive(Debug, Clone, Copy)]
+pub struct QaConfig {
+    /// Bounding-box diagonal (km) beyond which a street looks over-merged
+    pub max_diameter_km: f64,
+    /// Connected components (before distance merging) beyond which a street looks fragmented
+    pub max_components: usize,
+}
+
+impl Default for QaConfig {
+    fn default() -> Self {
+        QaConfig { max_diameter_km: 10.0, max_components: 5 }
+    }
+}
+
+/// A street that tripped at least one QA check
+#[derive(Debug, Clone)]
+pub struct QaRecord {
+    pub street_name: String,
+    pub state: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub num_segments: usize,
+    pub bbox_diameter_km: f64,
+    /// Components of segments sharing nodes, before the distance heuristic merged them
+    pub raw_components: usize,
+    /// `large_extent`, `many_components` and/or `distance_merged`, plus what hardened parsing
+    /// repaired in any of the segments (`lossy_tags`, `repeated_nodes`, `self_intersecting`)
+    pub flags: Vec<&'static str>,
+}
+
+/// Number of node-connected components among the segments
+pub fn raw_component_count(segs: &[&StreetSegment]) -> usize {
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut parent: Vec<usize> = (0..segs.len()).collect();
+    let mut first_seen: HashMap<i64, usize> = HashMap::new();
+    for (i, seg) in segs.iter().enumerate() {
+        for &node_id in &seg.node_ids {
+            let other = *first_seen.entry(node_id).or_insert(i);
+            let (a, b) = (find(&mut parent, i), find(&mut parent, other));
+            parent[a] = b;
+        }
+    }
+    (0..segs.len()).filter(|&i| find(&mut parent, i) == i).count()
+}
+
+/// Diagonal of the segments' lat/lon bounding box in km
+pub fn bbox_diameter_km(segs: &[&StreetSegment]) -> f64 {
+    let (mut min_lat, mut min_lon) = (f64::INFINITY, f64::INFINITY);
+    let (mut max_lat, mut max_lon) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for &(lat, lon) in segs.iter().flat_map(|seg| &seg.coords) {
+        min_lat = min_lat.min(lat);
+        min_lon = min_lon.min(lon);
+        max_lat = max_lat.max(lat);
+        max_lon = max_lon.max(lon);
+    }
+    haversine_km((min_lat, min_lon), (max_lat, max_lon))
+}
+
+/// Check one grouped street; `None` if nothing looks suspicious
+pub fn inspect(config: &QaConfig, street: &Street, segs: &[&StreetSegment]) -> Option<QaRecord> {
+    let diameter = bbox_diameter_km(segs);
+    let components = street.num_components_raw;
+
+    let mut flags = Vec::new();
+    if diameter > config.max_diameter_km {
+        flags.push("large_extent");
+    }
+    if components > config.max_components {
+        flags.push("many_components");
+    }
+    if street.merged_by_distance {
+        flags.push("distance_merged");
+    }
+    for &issue in segs.iter().flat_map(|seg| &seg.sanitized) {
+        if !flags.contains(&issue) {
+            flags.push(issue);
+        }
+    }
+    if flags.is_empty() {
+        return None;
+    }
+
+    Some(QaRecord {
+        street_name: street.street_name.clone(),
+        state: street.state.clone(),
+        lat: street.lat,
+        lon: street.lon,
+        num_segments: street.num_segments,
+        bbox_diameter_km: diameter,
+        raw_components: components,
+        flags,
+    })
+}
+
+/// QA records as a DataFrame, largest extent first; `flags` is a comma-separated list
+pub fn qa_to_dataframe(mut records: Vec<QaRecord>) -> Result<DataFrame> {
+    records.sort_unstable_by(|a, b| b.bbox_diameter_km.total_cmp(&a.bbox_diameter_km));
+
+    let df = DataFrame::new(vec![
+        Series::new("street_name", records.iter().map(|r| r.street_name.as_str()).collect::<Vec<_>>()),
+        Series::new("state", records.iter().map(|r| r.state.as_str()).collect::<Vec<_>>()),
+        Series::new("lat", records.iter().map(|r| r.lat).collect::<Vec<_>>()),
+        Series::new("lon", records.iter().map(|r| r.lon).collect::<Vec<_>>()),
+        Series::new("num_segments", records.iter().map(|r| r.num_segments as u32).collect::<Vec<_>>()),
+        Series::new("bbox_diameter_km", records.iter().map(|r| r.bbox_diameter_km).collect::<Vec<_>>()),
+        Series::new("raw_components", records.iter().map(|r| r.raw_components as u32).collect::<Vec<_>>()),
+        Series::new("flags", records.iter().map(|r| r.flags.join(",")).collect::<Vec<_>>()),
+    ])?;
+
+    Ok(df)
+}
+
+/// `<output>.qa.parquet` next to the streets file (`delaware_streets.qa.parquet`)
+pub fn qa_output_path(output_path: &Path) -> PathBuf {
+    let stem = output_path.file_stem().and_then(|s| s.to_str()).unwrap_or("streets");
+    output_path.with_file_name(format!("{}.qa.parquet", stem))
+}
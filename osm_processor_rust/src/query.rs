@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use polars::prelude::*;
+use polars::sql::SQLContext;
+use std::path::{Path, PathBuf};
+
+use osm_processor_rust::stats::scan_streets;
+use osm_processor_rust::write_table;
+
+/// SQL-safe table name for a file: its stem with non-alphanumerics replaced by `_`
+fn table_name(path: &Path) -> String {
+    let stem = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.trim_end_matches(".parquet"))
+        .unwrap_or("table");
+    stem.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// `query "<SQL>" <streets.parquet...> [-o out.(csv|parquet)]`
+///
+/// All inputs are available together as `streets`, and each file individually under its
+/// file stem (e.g. `delaware_streets`).
+pub fn run_query(sql: &str, paths: &[PathBuf], output_path: Option<&Path>) -> Result<()> {
+    let mut ctx = SQLContext::new();
+    ctx.register("streets", scan_streets(paths)?);
+    for path in paths {
+        let lf = LazyFrame::scan_parquet(path, ScanArgsParquet::default())
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        ctx.register(&table_name(path), lf);
+    }
+
+    let mut result = ctx
+        .execute(sql)
+        .context("Failed to plan SQL query")?
+        .collect()?;
+
+    println!("{}", result);
+    println!("({} rows)", result.height());
+
+    if let Some(output_path) = output_path {
+        println!("Saving to: {}", output_path.display());
+        write_table(&mut result, output_path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use geo::{BoundingRect, Contains, MultiPolygon, Point};
+use rayon::prelude::*;
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::{RTree, AABB};
+use std::path::Path;
+
+use crate::boundaries::{extract_boundaries, Boundary};
+use crate::error::{ProcessorError, Result};
+use crate::progress::ProgressSink;
+use crate::StreetSegment;
+
+/// `admin_level` of US states (and most countries' first-level subdivisions)
+pub const STATE_ADMIN_LEVEL: u8 = 4;
+
+/// Which area same-name segments are grouped within
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RegionLevel {
+    #[default]
+    State,
+    County,
+    City,
+}
+
+impl RegionLevel {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "state" => Ok(RegionLevel::State),
+            "county" => Ok(RegionLevel::County),
+            "city" => Ok(RegionLevel::City),
+            other => Err(ProcessorError::InvalidConfig(format!(
+                "Unknown region level: {} (expected state, county or city)",
+                other
+            ))),
+        }
+    }
+
+    /// The `--region-level` value for this level
+    pub fn name(self) -> &'static str {
+        match self {
+            RegionLevel::State => "state",
+            RegionLevel::County => "county",
+            RegionLevel::City => "city",
+        }
+    }
+
+    /// US `admin_level` of the boundaries for this level
+    pub fn admin_level(self) -> u8 {
+        match self {
+            RegionLevel::State => STATE_ADMIN_LEVEL,
+            RegionLevel::County => 6,
+            RegionLevel::City => 8,
+        }
+    }
+}
+
+/// A named area segments can be assigned to
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub name: String,
+    pub geometry: MultiPolygon<f64>,
+}
+
+/// Point-in-polygon lookup over a set of regions, prefiltered by bounding box
+pub struct RegionIndex {
+    regions: Vec<Region>,
+    tree: RTree<GeomWithData<Rectangle<[f64; 2]>, usize>>,
+}
+
+impl std::fmt::Debug for RegionIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RegionIndex").field("regions", &self.names()).finish()
+    }
+}
+
+impl RegionIndex {
+    pub fn new(regions: Vec<Region>) -> Self {
+        let boxes = regions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, region)| {
+                let rect = region.geometry.bounding_rect()?;
+                let (min, max) = (rect.min(), rect.max());
+                Some(GeomWithData::new(Rectangle::from_corners([min.x, min.y], [max.x, max.y]), i))
+            })
+            .collect();
+        RegionIndex { regions, tree: RTree::bulk_load(boxes) }
+    }
+
+    /// Regions from the `admin_level` boundaries of a PBF file, named by [`region_key`].
+    /// With `names`, only those regions are kept and each must be found.
+    pub fn from_pbf(pbf_path: &Path, admin_level: u8, names: Option<&[String]>) -> Result<Self> {
+        let boundaries = extract_boundaries(pbf_path)?;
+        Self::from_boundaries(boundaries, admin_level, names)
+    }
+
+    pub fn from_boundaries(boundaries: Vec<Boundary>, admin_level: u8, names: Option<&[String]>) -> Result<Self> {
+        let mut regions: Vec<Region> = boundaries
+            .into_iter()
+            .filter(|b| b.admin_level == Some(admin_level) && !b.name.is_empty())
+            .map(|b| Region { name: region_key(&b.name), geometry: b.geometry })
+            .collect();
+
+        if let Some(names) = names {
+            let wanted: Vec<String> = names.iter().map(|name| region_key(name)).collect();
+            if let Some(missing) = wanted.iter().find(|name| !regions.iter().any(|r| &r.name == *name)) {
+                return Err(ProcessorError::InvalidConfig(format!(
+                    "no admin_level={} boundary named {} in the input",
+                    admin_level, missing
+                )));
+            }
+            regions.retain(|r| wanted.contains(&r.name));
+        }
+        if regions.is_empty() {
+            return Err(ProcessorError::InvalidConfig(format!(
+                "no complete admin_level={} boundaries in the input",
+                admin_level
+            )));
+        }
+
+        Ok(RegionIndex::new(regions))
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Region names, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.regions.iter().map(|r| r.name.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// The region containing a (lat, lon) point; the first match if regions overlap
+    pub fn locate(&self, point: (f64, f64)) -> Option<&str> {
+        self.locate_position(point).map(|i| self.regions[i].name.as_str())
+    }
+
+    /// Like [`locate`](Self::locate), but the region's position in the list given to
+    /// [`RegionIndex::new`], for regions that carry more than a name
+    pub fn locate_position(&self, (lat, lon): (f64, f64)) -> Option<usize> {
+        let point = Point::new(lon, lat);
+        self.tree
+            .locate_in_envelope_intersecting(&AABB::from_point([lon, lat]))
+            .map(|candidate| candidate.data)
+            .filter(|&i| self.regions[i].geometry.contains(&point))
+            .min()
+    }
+}
+
+/// Lowercased, hyphenated region name as used for states elsewhere (`New York` -> `new-york`)
+pub fn region_key(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("-").to_lowercase()
+}
+
+/// The region containing each segment's middle vertex
+fn locate_segments<'a>(segments: &[StreetSegment], regions: &'a RegionIndex) -> Vec<Option<&'a str>> {
+    segments
+        .par_iter()
+        .map(|seg| regions.locate(seg.coords[seg.coords.len() / 2]))
+        .collect()
+}
+
+/// Set each segment's state to the region containing its middle vertex, dropping segments
+/// outside every region. Returns the number dropped.
+pub fn assign_regions(segments: &mut Vec<StreetSegment>, regions: &RegionIndex, progress: &dyn ProgressSink) -> usize {
+    progress.message(&format!("Assigning segments to {} regions...", regions.len()));
+    let before = segments.len();
+    let mut located = locate_segments(segments, regions).into_iter();
+    segments.retain_mut(|seg| match located.next().flatten() {
+        Some(name) => {
+            seg.state = name.to_string();
+            true
+        }
+        None => false,
+    });
+    let dropped = before - segments.len();
+    progress.message(&format!("  Assigned {} segments ({} outside all regions, dropped)", segments.len(), dropped));
+    dropped
+}
+
+/// Set each segment's `region` to the county/city containing its middle vertex; segments
+/// outside all of them (e.g. unincorporated land at city level) keep `None`
+pub fn assign_sub_regions(segments: &mut [StreetSegment], regions: &RegionIndex, level: RegionLevel, progress: &dyn ProgressSink) {
+    progress.message(&format!("Assigning segments to {} {} regions...", regions.len(), level.name()));
+    let located = locate_segments(segments, regions);
+    let mut outside = 0;
+    for (seg, region) in segments.iter_mut().zip(located) {
+        seg.region = region.map(str::to_string);
+        outside += usize::from(region.is_none());
+    }
+    progress.message(&format!("  {} segments outside every {}", outside, level.name()));
+}
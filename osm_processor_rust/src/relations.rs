@@ -0,0 +1,120 @@
+use osmpbf::{Element, ElementReader, RelMemberType};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::error::{pbf_error, Result};
+
+/// A relation member: (member type, member id, role)
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub member_type: RelMemberType,
+    pub id: i64,
+    pub role: String,
+}
+
+/// A relation with its tags and members, in file order
+#[derive(Debug, Clone)]
+pub struct OsmRelation {
+    pub id: i64,
+    pub tags: HashMap<String, String>,
+    pub members: Vec<Member>,
+}
+
+impl OsmRelation {
+    /// Way members, with their roles
+    pub fn way_members(&self) -> impl Iterator<Item = &Member> + '_ {
+        self.members
+            .iter()
+            .filter(|m| matches!(m.member_type, RelMemberType::Way))
+    }
+}
+
+/// Collect relations whose tags pass `keep`
+pub fn collect_relations<F>(pbf_path: &Path, keep: F) -> Result<Vec<OsmRelation>>
+where
+    F: Fn(&HashMap<String, String>) -> bool,
+{
+    let reader = ElementReader::from_path(pbf_path)
+        .map_err(pbf_error(pbf_path))?;
+
+    let mut relations = Vec::new();
+
+    reader.for_each(|element| {
+        if let Element::Relation(rel) = element {
+            let tags: HashMap<String, String> = rel
+                .tags()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+
+            if !keep(&tags) {
+                return;
+            }
+
+            let members = rel
+                .members()
+                .map(|member| Member {
+                    role: member.role().unwrap_or_default().to_string(),
+                    member_type: member.member_type,
+                    id: member.member_id,
+                })
+                .collect();
+
+            relations.push(OsmRelation {
+                id: rel.id(),
+                tags,
+                members,
+            });
+        }
+    })
+    .map_err(pbf_error(pbf_path))?;
+
+    Ok(relations)
+}
+
+/// Street relations of each member way: `type=associatedStreet` and `type=street` relations
+/// (their `street` role members), then `type=route` + `route=road`. A way in several takes a
+/// street relation over a route, and the lowest ID among equals.
+pub fn street_relations(pbf_path: &Path) -> Result<HashMap<i64, i64>> {
+    let relations = collect_relations(pbf_path, |tags| {
+        matches!(tags.get("type").map(String::as_str), Some("associatedStreet" | "street"))
+            || (tags.get("type").map(String::as_str) == Some("route")
+                && tags.get("route").map(String::as_str) == Some("road"))
+    })?;
+
+    // (is route, relation id): smaller wins
+    let mut best: HashMap<i64, (bool, i64)> = HashMap::new();
+    for relation in &relations {
+        let is_route = relation.tags.get("type").map(String::as_str) == Some("route");
+        for member in relation.way_members() {
+            // associatedStreet also lists the houses; only its street members are the street
+            if !is_route && member.role != "street" {
+                continue;
+            }
+            let candidate = (is_route, relation.id);
+            best.entry(member.id)
+                .and_modify(|current| *current = (*current).min(candidate))
+                .or_insert(candidate);
+        }
+    }
+
+    Ok(best.into_iter().map(|(way_id, (_, relation_id))| (way_id, relation_id)).collect())
+}
+
+/// Collect node references for the given ways
+pub fn collect_way_refs(pbf_path: &Path, way_ids: &HashSet<i64>) -> Result<HashMap<i64, Vec<i64>>> {
+    let reader = ElementReader::from_path(pbf_path)
+        .map_err(pbf_error(pbf_path))?;
+
+    let mut way_refs = HashMap::new();
+
+    reader.for_each(|element| {
+        if let Element::Way(way) = element {
+            if way_ids.contains(&way.id()) {
+                way_refs.insert(way.id(), way.refs().collect());
+            }
+        }
+    })
+    .map_err(pbf_error(pbf_path))?;
+
+    Ok(way_refs)
+}
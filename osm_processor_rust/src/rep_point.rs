@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use crate::error::{ProcessorError, Result};
+use crate::{haversine_km, polyline_length_km, StreetSegment};
+
+/// How a street's representative lat/lon is chosen from its segments
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RepPoint {
+    /// First node of the first segment (matches the Python implementation)
+    #[default]
+    FirstNode,
+    /// Point at half the total length along the segments chained into polylines
+    Midpoint,
+    /// First node of the longest segment, which also supplies the street's type, so short
+    /// stubs and connectors don't decide the street's metadata
+    LongestSegment,
+    /// Length-weighted centroid of all segment polylines; may lie off the street itself
+    Centroid,
+}
+
+impl RepPoint {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "first" => Ok(RepPoint::FirstNode),
+            "midpoint" => Ok(RepPoint::Midpoint),
+            "longest" => Ok(RepPoint::LongestSegment),
+            "centroid" => Ok(RepPoint::Centroid),
+            other => Err(ProcessorError::InvalidConfig(format!(
+                "Unknown representative point: {} (expected first, midpoint, longest or centroid)",
+                other
+            ))),
+        }
+    }
+
+    /// The `--rep-point` value for this strategy
+    pub fn name(self) -> &'static str {
+        match self {
+            RepPoint::FirstNode => "first",
+            RepPoint::Midpoint => "midpoint",
+            RepPoint::LongestSegment => "longest",
+            RepPoint::Centroid => "centroid",
+        }
+    }
+
+    /// (lat, lon) for one component of segments
+    pub fn locate(self, segs: &[&StreetSegment]) -> (f64, f64) {
+        match self {
+            RepPoint::FirstNode => segs[0].coords[0],
+            RepPoint::Midpoint => midpoint(segs),
+            RepPoint::LongestSegment => longest_segment(segs).coords[0],
+            RepPoint::Centroid => weighted_centroid(segs),
+        }
+    }
+}
+
+/// The segment with the greatest length (the first of equals)
+pub fn longest_segment<'a>(segs: &[&'a StreetSegment]) -> &'a StreetSegment {
+    segs.iter()
+        .copied()
+        .reduce(|best, seg| if seg.length_km > best.length_km { seg } else { best })
+        .expect("components are never empty")
+}
+
+/// Chain segments into polylines by joining them at shared end nodes, reversing segments
+/// where needed. Segments that meet at a junction of three or more continue one branch;
+/// the others start polylines of their own.
+pub fn merge_polylines(segs: &[&StreetSegment]) -> Vec<Vec<(f64, f64)>> {
+    let mut by_end_node: HashMap<i64, Vec<usize>> = HashMap::new();
+    for (i, seg) in segs.iter().enumerate() {
+        if let (Some(&first), Some(&last)) = (seg.node_ids.first(), seg.node_ids.last()) {
+            by_end_node.entry(first).or_default().push(i);
+            by_end_node.entry(last).or_default().push(i);
+        }
+    }
+
+    let mut used = vec![false; segs.len()];
+    let mut polylines = Vec::new();
+    for start in 0..segs.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let mut nodes: Vec<i64> = segs[start].node_ids.clone();
+        let mut coords: Vec<(f64, f64)> = segs[start].coords.clone();
+
+        // Extend forwards from the last node, then backwards from the first
+        for forwards in [true, false] {
+            loop {
+                let end = if forwards { *nodes.last().unwrap() } else { nodes[0] };
+                let next = by_end_node
+                    .get(&end)
+                    .and_then(|candidates| candidates.iter().copied().find(|&i| !used[i]));
+                let Some(next) = next else { break };
+                used[next] = true;
+
+                let seg = segs[next];
+                let mut seg_nodes = seg.node_ids.clone();
+                let mut seg_coords = seg.coords.clone();
+                // Orient the segment so it starts (forwards) or ends (backwards) at `end`
+                if (seg_nodes[0] == end) != forwards {
+                    seg_nodes.reverse();
+                    seg_coords.reverse();
+                }
+                // Drop the shared node; its coordinate only if it was loaded (coords can be
+                // shorter than node_ids when an extract is missing nodes)
+                if forwards {
+                    let skip = usize::from(seg_coords.first() == coords.last());
+                    nodes.extend_from_slice(&seg_nodes[1..]);
+                    coords.extend_from_slice(&seg_coords[skip..]);
+                } else {
+                    if seg_coords.last() == coords.first() {
+                        seg_coords.pop();
+                    }
+                    seg_nodes.pop();
+                    seg_nodes.append(&mut nodes);
+                    seg_coords.append(&mut coords);
+                    nodes = seg_nodes;
+                    coords = seg_coords;
+                }
+            }
+        }
+        polylines.push(coords);
+    }
+    polylines
+}
+
+/// Point `target_km` along a polyline, interpolated within the edge it falls on
+fn point_along(coords: &[(f64, f64)], target_km: f64) -> (f64, f64) {
+    let mut walked = 0.0;
+    for w in coords.windows(2) {
+        let edge_km = haversine_km(w[0], w[1]);
+        if walked + edge_km >= target_km && edge_km > 0.0 {
+            let t = (target_km - walked) / edge_km;
+            return (w[0].0 + t * (w[1].0 - w[0].0), w[0].1 + t * (w[1].1 - w[0].1));
+        }
+        walked += edge_km;
+    }
+    coords[coords.len() - 1]
+}
+
+/// Point at half the total length, walking the merged polylines one after another
+fn midpoint(segs: &[&StreetSegment]) -> (f64, f64) {
+    let polylines = merge_polylines(segs);
+    let lengths: Vec<f64> = polylines.iter().map(|line| polyline_length_km(line)).collect();
+    let mut remaining = lengths.iter().sum::<f64>() / 2.0;
+    for (line, &length) in polylines.iter().zip(&lengths) {
+        if remaining <= length {
+            return point_along(line, remaining);
+        }
+        remaining -= length;
+    }
+    segs[0].coords[0]
+}
+
+/// Centroid of the segments' edges, each edge's midpoint weighted by its length. Falls back to
+/// the plain mean of the nodes when every edge has zero length (e.g. single-node segments).
+fn weighted_centroid(segs: &[&StreetSegment]) -> (f64, f64) {
+    let (mut lat_sum, mut lon_sum, mut total_km) = (0.0, 0.0, 0.0);
+    for seg in segs {
+        for w in seg.coords.windows(2) {
+            let edge_km = haversine_km(w[0], w[1]);
+            lat_sum += edge_km * (w[0].0 + w[1].0) / 2.0;
+            lon_sum += edge_km * (w[0].1 + w[1].1) / 2.0;
+            total_km += edge_km;
+        }
+    }
+    if total_km > 0.0 {
+        return (lat_sum / total_km, lon_sum / total_km);
+    }
+
+    let points: Vec<(f64, f64)> = segs.iter().flat_map(|seg| seg.coords.iter().copied()).collect();
+    let n = points.len() as f64;
+    (points.iter().map(|p| p.0).sum::<f64>() / n, points.iter().map(|p| p.1).sum::<f64>() / n)
+}
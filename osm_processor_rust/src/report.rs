@@ -0,0 +1,252 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::matching::{read_rows, StreetRow};
+use osm_processor_rust::stats::load_streets;
+
+/// Street-length histogram buckets in km: (label, lower bound inclusive)
+const LENGTH_BUCKETS: [(&str, f64); 7] = [
+    ("< 0.1", 0.0),
+    ("0.1-0.5", 0.1),
+    ("0.5-1", 0.5),
+    ("1-2", 1.0),
+    ("2-5", 2.0),
+    ("5-10", 5.0),
+    ("10+", 10.0),
+];
+
+/// Background points drawn per map; larger states are thinned to this many
+const MAX_BACKGROUND_POINTS: usize = 4000;
+
+const MAP_WIDTH: f64 = 360.0;
+const MAP_HEIGHT: f64 = 260.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            other => anyhow::bail!("Unknown report format {} (expected md or html)", other),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+/// Minimal document model rendered to either Markdown or HTML
+enum Block {
+    Heading(String),
+    Paragraph(String),
+    Table(Vec<String>, Vec<Vec<String>>),
+    Svg(String),
+}
+
+fn render(title: &str, blocks: &[Block], format: ReportFormat) -> String {
+    let mut out = String::new();
+    match format {
+        ReportFormat::Markdown => {
+            writeln!(out, "# {}\n", title).unwrap();
+            for block in blocks {
+                match block {
+                    Block::Heading(text) => writeln!(out, "## {}\n", text).unwrap(),
+                    Block::Paragraph(text) => writeln!(out, "{}\n", text).unwrap(),
+                    Block::Table(headers, rows) => {
+                        writeln!(out, "| {} |", headers.join(" | ")).unwrap();
+                        writeln!(out, "|{}", " --- |".repeat(headers.len())).unwrap();
+                        for row in rows {
+                            writeln!(out, "| {} |", row.join(" | ")).unwrap();
+                        }
+                        out.push('\n');
+                    }
+                    // Markdown renderers generally pass inline SVG through as HTML
+                    Block::Svg(svg) => writeln!(out, "{}\n", svg).unwrap(),
+                }
+            }
+        }
+        ReportFormat::Html => {
+            writeln!(out, "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title>", escape(title)).unwrap();
+            writeln!(out, "<style>body{{font-family:sans-serif;max-width:900px;margin:auto}}table{{border-collapse:collapse}}td,th{{border:1px solid #ccc;padding:2px 8px}}</style>").unwrap();
+            writeln!(out, "</head><body>\n<h1>{}</h1>", escape(title)).unwrap();
+            for block in blocks {
+                match block {
+                    Block::Heading(text) => writeln!(out, "<h2>{}</h2>", escape(text)).unwrap(),
+                    Block::Paragraph(text) => writeln!(out, "<p>{}</p>", escape(text)).unwrap(),
+                    Block::Table(headers, rows) => {
+                        out.push_str("<table>\n<tr>");
+                        for header in headers {
+                            write!(out, "<th>{}</th>", escape(header)).unwrap();
+                        }
+                        out.push_str("</tr>\n");
+                        for row in rows {
+                            out.push_str("<tr>");
+                            for cell in row {
+                                write!(out, "<td>{}</td>", escape(cell)).unwrap();
+                            }
+                            out.push_str("</tr>\n");
+                        }
+                        out.push_str("</table>\n");
+                    }
+                    Block::Svg(svg) => writeln!(out, "{}", svg).unwrap(),
+                }
+            }
+            out.push_str("</body></html>\n");
+        }
+    }
+    out
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Small equirectangular SVG: all of the state's streets in grey, `highlight` in red
+fn fragment_map(all: &[&StreetRow], highlight: &[&StreetRow]) -> String {
+    let (mut min_lat, mut max_lat, mut min_lon, mut max_lon) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+    for row in all.iter().filter(|r| r.lat.is_finite() && r.lon.is_finite()) {
+        min_lat = min_lat.min(row.lat);
+        max_lat = max_lat.max(row.lat);
+        min_lon = min_lon.min(row.lon);
+        max_lon = max_lon.max(row.lon);
+    }
+    let x_scale = ((min_lat + max_lat) / 2.0).to_radians().cos();
+    let span = ((max_lon - min_lon) * x_scale / MAP_WIDTH).max((max_lat - min_lat) / MAP_HEIGHT).max(1e-9);
+    let project = |row: &StreetRow| ((row.lon - min_lon) * x_scale / span, MAP_HEIGHT - (row.lat - min_lat) / span);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\
+         <rect width=\"100%\" height=\"100%\" fill=\"#fafafa\" stroke=\"#ccc\"/>",
+        MAP_WIDTH, MAP_HEIGHT
+    );
+    let step = (all.len() / MAX_BACKGROUND_POINTS).max(1);
+    for row in all.iter().step_by(step).filter(|r| r.lat.is_finite() && r.lon.is_finite()) {
+        let (x, y) = project(row);
+        write!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"0.8\" fill=\"#bbb\"/>", x, y).unwrap();
+    }
+    for row in highlight.iter().filter(|r| r.lat.is_finite() && r.lon.is_finite()) {
+        let (x, y) = project(row);
+        write!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3.5\" fill=\"#d62728\" fill-opacity=\"0.8\"/>", x, y).unwrap();
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn state_report(rows: &[&StreetRow], top_n: usize, maps: usize) -> Vec<Block> {
+    let mut blocks = Vec::new();
+
+    let total_km: f64 = rows.iter().map(|r| r.length_km).sum();
+    let total_segments: u64 = rows.iter().map(|r| r.num_segments as u64).sum();
+    let mut by_name: HashMap<&str, Vec<&StreetRow>> = HashMap::new();
+    for &row in rows {
+        by_name.entry(row.street_name.as_str()).or_default().push(row);
+    }
+
+    blocks.push(Block::Heading("Summary".to_string()));
+    blocks.push(Block::Table(
+        vec!["streets".into(), "distinct names".into(), "total length (km)".into(), "mean segments".into()],
+        vec![vec![
+            rows.len().to_string(),
+            by_name.len().to_string(),
+            format!("{:.1}", total_km),
+            format!("{:.2}", total_segments as f64 / rows.len().max(1) as f64),
+        ]],
+    ));
+
+    let mut names: Vec<(&str, usize)> = by_name.iter().map(|(&name, streets)| (name, streets.len())).collect();
+    names.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    blocks.push(Block::Heading(format!("Top {} street names", top_n)));
+    blocks.push(Block::Table(
+        vec!["rank".into(), "street_name".into(), "streets".into()],
+        names.iter().take(top_n).enumerate().map(|(i, (name, n))| vec![(i + 1).to_string(), name.to_string(), n.to_string()]).collect(),
+    ));
+
+    let mut by_type: BTreeMap<&str, (usize, f64)> = BTreeMap::new();
+    for row in rows {
+        let entry = by_type.entry(row.street_type.as_deref().unwrap_or("")).or_default();
+        entry.0 += 1;
+        entry.1 += row.length_km;
+    }
+    if !(by_type.len() == 1 && by_type.contains_key("")) {
+        let mut types: Vec<_> = by_type.into_iter().collect();
+        types.sort_unstable_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.0.cmp(b.0)));
+        blocks.push(Block::Heading("Streets by type".to_string()));
+        blocks.push(Block::Table(
+            vec!["type".into(), "streets".into(), "length (km)".into()],
+            types.iter().map(|(t, (n, km))| vec![t.to_string(), n.to_string(), format!("{:.1}", km)]).collect(),
+        ));
+    }
+
+    let mut bucket_counts = [0usize; LENGTH_BUCKETS.len()];
+    for row in rows {
+        let bucket = LENGTH_BUCKETS.iter().rposition(|&(_, lo)| row.length_km >= lo).unwrap_or(0);
+        bucket_counts[bucket] += 1;
+    }
+    blocks.push(Block::Heading("Street length distribution".to_string()));
+    blocks.push(Block::Table(
+        vec!["length (km)".into(), "streets".into(), "share".into()],
+        LENGTH_BUCKETS
+            .iter()
+            .zip(bucket_counts)
+            .map(|(&(label, _), n)| vec![label.to_string(), n.to_string(), format!("{:.1}%", 100.0 * n as f64 / rows.len().max(1) as f64)])
+            .collect(),
+    ));
+
+    // The same name split into many separate streets is the usual sign of a grouping problem
+    blocks.push(Block::Heading("Most fragmented names".to_string()));
+    blocks.push(Block::Paragraph(
+        "Names that were grouped into the most separate streets; red points are each street's representative point.".to_string(),
+    ));
+    for (name, n) in names.iter().filter(|(_, n)| *n > 1).take(maps) {
+        blocks.push(Block::Paragraph(format!("{} ({} streets)", name, n)));
+        blocks.push(Block::Svg(fragment_map(rows, &by_name[name])));
+    }
+
+    blocks
+}
+
+/// `report <streets.parquet...> [--state S] [--format md|html] [--top N] [--maps N] --output-dir DIR`
+///
+/// Writes `<dir>/<state>_report.<md|html>` for each state in the input.
+pub fn run_report(
+    paths: &[PathBuf],
+    state: Option<&str>,
+    format: ReportFormat,
+    top_n: usize,
+    maps: usize,
+    output_dir: &Path,
+) -> Result<()> {
+    let rows = read_rows(&load_streets(paths)?)?;
+
+    let mut by_state: BTreeMap<&str, Vec<&StreetRow>> = BTreeMap::new();
+    for row in &rows {
+        if state.is_none_or(|s| s.eq_ignore_ascii_case(&row.state)) {
+            by_state.entry(row.state.as_str()).or_default().push(row);
+        }
+    }
+    if by_state.is_empty() {
+        anyhow::bail!("report: no streets found{}", state.map(|s| format!(" for state {}", s)).unwrap_or_default());
+    }
+
+    std::fs::create_dir_all(output_dir)?;
+    for (state_name, state_rows) in by_state {
+        let blocks = state_report(&state_rows, top_n, maps);
+        let document = render(&format!("Street report: {}", state_name), &blocks, format);
+        let path = output_dir.join(format!("{}_report.{}", state_name, format.extension()));
+        std::fs::write(&path, document)?;
+        println!("Saved {}", path.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use polars::prelude::*;
+use rstar::primitives::{GeomWithData, Line};
+use rstar::{PointDistance, RTree};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use osm_processor_rust::stats::load_streets;
+use osm_processor_rust::wkb::wkb_to_lines;
+
+/// Mean Earth radius, for converting chord lengths on the unit sphere back to km
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Point on the unit sphere, as in `nearest`
+fn unit_vector(lat: f64, lon: f64) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// Great-circle distance in km for a straight-line distance between unit vectors
+fn chord_to_km(chord: f64) -> f64 {
+    2.0 * EARTH_RADIUS_KM * (chord / 2.0).min(1.0).asin()
+}
+
+/// One row of the input: the street it names
+struct Target {
+    street_id: Option<String>,
+    street_name: String,
+    state: String,
+}
+
+/// R-tree over output geometries: every two-point piece of the segment polylines when the input
+/// has a WKB `geometry` column (the `--emit-segments` output), otherwise the streets'
+/// representative points. Pieces are chords between unit vectors, which for street-length
+/// pieces are indistinguishable from the arcs.
+pub struct ReverseIndex {
+    targets: Vec<Target>,
+    tree: RTree<GeomWithData<Line<[f64; 3]>, usize>>,
+}
+
+impl ReverseIndex {
+    pub fn from_dataframe(df: &DataFrame) -> Result<Self> {
+        let names = df.column("street_name")?.str()?;
+        let states = df.column("state")?.str()?;
+        let ids = match df.column("street_id") {
+            Ok(column) => Some(column.cast(&DataType::String)?),
+            Err(_) => None,
+        };
+        let ids = ids.as_ref().map(|c| c.str()).transpose()?;
+
+        let mut pieces = Vec::new();
+        match df.column("geometry") {
+            Ok(geometry) => {
+                for (i, wkb) in geometry.binary()?.into_iter().enumerate() {
+                    for line in wkb.and_then(wkb_to_lines).unwrap_or_default() {
+                        let points: Vec<[f64; 3]> = line.points().map(|p| unit_vector(p.y(), p.x())).collect();
+                        match points.as_slice() {
+                            [single] => pieces.push(GeomWithData::new(Line::new(*single, *single), i)),
+                            _ => pieces.extend(points.windows(2).map(|w| GeomWithData::new(Line::new(w[0], w[1]), i))),
+                        }
+                    }
+                }
+            }
+            Err(_) => {
+                let lats = df.column("lat")?.f64()?;
+                let lons = df.column("lon")?.f64()?;
+                for (i, (lat, lon)) in lats.into_iter().zip(lons).enumerate() {
+                    if let (Some(lat), Some(lon)) = (lat, lon) {
+                        let point = unit_vector(lat, lon);
+                        pieces.push(GeomWithData::new(Line::new(point, point), i));
+                    }
+                }
+            }
+        }
+
+        let targets = (0..df.height())
+            .map(|i| Target {
+                street_id: ids.and_then(|c| c.get(i)).map(str::to_string),
+                street_name: names.get(i).unwrap_or("").to_string(),
+                state: states.get(i).unwrap_or("").to_string(),
+            })
+            .collect();
+        Ok(ReverseIndex { targets, tree: RTree::bulk_load(pieces) })
+    }
+
+    /// The `k` distinct streets closest to (lat, lon), nearest first, with distances in km.
+    /// Streets are told apart by `street_id` when the input has it, else by name and state.
+    fn nearest(&self, lat: f64, lon: f64, k: usize) -> Vec<(&Target, f64)> {
+        let here = unit_vector(lat, lon);
+        let mut seen: HashSet<(&str, &str)> = HashSet::new();
+        let mut hits = Vec::new();
+        for piece in self.tree.nearest_neighbor_iter(&here) {
+            if hits.len() == k {
+                break;
+            }
+            let target = &self.targets[piece.data];
+            let key = match &target.street_id {
+                Some(id) => (id.as_str(), ""),
+                None => (target.street_name.as_str(), target.state.as_str()),
+            };
+            if seen.insert(key) {
+                hits.push((target, chord_to_km(piece.geom().distance_2(&here).sqrt())));
+            }
+        }
+        hits
+    }
+}
+
+/// `revgeocode <streets.parquet|segments.parquet...> --lat LAT --lon LON [--k N]`
+pub fn run_revgeocode(paths: &[PathBuf], lat: f64, lon: f64, k: usize) -> Result<()> {
+    let index = ReverseIndex::from_dataframe(&load_streets(paths)?)?;
+    let hits = index.nearest(lat, lon, k);
+
+    let out = DataFrame::new(vec![
+        Series::new("street_name", hits.iter().map(|h| h.0.street_name.as_str()).collect::<Vec<_>>()),
+        Series::new("state", hits.iter().map(|h| h.0.state.as_str()).collect::<Vec<_>>()),
+        Series::new("street_id", hits.iter().map(|h| h.0.street_id.as_deref()).collect::<Vec<_>>()),
+        Series::new("distance_km", hits.iter().map(|h| h.1).collect::<Vec<_>>()),
+    ])?;
+
+    println!("{} streets nearest to ({}, {}):", hits.len(), lat, lon);
+    println!("{}", out);
+    Ok(())
+}
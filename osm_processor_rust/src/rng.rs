@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+/// Small seeded PRNG (SplitMix64) so sampling and resampling are reproducible across runs
+/// and platforms without pulling in a dependency
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform integer in `0..bound` (bound > 0)
+    pub fn below(&mut self, bound: usize) -> usize {
+        // Multiply-shift reduction; bias is negligible for the sizes we sample from
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+
+    /// Choose `k` distinct items from `0..n` (partial Fisher-Yates), in selection order
+    pub fn sample_indices(&mut self, n: usize, k: usize) -> Vec<usize> {
+        let k = k.min(n);
+        let mut pool: Vec<usize> = (0..n).collect();
+        for i in 0..k {
+            let j = i + self.below(n - i);
+            pool.swap(i, j);
+        }
+        pool.truncate(k);
+        pool
+    }
+
+    /// Like [`sample_indices`](Self::sample_indices), but in O(k) time and memory by keeping
+    /// only the swapped slots, for drawing a few items from a very large `n`
+    pub fn sample_indices_sparse(&mut self, n: usize, k: usize) -> Vec<usize> {
+        let k = k.min(n);
+        let mut swapped: HashMap<usize, usize> = HashMap::with_capacity(k);
+        (0..k)
+            .map(|i| {
+                let j = i + self.below(n - i);
+                let at_j = swapped.get(&j).copied().unwrap_or(j);
+                let at_i = swapped.get(&i).copied().unwrap_or(i);
+                swapped.insert(j, at_i);
+                at_j
+            })
+            .collect()
+    }
+}
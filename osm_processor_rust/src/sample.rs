@@ -0,0 +1,87 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::rng::SplitMix64;
+use osm_processor_rust::stats::load_streets;
+use osm_processor_rust::write_table;
+
+/// Split `n` across strata proportionally to their sizes (largest-remainder rounding)
+fn allocate(sizes: &[usize], n: usize) -> Vec<usize> {
+    let total: usize = sizes.iter().sum();
+    if total == 0 {
+        return vec![0; sizes.len()];
+    }
+    let n = n.min(total);
+
+    let exact: Vec<f64> = sizes.iter().map(|&s| s as f64 * n as f64 / total as f64).collect();
+    let mut alloc: Vec<usize> = exact.iter().map(|e| e.floor() as usize).collect();
+
+    let mut remainders: Vec<(usize, f64)> = exact.iter().enumerate().map(|(i, e)| (i, e - e.floor())).collect();
+    remainders.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut missing = n - alloc.iter().sum::<usize>();
+    for (i, _) in remainders {
+        if missing == 0 {
+            break;
+        }
+        if alloc[i] < sizes[i] {
+            alloc[i] += 1;
+            missing -= 1;
+        }
+    }
+    alloc
+}
+
+/// Row indices of a seeded sample of `n` rows, optionally stratified by a column
+fn sample_rows(df: &DataFrame, n: usize, stratify_by: Option<&str>, seed: u64) -> Result<Vec<usize>> {
+    let mut rng = SplitMix64::new(seed);
+
+    let Some(column) = stratify_by else {
+        let mut rows = rng.sample_indices(df.height(), n);
+        rows.sort_unstable();
+        return Ok(rows);
+    };
+
+    // BTreeMap keeps strata in a stable order so the seed fully determines the sample
+    let mut strata: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let values = df.column(column)?.cast(&DataType::String)?;
+    for (i, value) in values.str()?.into_iter().enumerate() {
+        strata.entry(value.unwrap_or("").to_string()).or_default().push(i);
+    }
+
+    let sizes: Vec<usize> = strata.values().map(Vec::len).collect();
+    let alloc = allocate(&sizes, n);
+
+    let mut rows = Vec::with_capacity(n);
+    for (members, k) in strata.values().zip(alloc) {
+        rows.extend(rng.sample_indices(members.len(), k).into_iter().map(|i| members[i]));
+    }
+    rows.sort_unstable();
+    Ok(rows)
+}
+
+/// `sample <streets.parquet...> --n N [--stratify-by COL] [--seed S] -o out.(csv|parquet)`
+pub fn run_sample(
+    paths: &[PathBuf],
+    n: usize,
+    stratify_by: Option<&str>,
+    seed: u64,
+    output_path: &Path,
+) -> Result<()> {
+    let df = load_streets(paths)?;
+    let rows = sample_rows(&df, n, stratify_by, seed)?;
+
+    let idx = IdxCa::from_vec("idx", rows.iter().map(|&i| i as IdxSize).collect());
+    let mut sample = df.take(&idx)?;
+
+    match stratify_by {
+        Some(column) => println!("Sampled {} of {} streets, stratified by {} (seed {})", sample.height(), df.height(), column, seed),
+        None => println!("Sampled {} of {} streets (seed {})", sample.height(), df.height(), seed),
+    }
+
+    println!("Saving to: {}", output_path.display());
+    write_table(&mut sample, output_path)?;
+    Ok(())
+}
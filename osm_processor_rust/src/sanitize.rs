@@ -0,0 +1,120 @@
+use geo::{coord, Intersects, Line};
+use osmpbf::Way;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Segment issue: a tag key or value was not valid UTF-8
+pub const LOSSY_TAGS: &str = "lossy_tags";
+/// Segment issue: consecutive repeats of a node or point were collapsed
+pub const REPEATED_NODES: &str = "repeated_nodes";
+/// Segment issue: the way's geometry crosses itself
+pub const SELF_INTERSECTING: &str = "self_intersecting";
+
+/// What hardened parsing repaired or dropped over a run, counted per candidate way
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SanitizeCounts {
+    /// Tags that were not valid UTF-8, decoded with U+FFFD replacements
+    pub lossy_tags: u64,
+    /// Fewer than two distinct points once repeats were collapsed; dropped
+    pub short_ways: u64,
+    /// Consecutive repeats of a node or point, collapsed to one
+    pub repeated_nodes: u64,
+    /// Geometry crossing itself; kept and flagged in the QA report
+    pub self_intersecting: u64,
+}
+
+impl SanitizeCounts {
+    pub fn is_empty(&self) -> bool {
+        *self == SanitizeCounts::default()
+    }
+
+    /// `3 lossy_tags, 1 short_ways`, leaving out zeros
+    pub fn summary(&self) -> String {
+        let counts = [
+            (LOSSY_TAGS, self.lossy_tags),
+            ("short_ways", self.short_ways),
+            (REPEATED_NODES, self.repeated_nodes),
+            (SELF_INTERSECTING, self.self_intersecting),
+        ];
+        let parts: Vec<String> =
+            counts.iter().filter(|(_, n)| *n > 0).map(|(name, n)| format!("{} {}", n, name)).collect();
+        parts.join(", ")
+    }
+}
+
+/// A way's tags with invalid UTF-8 replaced by U+FFFD, and whether any needed replacing. The
+/// checked [`Way::tags`] stops at the first invalid string, losing the tags after it. An index
+/// past the end of the block's string table reads as a lone U+FFFD.
+pub(crate) fn lossy_tags(way: &Way) -> (HashMap<String, String>, bool) {
+    let strings = way.raw_stringtable();
+    let text = |index: u32| match strings.get(index as usize) {
+        Some(bytes) => String::from_utf8_lossy(bytes),
+        None => Cow::Owned(char::REPLACEMENT_CHARACTER.to_string()),
+    };
+    let mut lossy = false;
+    let tags = way
+        .raw_tags()
+        .map(|(key, value)| {
+            let (key, value) = (text(key), text(value));
+            lossy |= matches!(key, Cow::Owned(_)) || matches!(value, Cow::Owned(_));
+            (key.into_owned(), value.into_owned())
+        })
+        .collect();
+    (tags, lossy)
+}
+
+/// Whether two non-adjacent edges of the polyline touch or cross (a closed ring's first and last
+/// edges count as adjacent). Planar in lat/lon, which is close enough at street scale.
+pub fn self_intersects(coords: &[(f64, f64)]) -> bool {
+    let edges: Vec<Line<f64>> = coords
+        .windows(2)
+        .map(|w| Line::new(coord! { x: w[0].1, y: w[0].0 }, coord! { x: w[1].1, y: w[1].0 }))
+        .collect();
+    let closed = coords.len() > 3 && coords.first() == coords.last();
+    for i in 0..edges.len() {
+        for j in i + 2..edges.len() {
+            if closed && i == 0 && j == edges.len() - 1 {
+                continue;
+            }
+            if edges[i].intersects(&edges[j]) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Hardened checks on a candidate way, collapsing repeated nodes and points in place and
+/// tallying what was found in `counts`. Returns the issues to record on the segment, or `None`
+/// when fewer than two distinct points are left and the way should be dropped.
+pub(crate) fn sanitize_way(
+    counts: &mut SanitizeCounts,
+    lossy: bool,
+    node_ids: &mut Vec<i64>,
+    coords: &mut Vec<(f64, f64)>,
+) -> Option<Vec<&'static str>> {
+    let mut issues = Vec::new();
+    if lossy {
+        counts.lossy_tags += 1;
+        issues.push(LOSSY_TAGS);
+    }
+
+    let before = (node_ids.len(), coords.len());
+    node_ids.dedup();
+    coords.dedup();
+    if (node_ids.len(), coords.len()) != before {
+        counts.repeated_nodes += 1;
+        issues.push(REPEATED_NODES);
+    }
+
+    if coords.len() < 2 {
+        counts.short_ways += 1;
+        return None;
+    }
+    if self_intersects(coords) {
+        counts.self_intersecting += 1;
+        issues.push(SELF_INTERSECTING);
+    }
+    Some(issues)
+}
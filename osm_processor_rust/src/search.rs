@@ -0,0 +1,89 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+use osm_processor_rust::stats::load_streets;
+
+/// Lowercased character trigrams of `s`, padded so short words still produce some
+pub fn trigrams(s: &str) -> HashSet<[char; 3]> {
+    let padded: Vec<char> = format!("  {} ", s.to_lowercase()).chars().collect();
+    padded.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// Jaccard similarity of two trigram sets
+pub fn trigram_similarity(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f64 {
+    let shared = a.intersection(b).count();
+    let union = a.len() + b.len() - shared;
+    if union == 0 {
+        0.0
+    } else {
+        shared as f64 / union as f64
+    }
+}
+
+/// Case-insensitive Levenshtein edit distance
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let substitution = prev[j - 1] + (a[i - 1] != b[j - 1]) as usize;
+            curr[j] = substitution.min(prev[j] + 1).min(curr[j - 1] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+/// `search "<name>" <streets.parquet...> [--state S] [--limit N] [--min-similarity X]`
+///
+/// Candidates are prefiltered by trigram similarity, then ranked by edit distance. Each
+/// distinct (name, state) is listed once with how many streets carry it.
+pub fn run_search(
+    query: &str,
+    paths: &[PathBuf],
+    state: Option<&str>,
+    limit: usize,
+    min_similarity: f64,
+) -> Result<()> {
+    let df = load_streets(paths)?;
+    let names = df.column("street_name")?.str()?;
+    let states = df.column("state")?.str()?;
+
+    let mut counts: BTreeMap<(&str, &str), u32> = BTreeMap::new();
+    for (name, street_state) in names.into_iter().zip(states) {
+        let (Some(name), Some(street_state)) = (name, street_state) else { continue };
+        if state.is_none_or(|s| s.eq_ignore_ascii_case(street_state)) {
+            *counts.entry((name, street_state)).or_default() += 1;
+        }
+    }
+
+    let query_trigrams = trigrams(query);
+    let mut hits: Vec<(&str, &str, u32, f64, usize)> = counts
+        .into_iter()
+        .filter_map(|((name, street_state), count)| {
+            let similarity = trigram_similarity(&query_trigrams, &trigrams(name));
+            (similarity >= min_similarity)
+                .then(|| (name, street_state, count, similarity, levenshtein(query, name)))
+        })
+        .collect();
+    hits.sort_by(|a, b| a.4.cmp(&b.4).then(b.3.total_cmp(&a.3)).then(b.2.cmp(&a.2)));
+    hits.truncate(limit);
+
+    let out = DataFrame::new(vec![
+        Series::new("street_name", hits.iter().map(|h| h.0).collect::<Vec<_>>()),
+        Series::new("state", hits.iter().map(|h| h.1).collect::<Vec<_>>()),
+        Series::new("streets", hits.iter().map(|h| h.2).collect::<Vec<_>>()),
+        Series::new("similarity", hits.iter().map(|h| h.3).collect::<Vec<_>>()),
+        Series::new("edit_distance", hits.iter().map(|h| h.4 as u32).collect::<Vec<_>>()),
+    ])?;
+
+    println!("Closest names to \"{}\":", query);
+    println!("{}", out);
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! Extracted segments saved between runs (`--save-segments` / `--from-segments`).
+//!
+//! The PBF passes take minutes to hours on large states while grouping takes seconds to
+//! minutes, so tuning the distance threshold or aggregation options means re-running the cheap
+//! part many times. The artifact is the extraction result before regions, directionals or
+//! grouping are applied: one row per segment with its nodes, coordinates and tags as lists.
+//! Everything decided during extraction (feature, name language, relations, hardened parsing)
+//! is baked in when it is saved.
+
+use polars::prelude::*;
+use std::path::Path;
+
+use crate::error::{io_error, ProcessorError, Result};
+use crate::feature::Feature;
+use crate::sanitize::{LOSSY_TAGS, REPEATED_NODES, SELF_INTERSECTING};
+use crate::tags::Tags;
+use crate::{write_parquet, StreetSegment};
+
+/// Write extracted segments to `path`; the type column is named after `feature`, which is how
+/// [`load_segments`] tells artifacts of different features apart
+pub(crate) fn save_segments(segments: &[StreetSegment], feature: Feature, path: &Path) -> Result<()> {
+    let n = segments.len();
+    let num_nodes = segments.iter().map(|s| s.node_ids.len()).sum();
+    let num_coords = segments.iter().map(|s| s.coords.len()).sum();
+    let num_tags = segments.iter().map(|s| s.tags.len()).sum();
+
+    let mut node_ids = ListPrimitiveChunkedBuilder::<Int64Type>::new("node_ids", n, num_nodes, DataType::Int64);
+    let mut lats = ListPrimitiveChunkedBuilder::<Float64Type>::new("lat", n, num_coords, DataType::Float64);
+    let mut lons = ListPrimitiveChunkedBuilder::<Float64Type>::new("lon", n, num_coords, DataType::Float64);
+    let mut tag_keys = ListStringChunkedBuilder::new("tag_keys", n, num_tags);
+    let mut tag_values = ListStringChunkedBuilder::new("tag_values", n, num_tags);
+    let mut sanitized = ListStringChunkedBuilder::new("sanitized", n, n);
+    for seg in segments {
+        node_ids.append_slice(&seg.node_ids);
+        lats.append_slice(&seg.coords.iter().map(|&(lat, _)| lat).collect::<Vec<_>>());
+        lons.append_slice(&seg.coords.iter().map(|&(_, lon)| lon).collect::<Vec<_>>());
+        tag_keys.append_values_iter(seg.tags.keys());
+        tag_values.append_values_iter(seg.tags.iter().map(|(_, value)| value));
+        sanitized.append_values_iter(seg.sanitized.iter().copied());
+    }
+
+    let mut df = DataFrame::new(vec![
+        Series::new("way_id", segments.iter().map(|s| s.way_id).collect::<Vec<_>>()),
+        Series::new("street_name", segments.iter().map(|s| s.street_name.as_str()).collect::<Vec<_>>()),
+        Series::new("local_name", segments.iter().map(|s| s.local_name.as_deref()).collect::<Vec<_>>()),
+        Series::new("state", segments.iter().map(|s| s.state.as_str()).collect::<Vec<_>>()),
+        Series::new("relation_id", segments.iter().map(|s| s.relation_id).collect::<Vec<_>>()),
+        Series::new(feature.type_column(), segments.iter().map(|s| s.highway_type.as_str()).collect::<Vec<_>>()),
+        Series::new("length_km", segments.iter().map(|s| s.length_km).collect::<Vec<_>>()),
+        node_ids.finish().into_series(),
+        lats.finish().into_series(),
+        lons.finish().into_series(),
+        tag_keys.finish().into_series(),
+        tag_values.finish().into_series(),
+        sanitized.finish().into_series(),
+    ])?;
+    write_parquet(&mut df, path)
+}
+
+/// Read segments written by [`save_segments`] for `feature`
+pub(crate) fn load_segments(path: &Path, feature: Feature) -> Result<Vec<StreetSegment>> {
+    let file = std::fs::File::open(path).map_err(io_error(path))?;
+    let df = ParquetReader::new(file)
+        .finish()
+        .map_err(|source| ProcessorError::InputRead { path: path.to_path_buf(), source })?;
+    if !df.get_column_names().contains(&feature.type_column()) {
+        return Err(ProcessorError::InvalidConfig(format!(
+            "{} has no {} column; it was saved for a different feature",
+            path.display(),
+            feature.type_column()
+        )));
+    }
+    segments_from_frame(&df, feature).map_err(|source| ProcessorError::InputRead { path: path.to_path_buf(), source })
+}
+
+fn segments_from_frame(df: &DataFrame, feature: Feature) -> PolarsResult<Vec<StreetSegment>> {
+    let way_ids = df.column("way_id")?.i64()?;
+    let street_names = df.column("street_name")?.str()?;
+    let local_names = df.column("local_name")?.str()?;
+    let states = df.column("state")?.str()?;
+    let relation_ids = df.column("relation_id")?.i64()?;
+    let types = df.column(feature.type_column())?.str()?;
+    let lengths = df.column("length_km")?.f64()?;
+    let node_ids = lists(df, "node_ids", |s| Ok(s.i64()?.into_no_null_iter().collect()))?;
+    let lats = lists(df, "lat", |s| Ok(s.f64()?.into_no_null_iter().collect()))?;
+    let lons = lists(df, "lon", |s| Ok(s.f64()?.into_no_null_iter().collect()))?;
+    let tag_keys = lists(df, "tag_keys", |s| Ok(s.str()?.into_no_null_iter().map(str::to_string).collect()))?;
+    let tag_values = lists(df, "tag_values", |s| Ok(s.str()?.into_no_null_iter().map(str::to_string).collect()))?;
+    let sanitized = lists(df, "sanitized", |s| Ok(s.str()?.into_no_null_iter().filter_map(sanitize_issue).collect()))?;
+
+    let mut segments = Vec::with_capacity(df.height());
+    for (i, (((node_ids, (lats, lons)), (keys, values)), sanitized)) in node_ids
+        .into_iter()
+        .zip(lats.into_iter().zip(lons))
+        .zip(tag_keys.into_iter().zip(tag_values))
+        .zip(sanitized)
+        .enumerate()
+    {
+        segments.push(StreetSegment {
+            street_name: street_names.get(i).unwrap_or_default().to_string(),
+            local_name: local_names.get(i).map(str::to_string),
+            state: states.get(i).unwrap_or_default().to_string(),
+            region: None,
+            direction: None,
+            relation_id: relation_ids.get(i),
+            way_id: way_ids.get(i).unwrap_or_default(),
+            node_ids,
+            coords: lats.into_iter().zip(lons).collect(),
+            highway_type: types.get(i).unwrap_or_default().to_string(),
+            tags: Tags::from_pairs(keys.into_iter().zip(values)),
+            length_km: lengths.get(i).unwrap_or_default(),
+            sanitized,
+        });
+    }
+    Ok(segments)
+}
+
+/// Each row of list column `name` as a Vec; null rows are empty
+fn lists<T>(df: &DataFrame, name: &str, values: impl Fn(&Series) -> PolarsResult<Vec<T>>) -> PolarsResult<Vec<Vec<T>>> {
+    df.column(name)?
+        .list()?
+        .into_iter()
+        .map(|list| list.map_or_else(|| Ok(Vec::new()), |series| values(&series)))
+        .collect()
+}
+
+/// The `&'static` issue name matching a saved one; unknown names are dropped
+fn sanitize_issue(name: &str) -> Option<&'static str> {
+    [LOSSY_TAGS, REPEATED_NODES, SELF_INTERSECTING].into_iter().find(|&issue| issue == name)
+}
@@ -0,0 +1,143 @@
+use geo::LineString;
+use polars::prelude::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::crs::OutputCrs;
+use crate::error::Result;
+use crate::fast_hash::NodeMap;
+use crate::feature::Feature;
+use crate::simplify::simplify_polyline;
+use crate::wkb::linestring_to_wkb;
+use crate::{round_coord, Street, StreetSegment};
+
+/// A grouped segment and the street it ended up in
+#[derive(Debug, Clone)]
+pub struct SegmentRecord {
+    pub segment: StreetSegment,
+    /// Foreign key to the streets output
+    pub street_id: String,
+}
+
+/// A node of a grouped segment, once per street it belongs to
+#[derive(Debug, Clone)]
+pub struct NodeRecord {
+    pub node_id: i64,
+    pub lat: f64,
+    pub lon: f64,
+    pub street_id: String,
+}
+
+/// The `street_id` each segment was grouped into; `members` lists the segment indices of each
+/// street, parallel to `streets`
+fn street_of_segments<'a>(num_segments: usize, streets: &'a [Street], members: &[Vec<usize>]) -> Vec<Option<&'a str>> {
+    let mut street_of = vec![None; num_segments];
+    for (street, indices) in streets.iter().zip(members) {
+        for &i in indices {
+            street_of[i] = Some(street.street_id.as_str());
+        }
+    }
+    street_of
+}
+
+/// Pair every grouped segment with its street's ID
+pub fn segment_records(segments: Vec<StreetSegment>, streets: &[Street], members: &[Vec<usize>]) -> Vec<SegmentRecord> {
+    let street_of = street_of_segments(segments.len(), streets, members);
+    segments
+        .into_iter()
+        .zip(street_of)
+        .filter_map(|(segment, street_id)| Some(SegmentRecord { street_id: street_id?.to_string(), segment }))
+        .collect()
+}
+
+/// Every node of the grouped segments that has coordinates, once per (node, street), with
+/// coordinates rounded to `precision`
+pub fn node_records(
+    segments: &[StreetSegment],
+    streets: &[Street],
+    members: &[Vec<usize>],
+    node_coords: &NodeMap<(f64, f64)>,
+    precision: Option<u32>,
+) -> Vec<NodeRecord> {
+    let street_of = street_of_segments(segments.len(), streets, members);
+    let mut seen: HashSet<(i64, &str)> = HashSet::new();
+    let mut records = Vec::new();
+
+    for (seg, street_id) in segments.iter().zip(street_of) {
+        let Some(street_id) = street_id else { continue };
+        for &node_id in &seg.node_ids {
+            let Some(&(lat, lon)) = node_coords.get(&node_id) else { continue };
+            if seen.insert((node_id, street_id)) {
+                records.push(NodeRecord {
+                    node_id,
+                    lat: round_coord(lat, precision),
+                    lon: round_coord(lon, precision),
+                    street_id: street_id.to_string(),
+                });
+            }
+        }
+    }
+    records
+}
+
+/// One row per node and street: nodes shared by several streets are their intersections
+pub fn nodes_to_dataframe(records: &[NodeRecord]) -> Result<DataFrame> {
+    let df = DataFrame::new(vec![
+        Series::new("node_id", records.iter().map(|r| r.node_id).collect::<Vec<_>>()),
+        Series::new("lat", records.iter().map(|r| r.lat).collect::<Vec<_>>()),
+        Series::new("lon", records.iter().map(|r| r.lon).collect::<Vec<_>>()),
+        Series::new("street_id", records.iter().map(|r| r.street_id.as_str()).collect::<Vec<_>>()),
+    ])?;
+
+    Ok(df)
+}
+
+/// One row per segment: way, street it belongs to, type, length and WKB LineString geometry in
+/// `crs`, Douglas-Peucker simplified to `simplify_tolerance_m` if given (`length_km` stays the
+/// length of the full way)
+pub fn segments_to_dataframe(
+    records: &[SegmentRecord],
+    feature: Feature,
+    precision: Option<u32>,
+    simplify_tolerance_m: Option<f64>,
+    crs: OutputCrs,
+) -> Result<DataFrame> {
+    let geometries: Vec<Vec<u8>> = records
+        .iter()
+        .map(|r| {
+            let coords = match simplify_tolerance_m {
+                Some(tolerance_m) => simplify_polyline(&r.segment.coords, tolerance_m),
+                None => r.segment.coords.clone(),
+            };
+            let line: LineString<f64> = coords.iter().map(|&coord| crs.project(coord)).collect();
+            linestring_to_wkb(&line, precision)
+        })
+        .collect();
+
+    let df = DataFrame::new(vec![
+        Series::new("way_id", records.iter().map(|r| r.segment.way_id).collect::<Vec<_>>()),
+        Series::new("street_id", records.iter().map(|r| r.street_id.as_str()).collect::<Vec<_>>()),
+        Series::new("street_name", records.iter().map(|r| r.segment.street_name.as_str()).collect::<Vec<_>>()),
+        Series::new("state", records.iter().map(|r| r.segment.state.as_str()).collect::<Vec<_>>()),
+        Series::new(
+            feature.type_column(),
+            records.iter().map(|r| r.segment.highway_type.as_str()).collect::<Vec<_>>(),
+        ),
+        Series::new("length_km", records.iter().map(|r| r.segment.length_km).collect::<Vec<_>>()),
+        Series::new("geometry", geometries),
+    ])?;
+
+    Ok(df)
+}
+
+/// `<output stem>_segments.parquet` next to the streets file (`delaware_streets_segments.parquet`)
+pub fn segments_output_path(output_path: &Path) -> PathBuf {
+    let stem = output_path.file_stem().and_then(|s| s.to_str()).unwrap_or("streets");
+    output_path.with_file_name(format!("{}_segments.parquet", stem))
+}
+
+/// `<output stem>_nodes.parquet` next to the streets file (`delaware_streets_nodes.parquet`)
+pub fn nodes_output_path(output_path: &Path) -> PathBuf {
+    let stem = output_path.file_stem().and_then(|s| s.to_str()).unwrap_or("streets");
+    output_path.with_file_name(format!("{}_nodes.parquet", stem))
+}
@@ -0,0 +1,426 @@
+use osmpbf::{BlobDecode, BlobReader, Element, ElementReader};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::error::{io_error, pbf_error, ProcessorError, Result};
+use crate::fast_hash::NodeMap;
+use crate::node_set::NodeIdSet;
+use crate::progress::NoProgress;
+use crate::tags::Tags;
+use crate::{polyline_length_km, segments_by_name, OsmStreetProcessor, Street, StreetSegment};
+
+/// Length of a degree of latitude
+const KM_PER_DEG: f64 = 111.195;
+
+/// Bytes per node in a bucket file: ID, lat and lon as little-endian 8-byte values
+const NODE_BYTES: usize = 24;
+
+/// Bytes buffered per square before they are appended to its file; files are opened per
+/// append, so thousands of squares never mean thousands of open files
+const BUCKET_FLUSH: usize = 1 << 20;
+
+/// Distinguishes the bucket directories of concurrent runs within one process
+static RUN_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Spatial sharding for inputs too large to hold every node of at once
+///
+/// The input is read once for the named ways (node IDs and tags, no coordinates) and once for
+/// their nodes, whose coordinates go to one file per `shard_deg` square in the temp dir. Each
+/// shard then loads its own square's file plus those within `halo_deg` on every side, picks out
+/// the ways it has nodes of and groups them. A street is kept by exactly one shard: the one
+/// whose square holds the street's lowest node ID, provided that shard loaded every node of
+/// every way the street could join and none lies within the merge distance of the halo's outer
+/// edge. All other ways are set aside; once all shards are done, those not already part of a
+/// kept street are joined up from the pieces each shard saw and regrouped.
+///
+/// Only node coordinates are split up: the ways, with their tags and node IDs, are held for the
+/// whole run, the set of their node IDs for the node pass, and every shard scans all of the
+/// ways.
+#[derive(Debug, Clone, Copy)]
+pub struct ShardConfig {
+    /// Edge length of a shard in degrees
+    pub shard_deg: f64,
+    /// Border loaded around each shard (degrees); should exceed the merge distance
+    pub halo_deg: f64,
+    /// Shards processed at the same time; peak memory grows with this
+    pub jobs: usize,
+}
+
+impl Default for ShardConfig {
+    fn default() -> Self {
+        ShardConfig { shard_deg: 2.0, halo_deg: 0.2, jobs: 1 }
+    }
+}
+
+type Cell = (i32, i32);
+
+/// A way set aside by a shard, with the coordinates of each of its nodes where that shard had
+/// them
+type DeferredWay = (StreetSegment, Vec<Option<(f64, f64)>>);
+
+/// (min_lat, min_lon, max_lat, max_lon)
+#[derive(Debug, Clone, Copy)]
+struct Bounds(f64, f64, f64, f64);
+
+impl Bounds {
+    fn of_cell((row, col): Cell, deg: f64) -> Self {
+        Bounds(row as f64 * deg, col as f64 * deg, (row + 1) as f64 * deg, (col + 1) as f64 * deg)
+    }
+
+    fn expand(self, deg: f64) -> Self {
+        Bounds(self.0 - deg, self.1 - deg, self.2 + deg, self.3 + deg)
+    }
+
+    /// Half-open, so a point on a shared edge belongs to exactly one shard
+    fn contains(self, lat: f64, lon: f64) -> bool {
+        lat >= self.0 && lon >= self.1 && lat < self.2 && lon < self.3
+    }
+
+    /// Shrunk by `km` on every side, with longitude degrees taken at the latitude where they
+    /// are shortest
+    fn shrink_km(self, km: f64) -> Self {
+        let lat_deg = km / KM_PER_DEG;
+        let lon_deg = lat_deg / self.0.abs().max(self.2.abs()).min(89.0).to_radians().cos();
+        Bounds(self.0 + lat_deg, self.1 + lon_deg, self.2 - lat_deg, self.3 - lon_deg)
+    }
+}
+
+/// What one shard contributes
+#[derive(Default)]
+struct ShardOutput {
+    streets: Vec<Street>,
+    /// Ways of the streets in `streets`
+    owned_ways: Vec<i64>,
+    /// Ways of the components this shard did not keep, without regions assigned
+    deferred: Vec<DeferredWay>,
+}
+
+fn cell_of(lat: f64, lon: f64, deg: f64) -> Cell {
+    ((lat / deg).floor() as i32, (lon / deg).floor() as i32)
+}
+
+/// Way pass: the named ways of the processor's feature, with node IDs but no coordinates
+fn read_ways(processor: &OsmStreetProcessor) -> Result<Vec<StreetSegment>> {
+    let (pbf_path, feature, names) = (processor.input(), processor.feature(), processor.names());
+    let reader = ElementReader::from_path(pbf_path).map_err(pbf_error(pbf_path))?;
+    let mut ways = Vec::new();
+    reader
+        .for_each(|element| {
+            let Element::Way(way) = element else { return };
+            let tags: HashMap<&str, &str> = way.tags().collect();
+            let name = names.choose(|key| tags.get(key).copied()).map(str::to_string);
+            let highway_type = feature.way_type(|key| tags.get(key).copied()).map(str::to_string);
+            let (Some(street_name), Some(highway_type)) = (name, highway_type) else { return };
+            let local_name = if names.is_local() { None } else { tags.get("name").map(|n| n.to_string()) };
+            ways.push(StreetSegment {
+                street_name,
+                local_name,
+                state: processor.state().to_string(),
+                region: None,
+                direction: None,
+                relation_id: None,
+                way_id: way.id(),
+                node_ids: way.refs().collect(),
+                coords: Vec::new(),
+                highway_type,
+                tags: Tags::from_pairs(tags),
+                length_km: 0.0,
+                sanitized: Vec::new(),
+            });
+        })
+        .map_err(pbf_error(pbf_path))?;
+    Ok(ways)
+}
+
+/// Coordinates of the wanted nodes, one file per non-empty square, so each shard reads its
+/// own and its neighbours' instead of the input. The directory is removed on drop.
+struct Buckets {
+    dir: PathBuf,
+    deg: f64,
+    /// Nodes per non-empty square
+    counts: BTreeMap<Cell, u64>,
+}
+
+impl Buckets {
+    /// Node pass: write each node of `wanted` to the file of its square in `dir`
+    fn write(pbf_path: &Path, wanted: &NodeIdSet, deg: f64, dir: PathBuf) -> Result<Self> {
+        std::fs::create_dir_all(&dir).map_err(io_error(&dir))?;
+        let mut buckets = Buckets { dir, deg, counts: BTreeMap::new() };
+        let reader = BlobReader::from_path(pbf_path).map_err(pbf_error(pbf_path))?;
+
+        // Blobs are decoded in parallel; their nodes are appended to shared per-square
+        // buffers, which are written out as they fill
+        let pending: Mutex<HashMap<Cell, Vec<u8>>> = Mutex::default();
+        let counts: Mutex<BTreeMap<Cell, u64>> = Mutex::default();
+        reader.par_bridge().try_for_each(|blob| -> Result<()> {
+            let mut found: HashMap<Cell, Vec<u8>> = HashMap::new();
+            let mut add = |id: i64, lat: f64, lon: f64| {
+                if wanted.contains(id) {
+                    let bytes = found.entry(cell_of(lat, lon, deg)).or_default();
+                    bytes.extend_from_slice(&id.to_le_bytes());
+                    bytes.extend_from_slice(&lat.to_le_bytes());
+                    bytes.extend_from_slice(&lon.to_le_bytes());
+                }
+            };
+            let blob = blob.map_err(pbf_error(pbf_path))?;
+            if let BlobDecode::OsmData(block) = blob.decode().map_err(pbf_error(pbf_path))? {
+                for group in block.groups() {
+                    for node in group.nodes() {
+                        add(node.id(), node.lat(), node.lon());
+                    }
+                    for node in group.dense_nodes() {
+                        add(node.id(), node.lat(), node.lon());
+                    }
+                }
+            }
+
+            let mut counts = counts.lock().unwrap();
+            let mut pending = pending.lock().unwrap();
+            for (cell, bytes) in found {
+                *counts.entry(cell).or_default() += (bytes.len() / NODE_BYTES) as u64;
+                let buffer = pending.entry(cell).or_default();
+                buffer.extend_from_slice(&bytes);
+                if buffer.len() >= BUCKET_FLUSH {
+                    buckets.append(cell, buffer)?;
+                }
+            }
+            Ok(())
+        })?;
+
+        for (cell, mut bytes) in pending.into_inner().unwrap() {
+            if !bytes.is_empty() {
+                buckets.append(cell, &mut bytes)?;
+            }
+        }
+        buckets.counts = counts.into_inner().unwrap();
+        Ok(buckets)
+    }
+
+    fn path(&self, (row, col): Cell) -> PathBuf {
+        self.dir.join(format!("{}_{}.nodes", row, col))
+    }
+
+    /// Append `bytes` to the file of `cell` and clear them
+    fn append(&self, cell: Cell, bytes: &mut Vec<u8>) -> Result<()> {
+        let path = self.path(cell);
+        let mut file = OpenOptions::new().create(true).append(true).open(&path).map_err(io_error(&path))?;
+        file.write_all(bytes).map_err(io_error(&path))?;
+        bytes.clear();
+        Ok(())
+    }
+
+    /// Coordinates of the nodes inside `bounds`, read from the squares it overlaps
+    fn load(&self, bounds: Bounds) -> Result<NodeMap<(f64, f64)>> {
+        let (min_row, min_col) = cell_of(bounds.0, bounds.1, self.deg);
+        let (max_row, max_col) = cell_of(bounds.2, bounds.3, self.deg);
+        let cells: Vec<(Cell, u64)> = self
+            .counts
+            .range((min_row, min_col)..=(max_row, max_col))
+            .filter(|((_, col), _)| (min_col..=max_col).contains(col))
+            .map(|(&cell, &count)| (cell, count))
+            .collect();
+
+        let mut coords = NodeMap::with_capacity_and_hasher(cells.iter().map(|&(_, count)| count as usize).sum(), Default::default());
+        for (cell, _) in cells {
+            let path = self.path(cell);
+            let bytes = std::fs::read(&path).map_err(io_error(&path))?;
+            for node in bytes.chunks_exact(NODE_BYTES) {
+                let [id, lat, lon] = [0usize, 8, 16].map(|at| u64::from_le_bytes(node[at..at + 8].try_into().unwrap()));
+                let (lat, lon) = (f64::from_bits(lat), f64::from_bits(lon));
+                if bounds.contains(lat, lon) {
+                    coords.insert(id as i64, (lat, lon));
+                }
+            }
+        }
+        Ok(coords)
+    }
+}
+
+impl Drop for Buckets {
+    fn drop(&mut self) {
+        // Best effort: a leftover temp dir should not mask the processing result
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn process_shard(
+    processor: &OsmStreetProcessor,
+    config: &ShardConfig,
+    ways: &[StreetSegment],
+    buckets: &Buckets,
+    cell: Cell,
+) -> Result<ShardOutput> {
+    let core = Bounds::of_cell(cell, config.shard_deg);
+    let loaded = core.expand(config.halo_deg);
+    let node_coords = buckets.load(loaded)?;
+    let segments: Vec<StreetSegment> = ways
+        .iter()
+        .filter(|way| way.node_ids.iter().any(|id| node_coords.contains_key(id)))
+        .map(|way| {
+            let mut seg = way.clone();
+            seg.coords = seg.node_ids.iter().filter_map(|id| node_coords.get(id).copied()).collect();
+            seg.length_km = polyline_length_km(&seg.coords);
+            seg
+        })
+        .collect();
+
+    // Regions come from coordinates, which a cut-off way lacks some of, so ways are first
+    // grouped on their names alone. Only a component that is complete and entirely inside
+    // `inner` has every way it could merge with in view; its streets come out the same here as
+    // in an unsharded run.
+    let inner = loaded.shrink_km(processor.max_merge_km());
+    let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, seg) in segments.iter().enumerate() {
+        by_name.entry(&seg.street_name).or_default().push(i);
+    }
+
+    let mut output = ShardOutput::default();
+    for indices in by_name.into_values() {
+        let name_segments: Vec<_> = indices.iter().map(|&i| segments[i].clone()).collect();
+        for component in processor.grouping().group(&name_segments) {
+            let segs: Vec<_> = component.iter().map(|&i| name_segments[i].clone()).collect();
+            let complete = segs.iter().all(|seg| seg.coords.len() == seg.node_ids.len())
+                && segs.iter().flat_map(|seg| &seg.coords).all(|&(lat, lon)| inner.contains(lat, lon));
+
+            let mut kept = HashSet::new();
+            if complete {
+                let mut prepared = segs.clone();
+                processor.assign_regions(&mut prepared, &NoProgress);
+                for ((name, state, _), indices) in segments_by_name(&prepared) {
+                    let group: Vec<_> = indices.iter().map(|&i| prepared[i].clone()).collect();
+                    for street_component in processor.grouping().group(&group) {
+                        let street_segs: Vec<_> = street_component.iter().map(|&i| &group[i]).collect();
+                        let owned = street_segs
+                            .iter()
+                            .flat_map(|seg| &seg.node_ids)
+                            .min()
+                            .and_then(|id| node_coords.get(id))
+                            .is_some_and(|&(lat, lon)| core.contains(lat, lon));
+                        if owned {
+                            let street =
+                                processor.aggregator().aggregate(&name, &state, &street_segs, processor.feature());
+                            output.streets.push(street);
+                            kept.extend(street_segs.iter().map(|seg| seg.way_id));
+                        }
+                    }
+                }
+            }
+
+            output.owned_ways.extend(kept.iter().copied());
+            output.deferred.extend(segs.into_iter().filter(|seg| !kept.contains(&seg.way_id)).map(|seg| {
+                let known = seg.node_ids.iter().map(|id| node_coords.get(id).copied()).collect();
+                (seg, known)
+            }));
+        }
+    }
+    Ok(output)
+}
+
+/// Join the pieces of each deferred way seen by different shards, assign regions and regroup
+/// them, leaving out ways some shard already kept
+fn resolve_deferred(processor: &OsmStreetProcessor, deferred: Vec<DeferredWay>, owned_ways: &HashSet<i64>) -> Vec<Street> {
+    let mut by_way: HashMap<i64, DeferredWay> = HashMap::new();
+    for (seg, known) in deferred.into_iter().filter(|(seg, _)| !owned_ways.contains(&seg.way_id)) {
+        match by_way.get_mut(&seg.way_id) {
+            Some((_, merged)) => {
+                for (slot, coord) in merged.iter_mut().zip(known) {
+                    *slot = slot.or(coord);
+                }
+            }
+            None => {
+                by_way.insert(seg.way_id, (seg, known));
+            }
+        }
+    }
+
+    let mut segments: Vec<StreetSegment> = by_way
+        .into_values()
+        .map(|(mut seg, known)| {
+            seg.coords = known.into_iter().flatten().collect();
+            seg.length_km = polyline_length_km(&seg.coords);
+            seg
+        })
+        .collect();
+    processor.assign_regions(&mut segments, &NoProgress);
+
+    let mut streets = Vec::new();
+    for ((name, state, _), indices) in segments_by_name(&segments) {
+        let name_segments: Vec<_> = indices.iter().map(|&i| segments[i].clone()).collect();
+        for component in processor.grouping().group(&name_segments) {
+            let segs: Vec<_> = component.iter().map(|&i| &name_segments[i]).collect();
+            streets.push(processor.aggregator().aggregate(&name, &state, &segs, processor.feature()));
+        }
+    }
+    streets
+}
+
+/// Run `processor` shard by shard; see [`ShardConfig`]
+pub(crate) fn run_sharded(processor: &OsmStreetProcessor, config: &ShardConfig) -> Result<Vec<Street>> {
+    let progress = processor.progress();
+    progress.message("Sharding: reading ways...");
+    let mut ways = read_ways(processor)?;
+    processor.split_directionals(&mut ways);
+    let wanted: NodeIdSet = ways.iter().flat_map(|way| way.node_ids.iter().copied()).collect();
+    progress.message(&format!(
+        "  {} ways, {} nodes; bucketing node coordinates into {}° shards...",
+        ways.len(),
+        wanted.len(),
+        config.shard_deg
+    ));
+    let dir = processor.temp_dir().join(format!(
+        "osm_processor_shards_{}_{}",
+        std::process::id(),
+        RUN_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let buckets = Buckets::write(processor.input(), &wanted, config.shard_deg, dir)?;
+    drop(wanted);
+    let cells: Vec<Cell> = buckets.counts.keys().copied().collect();
+    progress.message(&format!("  {} non-empty shards, {} at a time", cells.len(), config.jobs));
+
+    let mut streets = Vec::new();
+    let mut owned_ways = HashSet::new();
+    let mut deferred = Vec::new();
+    let mut done = 0;
+
+    let (ways, buckets) = (&ways, &buckets);
+    for batch in cells.chunks(config.jobs.max(1)) {
+        let outputs = std::thread::scope(|scope| {
+            let handles: Vec<_> = batch
+                .iter()
+                .map(|&cell| scope.spawn(move || process_shard(processor, config, ways, buckets, cell)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().map_err(|_| ProcessorError::WorkerPanic)?)
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        for (&cell, output) in batch.iter().zip(outputs) {
+            done += 1;
+            let bounds = Bounds::of_cell(cell, config.shard_deg);
+            progress.message(&format!(
+                "  Shard {}/{} [{:.1}, {:.1}]: {} streets, {} segments set aside",
+                done,
+                cells.len(),
+                bounds.0,
+                bounds.1,
+                output.streets.len(),
+                output.deferred.len()
+            ));
+            streets.extend(output.streets);
+            owned_ways.extend(output.owned_ways);
+            deferred.extend(output.deferred);
+        }
+    }
+
+    progress.message(&format!("Regrouping {} segments near shard edges...", deferred.len()));
+    let joined = resolve_deferred(processor, deferred, &owned_ways);
+    progress.message(&format!("  {} streets crossing shard edges", joined.len()));
+    streets.extend(joined);
+    Ok(streets)
+}
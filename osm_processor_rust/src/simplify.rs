@@ -0,0 +1,48 @@
+/// Meters per degree of latitude (and of longitude at the equator)
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// Douglas-Peucker simplification of a (lat, lon) polyline: drops points closer than
+/// `tolerance_m` meters to the simplified line, always keeping both ends. Distances are taken in
+/// a local equirectangular projection around the first point, which is accurate to well under a
+/// percent over street-length lines.
+pub fn simplify_polyline(coords: &[(f64, f64)], tolerance_m: f64) -> Vec<(f64, f64)> {
+    if coords.len() <= 2 || tolerance_m <= 0.0 {
+        return coords.to_vec();
+    }
+
+    let lon_scale = coords[0].0.to_radians().cos() * METERS_PER_DEGREE;
+    let projected: Vec<(f64, f64)> = coords
+        .iter()
+        .map(|&(lat, lon)| (lon * lon_scale, lat * METERS_PER_DEGREE))
+        .collect();
+
+    let mut keep = vec![false; coords.len()];
+    keep[0] = true;
+    keep[coords.len() - 1] = true;
+
+    // Iterative rather than recursive, so long ways cannot exhaust the stack
+    let mut ranges = vec![(0, coords.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| (i, distance_to_segment(projected[i], projected[first], projected[last])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance_m {
+                keep[i] = true;
+                ranges.push((first, i));
+                ranges.push((i, last));
+            }
+        }
+    }
+
+    coords.iter().zip(keep).filter(|(_, kept)| *kept).map(|(&coord, _)| coord).collect()
+}
+
+/// Distance from `p` to the segment `a`-`b`, in the units of the coordinates
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = dx * dx + dy * dy;
+    let t = if len_sq == 0.0 { 0.0 } else { (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_sq).clamp(0.0, 1.0) };
+    let (x, y) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - x).powi(2) + (p.1 - y).powi(2)).sqrt()
+}
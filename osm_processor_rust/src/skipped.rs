@@ -0,0 +1,88 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{io_error, Result};
+
+/// Why a candidate way did not become a segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Carries the feature's type tag with an accepted value, but no `name`
+    NoName,
+    /// Named and of the right type, but none of its nodes are in the file
+    NoCoordinates,
+    /// Named and carries the type key, but with a value the feature does not accept
+    /// (e.g. `railway=abandoned` for `--feature railway`)
+    FilteredOut,
+    /// Fewer than two distinct points once repeated nodes were collapsed (hardened parsing only)
+    TooShort,
+}
+
+impl SkipReason {
+    pub fn name(self) -> &'static str {
+        match self {
+            SkipReason::NoName => "no_name",
+            SkipReason::NoCoordinates => "no_coordinates",
+            SkipReason::FilteredOut => "filtered_out",
+            SkipReason::TooShort => "too_short",
+        }
+    }
+}
+
+/// One NDJSON line of the skipped-ways log
+#[derive(Debug, Serialize)]
+pub struct SkippedWay<'a> {
+    pub way_id: i64,
+    pub reason: SkipReason,
+    pub name: Option<&'a str>,
+    /// The feature's type key (`highway`, `railway`, ...) and the way's value for it
+    pub key: &'static str,
+    pub value: Option<&'a str>,
+    /// First node, to locate ways whose coordinates were never loaded
+    pub first_node: Option<i64>,
+    pub num_nodes: usize,
+}
+
+/// NDJSON sidecar recording every candidate way that was skipped, with the reason
+pub struct SkipLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    counts: BTreeMap<SkipReason, u64>,
+    // Writes happen inside PBF callbacks, so the first failure is kept for `finish`
+    error: Option<std::io::Error>,
+}
+
+impl SkipLog {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path).map_err(io_error(path))?;
+        Ok(SkipLog { path: path.to_path_buf(), writer: BufWriter::new(file), counts: BTreeMap::new(), error: None })
+    }
+
+    pub fn record(&mut self, way: &SkippedWay) {
+        *self.counts.entry(way.reason).or_default() += 1;
+        if self.error.is_some() {
+            return;
+        }
+        let line = serde_json::to_string(way).expect("skipped way serializes");
+        if let Err(err) = writeln!(self.writer, "{}", line) {
+            self.error = Some(err);
+        }
+    }
+
+    /// Skipped ways per reason so far
+    pub fn counts(&self) -> &BTreeMap<SkipReason, u64> {
+        &self.counts
+    }
+
+    /// Flush the file, reporting any write error from `record`
+    pub fn finish(mut self) -> Result<BTreeMap<SkipReason, u64>> {
+        if let Some(err) = self.error.take() {
+            return Err(io_error(&self.path)(err));
+        }
+        self.writer.flush().map_err(io_error(&self.path))?;
+        Ok(self.counts)
+    }
+}
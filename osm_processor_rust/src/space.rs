@@ -0,0 +1,51 @@
+use polars::prelude::*;
+use std::path::Path;
+
+use crate::error::{ProcessorError, Result};
+
+/// Free space kept on top of an estimate, for parquet metadata and whatever else is writing there
+const HEADROOM_BYTES: u64 = 16 << 20;
+
+/// Upper estimate of a frame's size once written: fixed-width columns by row count and type
+/// width, strings and binary by their content plus a length prefix per row. Compression only
+/// makes the real file smaller.
+pub(crate) fn estimate_output_bytes(df: &DataFrame) -> u64 {
+    let rows = df.height() as u64;
+    df.get_columns()
+        .iter()
+        .map(|column| match column.dtype() {
+            DataType::Boolean => rows / 8 + 1,
+            DataType::Int8 | DataType::UInt8 => rows,
+            DataType::Int16 | DataType::UInt16 => rows * 2,
+            DataType::Int32 | DataType::UInt32 | DataType::Float32 | DataType::Date => rows * 4,
+            DataType::Int64 | DataType::UInt64 | DataType::Float64 | DataType::Datetime(..) | DataType::Duration(_) => {
+                rows * 8
+            }
+            DataType::String => column
+                .str()
+                .map(|values| values.into_iter().map(|v| v.map_or(0, str::len) as u64 + 4).sum())
+                .unwrap_or(0),
+            DataType::Binary => column
+                .binary()
+                .map(|values| values.into_iter().map(|v| v.map_or(0, <[u8]>::len) as u64 + 4).sum())
+                .unwrap_or(0),
+            _ => column.estimated_size() as u64,
+        })
+        .sum()
+}
+
+/// Fail before writing `needed` bytes to `path` (a file, or a directory for spill files) when its
+/// filesystem has less free space than that plus a margin. Filesystems that cannot report free
+/// space are not checked.
+pub(crate) fn ensure_free_space(path: &Path, needed: u64) -> Result<()> {
+    let dir = if path.is_dir() { path } else { path.parent().unwrap_or(Path::new(".")) };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let Ok(available) = fs2::available_space(dir) else {
+        return Ok(());
+    };
+    let required = needed + needed / 10 + HEADROOM_BYTES;
+    if available < required {
+        return Err(ProcessorError::InsufficientSpace { path: path.to_path_buf(), needed: required, available });
+    }
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use geo::{Coord, LineString, MultiPolygon, Polygon};
+use polars::prelude::*;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use osm_processor_rust::regions::{Region, RegionIndex};
+use osm_processor_rust::stats::load_streets;
+use osm_processor_rust::write_table;
+
+/// A zone polygon and the requested attributes, as strings (null when missing)
+struct Zone {
+    geometry: MultiPolygon<f64>,
+    attributes: Vec<Option<String>>,
+}
+
+fn ring(value: &Value) -> Option<LineString<f64>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|position| {
+            let position = position.as_array()?;
+            Some(Coord { x: position.first()?.as_f64()?, y: position.get(1)?.as_f64()? })
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(LineString::new)
+}
+
+fn polygon(value: &Value) -> Option<Polygon<f64>> {
+    let mut rings = value.as_array()?.iter().map(ring);
+    let exterior = rings.next()??;
+    let interiors = rings.collect::<Option<Vec<_>>>()?;
+    Some(Polygon::new(exterior, interiors))
+}
+
+/// Polygon or MultiPolygon GeoJSON geometry; other types (and malformed ones) are `None`
+fn geojson_polygons(geometry: &Value) -> Option<MultiPolygon<f64>> {
+    let coordinates = geometry.get("coordinates")?;
+    match geometry.get("type")?.as_str()? {
+        "Polygon" => Some(MultiPolygon::new(vec![polygon(coordinates)?])),
+        "MultiPolygon" => coordinates
+            .as_array()?
+            .iter()
+            .map(polygon)
+            .collect::<Option<Vec<_>>>()
+            .map(MultiPolygon::new),
+        _ => None,
+    }
+}
+
+/// Property as text: strings as they are, other JSON values in their JSON form
+fn property_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Polygon features of a GeoJSON FeatureCollection with the `keys` properties
+fn read_zones(path: &Path, keys: &[String]) -> Result<Vec<Zone>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let collection: Value = serde_json::from_str(&text).with_context(|| format!("Invalid GeoJSON in {}", path.display()))?;
+    let features = collection
+        .get("features")
+        .and_then(Value::as_array)
+        .with_context(|| format!("{} is not a GeoJSON FeatureCollection", path.display()))?;
+
+    let mut zones = Vec::new();
+    let mut skipped = 0;
+    for feature in features {
+        let Some(geometry) = feature.get("geometry").and_then(geojson_polygons) else {
+            skipped += 1;
+            continue;
+        };
+        let properties = feature.get("properties");
+        let attributes = keys
+            .iter()
+            .map(|key| properties.and_then(|p| p.get(key)).and_then(property_text))
+            .collect();
+        zones.push(Zone { geometry, attributes });
+    }
+    if skipped > 0 {
+        eprintln!("Warning: skipped {} features without a Polygon/MultiPolygon geometry", skipped);
+    }
+    if zones.is_empty() {
+        anyhow::bail!("No polygon features in {}", path.display());
+    }
+    Ok(zones)
+}
+
+/// `spatial-join <streets.parquet...> <zones.geojson> --key KEY[,KEY...] -o <out.csv|out.parquet>`
+///
+/// Adds each `--key` property of the zone containing a street's representative point as a
+/// column (null outside every zone; the first zone in file order where zones overlap).
+pub fn run_spatial_join(paths: &[PathBuf], zones_path: &Path, keys: &[String], output_path: &Path) -> Result<()> {
+    let mut df = load_streets(paths)?;
+    let zones = read_zones(zones_path, keys)?;
+    println!("Loaded {} zones from {}", zones.len(), zones_path.display());
+
+    let (attributes, geometries): (Vec<_>, Vec<_>) = zones.into_iter().map(|z| (z.attributes, z.geometry)).unzip();
+    let index = RegionIndex::new(
+        geometries
+            .into_iter()
+            .enumerate()
+            .map(|(i, geometry)| Region { name: i.to_string(), geometry })
+            .collect(),
+    );
+
+    let lats = df.column("lat")?.f64()?;
+    let lons = df.column("lon")?.f64()?;
+    let located: Vec<Option<usize>> = lats
+        .into_iter()
+        .zip(lons)
+        .map(|(lat, lon)| index.locate_position((lat?, lon?)))
+        .collect();
+    let inside = located.iter().filter(|zone| zone.is_some()).count();
+
+    for (k, key) in keys.iter().enumerate() {
+        let values: Vec<Option<&str>> = located
+            .iter()
+            .map(|zone| zone.and_then(|z| attributes[z][k].as_deref()))
+            .collect();
+        df.with_column(Series::new(key, values))?;
+    }
+
+    write_table(&mut df, output_path)?;
+    println!(
+        "Joined {} of {} streets to a zone; saved to {}",
+        inside,
+        df.height(),
+        output_path.display()
+    );
+    Ok(())
+}
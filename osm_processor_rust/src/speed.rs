@@ -0,0 +1,100 @@
+use crate::StreetSegment;
+use crate::feature::ExtraValue;
+
+const KPH_PER_MPH: f64 = 1.609344;
+const KPH_PER_KNOT: f64 = 1.852;
+
+/// Where a segment's speed limit came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SpeedSource {
+    Tagged,
+    Implicit,
+}
+
+/// Parse a `maxspeed` value into km/h. Handles bare numbers (km/h), "mph", "knots",
+/// "km/h" suffixes and `;`-separated lists (first value wins). Non-numeric values such
+/// as "none", "signals" or "variable" yield None.
+pub fn parse_maxspeed_kph(value: &str) -> Option<f64> {
+    let first = value.split(';').next()?.trim();
+
+    let (number, factor) = if let Some(n) = first.strip_suffix("mph") {
+        (n, KPH_PER_MPH)
+    } else if let Some(n) = first.strip_suffix("knots") {
+        (n, KPH_PER_KNOT)
+    } else if let Some(n) = first
+        .strip_suffix("km/h")
+        .or_else(|| first.strip_suffix("kmh"))
+        .or_else(|| first.strip_suffix("kph"))
+    {
+        (n, 1.0)
+    } else {
+        (first, 1.0)
+    };
+
+    let speed: f64 = number.trim().parse().ok()?;
+    (speed > 0.0).then_some(speed * factor)
+}
+
+/// Default speed limit in km/h for a highway type, using typical US statutory limits
+pub fn implicit_maxspeed_kph(highway_type: &str) -> Option<f64> {
+    let mph = match highway_type {
+        "motorway" => 65.0,
+        "trunk" => 55.0,
+        "primary" => 45.0,
+        "secondary" => 40.0,
+        "tertiary" => 35.0,
+        "motorway_link" => 45.0,
+        "trunk_link" | "primary_link" => 35.0,
+        "secondary_link" | "tertiary_link" => 30.0,
+        "unclassified" => 30.0,
+        "residential" => 25.0,
+        "living_street" | "service" => 15.0,
+        _ => return None,
+    };
+    Some(mph * KPH_PER_MPH)
+}
+
+/// Effective speed limit for a segment: tagged `maxspeed` if parseable, else the implicit default
+fn segment_speed(seg: &StreetSegment) -> Option<(f64, SpeedSource)> {
+    seg.tags
+        .get("maxspeed")
+        .and_then(parse_maxspeed_kph)
+        .map(|kph| (kph, SpeedSource::Tagged))
+        .or_else(|| implicit_maxspeed_kph(&seg.highway_type).map(|kph| (kph, SpeedSource::Implicit)))
+}
+
+/// Per-street speed columns: length-weighted mean, min and max limits, and the share of
+/// length whose limit is explicitly tagged
+pub fn speed_columns(segs: &[&StreetSegment]) -> Vec<(&'static str, ExtraValue)> {
+    let speeds: Vec<(f64, SpeedSource, f64)> = segs
+        .iter()
+        .filter_map(|s| segment_speed(s).map(|(kph, source)| (kph, source, s.length_km)))
+        .collect();
+
+    let total_km: f64 = segs.iter().map(|s| s.length_km).sum();
+    let known_km: f64 = speeds.iter().map(|&(_, _, km)| km).sum();
+    let tagged_km: f64 = speeds
+        .iter()
+        .filter(|&&(_, source, _)| source == SpeedSource::Tagged)
+        .map(|&(_, _, km)| km)
+        .sum();
+
+    let mean_kph = if known_km > 0.0 {
+        Some(speeds.iter().map(|&(kph, _, km)| kph * km).sum::<f64>() / known_km)
+    } else if !speeds.is_empty() {
+        // Zero-length segments only: plain mean
+        Some(speeds.iter().map(|&(kph, _, _)| kph).sum::<f64>() / speeds.len() as f64)
+    } else {
+        None
+    };
+    let min_kph = speeds.iter().map(|&(kph, _, _)| kph).reduce(f64::min);
+    let max_kph = speeds.iter().map(|&(kph, _, _)| kph).reduce(f64::max);
+
+    vec![
+        ("maxspeed_kph", ExtraValue::Float(mean_kph)),
+        ("maxspeed_mph", ExtraValue::Float(mean_kph.map(|kph| kph / KPH_PER_MPH))),
+        ("maxspeed_min_kph", ExtraValue::Float(min_kph)),
+        ("maxspeed_max_kph", ExtraValue::Float(max_kph)),
+        ("maxspeed_tagged_share", ExtraValue::Float(Some(if total_km > 0.0 { tagged_km / total_km } else { 0.0 }))),
+    ]
+}
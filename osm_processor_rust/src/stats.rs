@@ -0,0 +1,311 @@
+use polars::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::error::{io_error, ProcessorError, Result};
+use crate::write_parquet;
+
+/// Segment-count histogram buckets: (label, min, max inclusive)
+const SEGMENT_BUCKETS: [(&str, u32, u32); 9] = [
+    ("1", 1, 1),
+    ("2", 2, 2),
+    ("3", 3, 3),
+    ("4", 4, 4),
+    ("5", 5, 5),
+    ("6-10", 6, 10),
+    ("11-20", 11, 20),
+    ("21-50", 21, 50),
+    ("51+", 51, u32::MAX),
+];
+
+/// Lazily scan one or more street parquet files as a single frame
+pub fn scan_streets(paths: &[PathBuf]) -> Result<LazyFrame> {
+    let frames = paths
+        .iter()
+        .map(|path| {
+            LazyFrame::scan_parquet(path, ScanArgsParquet::default())
+                .map_err(|source| ProcessorError::InputRead { path: path.clone(), source })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(concat(frames, UnionArgs::default())?)
+}
+
+/// Read one or more street parquet files into a single DataFrame
+pub fn load_streets(paths: &[PathBuf]) -> Result<DataFrame> {
+    Ok(scan_streets(paths)?.collect()?)
+}
+
+/// Distinct values of `column` across the inputs, sorted, for `--stratify-by`; nulls are left out
+pub fn stratum_values(paths: &[PathBuf], column: &str) -> Result<Vec<String>> {
+    let df = scan_streets(paths)?.select([col(column).cast(DataType::String)]).collect()?;
+    let values: BTreeSet<String> = df.column(column)?.str()?.into_iter().flatten().map(str::to_string).collect();
+    Ok(values.into_iter().collect())
+}
+
+/// The streets whose `column` is `value`
+pub fn load_stratum(paths: &[PathBuf], column: &str, value: &str) -> Result<DataFrame> {
+    Ok(scan_streets(paths)?.filter(col(column).cast(DataType::String).eq(lit(value))).collect()?)
+}
+
+/// `<dir>/<column>=<value>` (hive-style, so the per-stratum outputs read back as one dataset),
+/// with characters that are unsafe in file names replaced by `_`
+pub fn stratum_dir(dir: &Path, column: &str, value: &str) -> PathBuf {
+    let value: String =
+        value.chars().map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' }).collect();
+    dir.join(format!("{}={}", column, value))
+}
+
+/// Name of the type column (highway_type, railway_type, ...) if the frame has one
+pub fn type_column(df: &DataFrame) -> Option<String> {
+    df.get_column_names()
+        .into_iter()
+        .find(|name| name.ends_with("_type"))
+        .map(str::to_string)
+}
+
+/// Summary tables computed over a streets DataFrame
+pub struct StreetStats {
+    pub coverage: DataFrame,
+    pub coverage_by_state: DataFrame,
+    pub top_names: DataFrame,
+    pub top_names_by_state: DataFrame,
+    pub type_distribution: Option<DataFrame>,
+    pub segment_histogram: DataFrame,
+}
+
+impl StreetStats {
+    pub fn compute(df: &DataFrame, top_n: usize) -> Result<Self> {
+        let names: Vec<&str> = df.column("street_name")?.str()?.into_iter().map(|v| v.unwrap_or("")).collect();
+        let states: Vec<&str> = df.column("state")?.str()?.into_iter().map(|v| v.unwrap_or("")).collect();
+        let lengths: Vec<f64> = df.column("length_km")?.f64()?.into_iter().map(|v| v.unwrap_or(0.0)).collect();
+        let segments: Vec<u32> = df
+            .column("num_segments")?
+            .cast(&DataType::UInt32)?
+            .u32()?
+            .into_iter()
+            .map(|v| v.unwrap_or(0))
+            .collect();
+
+        Ok(StreetStats {
+            coverage: coverage(&names, &states, &lengths, &segments)?,
+            coverage_by_state: coverage_by_state(&names, &states, &lengths, &segments)?,
+            top_names: top_names(&names, top_n)?,
+            top_names_by_state: top_names_by_state(&names, &states, top_n)?,
+            type_distribution: match type_column(df) {
+                Some(column) => Some(type_distribution(df, &column, &lengths)?),
+                None => None,
+            },
+            segment_histogram: segment_histogram(&segments)?,
+        })
+    }
+
+    /// Write each table as `<dir>/<table>.parquet`
+    pub fn write_to_dir(&mut self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir).map_err(io_error(dir))?;
+        write_parquet(&mut self.coverage, &dir.join("coverage.parquet"))?;
+        write_parquet(&mut self.coverage_by_state, &dir.join("coverage_by_state.parquet"))?;
+        write_parquet(&mut self.top_names, &dir.join("top_names.parquet"))?;
+        write_parquet(&mut self.top_names_by_state, &dir.join("top_names_by_state.parquet"))?;
+        if let Some(types) = self.type_distribution.as_mut() {
+            write_parquet(types, &dir.join("type_distribution.parquet"))?;
+        }
+        write_parquet(&mut self.segment_histogram, &dir.join("segment_histogram.parquet"))?;
+        Ok(())
+    }
+}
+
+fn coverage(names: &[&str], states: &[&str], lengths: &[f64], segments: &[u32]) -> Result<DataFrame> {
+    let distinct_names: HashSet<&str> = names.iter().copied().collect();
+    let distinct_states: HashSet<&str> = states.iter().copied().collect();
+
+    let df = DataFrame::new(vec![
+        Series::new("streets", [names.len() as u64]),
+        Series::new("distinct_names", [distinct_names.len() as u64]),
+        Series::new("states", [distinct_states.len() as u64]),
+        Series::new("multi_segment_streets", [segments.iter().filter(|&&n| n > 1).count() as u64]),
+        Series::new("total_segments", [segments.iter().map(|&n| n as u64).sum::<u64>()]),
+        Series::new("total_length_km", [lengths.iter().sum::<f64>()]),
+    ])?;
+    Ok(df)
+}
+
+fn coverage_by_state(names: &[&str], states: &[&str], lengths: &[f64], segments: &[u32]) -> Result<DataFrame> {
+    // state -> (streets, distinct names, total km, total segments)
+    let mut by_state: HashMap<&str, (u64, HashSet<&str>, f64, u64)> = HashMap::new();
+    for i in 0..names.len() {
+        let entry = by_state.entry(states[i]).or_default();
+        entry.0 += 1;
+        entry.1.insert(names[i]);
+        entry.2 += lengths[i];
+        entry.3 += segments[i] as u64;
+    }
+
+    let mut rows: Vec<_> = by_state.into_iter().collect();
+    rows.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+    let df = DataFrame::new(vec![
+        Series::new("state", rows.iter().map(|r| r.0).collect::<Vec<_>>()),
+        Series::new("streets", rows.iter().map(|r| r.1 .0).collect::<Vec<_>>()),
+        Series::new("distinct_names", rows.iter().map(|r| r.1 .1.len() as u64).collect::<Vec<_>>()),
+        Series::new("total_length_km", rows.iter().map(|r| r.1 .2).collect::<Vec<_>>()),
+        Series::new(
+            "mean_segments",
+            rows.iter().map(|r| r.1 .3 as f64 / r.1 .0 as f64).collect::<Vec<_>>(),
+        ),
+    ])?;
+    Ok(df)
+}
+
+/// Count occurrences and return the `top_n` most common, ties broken alphabetically
+fn ranked_counts<'a>(values: impl Iterator<Item = &'a str>, top_n: usize) -> Vec<(&'a str, u64)> {
+    let mut counts: HashMap<&str, u64> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    let mut ranked: Vec<_> = counts.into_iter().collect();
+    ranked.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    ranked.truncate(top_n);
+    ranked
+}
+
+fn top_names(names: &[&str], top_n: usize) -> Result<DataFrame> {
+    let ranked = ranked_counts(names.iter().copied(), top_n);
+    let df = DataFrame::new(vec![
+        Series::new("street_name", ranked.iter().map(|r| r.0).collect::<Vec<_>>()),
+        Series::new("count", ranked.iter().map(|r| r.1).collect::<Vec<_>>()),
+    ])?;
+    Ok(df)
+}
+
+fn top_names_by_state(names: &[&str], states: &[&str], top_n: usize) -> Result<DataFrame> {
+    let mut by_state: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (&state, &name) in states.iter().zip(names) {
+        by_state.entry(state).or_default().push(name);
+    }
+    let mut state_list: Vec<&str> = by_state.keys().copied().collect();
+    state_list.sort_unstable();
+
+    let mut out_states = Vec::new();
+    let mut out_ranks = Vec::new();
+    let mut out_names = Vec::new();
+    let mut out_counts = Vec::new();
+    for state in state_list {
+        for (rank, (name, count)) in ranked_counts(by_state[state].iter().copied(), top_n).into_iter().enumerate() {
+            out_states.push(state);
+            out_ranks.push(rank as u32 + 1);
+            out_names.push(name);
+            out_counts.push(count);
+        }
+    }
+
+    let df = DataFrame::new(vec![
+        Series::new("state", out_states),
+        Series::new("rank", out_ranks),
+        Series::new("street_name", out_names),
+        Series::new("count", out_counts),
+    ])?;
+    Ok(df)
+}
+
+fn type_distribution(df: &DataFrame, column: &str, lengths: &[f64]) -> Result<DataFrame> {
+    let types: Vec<&str> = df.column(column)?.str()?.into_iter().map(|v| v.unwrap_or("")).collect();
+
+    let mut by_type: HashMap<&str, (u64, f64)> = HashMap::new();
+    for (&t, &km) in types.iter().zip(lengths) {
+        let entry = by_type.entry(t).or_default();
+        entry.0 += 1;
+        entry.1 += km;
+    }
+    let mut rows: Vec<_> = by_type.into_iter().collect();
+    rows.sort_unstable_by(|a, b| b.1 .0.cmp(&a.1 .0).then_with(|| a.0.cmp(b.0)));
+
+    let total = types.len().max(1) as f64;
+    let out = DataFrame::new(vec![
+        Series::new(column, rows.iter().map(|r| r.0).collect::<Vec<_>>()),
+        Series::new("count", rows.iter().map(|r| r.1 .0).collect::<Vec<_>>()),
+        Series::new("share", rows.iter().map(|r| r.1 .0 as f64 / total).collect::<Vec<_>>()),
+        Series::new("total_length_km", rows.iter().map(|r| r.1 .1).collect::<Vec<_>>()),
+    ])?;
+    Ok(out)
+}
+
+fn segment_histogram(segments: &[u32]) -> Result<DataFrame> {
+    let counts: Vec<u64> = SEGMENT_BUCKETS
+        .iter()
+        .map(|&(_, lo, hi)| segments.iter().filter(|&&n| n >= lo && n <= hi).count() as u64)
+        .collect();
+
+    let df = DataFrame::new(vec![
+        Series::new("num_segments", SEGMENT_BUCKETS.iter().map(|b| b.0).collect::<Vec<_>>()),
+        Series::new("streets", counts),
+    ])?;
+    Ok(df)
+}
+
+/// Short summary printed at the end of a processing run
+pub fn print_run_summary(df: &DataFrame) -> Result<()> {
+    let stats = StreetStats::compute(df, 10)?;
+
+    println!("\n{}", "=".repeat(70));
+    println!("SUMMARY STATISTICS");
+    println!("{}", "=".repeat(70));
+    println!("{}", stats.coverage);
+    println!("\nTop 10 street names:");
+    println!("{}", stats.top_names);
+    Ok(())
+}
+
+/// `stats <streets.parquet...> [--top N] [--stratify-by COLUMN] [--output-dir DIR]`
+///
+/// With `stratify_by`, the tables are computed separately for each value of that column (e.g.
+/// each highway_type) and written to `<dir>/<column>=<value>/`.
+pub fn run_stats(paths: &[PathBuf], top_n: usize, stratify_by: Option<&str>, output_dir: Option<&Path>) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("STREET DATASET STATISTICS");
+    println!("{}", "=".repeat(70));
+    for path in paths {
+        println!("Input file:  {}", path.display());
+    }
+    println!("{}", "=".repeat(70));
+
+    let Some(column) = stratify_by else {
+        return print_stats(&load_streets(paths)?, top_n, output_dir);
+    };
+    for value in stratum_values(paths, column)? {
+        println!("\n{}", "=".repeat(70));
+        println!("{} = {}", column, value);
+        println!("{}", "=".repeat(70));
+        let df = load_stratum(paths, column, &value)?;
+        let dir = output_dir.map(|dir| stratum_dir(dir, column, &value));
+        print_stats(&df, top_n, dir.as_deref())?;
+    }
+    Ok(())
+}
+
+/// Print the stats tables of `df`, saving them to `output_dir` if given
+fn print_stats(df: &DataFrame, top_n: usize, output_dir: Option<&Path>) -> Result<()> {
+    let mut stats = StreetStats::compute(df, top_n)?;
+
+    println!("\nCoverage:");
+    println!("{}", stats.coverage);
+    println!("\nCoverage by state:");
+    println!("{}", stats.coverage_by_state);
+    println!("\nTop {} street names:", top_n);
+    println!("{}", stats.top_names);
+    println!("\nTop {} street names by state:", top_n);
+    println!("{}", stats.top_names_by_state);
+    if let Some(types) = &stats.type_distribution {
+        println!("\nType distribution:");
+        println!("{}", types);
+    }
+    println!("\nSegment-count histogram:");
+    println!("{}", stats.segment_histogram);
+
+    if let Some(dir) = output_dir {
+        println!("\nSaving tables to: {}", dir.display());
+        stats.write_to_dir(dir)?;
+    }
+
+    Ok(())
+}
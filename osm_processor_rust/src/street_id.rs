@@ -0,0 +1,165 @@
+use crate::error::{ProcessorError, Result};
+use crate::StreetSegment;
+
+/// Grid size for [`StreetIdMode::Point`]: 0.001° is roughly 100 m
+const POINT_QUANTUM_DEG: f64 = 0.001;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// What besides (state, normalized name) identifies a street in its `street_id`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StreetIdMode {
+    /// The sorted member way IDs: exact, but changes when a way is split or replaced
+    #[default]
+    Ways,
+    /// The representative point snapped to a ~100 m grid: survives way edits, but changes if
+    /// the point moves across a grid line
+    Point,
+}
+
+impl StreetIdMode {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "ways" => Ok(StreetIdMode::Ways),
+            "point" => Ok(StreetIdMode::Point),
+            other => Err(ProcessorError::InvalidConfig(format!(
+                "Unknown street id mode: {} (expected ways or point)",
+                other
+            ))),
+        }
+    }
+
+    /// The `--street-id` value for this mode
+    pub fn name(self) -> &'static str {
+        match self {
+            StreetIdMode::Ways => "ways",
+            StreetIdMode::Point => "point",
+        }
+    }
+}
+
+/// FNV-1a, chosen over `DefaultHasher` because its output is fixed across Rust releases
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// FNV-1a hash of `bytes`, stable across runs and Rust releases
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1a(FNV_OFFSET);
+    hasher.write(bytes);
+    hasher.0
+}
+
+/// Lowercase with runs of whitespace collapsed, so spacing/case edits keep the ID
+pub fn normalize_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Stable 16-hex-digit ID for a street from its state, name and either its member ways or
+/// its representative point `(lat, lon)`
+pub fn street_id(mode: StreetIdMode, state: &str, name: &str, segs: &[&StreetSegment], rep: (f64, f64)) -> String {
+    let mut hasher = Fnv1a(FNV_OFFSET);
+    hasher.write(state.as_bytes());
+    hasher.write(&[0]);
+    hasher.write(normalize_name(name).as_bytes());
+    hasher.write(&[0]);
+
+    match mode {
+        StreetIdMode::Ways => {
+            let mut way_ids: Vec<i64> = segs.iter().map(|s| s.way_id).collect();
+            way_ids.sort_unstable();
+            way_ids.dedup();
+            for id in way_ids {
+                hasher.write(&id.to_le_bytes());
+            }
+        }
+        StreetIdMode::Point => {
+            for value in [rep.0, rep.1] {
+                hasher.write(&((value / POINT_QUANTUM_DEG).round() as i64).to_le_bytes());
+            }
+        }
+    }
+
+    format!("{:016x}", hasher.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::Tags;
+
+    fn segment(way_id: i64) -> StreetSegment {
+        StreetSegment {
+            street_name: "Main Street".to_string(),
+            local_name: None,
+            state: "delaware".to_string(),
+            region: None,
+            direction: None,
+            relation_id: None,
+            way_id,
+            node_ids: vec![1, 2],
+            coords: vec![(39.0, -75.0), (39.001, -75.0)],
+            highway_type: "residential".to_string(),
+            tags: Tags::default(),
+            length_km: 0.1,
+            sanitized: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn normalize_name_folds_case_and_spacing() {
+        assert_eq!(normalize_name("  Main \t Street "), "main street");
+        assert_eq!(normalize_name("ÉCOLE  Road"), "école road");
+    }
+
+    #[test]
+    fn ways_id_is_pinned() {
+        // Golden value: changing it breaks IDs joined across runs
+        let segs = [segment(3), segment(1), segment(2), segment(1)];
+        let refs: Vec<&StreetSegment> = segs.iter().collect();
+        assert_eq!(street_id(StreetIdMode::Ways, "delaware", "Main Street", &refs, (0.0, 0.0)), "5368b1afdef4de1c");
+    }
+
+    #[test]
+    fn point_id_is_pinned() {
+        let id = street_id(StreetIdMode::Point, "delaware", "Main Street", &[], (39.1582, -75.5244));
+        assert_eq!(id, "ad705695a73e54c1");
+    }
+
+    #[test]
+    fn ways_id_ignores_way_order_and_name_spacing() {
+        let (a, b) = ([segment(1), segment(2)], [segment(2), segment(1)]);
+        let a: Vec<&StreetSegment> = a.iter().collect();
+        let b: Vec<&StreetSegment> = b.iter().collect();
+        assert_eq!(
+            street_id(StreetIdMode::Ways, "delaware", "Main Street", &a, (0.0, 0.0)),
+            street_id(StreetIdMode::Ways, "delaware", "main  STREET", &b, (0.0, 0.0))
+        );
+        assert_ne!(
+            street_id(StreetIdMode::Ways, "delaware", "Main Street", &a, (0.0, 0.0)),
+            street_id(StreetIdMode::Ways, "delaware", "Main Street", &a[..1], (0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn point_id_is_stable_within_a_grid_cell() {
+        let id = |rep| street_id(StreetIdMode::Point, "delaware", "Main Street", &[], rep);
+        assert_eq!(id((39.15821, -75.52441)), id((39.15819, -75.52439)));
+        assert_ne!(id((39.1582, -75.5244)), id((39.1592, -75.5244)));
+    }
+
+    #[test]
+    fn mode_parses() {
+        assert_eq!(StreetIdMode::parse("ways").unwrap(), StreetIdMode::Ways);
+        assert_eq!(StreetIdMode::parse("point").unwrap(), StreetIdMode::Point);
+        assert!(StreetIdMode::parse("hash").is_err());
+    }
+}
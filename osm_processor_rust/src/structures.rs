@@ -0,0 +1,242 @@
+use osmpbf::{Element, ElementReader};
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::{pbf_error, Result};
+use crate::grouping::find_connected_components;
+use crate::node_set::NodeIdSet;
+use crate::tags::Tags;
+use crate::{
+    default_output_path, load_node_coords, polyline_length_km, round_coord, write_parquet,
+    ConsoleProgress, StreetSegment,
+};
+
+/// A way tagged `bridge=*` or `tunnel=*`
+struct StructureWay {
+    id: i64,
+    refs: Vec<i64>,
+    kind: &'static str,
+    tags: HashMap<String, String>,
+}
+
+/// Names of the named non-structure ways ending at each node
+type RoadNames = HashMap<i64, Vec<String>>;
+
+/// A named bridge or tunnel (one or more connected ways sharing a structure name)
+#[derive(Debug)]
+struct Structure {
+    kind: &'static str,
+    structure_name: String,
+    name_source: &'static str,
+    road_name: Option<String>,
+    way_type: String,
+    lat: f64,
+    lon: f64,
+    num_ways: usize,
+    length_km: f64,
+}
+
+/// "bridge" or "tunnel" if the way is tagged as one
+fn structure_kind(tags: &HashMap<String, String>) -> Option<&'static str> {
+    let is_set = |key: &str| tags.get(key).is_some_and(|v| v != "no");
+    if is_set("bridge") {
+        Some("bridge")
+    } else if is_set("tunnel") {
+        Some("tunnel")
+    } else {
+        None
+    }
+}
+
+/// The structure's own name and where it came from: `bridge:name`/`tunnel:name`, or a
+/// plain `name` that no adjoining road shares (i.e. it names the structure, not the road).
+/// `road_names` holds only non-structure ways, so a structure split into several ways that all
+/// carry its name still counts as named.
+fn structure_name(
+    way: &StructureWay,
+    road_names: &RoadNames,
+) -> Option<(String, &'static str)> {
+    let name_key = if way.kind == "bridge" { "bridge:name" } else { "tunnel:name" };
+    if let Some(name) = way.tags.get(name_key) {
+        return Some((name.clone(), name_key));
+    }
+
+    let name = way.tags.get("name")?;
+    let endpoints = [way.refs[0], way.refs[way.refs.len() - 1]];
+    let shared_with_road = endpoints.iter().any(|node| {
+        road_names.get(node).is_some_and(|names| names.contains(name))
+    });
+
+    (!shared_with_road).then(|| (name.clone(), "name"))
+}
+
+/// Pass 1: collect bridge/tunnel ways and the names of the named non-structure ways at each
+/// way endpoint
+fn collect_structure_ways(pbf_path: &Path) -> Result<(Vec<StructureWay>, RoadNames)> {
+    println!("Pass 1: Collecting bridge and tunnel ways...");
+    let reader = ElementReader::from_path(pbf_path)
+        .map_err(pbf_error(pbf_path))?;
+
+    let mut structure_ways = Vec::new();
+    let mut road_names = RoadNames::new();
+
+    reader.for_each(|element| {
+        if let Element::Way(way) = element {
+            let tags: HashMap<String, String> = way
+                .tags()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            let refs: Vec<i64> = way.refs().collect();
+            if refs.len() < 2 {
+                return;
+            }
+
+            if let Some(kind) = structure_kind(&tags) {
+                structure_ways.push(StructureWay { id: way.id(), refs, kind, tags });
+            } else if let Some(name) = tags.get("name") {
+                road_names.entry(refs[0]).or_default().push(name.clone());
+                road_names.entry(refs[refs.len() - 1]).or_default().push(name.clone());
+            }
+        }
+    })
+    .map_err(pbf_error(pbf_path))?;
+
+    println!("  Found {} bridge/tunnel ways", structure_ways.len());
+    Ok((structure_ways, road_names))
+}
+
+/// Extract named bridges and tunnels, grouping connected ways with the same structure name
+fn extract_structures(pbf_path: &Path, state_name: &str) -> Result<Vec<Structure>> {
+    let (structure_ways, road_names) = collect_structure_ways(pbf_path)?;
+
+    println!("Pass 2: Loading structure node coordinates...");
+    let wanted_nodes: NodeIdSet = structure_ways.iter().flat_map(|w| w.refs.iter().copied()).collect();
+    let node_coords = load_node_coords(pbf_path, &wanted_nodes, &ConsoleProgress::default())?;
+
+    // Reuse the street segment machinery: one segment per named structure way,
+    // grouped by (kind, structure name) and connectivity
+    let mut by_name: HashMap<(&'static str, String), Vec<(StreetSegment, &'static str)>> = HashMap::new();
+    for way in &structure_ways {
+        let Some((name, source)) = structure_name(way, &road_names) else { continue };
+        let coords: Vec<(f64, f64)> = way
+            .refs
+            .iter()
+            .filter_map(|id| node_coords.get(id).copied())
+            .collect();
+        if coords.is_empty() {
+            continue;
+        }
+
+        let way_type = way
+            .tags
+            .get("highway")
+            .or_else(|| way.tags.get("railway"))
+            .or_else(|| way.tags.get("waterway"))
+            .cloned()
+            .unwrap_or_default();
+
+        let segment = StreetSegment {
+            street_name: name.clone(),
+            local_name: None,
+            state: state_name.to_string(),
+            region: None,
+            direction: None,
+            relation_id: None,
+            way_id: way.id,
+            node_ids: way.refs.clone(),
+            length_km: polyline_length_km(&coords),
+            coords,
+            highway_type: way_type,
+            tags: Tags::from_pairs(&way.tags),
+            sanitized: Vec::new(),
+        };
+        by_name.entry((way.kind, name)).or_default().push((segment, source));
+    }
+
+    let mut structures = Vec::new();
+    for ((kind, structure_name), entries) in by_name {
+        let (segments, sources): (Vec<StreetSegment>, Vec<&'static str>) = entries.into_iter().unzip();
+
+        for component in find_connected_components(&segments) {
+            let segs: Vec<&StreetSegment> = component.iter().map(|&i| &segments[i]).collect();
+            let (lat, lon) = segs[0].rep_coords();
+
+            // The road carried by the structure, when its name differs from the structure's
+            let road_name = segs
+                .iter()
+                .filter_map(|s| s.tags.get("name"))
+                .find(|n| *n != structure_name)
+                .map(str::to_string);
+
+            structures.push(Structure {
+                kind,
+                structure_name: structure_name.clone(),
+                name_source: sources[component[0]],
+                road_name,
+                way_type: segs[0].highway_type.clone(),
+                lat,
+                lon,
+                num_ways: segs.len(),
+                length_km: segs.iter().map(|s| s.length_km).sum(),
+            });
+        }
+    }
+
+    println!("  Found {} named structures", structures.len());
+    Ok(structures)
+}
+
+fn structures_to_dataframe(
+    structures: &[Structure],
+    state_name: &str,
+    coord_precision: Option<u32>,
+) -> Result<DataFrame> {
+    let df = DataFrame::new(vec![
+        Series::new("structure_name", structures.iter().map(|s| s.structure_name.as_str()).collect::<Vec<_>>()),
+        Series::new("state", vec![state_name; structures.len()]),
+        Series::new("kind", structures.iter().map(|s| s.kind).collect::<Vec<_>>()),
+        Series::new("name_source", structures.iter().map(|s| s.name_source).collect::<Vec<_>>()),
+        Series::new("road_name", structures.iter().map(|s| s.road_name.as_deref()).collect::<Vec<_>>()),
+        Series::new("way_type", structures.iter().map(|s| s.way_type.as_str()).collect::<Vec<_>>()),
+        Series::new("lat", structures.iter().map(|s| round_coord(s.lat, coord_precision)).collect::<Vec<_>>()),
+        Series::new("lon", structures.iter().map(|s| round_coord(s.lon, coord_precision)).collect::<Vec<_>>()),
+        Series::new("num_ways", structures.iter().map(|s| s.num_ways as u32).collect::<Vec<_>>()),
+        Series::new("length_km", structures.iter().map(|s| s.length_km).collect::<Vec<_>>()),
+    ])?;
+
+    Ok(df)
+}
+
+/// Structures mode: extract named bridges and tunnels to parquet
+pub fn process_structures_to_parquet(
+    pbf_path: &Path,
+    state_name: &str,
+    output_path: Option<PathBuf>,
+    coord_precision: Option<u32>,
+) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("OSM BRIDGES AND TUNNELS TO PARQUET (Rust)");
+    println!("{}", "=".repeat(70));
+    println!("Input file:  {}", pbf_path.display());
+    println!("State:       {}", state_name);
+    println!("{}", "=".repeat(70));
+
+    let output_path = output_path.unwrap_or_else(|| default_output_path(pbf_path, state_name, "structures"));
+
+    let structures = extract_structures(pbf_path, state_name)?;
+    let mut df = structures_to_dataframe(&structures, state_name, coord_precision)?;
+
+    for kind in ["bridge", "tunnel"] {
+        let count = structures.iter().filter(|s| s.kind == kind).count();
+        println!("  {:<7} {}", kind, count);
+    }
+
+    println!("\nSaving to: {}", output_path.display());
+    write_parquet(&mut df, &output_path)?;
+
+    println!("Done!");
+    println!("{}", "=".repeat(70));
+
+    Ok(())
+}
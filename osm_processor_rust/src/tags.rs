@@ -0,0 +1,96 @@
+//! Compact tag storage for segments.
+//!
+//! A large state has millions of named ways, and a `HashMap<String, String>` per way made tag
+//! storage the main memory and allocation cost of way extraction, with the same few keys and
+//! many repeated values (`highway=residential`, `tiger:county=...`) allocated over and over.
+//! [`Tags`] holds a way's tags as a sorted slice of interned key/value IDs instead: one
+//! allocation per way, eight bytes per tag. Interned strings are shared process-wide and live
+//! for the rest of the process, so only the tags of extracted ways are interned.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+/// Process-wide string table: each distinct key or value is stored once
+#[derive(Default)]
+struct Interner {
+    strings: Vec<&'static str>,
+    ids: HashMap<&'static str, u32>,
+}
+
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+/// ID of `s`, adding it on first sight
+fn intern(s: &str) -> u32 {
+    if let Some(id) = lookup(s) {
+        return id;
+    }
+    let mut interner = interner().write().unwrap_or_else(PoisonError::into_inner);
+    if let Some(&id) = interner.ids.get(s) {
+        return id;
+    }
+    let id = interner.strings.len() as u32;
+    let s: &'static str = Box::leak(s.into());
+    interner.strings.push(s);
+    interner.ids.insert(s, id);
+    id
+}
+
+/// ID of `s` if it was ever interned; a key that never was is on no segment
+fn lookup(s: &str) -> Option<u32> {
+    interner().read().unwrap_or_else(PoisonError::into_inner).ids.get(s).copied()
+}
+
+fn resolve(id: u32) -> &'static str {
+    interner().read().unwrap_or_else(PoisonError::into_inner).strings[id as usize]
+}
+
+/// A way's tags as interned `(key, value)` IDs sorted by key
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Tags(Box<[(u32, u32)]>);
+
+impl Tags {
+    /// Intern the pairs; a repeated key keeps its first value
+    pub fn from_pairs<K: AsRef<str>, V: AsRef<str>>(pairs: impl IntoIterator<Item = (K, V)>) -> Self {
+        let mut ids: Vec<(u32, u32)> =
+            pairs.into_iter().map(|(key, value)| (intern(key.as_ref()), intern(value.as_ref()))).collect();
+        ids.sort_by_key(|&(key, _)| key);
+        ids.dedup_by_key(|&mut (key, _)| key);
+        Tags(ids.into_boxed_slice())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&'static str> {
+        let key = lookup(key)?;
+        let index = self.0.binary_search_by_key(&key, |&(k, _)| k).ok()?;
+        Some(resolve(self.0[index].1))
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &'static str)> + '_ {
+        self.0.iter().map(|&(key, value)| (resolve(key), resolve(value)))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.0.iter().map(|&(key, _)| resolve(key))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Tags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::path::{Path, PathBuf};
+
+use osm_processor_rust::stats::{load_streets, type_column};
+use osm_processor_rust::write_table;
+
+/// `state, name, count, total_streets, rate`: one row per name and state, `rate` being the
+/// name's share of the state's streets, sorted by state then descending count
+fn name_counts(streets: LazyFrame) -> LazyFrame {
+    streets
+        .group_by([col("state"), col("street_name").alias("name")])
+        .agg([len().cast(DataType::UInt64).alias("count")])
+        .with_column(col("count").sum().over([col("state")]).alias("total_streets"))
+        .with_column((col("count").cast(DataType::Float64) / col("total_streets").cast(DataType::Float64)).alias("rate"))
+        .sort_by_exprs(
+            [col("state"), col("count"), col("name")],
+            SortMultipleOptions::default().with_order_descending_multi([false, true, false]),
+        )
+}
+
+/// One row per street with covariates ready for a regression: plain column names, numeric
+/// flags instead of nullable strings, and how common the street's name is in its state and
+/// overall
+fn street_covariates(df: &DataFrame) -> Result<LazyFrame> {
+    let has = |name: &str| df.get_column_names().contains(&name);
+
+    let mut columns = vec![col("state"), col("street_name").alias("name")];
+    if has("street_id") {
+        columns.insert(0, col("street_id"));
+    }
+    if let Some(column) = type_column(df) {
+        columns.push(col(&column).alias("road_type"));
+    }
+    columns.extend([
+        col("length_km"),
+        col("num_segments").cast(DataType::UInt32),
+        col("lat"),
+        col("lon"),
+    ]);
+    if has("street_type_norm") {
+        columns.push(col("street_type_norm"));
+    }
+    if has("is_numbered") {
+        columns.push(col("is_numbered").cast(DataType::UInt8));
+    }
+    if has("is_loop") {
+        columns.push(col("is_loop").cast(DataType::UInt8));
+    }
+    if has("direction") {
+        columns.push(col("direction").is_not_null().cast(DataType::UInt8).alias("has_direction"));
+    }
+    columns.extend([
+        len().over([col("state"), col("street_name")]).cast(DataType::UInt32).alias("name_state_count"),
+        len().over([col("street_name")]).cast(DataType::UInt32).alias("name_total_count"),
+    ]);
+
+    Ok(df.clone().lazy().select(columns))
+}
+
+/// `tidy <streets.parquet...> -o counts.(csv|parquet) [--streets streets.(csv|parquet)]`
+///
+/// Long-format tables for R and pandas: name counts per state, and optionally one row per
+/// street with covariates. Writes CSV or parquet by extension.
+pub fn run_tidy(paths: &[PathBuf], output_path: &Path, streets_path: Option<&Path>) -> Result<()> {
+    let df = load_streets(paths)?;
+
+    let mut counts = name_counts(df.clone().lazy()).collect()?;
+    println!("{}", counts.head(Some(10)));
+    println!("Saving {} name × state rows to: {}", counts.height(), output_path.display());
+    write_table(&mut counts, output_path)?;
+
+    if let Some(streets_path) = streets_path {
+        let mut streets = street_covariates(&df)?.collect()?;
+        println!("Saving {} streets to: {}", streets.height(), streets_path.display());
+        write_table(&mut streets, streets_path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,535 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use osm_processor_rust::stats::load_streets;
+
+use crate::export::{minzoom, read_export_rows, ExportGeometry, ExportRow};
+
+/// Tile coordinate range in vector tile units
+const EXTENT: i64 = 4096;
+/// Units kept around each tile so lines join cleanly across tile edges
+const BUFFER: i64 = 64;
+/// Latitude limit of Web Mercator
+const MAX_LAT: f64 = 85.051_128_779_806_59;
+/// Size of the PMTiles v3 header
+const HEADER_LEN: usize = 127;
+/// The PMTiles header and root directory must fit in the first 16 KiB of the archive
+const ROOT_MAX_LEN: usize = 16_384;
+/// Highest zoom `--max-zoom` accepts; streets need nothing deeper, and memory grows 4x per level
+const MAX_ZOOM: u8 = 15;
+/// Name of the single layer, as in `export`'s tippecanoe hints
+const LAYER: &str = "streets";
+
+/// Position on the Web Mercator world square, both coordinates in [0, 1]
+fn world_xy(lat: f64, lon: f64) -> (f64, f64) {
+    let lat = lat.clamp(-MAX_LAT, MAX_LAT).to_radians();
+    ((lon + 180.0) / 360.0, (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0)
+}
+
+/// A row's geometry on the world square
+enum WorldGeometry {
+    Point((f64, f64)),
+    Lines(Vec<Vec<(f64, f64)>>),
+}
+
+fn world_geometry(geometry: &ExportGeometry) -> WorldGeometry {
+    match geometry {
+        ExportGeometry::Point { lat, lon } => WorldGeometry::Point(world_xy(*lat, *lon)),
+        ExportGeometry::Lines(lines) => WorldGeometry::Lines(
+            lines
+                .iter()
+                .map(|line| line.points().map(|p| world_xy(p.y(), p.x())).collect())
+                .collect(),
+        ),
+    }
+}
+
+/// Hilbert-curve tile id of the PMTiles v3 spec: tiles of lower zooms first, then along the
+/// curve within a zoom
+fn tile_id(z: u8, x: u32, y: u32) -> u64 {
+    let base = ((1u64 << (2 * z as u32)) - 1) / 3;
+    let n = 1u64 << z;
+    let (mut x, mut y) = (x as u64, y as u64);
+    let mut d = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = u64::from((x & s) > 0);
+        let ry = u64::from((y & s) > 0);
+        d += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    base + d
+}
+
+/// The ends of a clipped segment and whether each was moved
+type ClippedSegment = ((f64, f64), (f64, f64), bool, bool);
+
+/// Liang-Barsky clip of segment `a`-`b` to the square [min, max]²
+fn clip_segment(a: (f64, f64), b: (f64, f64), min: f64, max: f64) -> Option<ClippedSegment> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [(-dx, a.0 - min), (dx, max - a.0), (-dy, a.1 - min), (dy, max - a.1)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    Some(((a.0 + t0 * dx, a.1 + t0 * dy), (a.0 + t1 * dx, a.1 + t1 * dy), t0 > 0.0, t1 < 1.0))
+}
+
+/// Pieces of a tile-local polyline inside the buffered tile, snapped to whole units; pieces
+/// that collapse to a single unit are dropped
+fn clip_line(points: &[(f64, f64)]) -> Vec<Vec<(i64, i64)>> {
+    fn flush(parts: &mut Vec<Vec<(i64, i64)>>, current: &mut Vec<(i64, i64)>) {
+        if current.len() >= 2 {
+            parts.push(std::mem::take(current));
+        } else {
+            current.clear();
+        }
+    }
+    fn push(current: &mut Vec<(i64, i64)>, point: (f64, f64)) {
+        let point = (point.0.round() as i64, point.1.round() as i64);
+        if current.last() != Some(&point) {
+            current.push(point);
+        }
+    }
+
+    let (min, max) = (-BUFFER as f64, (EXTENT + BUFFER) as f64);
+    let mut parts = Vec::new();
+    let mut current = Vec::new();
+    for pair in points.windows(2) {
+        match clip_segment(pair[0], pair[1], min, max) {
+            None => flush(&mut parts, &mut current),
+            Some((start, end, entered, left)) => {
+                if entered || current.is_empty() {
+                    flush(&mut parts, &mut current);
+                    push(&mut current, start);
+                }
+                push(&mut current, end);
+                if left {
+                    flush(&mut parts, &mut current);
+                }
+            }
+        }
+    }
+    flush(&mut parts, &mut current);
+    parts
+}
+
+fn zigzag(value: i64) -> u32 {
+    ((value << 1) ^ (value >> 63)) as u32
+}
+
+fn command(id: u32, count: usize) -> u32 {
+    (id & 0x7) | ((count as u32) << 3)
+}
+
+/// Vector tile geometry commands for a point
+fn point_commands((x, y): (i64, i64)) -> Vec<u32> {
+    vec![command(1, 1), zigzag(x), zigzag(y)]
+}
+
+/// Vector tile geometry commands for one or more lines, with positions as deltas
+fn line_commands(lines: &[Vec<(i64, i64)>]) -> Vec<u32> {
+    let mut commands = Vec::new();
+    let mut cursor = (0, 0);
+    for line in lines {
+        for (i, &(x, y)) in line.iter().enumerate() {
+            match i {
+                0 => commands.push(command(1, 1)),
+                1 => commands.push(command(2, line.len() - 1)),
+                _ => {}
+            }
+            commands.push(zigzag(x - cursor.0));
+            commands.push(zigzag(y - cursor.1));
+            cursor = (x, y);
+        }
+    }
+    commands
+}
+
+/// A row clipped to one tile: the row index, the vector tile geometry type and its commands
+struct TileFeature {
+    row: usize,
+    geom_type: u32,
+    commands: Vec<u32>,
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_key(buf: &mut Vec<u8>, field: u32, wire_type: u32) {
+    put_varint(buf, ((field << 3) | wire_type) as u64);
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_key(buf, field, 2);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn put_packed(buf: &mut Vec<u8>, field: u32, values: &[u32]) {
+    let mut packed = Vec::new();
+    for &value in values {
+        put_varint(&mut packed, value as u64);
+    }
+    put_bytes(buf, field, &packed);
+}
+
+/// Attribute value of the layer's value table; doubles by their bits so they can be keys
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum TagValue {
+    Text(String),
+    Double(u64),
+}
+
+/// Attributes of a row, as (key, value) pairs; nulls are left out
+fn row_tags<'a>(row: &ExportRow, type_name: &'a str) -> Vec<(&'a str, TagValue)> {
+    let mut tags = vec![
+        ("street_name", TagValue::Text(row.street_name.clone())),
+        ("state", TagValue::Text(row.state.clone())),
+    ];
+    if let Some(street_type) = &row.street_type {
+        tags.push((type_name, TagValue::Text(street_type.clone())));
+    }
+    if let Some(id) = &row.street_id {
+        tags.push(("street_id", TagValue::Text(id.clone())));
+    }
+    if let Some(length) = row.length_km {
+        tags.push(("length_km", TagValue::Double(length.to_bits())));
+    }
+    if let Some(segments) = row.num_segments {
+        tags.push(("num_segments", TagValue::Double((segments as f64).to_bits())));
+    }
+    tags
+}
+
+/// Mapbox Vector Tile with a single `streets` layer
+fn encode_tile(features: &[TileFeature], rows: &[ExportRow], type_name: &str) -> Vec<u8> {
+    let mut keys: Vec<&str> = Vec::new();
+    let mut key_index: HashMap<&str, u32> = HashMap::new();
+    let mut values: Vec<TagValue> = Vec::new();
+    let mut value_index: HashMap<TagValue, u32> = HashMap::new();
+
+    let mut layer = Vec::new();
+    put_key(&mut layer, 15, 0);
+    put_varint(&mut layer, 2);
+    put_bytes(&mut layer, 1, LAYER.as_bytes());
+    for feature in features {
+        let mut tags = Vec::new();
+        for (key, value) in row_tags(&rows[feature.row], type_name) {
+            let k = *key_index.entry(key).or_insert_with(|| {
+                keys.push(key);
+                keys.len() as u32 - 1
+            });
+            let v = match value_index.get(&value) {
+                Some(&v) => v,
+                None => {
+                    let v = values.len() as u32;
+                    values.push(value.clone());
+                    value_index.insert(value, v);
+                    v
+                }
+            };
+            tags.extend([k, v]);
+        }
+
+        let mut encoded = Vec::new();
+        put_packed(&mut encoded, 2, &tags);
+        put_key(&mut encoded, 3, 0);
+        put_varint(&mut encoded, feature.geom_type as u64);
+        put_packed(&mut encoded, 4, &feature.commands);
+        put_bytes(&mut layer, 2, &encoded);
+    }
+    for key in keys {
+        put_bytes(&mut layer, 3, key.as_bytes());
+    }
+    for value in values {
+        let mut encoded = Vec::new();
+        match value {
+            TagValue::Text(text) => put_bytes(&mut encoded, 1, text.as_bytes()),
+            TagValue::Double(bits) => {
+                put_key(&mut encoded, 3, 1);
+                encoded.extend_from_slice(&bits.to_le_bytes());
+            }
+        }
+        put_bytes(&mut layer, 4, &encoded);
+    }
+    put_key(&mut layer, 5, 0);
+    put_varint(&mut layer, EXTENT as u64);
+
+    let mut tile = Vec::new();
+    put_bytes(&mut tile, 3, &layer);
+    tile
+}
+
+/// Features of every tile of zoom `z` that has any, keyed by (x, y)
+fn tile_zoom(z: u8, rows: &[ExportRow], geometries: &[WorldGeometry]) -> HashMap<(u32, u32), Vec<TileFeature>> {
+    let n = 1i64 << z;
+    let scale = (n * EXTENT) as f64;
+    let tile_range = |min: f64, max: f64| {
+        let first = ((min - BUFFER as f64) / EXTENT as f64).floor() as i64;
+        let last = ((max + BUFFER as f64) / EXTENT as f64).floor() as i64;
+        first.max(0)..=last.min(n - 1)
+    };
+
+    let mut tiles: HashMap<(u32, u32), Vec<TileFeature>> = HashMap::new();
+    for (i, (row, geometry)) in rows.iter().zip(geometries).enumerate() {
+        if minzoom(row.street_type.as_deref()) > z {
+            continue;
+        }
+        match geometry {
+            WorldGeometry::Point((wx, wy)) => {
+                let (px, py) = (wx * scale, wy * scale);
+                let (tx, ty) = ((px / EXTENT as f64).floor() as i64, (py / EXTENT as f64).floor() as i64);
+                if !(0..n).contains(&tx) || !(0..n).contains(&ty) {
+                    continue;
+                }
+                let local = ((px - (tx * EXTENT) as f64) as i64, (py - (ty * EXTENT) as f64) as i64);
+                tiles.entry((tx as u32, ty as u32)).or_default().push(TileFeature {
+                    row: i,
+                    geom_type: 1,
+                    commands: point_commands(local),
+                });
+            }
+            WorldGeometry::Lines(lines) => {
+                let pixels: Vec<Vec<(f64, f64)>> =
+                    lines.iter().map(|line| line.iter().map(|&(wx, wy)| (wx * scale, wy * scale)).collect()).collect();
+                let all = pixels.iter().flatten();
+                let (min_x, max_x) = all.clone().fold((f64::MAX, f64::MIN), |(lo, hi), p| (lo.min(p.0), hi.max(p.0)));
+                let (min_y, max_y) = all.fold((f64::MAX, f64::MIN), |(lo, hi), p| (lo.min(p.1), hi.max(p.1)));
+                if min_x > max_x {
+                    continue;
+                }
+                for tx in tile_range(min_x, max_x) {
+                    for ty in tile_range(min_y, max_y) {
+                        let (ox, oy) = ((tx * EXTENT) as f64, (ty * EXTENT) as f64);
+                        let parts: Vec<Vec<(i64, i64)>> = pixels
+                            .iter()
+                            .flat_map(|line| clip_line(&line.iter().map(|&(x, y)| (x - ox, y - oy)).collect::<Vec<_>>()))
+                            .collect();
+                        if !parts.is_empty() {
+                            tiles.entry((tx as u32, ty as u32)).or_default().push(TileFeature {
+                                row: i,
+                                geom_type: 2,
+                                commands: line_commands(&parts),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    tiles
+}
+
+/// A PMTiles directory entry; `run_length` 0 marks a leaf directory
+struct Entry {
+    tile_id: u64,
+    offset: u64,
+    length: u32,
+    run_length: u32,
+}
+
+/// Uncompressed PMTiles v3 directory: ids as deltas, then run lengths, lengths and offsets
+/// (0 when contiguous with the previous entry)
+fn serialize_directory(entries: &[Entry]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    put_varint(&mut buf, entries.len() as u64);
+    let mut last_id = 0;
+    for entry in entries {
+        put_varint(&mut buf, entry.tile_id - last_id);
+        last_id = entry.tile_id;
+    }
+    for entry in entries {
+        put_varint(&mut buf, entry.run_length as u64);
+    }
+    for entry in entries {
+        put_varint(&mut buf, entry.length as u64);
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        let contiguous = i > 0 && entry.offset == entries[i - 1].offset + entries[i - 1].length as u64;
+        put_varint(&mut buf, if contiguous { 0 } else { entry.offset + 1 });
+    }
+    buf
+}
+
+/// Root directory and leaf directories: everything in the root when it fits, otherwise leaves
+/// of a doubling number of entries until the root pointing at them does
+fn build_directories(entries: &[Entry]) -> (Vec<u8>, Vec<u8>) {
+    let root = serialize_directory(entries);
+    if root.len() <= ROOT_MAX_LEN - HEADER_LEN {
+        return (root, Vec::new());
+    }
+    let mut leaf_size = 4096;
+    loop {
+        let mut leaves = Vec::new();
+        let mut root_entries = Vec::new();
+        for chunk in entries.chunks(leaf_size) {
+            let leaf = serialize_directory(chunk);
+            root_entries.push(Entry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: leaf.len() as u32,
+                run_length: 0,
+            });
+            leaves.extend(leaf);
+        }
+        let root = serialize_directory(&root_entries);
+        if root.len() <= ROOT_MAX_LEN - HEADER_LEN {
+            return (root, leaves);
+        }
+        leaf_size *= 2;
+    }
+}
+
+/// Bounds of the input as (min_lon, min_lat, max_lon, max_lat)
+fn bounds(rows: &[ExportRow]) -> (f64, f64, f64, f64) {
+    let mut bounds = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    let mut extend = |lon: f64, lat: f64| {
+        bounds = (bounds.0.min(lon), bounds.1.min(lat), bounds.2.max(lon), bounds.3.max(lat));
+    };
+    for row in rows {
+        match &row.geometry {
+            ExportGeometry::Point { lat, lon } => extend(*lon, *lat),
+            ExportGeometry::Lines(lines) => lines.iter().flat_map(|l| l.points()).for_each(|p| extend(p.x(), p.y())),
+        }
+    }
+    bounds
+}
+
+fn e7(degrees: f64) -> [u8; 4] {
+    ((degrees * 1e7).round() as i32).to_le_bytes()
+}
+
+/// `tiles <streets.parquet|segments.parquet...> -o <out.pmtiles> [--min-zoom Z] [--max-zoom Z]`
+///
+/// Renders a PMTiles v3 archive of uncompressed vector tiles with one `streets` layer. A street
+/// enters at `export`'s tippecanoe minzoom for its highway type, so low zooms carry only the
+/// major network. Tiles of one zoom are built in memory at a time; the encoded tiles are staged
+/// in `temp_dir`.
+pub fn run_tiles(paths: &[PathBuf], output_path: &Path, min_zoom: u8, max_zoom: u8, temp_dir: &Path) -> Result<()> {
+    if min_zoom > max_zoom || max_zoom > MAX_ZOOM {
+        anyhow::bail!("tiles: expected --min-zoom <= --max-zoom <= {} (got {} and {})", MAX_ZOOM, min_zoom, max_zoom);
+    }
+    let df = load_streets(paths)?;
+    let (rows, type_name) = read_export_rows(&df)?;
+    if rows.is_empty() {
+        anyhow::bail!("tiles: no rows with a geometry in the input");
+    }
+    let type_name = type_name.unwrap_or_else(|| "highway_type".to_string());
+    let geometries: Vec<WorldGeometry> = rows.iter().map(|row| world_geometry(&row.geometry)).collect();
+
+    // Tile data goes to a scratch file first, since the directories in front of it are only
+    // known once every tile is written
+    let stem = output_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let data_path = temp_dir.join(format!("osm_processor_{}_{}.pmtiles.data", stem, std::process::id()));
+    let mut data = BufWriter::new(File::create(&data_path).with_context(|| format!("Failed to create {}", data_path.display()))?);
+    let mut entries = Vec::new();
+    let mut offset = 0u64;
+    for z in min_zoom..=max_zoom {
+        let tiles = tile_zoom(z, &rows, &geometries);
+        let mut ids: Vec<(u64, (u32, u32))> = tiles.keys().map(|&(x, y)| (tile_id(z, x, y), (x, y))).collect();
+        ids.sort_unstable();
+        for (id, key) in &ids {
+            let tile = encode_tile(&tiles[key], &rows, &type_name);
+            data.write_all(&tile)?;
+            entries.push(Entry { tile_id: *id, offset, length: tile.len() as u32, run_length: 1 });
+            offset += tile.len() as u64;
+        }
+        println!("  z{}: {} tiles", z, ids.len());
+    }
+    data.flush()?;
+    drop(data);
+
+    let fields: serde_json::Map<String, serde_json::Value> = ["street_name", "state", type_name.as_str(), "street_id"]
+        .iter()
+        .map(|&field| (field.to_string(), json!("String")))
+        .chain(["length_km", "num_segments"].iter().map(|&field| (field.to_string(), json!("Number"))))
+        .collect();
+    let metadata = serde_json::to_vec(&json!({
+        "name": output_path.file_stem().map(|s| s.to_string_lossy().into_owned()),
+        "format": "pbf",
+        "type": "overlay",
+        "vector_layers": [{ "id": LAYER, "minzoom": min_zoom, "maxzoom": max_zoom, "fields": fields }],
+    }))?;
+    let (root, leaves) = build_directories(&entries);
+
+    let root_offset = HEADER_LEN as u64;
+    let metadata_offset = root_offset + root.len() as u64;
+    let leaves_offset = metadata_offset + metadata.len() as u64;
+    let data_offset = leaves_offset + leaves.len() as u64;
+    let (min_lon, min_lat, max_lon, max_lat) = bounds(&rows);
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(b"PMTiles");
+    header.push(3);
+    for value in [
+        root_offset,
+        root.len() as u64,
+        metadata_offset,
+        metadata.len() as u64,
+        leaves_offset,
+        leaves.len() as u64,
+        data_offset,
+        offset,
+        entries.len() as u64,
+        entries.len() as u64,
+        entries.len() as u64,
+    ] {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+    // Clustered; internal and tile compression none; vector tiles; zoom range
+    header.extend_from_slice(&[1, 1, 1, 1, min_zoom, max_zoom]);
+    for degrees in [min_lon, min_lat, max_lon, max_lat] {
+        header.extend_from_slice(&e7(degrees));
+    }
+    header.push(min_zoom);
+    header.extend_from_slice(&e7((min_lon + max_lon) / 2.0));
+    header.extend_from_slice(&e7((min_lat + max_lat) / 2.0));
+
+    let file = File::create(output_path).with_context(|| format!("Failed to create {}", output_path.display()))?;
+    let mut out = BufWriter::new(file);
+    out.write_all(&header)?;
+    out.write_all(&root)?;
+    out.write_all(&metadata)?;
+    out.write_all(&leaves)?;
+    std::io::copy(&mut File::open(&data_path)?, &mut out)?;
+    out.flush()?;
+    std::fs::remove_file(&data_path)?;
+
+    println!(
+        "Wrote {} tiles (z{}-z{}) of {} features to {}",
+        entries.len(),
+        min_zoom,
+        max_zoom,
+        rows.len(),
+        output_path.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use geo::{LineString, MultiLineString};
+use polars::prelude::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::node_set::NodeIdSet;
+use crate::relations::{collect_relations, collect_way_refs};
+use crate::wkb::multilinestring_to_wkb;
+use crate::{
+    default_output_path, load_node_coords, polyline_length_km, write_parquet, ConsoleProgress,
+};
+
+/// Route types kept by the transit extractor
+const ROUTE_TYPES: [&str; 3] = ["bus", "tram", "train"];
+
+/// A transit route relation with its approximate geometry
+#[derive(Debug)]
+struct TransitRoute {
+    osm_id: i64,
+    route: String,
+    name: Option<String>,
+    route_ref: Option<String>,
+    operator: Option<String>,
+    network: Option<String>,
+    member_ways: usize,
+    length_km: f64,
+    geometry: MultiLineString<f64>,
+}
+
+/// Extract `type=route` relations for bus, tram and train routes.
+/// Geometry is the unordered set of member ways found in the extract.
+fn extract_routes(pbf_path: &Path) -> Result<Vec<TransitRoute>> {
+    println!("Pass 1: Collecting route relations...");
+    let relations = collect_relations(pbf_path, |tags| {
+        tags.get("type").map(String::as_str) == Some("route")
+            && tags.get("route").is_some_and(|r| ROUTE_TYPES.contains(&r.as_str()))
+    })?;
+    println!("  Found {} route relations", relations.len());
+
+    println!("Pass 2: Collecting route member ways...");
+    let member_ways: HashSet<i64> = relations
+        .iter()
+        .flat_map(|r| r.way_members().map(|m| m.id))
+        .collect();
+    let way_refs = collect_way_refs(pbf_path, &member_ways)?;
+    println!("  Found {} of {} member ways", way_refs.len(), member_ways.len());
+
+    println!("Pass 3: Loading route node coordinates...");
+    let wanted_nodes: NodeIdSet = way_refs.values().flatten().copied().collect();
+    let node_coords = load_node_coords(pbf_path, &wanted_nodes, &ConsoleProgress::default())?;
+
+    let routes = relations
+        .iter()
+        .map(|rel| {
+            let mut lines = Vec::new();
+            let mut length_km = 0.0;
+
+            for member in rel.way_members() {
+                let Some(refs) = way_refs.get(&member.id) else { continue };
+                let coords: Vec<(f64, f64)> = refs
+                    .iter()
+                    .filter_map(|id| node_coords.get(id).copied())
+                    .collect();
+                if coords.len() < 2 {
+                    continue;
+                }
+                length_km += polyline_length_km(&coords);
+                lines.push(LineString::from(
+                    coords.iter().map(|&(lat, lon)| (lon, lat)).collect::<Vec<_>>(),
+                ));
+            }
+
+            TransitRoute {
+                osm_id: rel.id,
+                route: rel.tags["route"].clone(),
+                name: rel.tags.get("name").cloned(),
+                route_ref: rel.tags.get("ref").cloned(),
+                operator: rel.tags.get("operator").cloned(),
+                network: rel.tags.get("network").cloned(),
+                member_ways: rel.way_members().count(),
+                length_km,
+                geometry: MultiLineString::new(lines),
+            }
+        })
+        .collect();
+
+    Ok(routes)
+}
+
+fn routes_to_dataframe(
+    routes: &[TransitRoute],
+    state_name: &str,
+    coord_precision: Option<u32>,
+) -> Result<DataFrame> {
+    let geometries: Vec<Vec<u8>> = routes
+        .iter()
+        .map(|r| multilinestring_to_wkb(&r.geometry, coord_precision))
+        .collect();
+
+    let df = DataFrame::new(vec![
+        Series::new("osm_id", routes.iter().map(|r| r.osm_id).collect::<Vec<_>>()),
+        Series::new("state", vec![state_name; routes.len()]),
+        Series::new("route", routes.iter().map(|r| r.route.as_str()).collect::<Vec<_>>()),
+        Series::new("name", routes.iter().map(|r| r.name.as_deref()).collect::<Vec<_>>()),
+        Series::new("ref", routes.iter().map(|r| r.route_ref.as_deref()).collect::<Vec<_>>()),
+        Series::new("operator", routes.iter().map(|r| r.operator.as_deref()).collect::<Vec<_>>()),
+        Series::new("network", routes.iter().map(|r| r.network.as_deref()).collect::<Vec<_>>()),
+        Series::new("member_ways", routes.iter().map(|r| r.member_ways as u32).collect::<Vec<_>>()),
+        Series::new("length_km", routes.iter().map(|r| r.length_km).collect::<Vec<_>>()),
+        Series::new("geometry", geometries),
+    ])?;
+
+    Ok(df)
+}
+
+/// Routes mode: extract bus/tram/train route relations to parquet
+pub fn process_routes_to_parquet(
+    pbf_path: &Path,
+    state_name: &str,
+    output_path: Option<PathBuf>,
+    coord_precision: Option<u32>,
+) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("OSM TRANSIT ROUTES TO PARQUET (Rust)");
+    println!("{}", "=".repeat(70));
+    println!("Input file:  {}", pbf_path.display());
+    println!("State:       {}", state_name);
+    println!("{}", "=".repeat(70));
+
+    let output_path = output_path.unwrap_or_else(|| default_output_path(pbf_path, state_name, "routes"));
+
+    let routes = extract_routes(pbf_path)?;
+    let mut df = routes_to_dataframe(&routes, state_name, coord_precision)?;
+
+    for route_type in ROUTE_TYPES {
+        let count = routes.iter().filter(|r| r.route == route_type).count();
+        println!("  {:<6} {}", route_type, count);
+    }
+
+    println!("\nSaving to: {}", output_path.display());
+    write_parquet(&mut df, &output_path)?;
+
+    println!("Done!");
+    println!("{}", "=".repeat(70));
+
+    Ok(())
+}
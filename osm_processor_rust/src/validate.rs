@@ -0,0 +1,133 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::matching::{match_streets, read_rows, row_distance_km, StreetRow};
+use osm_processor_rust::stats::load_streets;
+use osm_processor_rust::write_parquet;
+
+/// Options for `validate`
+pub struct ValidateOptions {
+    /// Representative points further apart than this are reported as discrepancies
+    pub tolerance_km: f64,
+    /// Streets further apart than this are not considered the same street at all
+    pub max_match_km: f64,
+}
+
+/// (name, state) groups whose street counts differ between output and reference,
+/// i.e. where segments were grouped differently
+fn grouping_differences<'a>(
+    rows: &'a [StreetRow],
+    reference: &'a [StreetRow],
+) -> Vec<((&'a str, &'a str), usize, usize)> {
+    let mut counts: BTreeMap<(&str, &str), (usize, usize)> = BTreeMap::new();
+    for row in rows {
+        counts.entry((row.street_name.as_str(), row.state.as_str())).or_default().0 += 1;
+    }
+    for row in reference {
+        counts.entry((row.street_name.as_str(), row.state.as_str())).or_default().1 += 1;
+    }
+
+    counts
+        .into_iter()
+        .filter(|(_, (ours, theirs))| ours != theirs)
+        .map(|(key, (ours, theirs))| (key, ours, theirs))
+        .collect()
+}
+
+fn state_counts(rows: &[StreetRow]) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for row in rows {
+        *counts.entry(row.state.as_str()).or_default() += 1;
+    }
+    counts
+}
+
+/// `validate <output.parquet> --reference <python.parquet> [--tolerance-km X] [--max-match-km X] [--output-dir DIR]`
+pub fn run_validate(
+    output_path: &Path,
+    reference_path: &Path,
+    options: &ValidateOptions,
+    report_dir: Option<&Path>,
+) -> Result<()> {
+    println!("\n{}", "=".repeat(70));
+    println!("VALIDATE AGAINST REFERENCE");
+    println!("{}", "=".repeat(70));
+    println!("Output:      {}", output_path.display());
+    println!("Reference:   {}", reference_path.display());
+    println!("Tolerance:   {} km (match radius {} km)", options.tolerance_km, options.max_match_km);
+    println!("{}", "=".repeat(70));
+
+    let rows = read_rows(&load_streets(&[output_path.to_path_buf()])?)?;
+    let reference = read_rows(&load_streets(&[reference_path.to_path_buf()])?)?;
+
+    // Count deltas
+    println!("\nStreet counts: output {}, reference {} (delta {:+})",
+             rows.len(), reference.len(), rows.len() as i64 - reference.len() as i64);
+    let ours_by_state = state_counts(&rows);
+    let theirs_by_state = state_counts(&reference);
+    let mut states: Vec<&str> = ours_by_state.keys().chain(theirs_by_state.keys()).copied().collect();
+    states.sort_unstable();
+    states.dedup();
+    for state in states {
+        let ours = ours_by_state.get(state).copied().unwrap_or(0);
+        let theirs = theirs_by_state.get(state).copied().unwrap_or(0);
+        if ours != theirs {
+            println!("  {:<20} output {:>8}  reference {:>8}  delta {:+}", state, ours, theirs, ours as i64 - theirs as i64);
+        }
+    }
+
+    // Match streets and compare coordinates
+    let matches = match_streets(&rows, &reference, options.max_match_km);
+    let discrepancies: Vec<(usize, usize, f64)> = matches
+        .matched
+        .iter()
+        .map(|&(i, j)| (i, j, row_distance_km(&rows[i], &reference[j])))
+        .filter(|&(_, _, dist)| dist > options.tolerance_km)
+        .collect();
+
+    println!("\nMatched streets:           {}", matches.matched.len());
+    println!("Only in output:            {}", matches.left_only.len());
+    println!("Only in reference:         {}", matches.right_only.len());
+    println!("Coordinate discrepancies:  {} (> {} km)", discrepancies.len(), options.tolerance_km);
+
+    let grouping = grouping_differences(&rows, &reference);
+    println!("Grouping differences:      {} (name, state) pairs with different street counts", grouping.len());
+    for ((name, state), ours, theirs) in grouping.iter().take(10) {
+        println!("  {} ({}): output {} vs reference {}", name, state, ours, theirs);
+    }
+
+    let identical = matches.left_only.is_empty()
+        && matches.right_only.is_empty()
+        && discrepancies.is_empty()
+        && grouping.is_empty();
+    println!("\nResult: {}", if identical { "MATCHES REFERENCE" } else { "DIFFERS FROM REFERENCE" });
+
+    if let Some(dir) = report_dir {
+        std::fs::create_dir_all(dir)?;
+
+        let mut grouping_df = DataFrame::new(vec![
+            Series::new("street_name", grouping.iter().map(|g| g.0 .0).collect::<Vec<_>>()),
+            Series::new("state", grouping.iter().map(|g| g.0 .1).collect::<Vec<_>>()),
+            Series::new("output_count", grouping.iter().map(|g| g.1 as u32).collect::<Vec<_>>()),
+            Series::new("reference_count", grouping.iter().map(|g| g.2 as u32).collect::<Vec<_>>()),
+        ])?;
+        write_parquet(&mut grouping_df, &dir.join("grouping_differences.parquet"))?;
+
+        let mut coords_df = DataFrame::new(vec![
+            Series::new("street_name", discrepancies.iter().map(|d| rows[d.0].street_name.as_str()).collect::<Vec<_>>()),
+            Series::new("state", discrepancies.iter().map(|d| rows[d.0].state.as_str()).collect::<Vec<_>>()),
+            Series::new("lat", discrepancies.iter().map(|d| rows[d.0].lat).collect::<Vec<_>>()),
+            Series::new("lon", discrepancies.iter().map(|d| rows[d.0].lon).collect::<Vec<_>>()),
+            Series::new("reference_lat", discrepancies.iter().map(|d| reference[d.1].lat).collect::<Vec<_>>()),
+            Series::new("reference_lon", discrepancies.iter().map(|d| reference[d.1].lon).collect::<Vec<_>>()),
+            Series::new("distance_km", discrepancies.iter().map(|d| d.2).collect::<Vec<_>>()),
+        ])?;
+        write_parquet(&mut coords_df, &dir.join("coordinate_discrepancies.parquet"))?;
+
+        println!("Reports saved to: {}", dir.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,249 @@
+//! Wikidata items linked from street ways.
+//!
+//! Ways link to Wikidata through `wikidata=Q...` (the street itself, mostly major roads) and
+//! `name:etymology:wikidata=Q...` (what the street is named after). Aggregation carries both
+//! through as the `wikidata_id` and `etymology_wikidata` columns; [`resolve_etymology`] then
+//! looks the etymology items up and adds what street-naming questions need about them: label,
+//! description, kind of item (`instance of`) and, for people, gender and birth and death years.
+//!
+//! Items come from a local JSON dump, scanned once and parsing only the wanted entities, or from
+//! the `wbgetentities` API in batches of 50 (`async` feature).
+
+use polars::prelude::*;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::io::BufRead;
+use std::path::PathBuf;
+
+use crate::error::{io_error, ProcessorError, Result};
+
+/// The public Wikidata API endpoint
+pub const API_URL: &str = "https://www.wikidata.org/w/api.php";
+
+/// Items per `wbgetentities` request, the API's limit for anonymous clients
+#[cfg(feature = "async")]
+const API_BATCH: usize = 50;
+
+/// Where item data comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WikidataSource {
+    /// An uncompressed JSON dump (`latest-all.json`) or a filtered subset of it, one entity per
+    /// line
+    Dump(PathBuf),
+    /// A MediaWiki API endpoint serving `wbgetentities`
+    Api(String),
+}
+
+impl WikidataSource {
+    /// `api` for the public API, an `http(s)://` URL for another endpoint, else a dump path
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "api" => WikidataSource::Api(API_URL.to_string()),
+            url if url.starts_with("http://") || url.starts_with("https://") => WikidataSource::Api(url.to_string()),
+            path => WikidataSource::Dump(PathBuf::from(path)),
+        }
+    }
+
+    /// As recorded in run manifests
+    pub fn name(&self) -> String {
+        match self {
+            WikidataSource::Dump(path) => path.display().to_string(),
+            WikidataSource::Api(url) => url.clone(),
+        }
+    }
+}
+
+/// Etymology resolution settings (`--wikidata`, `--wikidata-lang`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikidataConfig {
+    pub source: WikidataSource,
+    /// Language of labels and descriptions
+    pub lang: String,
+}
+
+/// What is resolved about one item
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Entity {
+    pub label: Option<String>,
+    pub description: Option<String>,
+    /// First `instance of` (P31) item, e.g. `Q5` for a human
+    pub instance_of: Option<String>,
+    /// `sex or gender` (P21): `female`, `male`, ... or the item ID for less common values
+    pub gender: Option<String>,
+    /// Year of `date of birth` (P569); negative for BCE
+    pub birth_year: Option<i32>,
+    /// Year of `date of death` (P570)
+    pub death_year: Option<i32>,
+}
+
+/// The first item ID of a tag value, which may list several (`Q1;Q2`)
+pub fn first_item_id(value: &str) -> Option<&str> {
+    let id = value.split(';').next()?.trim();
+    let digits = id.strip_prefix('Q')?;
+    (!digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())).then_some(id)
+}
+
+/// Look up the items of the `etymology_wikidata` column and add `etymology`,
+/// `etymology_description`, `etymology_instance_of`, `etymology_gender`, `etymology_birth_year`
+/// and `etymology_death_year` after it. Streets listing several items get the first. Returns the
+/// number of items found; a frame without the column (no street was tagged) is left as it is.
+pub fn resolve_etymology(df: &mut DataFrame, config: &WikidataConfig) -> Result<usize> {
+    let Some(index) = df.get_column_index("etymology_wikidata") else {
+        return Ok(0);
+    };
+    let ids: Vec<Option<String>> = df
+        .column("etymology_wikidata")?
+        .str()?
+        .into_iter()
+        .map(|value| value.and_then(first_item_id).map(str::to_string))
+        .collect();
+    let wanted: BTreeSet<&str> = ids.iter().flatten().map(String::as_str).collect();
+    println!("Resolving {} etymology items from {}...", wanted.len(), config.source.name());
+    let entities = match &config.source {
+        WikidataSource::Dump(path) => read_dump(path, &wanted, &config.lang)?,
+        WikidataSource::Api(url) => fetch_entities(url, &wanted, &config.lang)?,
+    };
+    println!("  Found {} of {}", entities.len(), wanted.len());
+
+    let entity = |id: &Option<String>| id.as_ref().and_then(|id| entities.get(id));
+    let text = |f: fn(&Entity) -> Option<&str>| ids.iter().map(|id| entity(id).and_then(f)).collect::<Vec<_>>();
+    let year = |f: fn(&Entity) -> Option<i32>| ids.iter().map(|id| entity(id).and_then(f)).collect::<Vec<_>>();
+    let columns = [
+        Series::new("etymology", text(|e| e.label.as_deref())),
+        Series::new("etymology_description", text(|e| e.description.as_deref())),
+        Series::new("etymology_instance_of", text(|e| e.instance_of.as_deref())),
+        Series::new("etymology_gender", text(|e| e.gender.as_deref())),
+        Series::new("etymology_birth_year", year(|e| e.birth_year)),
+        Series::new("etymology_death_year", year(|e| e.death_year)),
+    ];
+    for (offset, column) in columns.into_iter().enumerate() {
+        df.insert_column(index + 1 + offset, column)?;
+    }
+    Ok(entities.len())
+}
+
+/// The wanted entities of a JSON dump. Dump lines are `{"type":"item","id":"Q42",...},`, so
+/// the ID is read off the start of the line and only wanted lines are parsed.
+fn read_dump(path: &std::path::Path, wanted: &BTreeSet<&str>, lang: &str) -> Result<HashMap<String, Entity>> {
+    let file = std::fs::File::open(path).map_err(io_error(path))?;
+    let mut entities = HashMap::new();
+    for line in std::io::BufReader::with_capacity(1 << 20, file).lines() {
+        let line = line.map_err(io_error(path))?;
+        let line = line.trim().trim_end_matches(',');
+        let Some(id) = line_entity_id(line) else { continue };
+        if !wanted.contains(id) || entities.contains_key(id) {
+            continue;
+        }
+        let value: Value = serde_json::from_str(line).map_err(|err| {
+            ProcessorError::InvalidConfig(format!("malformed Wikidata entity {} in {}: {}", id, path.display(), err))
+        })?;
+        entities.insert(id.to_string(), entity_from_json(&value, lang));
+        if entities.len() == wanted.len() {
+            break;
+        }
+    }
+    Ok(entities)
+}
+
+/// The first `"id"` of an entity line, which is the entity's own
+fn line_entity_id(line: &str) -> Option<&str> {
+    if !line.starts_with('{') {
+        return None;
+    }
+    let start = line.find("\"id\":\"")? + 6;
+    let len = line[start..].find('"')?;
+    Some(&line[start..start + len])
+}
+
+#[cfg(feature = "async")]
+fn fetch_entities(url: &str, wanted: &BTreeSet<&str>, lang: &str) -> Result<HashMap<String, Entity>> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(ProcessorError::Runtime)?;
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("osm_processor_rust/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|source| ProcessorError::Fetch { url: url.to_string(), source })?;
+
+    let ids: Vec<&str> = wanted.iter().copied().collect();
+    let mut entities = HashMap::new();
+    for batch in ids.chunks(API_BATCH) {
+        let ids = batch.join("|");
+        let request = client.get(url).query(&[
+            ("action", "wbgetentities"),
+            ("ids", ids.as_str()),
+            ("props", "labels|descriptions|claims"),
+            ("languages", lang),
+            ("format", "json"),
+        ]);
+        let body = runtime
+            .block_on(async { request.send().await?.error_for_status()?.text().await })
+            .map_err(|source| ProcessorError::Fetch { url: url.to_string(), source })?;
+        let response: Value = serde_json::from_str(&body)
+            .map_err(|err| ProcessorError::InvalidConfig(format!("unexpected response from {}: {}", url, err)))?;
+        if let Some(error) = response.get("error") {
+            return Err(ProcessorError::InvalidConfig(format!("{} returned an error: {}", url, error)));
+        }
+        let Some(found) = response["entities"].as_object() else { continue };
+        for (id, value) in found {
+            if value.get("missing").is_none() {
+                entities.insert(id.clone(), entity_from_json(value, lang));
+            }
+        }
+    }
+    Ok(entities)
+}
+
+#[cfg(not(feature = "async"))]
+fn fetch_entities(url: &str, _wanted: &BTreeSet<&str>, _lang: &str) -> Result<HashMap<String, Entity>> {
+    Err(ProcessorError::InvalidConfig(format!(
+        "querying {} needs the `async` feature; pass a local Wikidata dump instead",
+        url
+    )))
+}
+
+/// Values of an entity's non-deprecated `property` statements, preferred rank first
+fn claim_values<'a>(value: &'a Value, property: &str) -> Vec<&'a Value> {
+    let mut statements: Vec<&Value> = value["claims"][property]
+        .as_array()
+        .map(|statements| statements.iter().filter(|s| s["rank"] != "deprecated").collect())
+        .unwrap_or_default();
+    statements.sort_by_key(|s| s["rank"] != "preferred");
+    statements.iter().map(|s| &s["mainsnak"]["datavalue"]["value"]).filter(|v| !v.is_null()).collect()
+}
+
+fn item_claim(value: &Value, property: &str) -> Option<String> {
+    claim_values(value, property).into_iter().find_map(|v| v["id"].as_str()).map(str::to_string)
+}
+
+/// Year of a time value (`+1952-03-11T00:00:00Z`, `-0044-03-15T00:00:00Z`)
+fn year_claim(value: &Value, property: &str) -> Option<i32> {
+    let time = claim_values(value, property).into_iter().find_map(|v| v["time"].as_str())?;
+    let (sign, rest) = match time.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, time.trim_start_matches('+')),
+    };
+    rest.split('-').next()?.parse::<i32>().ok().map(|year| sign * year)
+}
+
+fn gender_name(id: String) -> String {
+    match id.as_str() {
+        "Q6581072" => "female".to_string(),
+        "Q6581097" => "male".to_string(),
+        "Q1097630" => "intersex".to_string(),
+        "Q1052281" => "trans woman".to_string(),
+        "Q2449503" => "trans man".to_string(),
+        "Q48270" => "non-binary".to_string(),
+        _ => id,
+    }
+}
+
+fn entity_from_json(value: &Value, lang: &str) -> Entity {
+    let text = |field: &str| value[field][lang]["value"].as_str().map(str::to_string);
+    Entity {
+        label: text("labels"),
+        description: text("descriptions"),
+        instance_of: item_claim(value, "P31"),
+        gender: item_claim(value, "P21").map(gender_name),
+        birth_year: year_claim(value, "P569"),
+        death_year: year_claim(value, "P570"),
+    }
+}
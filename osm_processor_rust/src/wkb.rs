@@ -0,0 +1,133 @@
+use geo::{LineString, MultiLineString, MultiPolygon, Point, Polygon};
+
+use crate::round_coord;
+
+// Well-known binary geometry type codes
+const WKB_POINT: u32 = 1;
+const WKB_LINESTRING: u32 = 2;
+const WKB_POLYGON: u32 = 3;
+const WKB_MULTILINESTRING: u32 = 5;
+const WKB_MULTIPOLYGON: u32 = 6;
+
+/// Little-endian byte order marker
+const LITTLE_ENDIAN: u8 = 1;
+
+/// Reads WKB values in the byte order given by the geometry's marker
+struct WkbReader<'a> {
+    bytes: &'a [u8],
+    little_endian: bool,
+}
+
+impl WkbReader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.bytes.split_first_chunk::<N>()?;
+        self.bytes = rest;
+        Some(*head)
+    }
+
+    fn header(&mut self) -> Option<u32> {
+        let [order] = self.take::<1>()?;
+        self.little_endian = order == LITTLE_ENDIAN;
+        self.u32()
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.take::<4>()?;
+        Some(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        let bytes = self.take::<8>()?;
+        Some(if self.little_endian { f64::from_le_bytes(bytes) } else { f64::from_be_bytes(bytes) })
+    }
+
+    fn points(&mut self) -> Option<LineString<f64>> {
+        let len = self.u32()? as usize;
+        // Each point takes 16 bytes; refuse counts the remaining input cannot hold
+        if len > self.bytes.len() / 16 {
+            return None;
+        }
+        (0..len).map(|_| Some((self.f64()?, self.f64()?))).collect::<Option<Vec<_>>>().map(LineString::from)
+    }
+}
+
+fn write_header(buf: &mut Vec<u8>, geometry_type: u32) {
+    buf.push(LITTLE_ENDIAN);
+    buf.extend_from_slice(&geometry_type.to_le_bytes());
+}
+
+fn write_points(buf: &mut Vec<u8>, line: &LineString<f64>, precision: Option<u32>) {
+    buf.extend_from_slice(&(line.0.len() as u32).to_le_bytes());
+    for coord in &line.0 {
+        buf.extend_from_slice(&round_coord(coord.x, precision).to_le_bytes());
+        buf.extend_from_slice(&round_coord(coord.y, precision).to_le_bytes());
+    }
+}
+
+fn write_polygon(buf: &mut Vec<u8>, polygon: &Polygon<f64>, precision: Option<u32>) {
+    write_header(buf, WKB_POLYGON);
+    buf.extend_from_slice(&(1 + polygon.interiors().len() as u32).to_le_bytes());
+    write_points(buf, polygon.exterior(), precision);
+    for interior in polygon.interiors() {
+        write_points(buf, interior, precision);
+    }
+}
+
+/// Encode a MultiPolygon as little-endian WKB (x = lon, y = lat), optionally rounding coordinates
+pub fn multipolygon_to_wkb(multipolygon: &MultiPolygon<f64>, precision: Option<u32>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_header(&mut buf, WKB_MULTIPOLYGON);
+    buf.extend_from_slice(&(multipolygon.0.len() as u32).to_le_bytes());
+    for polygon in &multipolygon.0 {
+        write_polygon(&mut buf, polygon, precision);
+    }
+    buf
+}
+
+/// Encode a MultiLineString as little-endian WKB (x = lon, y = lat), optionally rounding coordinates
+pub fn multilinestring_to_wkb(lines: &MultiLineString<f64>, precision: Option<u32>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_header(&mut buf, WKB_MULTILINESTRING);
+    buf.extend_from_slice(&(lines.0.len() as u32).to_le_bytes());
+    for line in &lines.0 {
+        write_header(&mut buf, WKB_LINESTRING);
+        write_points(&mut buf, line, precision);
+    }
+    buf
+}
+
+/// Encode a LineString as little-endian WKB (x = lon, y = lat), optionally rounding coordinates
+pub fn linestring_to_wkb(line: &LineString<f64>, precision: Option<u32>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_header(&mut buf, WKB_LINESTRING);
+    write_points(&mut buf, line, precision);
+    buf
+}
+
+/// Decode a WKB Point (either byte order); `None` for other geometry types or truncated input
+pub fn wkb_to_point(bytes: &[u8]) -> Option<Point<f64>> {
+    let mut reader = WkbReader { bytes, little_endian: true };
+    match reader.header()? {
+        WKB_POINT => Some(Point::new(reader.f64()?, reader.f64()?)),
+        _ => None,
+    }
+}
+
+/// Decode a WKB LineString or MultiLineString (either byte order) into its lines; `None` for
+/// other geometry types or truncated input
+pub fn wkb_to_lines(bytes: &[u8]) -> Option<Vec<LineString<f64>>> {
+    let mut reader = WkbReader { bytes, little_endian: true };
+    match reader.header()? {
+        WKB_LINESTRING => Some(vec![reader.points()?]),
+        WKB_MULTILINESTRING => {
+            let len = reader.u32()?;
+            (0..len)
+                .map(|_| match reader.header()? {
+                    WKB_LINESTRING => reader.points(),
+                    _ => None,
+                })
+                .collect()
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,248 @@
+//! Grouping and parsing edge cases on synthetic PBF files from `testkit` (run with
+//! `--features testkit`)
+
+use osm_processor_rust::qa::QaConfig;
+use osm_processor_rust::sanitize::LOSSY_TAGS;
+use osm_processor_rust::testkit::{MemberType, PbfBuilder};
+use osm_processor_rust::{OsmStreetProcessor, ShardConfig, Street};
+use osmpbf::{Element, ElementReader, RelMemberType};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Write `pbf` to a file of its own in the temp dir
+fn fixture(name: &str, pbf: &PbfBuilder) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("osm_processor_test_{}_{}.osm.pbf", std::process::id(), name));
+    pbf.write(&path).unwrap();
+    path
+}
+
+fn streets(path: &PathBuf, threshold_km: f64) -> Vec<Street> {
+    let result = OsmStreetProcessor::builder()
+        .input(path)
+        .state("testland")
+        .distance_threshold_km(threshold_km)
+        .run()
+        .unwrap();
+    std::fs::remove_file(path).ok();
+    result.streets
+}
+
+/// Segments per street, by name, sorted
+fn segment_counts(streets: &[Street]) -> HashMap<String, Vec<usize>> {
+    let mut counts: HashMap<String, Vec<usize>> = HashMap::new();
+    for street in streets {
+        counts.entry(street.street_name.clone()).or_default().push(street.num_segments);
+    }
+    for list in counts.values_mut() {
+        list.sort_unstable();
+    }
+    counts
+}
+
+/// Two pieces of Main St about 5.5 km apart, each of two ways sharing a node
+fn two_pieces() -> PbfBuilder {
+    let mut pbf = PbfBuilder::new();
+    for lat in [39.0, 39.05] {
+        let (_, nodes) = pbf.add_street("Main St", "residential", &[(lat, -75.0), (lat + 0.001, -75.0)]);
+        let next = pbf.add_node(lat + 0.002, -75.0);
+        pbf.add_way(&[nodes[1], next], &[("highway", "residential"), ("name", "Main St")]);
+    }
+    pbf
+}
+
+#[test]
+fn disconnected_pieces_stay_apart_beyond_the_threshold() {
+    let path = fixture("disconnected", &two_pieces());
+    let counts = segment_counts(&streets(&path, 0.2));
+    assert_eq!(counts["Main St"], vec![2, 2]);
+}
+
+#[test]
+fn disconnected_pieces_merge_within_the_threshold() {
+    let path = fixture("disconnected_merged", &two_pieces());
+    let counts = segment_counts(&streets(&path, 10.0));
+    assert_eq!(counts["Main St"], vec![4]);
+}
+
+#[test]
+fn ways_meeting_at_a_node_join_only_by_name() {
+    let mut pbf = PbfBuilder::new();
+    let (_, main) = pbf.add_street("Main St", "residential", &[(39.0, -75.0), (39.001, -75.0)]);
+    let end = pbf.add_node(39.002, -75.0);
+    pbf.add_way(&[main[1], end], &[("highway", "residential"), ("name", "Main St")]);
+    // Crosses Main St at its middle node
+    let west = pbf.add_node(39.001, -75.001);
+    let east = pbf.add_node(39.001, -74.999);
+    pbf.add_way(&[west, main[1], east], &[("highway", "residential"), ("name", "Oak Ave")]);
+
+    let path = fixture("border_nodes", &pbf);
+    let counts = segment_counts(&streets(&path, 0.0));
+    assert_eq!(counts["Main St"], vec![2]);
+    assert_eq!(counts["Oak Ave"], vec![1]);
+}
+
+#[test]
+fn ways_cut_off_at_the_extract_border_keep_the_nodes_present() {
+    let mut pbf = PbfBuilder::new();
+    let inside = pbf.add_node(39.0, -75.0);
+    let also_inside = pbf.add_node(39.001, -75.0);
+    // Node 1000 was clipped away with the rest of the way outside the extract
+    pbf.add_way(&[inside, also_inside, 1000], &[("highway", "primary"), ("name", "Border Rd")]);
+    // Only outside nodes: nothing to place it with
+    pbf.add_way(&[1001, 1002], &[("highway", "primary"), ("name", "Elsewhere Rd")]);
+
+    let path = fixture("extract_border", &pbf);
+    let streets = streets(&path, 0.2);
+    assert_eq!(streets.len(), 1);
+    let border = &streets[0];
+    assert_eq!(border.street_name, "Border Rd");
+    assert!((border.length_km - 0.111).abs() < 0.001, "length {}", border.length_km);
+}
+
+#[test]
+fn roundabout_is_one_street_with_its_same_name_approach() {
+    let mut pbf = PbfBuilder::new();
+    let ring = [(39.01, -75.0), (39.01, -75.001), (39.011, -75.0005)];
+    let (_, ring_nodes) = pbf.add_ring_street("Circle Dr", "tertiary", &ring);
+    assert_eq!(ring_nodes.first(), ring_nodes.last());
+    let approach = pbf.add_node(39.009, -75.0);
+    pbf.add_way(&[approach, ring_nodes[0]], &[("highway", "tertiary"), ("name", "Circle Dr")]);
+    let side = pbf.add_node(39.012, -75.0005);
+    pbf.add_way(&[ring_nodes[2], side], &[("highway", "residential"), ("name", "Spur Ln")]);
+
+    let path = fixture("roundabout", &pbf);
+    let streets = streets(&path, 0.0);
+    let counts = segment_counts(&streets);
+    assert_eq!(counts["Circle Dr"], vec![2]);
+    assert_eq!(counts["Spur Ln"], vec![1]);
+    let circle = streets.iter().find(|s| s.street_name == "Circle Dr").unwrap();
+    assert_eq!(circle.tags.get("junction").map(String::as_str), Some("roundabout"));
+}
+
+#[test]
+fn hardened_parsing_keeps_ways_with_invalid_utf8_tags() {
+    let mut pbf = PbfBuilder::new();
+    let (_, nodes) = pbf.add_street("Main St", "residential", &[(39.0, -75.0), (39.001, -75.0)]);
+    let end = pbf.add_node(39.002, -75.0);
+    // Ahead of the way's highway and name tags, which a checked read would lose with it
+    pbf.add_way(&[nodes[1], end], &[("note", "caf@@"), ("highway", "residential"), ("name", "Main St")]);
+    let mut bytes = pbf.to_bytes();
+    let at = bytes.windows(5).position(|w| w == b"caf@@").unwrap();
+    bytes[at + 3..at + 5].copy_from_slice(&[0xff, 0xfe]);
+    let path = std::env::temp_dir().join(format!("osm_processor_test_{}_lossy.osm.pbf", std::process::id()));
+    std::fs::write(&path, bytes).unwrap();
+
+    let run = |hardened: bool| {
+        OsmStreetProcessor::builder()
+            .input(&path)
+            .state("testland")
+            .hardened(hardened)
+            .qa(QaConfig::default())
+            .run()
+            .unwrap()
+    };
+    let plain = run(false);
+    let hardened = run(true);
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(plain.streets[0].num_segments, 1);
+    assert_eq!(hardened.streets.len(), 1);
+    let street = &hardened.streets[0];
+    assert_eq!(street.num_segments, 2);
+    assert_eq!(street.tags.get("highway").map(String::as_str), Some("residential"));
+    assert_eq!(hardened.sanitized.unwrap().lossy_tags, 1);
+    let qa = hardened.qa.unwrap();
+    assert_eq!(qa.len(), 1);
+    assert!(qa[0].flags.contains(&LOSSY_TAGS), "flags {:?}", qa[0].flags);
+}
+
+#[test]
+fn sharded_run_matches_unsharded_across_shard_edges() {
+    let mut pbf = PbfBuilder::new();
+    // Crosses the 1° shard edge at lon -75, and a second piece of it 150 m away on the far side
+    let (_, nodes) = pbf.add_street("Edge St", "residential", &[(39.5, -75.002), (39.5, -74.999)]);
+    let next = pbf.add_node(39.5, -74.998);
+    pbf.add_way(&[nodes[1], next], &[("highway", "residential"), ("name", "Edge St")]);
+    pbf.add_street("Edge St", "residential", &[(39.5, -74.9962), (39.5, -74.995)]);
+    pbf.add_street("Inner Ave", "residential", &[(39.5, -75.5), (39.501, -75.5)]);
+    let path = fixture("sharded", &pbf);
+
+    let summary = |streets: &[Street]| {
+        let mut rows: Vec<(String, usize, String)> = streets
+            .iter()
+            .map(|s| (s.street_name.clone(), s.num_segments, format!("{:.6}", s.length_km)))
+            .collect();
+        rows.sort();
+        rows
+    };
+    let run = |shards: Option<ShardConfig>| {
+        let mut builder = OsmStreetProcessor::builder().input(&path).state("testland").distance_threshold_km(0.2);
+        if let Some(config) = shards {
+            builder = builder.shards(config);
+        }
+        summary(&builder.run().unwrap().streets)
+    };
+    let unsharded = run(None);
+    let sharded = run(Some(ShardConfig { shard_deg: 1.0, halo_deg: 0.05, jobs: 2 }));
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(unsharded.len(), 2);
+    assert_eq!(unsharded[0].1, 3);
+    assert_eq!(sharded, unsharded);
+}
+
+#[test]
+fn round_trips_through_osmpbf() {
+    let mut pbf = PbfBuilder::new();
+    let stop = pbf.add_tagged_node(39.123_456_7, -75.765_432_1, &[("highway", "stop")]);
+    let (way, nodes) = pbf.add_street("Main St", "residential", &[(39.0, -75.0), (-33.9, 151.2)]);
+    let relation = pbf.add_relation(
+        &[(MemberType::Way, way, "street"), (MemberType::Node, stop, "")],
+        &[("type", "associatedStreet"), ("name", "Main St")],
+    );
+    let path = fixture("round_trip", &pbf);
+
+    let (mut nodes_seen, mut ways_seen, mut relations_seen) = (Vec::new(), Vec::new(), Vec::new());
+    ElementReader::from_path(&path)
+        .unwrap()
+        .for_each(|element| match element {
+            Element::Node(node) => {
+                let tags: Vec<_> = node.tags().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+                nodes_seen.push((node.id(), node.lat(), node.lon(), tags));
+            }
+            Element::DenseNode(node) => nodes_seen.push((node.id(), node.lat(), node.lon(), Vec::new())),
+            Element::Way(way) => {
+                let tags: Vec<_> = way.tags().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+                ways_seen.push((way.id(), way.refs().collect::<Vec<_>>(), tags));
+            }
+            Element::Relation(relation) => {
+                let members: Vec<_> = relation
+                    .members()
+                    .map(|m| {
+                        let role = m.role().unwrap().to_string();
+                        (m.member_type, m.member_id, role)
+                    })
+                    .collect();
+                let tags: Vec<_> = relation.tags().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+                relations_seen.push((relation.id(), members, tags));
+            }
+        })
+        .unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(nodes_seen.len(), 3);
+    let (id, lat, lon, tags) = &nodes_seen[0];
+    assert_eq!(*id, stop);
+    assert!((lat - 39.123_456_7).abs() < 1e-7 && (lon + 75.765_432_1).abs() < 1e-7);
+    assert_eq!(tags, &[("highway".to_string(), "stop".to_string())]);
+    let (_, lat, lon, _) = &nodes_seen[2];
+    assert!((lat + 33.9).abs() < 1e-7 && (lon - 151.2).abs() < 1e-7);
+
+    let street_tags = vec![("highway".to_string(), "residential".to_string()), ("name".to_string(), "Main St".to_string())];
+    assert_eq!(ways_seen, vec![(way, nodes, street_tags)]);
+
+    let (id, members, tags) = &relations_seen[0];
+    assert_eq!(*id, relation);
+    assert_eq!(members, &[(RelMemberType::Way, way, "street".to_string()), (RelMemberType::Node, stop, String::new())]);
+    assert_eq!(tags.len(), 2);
+}